@@ -0,0 +1,113 @@
+#[macro_use]
+extern crate criterion;
+extern crate cgmath;
+extern crate meshlite;
+
+use cgmath::Point3;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use meshlite::iterator::FaceIterator;
+use meshlite::mesh::Mesh;
+use meshlite::primitives::cube;
+use meshlite::selection::Selection;
+use meshlite::simplify::SimplifyOptions;
+use meshlite::subdivide::CatmullClarkSubdivider;
+use meshlite::triangulate::Triangulate;
+use std::f32::consts::PI;
+
+fn sphere() -> Mesh {
+    Mesh::from_grid(32, 16, |u, v| {
+        let (theta, phi) = (u * 2.0 * PI, v * PI);
+        Point3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin())
+    }, true, false)
+}
+
+/// Throughput counts the faces made, as in the `benchmark_suite` example.
+fn catmull_clark(c: &mut Criterion) {
+    let grid = Mesh::from_grid(128, 128, |u, v| Point3::new(u, v, 0.0), false, false);
+    let inputs = [("cube", cube()), ("sphere", sphere()), ("grid", grid)];
+    let mut group = c.benchmark_group("catmull-clark");
+    group.sample_size(10);
+    for &(name, ref mesh) in inputs.iter() {
+        for levels in 1..5 {
+            if name == "grid" && levels > 3 {
+                continue;
+            }
+            group.throughput(Throughput::Elements(mesh.subdivide_n(levels).face_count as u64));
+            group.bench_with_input(BenchmarkId::new(name, levels), &levels, |b, &levels| b.iter(|| mesh.subdivide_n(levels)));
+        }
+    }
+    group.finish();
+}
+
+/// Loop and Catmull-Clark on the same triangles, both making four faces
+/// from every triangle.
+fn loop_comparison(c: &mut Criterion) {
+    let triangles = sphere().subdivide_n(1).triangulate();
+    let mut group = c.benchmark_group("loop comparison");
+    group.throughput(Throughput::Elements(triangles.face_count as u64 * 4));
+    group.bench_function("loop", |b| b.iter(|| triangles.loop_subdivide().unwrap()));
+    group.bench_function("catmull-clark", |b| b.iter(|| triangles.subdivide_n(1)));
+    group.finish();
+}
+
+/// Every face of a subdivided grid is a quad, walked into a stack array or,
+/// with the fast path off, collected in a Vec like any other face.
+fn quad_path(c: &mut Criterion) {
+    let quads = Mesh::from_grid(128, 128, |u, v| Point3::new(u, v, 0.0), false, false).subdivide_n(1);
+    let mut group = c.benchmark_group("catmull-clark subdivided grid");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(quads.face_count as u64 * 4));
+    group.bench_function("stack array", |b| b.iter(|| CatmullClarkSubdivider::new(&quads).generate()));
+    group.bench_function("per-face vec", |b| b.iter(|| CatmullClarkSubdivider::new(&quads).with_quad_fast_path(false).generate()));
+    group.finish();
+}
+
+/// The subdivider keeps its edge point and vertex maps in FnvHashMaps, the
+/// child map adds another lookup per output face.
+fn hash_map_paths(c: &mut Criterion) {
+    let dense = cube().subdivide_n(5);
+    let mut group = c.benchmark_group("catmull-clark maps");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(dense.face_count as u64 * 4));
+    group.bench_function("child map", |b| b.iter(|| CatmullClarkSubdivider::new(&dense).generate_with_child_map().0));
+    let mut buffer = Mesh::new();
+    group.bench_function("reused output", |b| b.iter(|| dense.subdivide_into(&mut buffer)));
+    let half = Selection::from_faces(FaceIterator::new(&dense).filter(|&face_id| dense.face_center(face_id).x > 0.0));
+    group.throughput(Throughput::Elements(half.len() as u64 * 4));
+    group.bench_function("region", |b| b.iter(|| {
+        let mut refined = dense.duplicate();
+        refined.subdivide_region(&half);
+        refined
+    }));
+    group.finish();
+}
+
+fn smoothing(c: &mut Criterion) {
+    let dense = cube().subdivide_n(5);
+    let mut group = c.benchmark_group("smoothing");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(dense.face_count as u64));
+    group.bench_function("laplacian", |b| b.iter(|| {
+        let mut smoothed = dense.clone();
+        smoothed.smooth(0.5, None);
+        smoothed
+    }));
+    group.bench_function("bilateral x3", |b| b.iter(|| dense.bilateral_smooth(3, 0.1, 0.3)));
+    group.finish();
+}
+
+/// Throughput counts the faces consumed.
+fn simplification(c: &mut Criterion) {
+    let triangles = cube().subdivide_n(4).triangulate();
+    let target = triangles.face_count / 10;
+    let mut group = c.benchmark_group("simplification");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(triangles.face_count as u64));
+    group.bench_function("to 10%", |b| b.iter(|| triangles.simplify_to(target, &SimplifyOptions::default())));
+    group.finish();
+}
+
+criterion_group!(benches, catmull_clark, loop_comparison, quad_path, hash_map_paths, smoothing, simplification);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+extern crate cgmath;
+extern crate meshlite;
+
+use cgmath::Vector3;
+use meshlite::cache::NormalCache;
+use meshlite::primitives::cube;
+use std::time::{Duration, Instant};
+
+const LEVELS: usize = 4;
+const MOVES: usize = 200;
+
+fn main() {
+    let mut mesh = cube().subdivide_n(LEVELS);
+    let vertex_ids: Vec<usize> = (0..MOVES).map(|i| (i * 7919) % mesh.vertices.len() + 1).collect();
+    println!("Moving {} vertices one at a time on {} faces", MOVES, mesh.face_count);
+
+    let now = Instant::now();
+    let mut full = Vec::new();
+    for (i, &vertex_id) in vertex_ids.iter().enumerate() {
+        mesh.vertex_mut(vertex_id).unwrap().position += Vector3::new(0.0, 0.0, 0.001 * i as f32);
+        full = mesh.vertices.iter().map(|vertex| mesh.vertex_norm(vertex.id)).collect();
+    }
+    let full_seconds = to_seconds_f64(&now.elapsed());
+
+    let mut cache = NormalCache::new(&mesh);
+    let now = Instant::now();
+    for (i, &vertex_id) in vertex_ids.iter().enumerate() {
+        mesh.vertex_mut(vertex_id).unwrap().position -= Vector3::new(0.0, 0.0, 0.001 * i as f32);
+        cache.mark_vertex_moved(&mesh, vertex_id);
+        cache.refresh(&mesh);
+    }
+    let incremental_seconds = to_seconds_f64(&now.elapsed());
+
+    assert_eq!(full.len(), mesh.vertex_count);
+    println!("full recompute         | {:.2} us per move", full_seconds * 1e6 / MOVES as f64);
+    println!("NormalCache            | {:.2} us per move", incremental_seconds * 1e6 / MOVES as f64);
+}
+
+fn to_seconds_f64(d: &Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
@@ -0,0 +1,93 @@
+extern crate meshlite;
+
+use meshlite::mesh::Export;
+use meshlite::mesh::Import;
+use meshlite::mesh::Mesh;
+use meshlite::primitives::cube;
+use meshlite::subdivide::Subdivide;
+use std::env;
+use std::path::Path;
+use std::fs;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// Peak resident set size of this process in kB, only available on Linux.
+fn peak_rss_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find(|line| line.starts_with("VmHWM:"))
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|value| value.parse().ok())
+}
+
+fn to_seconds_f64(d: &Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
+
+/// Loads the file in a fresh process so each loader starts from a clean
+/// peak memory measurement.
+fn load(loader: &str, filename: &str) {
+    let now = Instant::now();
+    let mesh = match loader {
+        "streaming" => Mesh::load_obj_streaming(filename).expect("load file failed"),
+        // How the loaders taking options read files before they streamed.
+        "whole file" => {
+            let bytes = fs::read(filename).expect("read file failed");
+            Mesh::load_obj_from_bytes(&bytes, Path::new(filename).parent().unwrap()).expect("load file failed")
+        },
+        #[cfg(all(feature = "mmap", unix))]
+        "mmap" => Mesh::load_obj_mmap(filename, &meshlite::wavefront::ObjImportOptions::default()).expect("load file failed").0,
+        _ => {
+            let mut mesh = Mesh::new();
+            mesh.import(filename).expect("load file failed");
+            mesh
+        },
+    };
+    let seconds = to_seconds_f64(&now.elapsed());
+    println!(
+        "{: <10} | {: <9} | {: <9} | {: <9.2} | {}",
+        loader,
+        mesh.face_count,
+        mesh.vertex_count,
+        seconds * 1000.0,
+        peak_rss_kb().map_or("n/a".to_string(), |kb| kb.to_string())
+    );
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 3 {
+        load(&args[1], &args[2]);
+        return;
+    }
+    let levels = args.get(1).and_then(|value| value.parse().ok()).unwrap_or(8);
+    let mut mesh = cube();
+    for _ in 0..levels {
+        mesh = mesh.subdivide();
+    }
+    let path = env::temp_dir().join("meshlite_benchmark_obj_load.obj");
+    let filename = path.to_str().unwrap();
+    mesh.export(filename).expect("save file failed");
+    drop(mesh);
+    println!(
+        "file size: {} kB",
+        fs::metadata(filename).map(|m| m.len() / 1024).unwrap_or(0)
+    );
+    println!("loader     | faces     | vertices  | time (ms) | peak rss (kB)");
+    println!("-----------+-----------+-----------+-----------+--------------");
+    let exe = env::current_exe().unwrap();
+    let mut loaders = vec!["import", "streaming", "whole file"];
+    if cfg!(all(feature = "mmap", unix)) {
+        loaders.push("mmap");
+    }
+    for loader in loaders.iter() {
+        let output = Command::new(&exe)
+            .arg(loader)
+            .arg(filename)
+            .output()
+            .expect("run loader failed");
+        print!("{}", String::from_utf8_lossy(&output.stdout));
+    }
+    fs::remove_file(filename).ok();
+}
@@ -0,0 +1,35 @@
+extern crate cgmath;
+extern crate meshlite;
+
+use cgmath::Point3;
+use cgmath::Vector3;
+use meshlite::primitives::cube;
+use std::time::{Duration, Instant};
+
+const LEVELS: usize = 4;
+const RAYS: usize = 2000;
+
+fn main() {
+    let mesh = cube().subdivide_n(LEVELS);
+    let rays: Vec<(Point3<f32>, Vector3<f32>)> = (0..RAYS).map(|i| {
+        let t = i as f32 * 0.618;
+        (Point3::new(0.0, 0.0, 0.0), Vector3::new(t.cos(), (t * 1.7).sin(), (t * 0.3).cos() - 0.5))
+    }).collect();
+    println!("Casting {} rays against {} faces", RAYS, mesh.face_count);
+
+    let now = Instant::now();
+    let individual: Vec<_> = rays.iter().map(|&(origin, direct)| mesh.raycast(origin, direct)).collect();
+    let individual_seconds = to_seconds_f64(&now.elapsed());
+
+    let now = Instant::now();
+    let batch = mesh.raycast_batch(&rays);
+    let batch_seconds = to_seconds_f64(&now.elapsed());
+
+    assert_eq!(individual.len(), batch.len());
+    println!("individual raycast     | {:.2} us per ray", individual_seconds * 1e6 / RAYS as f64);
+    println!("raycast_batch          | {:.2} us per ray", batch_seconds * 1e6 / RAYS as f64);
+}
+
+fn to_seconds_f64(d: &Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
@@ -0,0 +1,40 @@
+extern crate cgmath;
+extern crate meshlite;
+
+use cgmath::Point3;
+use meshlite::mesh::Mesh;
+use meshlite::subdivide::CatmullClarkSubdivider;
+use std::time::{Duration, Instant};
+
+const STEPS: usize = 200;
+const ROUNDS: usize = 20;
+
+fn main() {
+    // A flat panel with a single ridge, like a wall with a molding.
+    let mesh = Mesh::from_grid(STEPS, STEPS, |u, v| {
+        let ridge = if (u - 0.5).abs() < 0.01 { 0.05 } else { 0.0 };
+        Point3::new(u, v, ridge)
+    }, false, false);
+    println!("Subdividing {} mostly flat faces {} times", mesh.face_count, ROUNDS);
+
+    let now = Instant::now();
+    for _ in 0..ROUNDS {
+        let new_mesh = CatmullClarkSubdivider::new(&mesh).generate();
+        assert!(new_mesh.face_count > 0);
+    }
+    let full = to_seconds_f64(&now.elapsed());
+
+    let now = Instant::now();
+    for _ in 0..ROUNDS {
+        let new_mesh = CatmullClarkSubdivider::new(&mesh).with_flat_optimization(1e-5).generate();
+        assert!(new_mesh.face_count > 0);
+    }
+    let flat = to_seconds_f64(&now.elapsed());
+
+    println!("full rules             | {:.2} ms per call", full * 1000.0 / ROUNDS as f64);
+    println!("flat optimization      | {:.2} ms per call", flat * 1000.0 / ROUNDS as f64);
+}
+
+fn to_seconds_f64(d: &Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
@@ -0,0 +1,36 @@
+extern crate meshlite;
+
+use meshlite::mesh::Mesh;
+use meshlite::primitives::cube;
+use meshlite::subdivide::Subdivide;
+use std::time::{Duration, Instant};
+
+const LEVELS: usize = 5;
+const ROUNDS: usize = 20;
+
+fn main() {
+    let mesh = cube().subdivide_n(LEVELS);
+    println!("Subdividing {} faces {} times", mesh.face_count, ROUNDS);
+
+    let now = Instant::now();
+    for _ in 0..ROUNDS {
+        let new_mesh = mesh.subdivide();
+        assert!(new_mesh.face_count > 0);
+    }
+    let allocating = to_seconds_f64(&now.elapsed());
+
+    let mut buffer = Mesh::new();
+    let now = Instant::now();
+    for _ in 0..ROUNDS {
+        mesh.subdivide_into(&mut buffer);
+        assert!(buffer.face_count > 0);
+    }
+    let reusing = to_seconds_f64(&now.elapsed());
+
+    println!("new mesh per call      | {:.2} ms per call", allocating * 1000.0 / ROUNDS as f64);
+    println!("reused output mesh     | {:.2} ms per call", reusing * 1000.0 / ROUNDS as f64);
+}
+
+fn to_seconds_f64(d: &Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
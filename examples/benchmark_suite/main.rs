@@ -0,0 +1,116 @@
+extern crate cgmath;
+extern crate meshlite;
+
+use cgmath::Point3;
+use meshlite::iterator::FaceIterator;
+use meshlite::mesh::Mesh;
+use meshlite::primitives::cube;
+use meshlite::selection::Selection;
+use meshlite::simplify::SimplifyOptions;
+use meshlite::subdivide::CatmullClarkSubdivider;
+use meshlite::triangulate::Triangulate;
+use std::f32::consts::PI;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const ROUNDS: usize = 5;
+
+fn main() {
+    let sphere = Mesh::from_grid(32, 16, |u, v| {
+        let (theta, phi) = (u * 2.0 * PI, v * PI);
+        Point3::new(phi.sin() * theta.cos(), phi.cos(), phi.sin() * theta.sin())
+    }, true, false);
+    let grid = Mesh::from_grid(128, 128, |u, v| Point3::new(u, v, 0.0), false, false);
+    let inputs = [("cube", cube()), ("sphere", sphere), ("grid", grid)];
+
+    println!("operation                       | faces in  | faces out | time (ms) | faces/s");
+    println!("--------------------------------+-----------+-----------+-----------+-----------");
+    for &(name, ref mesh) in inputs.iter() {
+        for levels in 1..5 {
+            if name == "grid" && levels > 3 {
+                continue;
+            }
+            report(&format!("catmull-clark {} x{}", name, levels), mesh, || mesh.subdivide_n(levels));
+        }
+    }
+
+    // Loop and Catmull-Clark on the same triangles, both making four faces
+    // from every triangle.
+    let triangles = inputs[1].1.subdivide_n(1).triangulate();
+    report("loop sphere triangles", &triangles, || triangles.loop_subdivide().unwrap());
+    report("catmull-clark sphere triangles", &triangles, || triangles.subdivide_n(1));
+
+    // Every face of a subdivided grid is a quad, walked into a stack array
+    // or, with the fast path off, collected in a Vec like any other face.
+    let quads = inputs[2].1.subdivide_n(1);
+    report("catmull-clark grid stack array", &quads, || CatmullClarkSubdivider::new(&quads).generate());
+    report("catmull-clark grid per-face Vec", &quads, || CatmullClarkSubdivider::new(&quads).with_quad_fast_path(false).generate());
+
+    // The subdivider keeps its edge point and vertex maps in FnvHashMaps,
+    // the child map adds another lookup per output face.
+    let dense = cube().subdivide_n(5);
+    report("catmull-clark child map", &dense, || CatmullClarkSubdivider::new(&dense).generate_with_child_map().0);
+    let mut buffer = Mesh::new();
+    let seconds = time(|| dense.subdivide_into(&mut buffer));
+    print_row("catmull-clark reused output", dense.face_count, buffer.face_count, seconds);
+
+    let half = Selection::from_faces(FaceIterator::new(&dense).filter(|&face_id| dense.face_center(face_id).x > 0.0));
+    let seconds = time(|| {
+        let mut refined = dense.duplicate();
+        refined.subdivide_region(&half);
+    });
+    print_row("catmull-clark region", half.len(), half.len() * 4, seconds);
+
+    let seconds = time(|| {
+        let mut smoothed = dense.clone();
+        smoothed.smooth(0.5, None);
+    });
+    print_row("laplacian smooth", dense.face_count, dense.face_count, seconds);
+    report("bilateral smooth x3", &dense, || dense.bilateral_smooth(3, 0.1, 0.3));
+
+    // Parsing bytes already in memory is what a memory mapped loader would
+    // do, minus the page faults. The file based loaders are compared in the
+    // `benchmark_obj_load` example.
+    let mut bytes = Vec::new();
+    dense.write_obj(&mut bytes).unwrap();
+    let (mut read, mut parsed) = (Mesh::new(), Mesh::new());
+    let seconds = time(|| {
+        read = Mesh::new();
+        read.read_obj(&bytes[..]).unwrap();
+    });
+    print_row("obj read from a reader", dense.face_count, read.face_count, seconds);
+    let seconds = time(|| parsed = Mesh::load_obj_from_bytes(&bytes, Path::new(".")).unwrap());
+    print_row("obj load from bytes", dense.face_count, parsed.face_count, seconds);
+
+    let triangles = cube().subdivide_n(4).triangulate();
+    let target = triangles.face_count / 10;
+    report("simplify to 10%", &triangles, || triangles.simplify_to(target, &SimplifyOptions::default()));
+}
+
+/// Times an operation making a mesh and prints its throughput.
+fn report<F: FnMut() -> Mesh>(name: &str, input: &Mesh, mut operation: F) {
+    let mut output_faces = 0;
+    let seconds = time(|| output_faces = operation().face_count);
+    print_row(name, input.face_count, output_faces, seconds);
+}
+
+/// Average seconds per call over `ROUNDS` calls, after one warm up call.
+fn time<F: FnMut()>(mut operation: F) -> f64 {
+    operation();
+    let now = Instant::now();
+    for _ in 0..ROUNDS {
+        operation();
+    }
+    to_seconds_f64(&now.elapsed()) / ROUNDS as f64
+}
+
+/// Throughput counts the larger of the input and output faces, the faces
+/// made when subdividing and the faces consumed when simplifying.
+fn print_row(name: &str, faces_in: usize, faces_out: usize, seconds: f64) {
+    println!("{: <31} | {: <9} | {: <9} | {: <9.2} | {:.0}",
+        name, faces_in, faces_out, seconds * 1000.0, faces_in.max(faces_out) as f64 / seconds);
+}
+
+fn to_seconds_f64(d: &Duration) -> f64 {
+    d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
@@ -0,0 +1,382 @@
+use cgmath::Point2;
+use cgmath::Point3;
+use cgmath::Vector2;
+use cgmath::Vector3;
+use cgmath::Vector4;
+use cgmath::prelude::*;
+use fnv::FnvHashMap;
+use mesh::EdgeEndpoints;
+use mesh::Id;
+use std::any::Any;
+use std::fmt;
+use std::hash::Hash;
+
+/// A value that can be stored per element in an `AttributeStore`. Operations
+/// that create elements from others, like subdivision, fill in the new values
+/// with `interpolate`.
+pub trait Attribute: Any + Clone {
+    /// Blends the values by their weights, which are positive and add up to
+    /// one.
+    fn interpolate(weighted: &[(&Self, f32)]) -> Self;
+}
+
+macro_rules! linear_attribute {
+    ($type:ty, $zero:expr) => {
+        impl Attribute for $type {
+            fn interpolate(weighted: &[(&Self, f32)]) -> Self {
+                weighted.iter().fold($zero, |sum, &(value, weight)| sum + *value * weight)
+            }
+        }
+    }
+}
+
+linear_attribute!(f32, 0.0);
+linear_attribute!(Vector2<f32>, Vector2::zero());
+linear_attribute!(Vector3<f32>, Vector3::zero());
+linear_attribute!(Vector4<f32>, Vector4::zero());
+
+impl Attribute for f64 {
+    fn interpolate(weighted: &[(&Self, f32)]) -> Self {
+        weighted.iter().fold(0.0, |sum, &(value, weight)| sum + *value * weight as f64)
+    }
+}
+
+impl Attribute for Point2<f32> {
+    fn interpolate(weighted: &[(&Self, f32)]) -> Self {
+        Point2::from_vec(weighted.iter().fold(Vector2::zero(), |sum, &(value, weight)| sum + value.to_vec() * weight))
+    }
+}
+
+impl Attribute for Point3<f32> {
+    fn interpolate(weighted: &[(&Self, f32)]) -> Self {
+        Point3::from_vec(weighted.iter().fold(Vector3::zero(), |sum, &(value, weight)| sum + value.to_vec() * weight))
+    }
+}
+
+/// The values of one attribute with the type erased, so attributes of
+/// different types can be kept and copied together.
+trait AttributeValues<K> {
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    fn box_clone(&self) -> Box<dyn AttributeValues<K>>;
+
+    /// An attribute of the same type without values.
+    fn empty_like(&self) -> Box<dyn AttributeValues<K>>;
+
+    /// Sets every target element of `into`, which has to be of the same
+    /// type, to the blend of the source elements of `self` with their
+    /// weights. Sources without a value are left out and the rest weighted
+    /// up, and targets without any source value are left unset.
+    fn resample_into(&self, into: &mut dyn AttributeValues<K>, targets: &[(K, Vec<(K, f32)>)]);
+
+    /// Gives every target element the value of its source element, leaving
+    /// targets with a source without a value unset.
+    fn copy_within(&mut self, copies: &[(K, K)]);
+}
+
+impl<K: Hash + Eq + Clone + 'static, T: Attribute> AttributeValues<K> for FnvHashMap<K, T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn box_clone(&self) -> Box<dyn AttributeValues<K>> {
+        Box::new(self.clone())
+    }
+
+    fn empty_like(&self) -> Box<dyn AttributeValues<K>> {
+        Box::new(FnvHashMap::<K, T>::default())
+    }
+
+    fn resample_into(&self, into: &mut dyn AttributeValues<K>, targets: &[(K, Vec<(K, f32)>)]) {
+        let into = match into.as_any_mut().downcast_mut::<FnvHashMap<K, T>>() {
+            Some(into) => into,
+            None => return,
+        };
+        let mut weighted = Vec::new();
+        for (target, sources) in targets.iter() {
+            weighted.clear();
+            weighted.extend(sources.iter().filter_map(|(source, weight)| self.get(source).map(|value| (value, *weight))));
+            let total: f32 = weighted.iter().map(|&(_, weight)| weight).sum();
+            if weighted.is_empty() || total <= 0.0 {
+                continue;
+            }
+            for entry in weighted.iter_mut() {
+                entry.1 /= total;
+            }
+            into.insert(target.clone(), T::interpolate(&weighted));
+        }
+    }
+
+    fn copy_within(&mut self, copies: &[(K, K)]) {
+        for (source, target) in copies.iter() {
+            if let Some(value) = self.get(source).cloned() {
+                self.insert(target.clone(), value);
+            }
+        }
+    }
+}
+
+/// Named attributes of one kind of element.
+struct AttributeDomain<K> {
+    attributes: FnvHashMap<String, Box<dyn AttributeValues<K>>>,
+}
+
+impl<K: Hash + Eq + Clone + 'static> AttributeDomain<K> {
+    fn new() -> Self {
+        AttributeDomain {
+            attributes: FnvHashMap::default(),
+        }
+    }
+
+    fn add<T: Attribute>(&mut self, name: &str) {
+        if self.values::<T>(name).is_none() {
+            self.attributes.insert(name.to_string(), Box::new(FnvHashMap::<K, T>::default()));
+        }
+    }
+
+    fn values<T: Attribute>(&self, name: &str) -> Option<&FnvHashMap<K, T>> {
+        self.attributes.get(name).and_then(|values| values.as_any().downcast_ref())
+    }
+
+    fn values_mut<T: Attribute>(&mut self, name: &str) -> Option<&mut FnvHashMap<K, T>> {
+        self.attributes.get_mut(name).and_then(|values| values.as_any_mut().downcast_mut())
+    }
+
+    fn set<T: Attribute>(&mut self, name: &str, key: K, value: T) -> bool {
+        match self.values_mut(name) {
+            Some(values) => {
+                values.insert(key, value);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.attributes.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+
+    /// Adds the blended values of `other` to the attributes of the same name,
+    /// registering those missing here.
+    fn resample_from(&mut self, other: &AttributeDomain<K>, targets: &[(K, Vec<(K, f32)>)]) {
+        for (name, values) in other.attributes.iter() {
+            let into = self.attributes.entry(name.clone()).or_insert_with(|| values.empty_like());
+            values.resample_into(into.as_mut(), targets);
+        }
+    }
+
+    fn copy_within(&mut self, copies: &[(K, K)]) {
+        for values in self.attributes.values_mut() {
+            values.copy_within(copies);
+        }
+    }
+}
+
+impl<K> Clone for AttributeDomain<K> {
+    fn clone(&self) -> Self {
+        AttributeDomain {
+            attributes: self.attributes.iter().map(|(name, values)| (name.clone(), values.box_clone())).collect(),
+        }
+    }
+}
+
+/// Typed values attached to the vertices, faces, halfedges or edges of a mesh
+/// by name, for data the mesh has no field for. Any `Attribute` type can be
+/// stored, and attributes of different types can share a name across the
+/// kinds of elements but not within one.
+///
+/// `Mesh::clone`, `duplicate` and `add_mesh` carry all attributes over.
+/// Subdivision blends the vertex attributes with the weights of the
+/// subdivision rules and gives every face the values of the face it came
+/// from, dropping halfedge and edge attributes. Simplification and
+/// `ProgressiveMesh` keep the values of the elements that are left, and
+/// `split_face` copies the values of the face and corners it splits.
+#[derive(Clone)]
+pub struct AttributeStore {
+    vertices: AttributeDomain<Id>,
+    faces: AttributeDomain<Id>,
+    halfedges: AttributeDomain<Id>,
+    edges: AttributeDomain<EdgeEndpoints>,
+}
+
+impl fmt::Debug for AttributeStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AttributeStore")
+            .field("vertices", &self.vertices.names())
+            .field("faces", &self.faces.names())
+            .field("halfedges", &self.halfedges.names())
+            .field("edges", &self.edges.names())
+            .finish()
+    }
+}
+
+impl Default for AttributeStore {
+    fn default() -> Self {
+        AttributeStore::new()
+    }
+}
+
+impl AttributeStore {
+    pub fn new() -> Self {
+        AttributeStore {
+            vertices: AttributeDomain::new(),
+            faces: AttributeDomain::new(),
+            halfedges: AttributeDomain::new(),
+            edges: AttributeDomain::new(),
+        }
+    }
+
+    /// Removes every attribute.
+    pub fn clear(&mut self) {
+        *self = AttributeStore::new();
+    }
+
+    /// Registers a vertex attribute without values. Does nothing if it
+    /// already exists with the type, and replaces an attribute of another
+    /// type with the name.
+    pub fn add_vertex_attribute<T: Attribute>(&mut self, name: &str) {
+        self.vertices.add::<T>(name);
+    }
+
+    /// The value of the vertex, if the attribute exists with the type and the
+    /// vertex has a value.
+    pub fn vertex_attribute<T: Attribute>(&self, name: &str, id: Id) -> Option<&T> {
+        self.vertices.values(name).and_then(|values| values.get(&id))
+    }
+
+    /// Sets the value of the vertex, returning false without setting it if
+    /// no attribute of the type was added under the name.
+    pub fn set_vertex_attribute<T: Attribute>(&mut self, name: &str, id: Id, value: T) -> bool {
+        self.vertices.set(name, id, value)
+    }
+
+    /// All values of a vertex attribute by vertex id.
+    pub fn vertex_attribute_values<T: Attribute>(&self, name: &str) -> Option<&FnvHashMap<Id, T>> {
+        self.vertices.values(name)
+    }
+
+    pub fn remove_vertex_attribute(&mut self, name: &str) -> bool {
+        self.vertices.attributes.remove(name).is_some()
+    }
+
+    pub fn vertex_attribute_names(&self) -> Vec<&str> {
+        self.vertices.names()
+    }
+
+    pub fn add_face_attribute<T: Attribute>(&mut self, name: &str) {
+        self.faces.add::<T>(name);
+    }
+
+    pub fn face_attribute<T: Attribute>(&self, name: &str, id: Id) -> Option<&T> {
+        self.faces.values(name).and_then(|values| values.get(&id))
+    }
+
+    pub fn set_face_attribute<T: Attribute>(&mut self, name: &str, id: Id, value: T) -> bool {
+        self.faces.set(name, id, value)
+    }
+
+    pub fn face_attribute_values<T: Attribute>(&self, name: &str) -> Option<&FnvHashMap<Id, T>> {
+        self.faces.values(name)
+    }
+
+    pub fn remove_face_attribute(&mut self, name: &str) -> bool {
+        self.faces.attributes.remove(name).is_some()
+    }
+
+    pub fn face_attribute_names(&self) -> Vec<&str> {
+        self.faces.names()
+    }
+
+    pub fn add_halfedge_attribute<T: Attribute>(&mut self, name: &str) {
+        self.halfedges.add::<T>(name);
+    }
+
+    pub fn halfedge_attribute<T: Attribute>(&self, name: &str, id: Id) -> Option<&T> {
+        self.halfedges.values(name).and_then(|values| values.get(&id))
+    }
+
+    pub fn set_halfedge_attribute<T: Attribute>(&mut self, name: &str, id: Id, value: T) -> bool {
+        self.halfedges.set(name, id, value)
+    }
+
+    pub fn halfedge_attribute_values<T: Attribute>(&self, name: &str) -> Option<&FnvHashMap<Id, T>> {
+        self.halfedges.values(name)
+    }
+
+    pub fn remove_halfedge_attribute(&mut self, name: &str) -> bool {
+        self.halfedges.attributes.remove(name).is_some()
+    }
+
+    pub fn halfedge_attribute_names(&self) -> Vec<&str> {
+        self.halfedges.names()
+    }
+
+    /// Registers an edge attribute, whose values are keyed by the end points
+    /// of the edge so both of its halfedges share them.
+    pub fn add_edge_attribute<T: Attribute>(&mut self, name: &str) {
+        self.edges.add::<T>(name);
+    }
+
+    pub fn edge_attribute<T: Attribute>(&self, name: &str, endpoints: &EdgeEndpoints) -> Option<&T> {
+        self.edges.values(name).and_then(|values| values.get(endpoints))
+    }
+
+    pub fn set_edge_attribute<T: Attribute>(&mut self, name: &str, endpoints: EdgeEndpoints, value: T) -> bool {
+        self.edges.set(name, endpoints, value)
+    }
+
+    pub fn edge_attribute_values<T: Attribute>(&self, name: &str) -> Option<&FnvHashMap<EdgeEndpoints, T>> {
+        self.edges.values(name)
+    }
+
+    pub fn remove_edge_attribute(&mut self, name: &str) -> bool {
+        self.edges.attributes.remove(name).is_some()
+    }
+
+    pub fn edge_attribute_names(&self) -> Vec<&str> {
+        self.edges.names()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.attributes.is_empty() && self.faces.attributes.is_empty()
+            && self.halfedges.attributes.is_empty() && self.edges.attributes.is_empty()
+    }
+
+    /// Sets the vertex values of `other` blended by the weights of every
+    /// target vertex, registering attributes missing here.
+    pub fn resample_vertices(&mut self, other: &AttributeStore, targets: &[(Id, Vec<(Id, f32)>)]) {
+        self.vertices.resample_from(&other.vertices, targets);
+    }
+
+    pub fn resample_faces(&mut self, other: &AttributeStore, targets: &[(Id, Vec<(Id, f32)>)]) {
+        self.faces.resample_from(&other.faces, targets);
+    }
+
+    pub fn resample_halfedges(&mut self, other: &AttributeStore, targets: &[(Id, Vec<(Id, f32)>)]) {
+        self.halfedges.resample_from(&other.halfedges, targets);
+    }
+
+    /// Gives every target face the values of its source face, as `(source,
+    /// target)` pairs.
+    pub fn copy_faces(&mut self, copies: &[(Id, Id)]) {
+        self.faces.copy_within(copies);
+    }
+
+    /// Gives every target halfedge the values of its source halfedge, as
+    /// `(source, target)` pairs.
+    pub fn copy_halfedges(&mut self, copies: &[(Id, Id)]) {
+        self.halfedges.copy_within(copies);
+    }
+
+    pub fn resample_edges(&mut self, other: &AttributeStore, targets: &[(EdgeEndpoints, Vec<(EdgeEndpoints, f32)>)]) {
+        self.edges.resample_from(&other.edges, targets);
+    }
+}
@@ -0,0 +1,373 @@
+use cgmath::EuclideanSpace;
+use cgmath::InnerSpace;
+use cgmath::MetricSpace;
+use cgmath::Point2;
+use cgmath::Point3;
+use error::TopologyError;
+use fnv::FnvHashSet;
+use iterator::FaceHalfedgeIterator;
+use mesh::EdgeEndpoints;
+use mesh::Id;
+use mesh::Mesh;
+use std::cmp::Ordering;
+use std::f32;
+
+/// Boundary vertices where the outline turns by more than this are kept by
+/// `resample_boundary`.
+const CORNER_TURN_DEGREES: f32 = 40.0;
+
+impl Mesh {
+    /// The boundary halfedge following `halfedge_id` along its boundary loop,
+    /// leaving the vertex the given halfedge ends at.
+    fn boundary_next_id(&self, halfedge_id: Id) -> Option<Id> {
+        let next_id = self.halfedge_next_id(halfedge_id)?;
+        let vertex = self.halfedge_start_vertex(next_id)?;
+        vertex.halfedges.iter().cloned().find(|&id| self.halfedge_opposite_id(id).is_none())
+    }
+
+    /// Returns every boundary loop as the halfedges without an opposite, in the
+    /// order they follow each other. The faces are on the left of the loops.
+    pub fn boundary_loops(&self) -> Vec<Vec<Id>> {
+        let mut loops = Vec::new();
+        let mut visited = FnvHashSet::default();
+        for halfedge in self.halfedges.iter() {
+            if !halfedge.alive || self.halfedge_opposite_id(halfedge.id).is_some() || visited.contains(&halfedge.id) {
+                continue;
+            }
+            let mut boundary_loop = Vec::new();
+            let mut halfedge_id = halfedge.id;
+            while visited.insert(halfedge_id) {
+                boundary_loop.push(halfedge_id);
+                halfedge_id = match self.boundary_next_id(halfedge_id) {
+                    Some(next_id) => next_id,
+                    None => break,
+                };
+            }
+            loops.push(boundary_loop);
+        }
+        loops
+    }
+
+    /// Total length of the halfedges without an opposite, 0 for a closed
+    /// mesh.
+    pub fn boundary_length(&self) -> f32 {
+        self.iter_halfedges()
+            .filter(|&(_, halfedge)| halfedge.opposite == 0)
+            .map(|(halfedge_id, _)| self.halfedge_direct(halfedge_id).magnitude())
+            .sum()
+    }
+
+    /// Number of boundary loops, each a hole or open rim of the surface, 0
+    /// for a closed mesh.
+    pub fn boundary_loop_count(&self) -> usize {
+        self.boundary_loops().len()
+    }
+
+    /// Start vertices of the boundary loop through `halfedge_id`, in walking
+    /// order starting with the given halfedge. Empty if the halfedge is not on
+    /// a boundary.
+    fn boundary_loop_vertices(&self, halfedge_id: Id) -> Vec<Id> {
+        let mut vertices = Vec::new();
+        if self.halfedge(halfedge_id).is_none() || self.halfedge_opposite_id(halfedge_id).is_some() {
+            return vertices;
+        }
+        let mut id = halfedge_id;
+        loop {
+            vertices.push(self.halfedge_start_vertex_id(id).unwrap());
+            id = match self.boundary_next_id(id) {
+                Some(next_id) => next_id,
+                None => return Vec::new(),
+            };
+            if id == halfedge_id {
+                return vertices;
+            }
+            if vertices.len() > self.halfedges.len() {
+                return Vec::new();
+            }
+        }
+    }
+
+    /// Connects two boundary loops, each given by one of its halfedges, with a
+    /// ring of new faces and returns them. The loops are matched by arc length
+    /// starting from the closest pair of vertices, so loops with the same
+    /// number of vertices get quads, and triangles make up for the difference
+    /// otherwise. The loops must face each other, as the two ends of a tube
+    /// do. Fails with `InvalidElement` if either halfedge is not on a
+    /// boundary, with `NonManifoldResult` if both are on the same loop, and
+    /// with `WouldCreateDuplicateEdge` if a new edge across the ring would
+    /// join two vertices an edge already joins, as between the two ends of a
+    /// one row tube.
+    pub fn bridge(&mut self, loop_a: Id, loop_b: Id) -> Result<Vec<Id>, TopologyError> {
+        let a = self.boundary_loop_vertices(loop_a);
+        if a.is_empty() {
+            return Err(TopologyError::InvalidElement { id: loop_a });
+        }
+        let b = self.boundary_loop_vertices(loop_b);
+        if b.is_empty() {
+            return Err(TopologyError::InvalidElement { id: loop_b });
+        }
+        if b.contains(&a[0]) {
+            return Err(TopologyError::NonManifoldResult);
+        }
+        // The faces have to run against both loops, so the second one is
+        // walked backwards from the vertex closest to the start of the first.
+        let first = self.vertex(a[0]).unwrap().position;
+        let nearest = (0..b.len()).min_by(|&i, &j| {
+            let distance = |index: usize| self.vertex(b[index]).unwrap().position.distance2(first);
+            distance(i).partial_cmp(&distance(j)).unwrap_or(Ordering::Equal)
+        }).unwrap();
+        let b: Vec<Id> = (0..b.len()).map(|j| b[(nearest + b.len() - j) % b.len()]).collect();
+        let a_params = self.loop_arc_lengths(&a);
+        let b_params = self.loop_arc_lengths(&b);
+        let (mut i, mut j) = (0, 0);
+        let mut loops = Vec::new();
+        while i < a.len() || j < b.len() {
+            let advance_a = if i < a.len() { (a_params[i + 1] - b_params[j]).abs() } else { f32::MAX };
+            let advance_b = if j < b.len() { (a_params[i] - b_params[j + 1]).abs() } else { f32::MAX };
+            let advance_both = if i < a.len() && j < b.len() { (a_params[i + 1] - b_params[j + 1]).abs() } else { f32::MAX };
+            let (a_now, a_next) = (a[i % a.len()], a[(i + 1) % a.len()]);
+            let (b_now, b_next) = (b[j % b.len()], b[(j + 1) % b.len()]);
+            if advance_both <= advance_a && advance_both <= advance_b {
+                loops.push(vec![a_next, a_now, b_now, b_next]);
+                i += 1;
+                j += 1;
+            } else if advance_a <= advance_b {
+                loops.push(vec![a_next, a_now, b_now]);
+                i += 1;
+            } else {
+                loops.push(vec![a_now, b_now, b_next]);
+                j += 1;
+            }
+        }
+        let on_a: FnvHashSet<Id> = a.iter().cloned().collect();
+        for vertices in loops.iter() {
+            for k in 0..vertices.len() {
+                let (first, second) = (vertices[k], vertices[(k + 1) % vertices.len()]);
+                if on_a.contains(&first) != on_a.contains(&second) && self.edges.contains_key(&EdgeEndpoints::new(first, second)) {
+                    return Err(TopologyError::WouldCreateDuplicateEdge { first, second });
+                }
+            }
+        }
+        Ok(loops.into_iter().map(|vertices| self.add_vertices(vertices)).collect())
+    }
+
+    /// Normalized arc length at every vertex of a closed loop, with the start
+    /// repeated at the end as 1.
+    fn loop_arc_lengths(&self, vertices: &[Id]) -> Vec<f32> {
+        let mut params = vec![0.0];
+        let mut total = 0.0;
+        for i in 0..vertices.len() {
+            let from = self.vertex(vertices[i]).unwrap().position;
+            let to = self.vertex(vertices[(i + 1) % vertices.len()]).unwrap().position;
+            total += from.distance(to);
+            params.push(total);
+        }
+        if total > 0.0 {
+            for param in params.iter_mut() {
+                *param /= total;
+            }
+        }
+        params
+    }
+
+    fn halfedge_stop_vertex_id(&self, halfedge_id: Id) -> Id {
+        self.halfedge_start_vertex_id(self.halfedge_next_id(halfedge_id).unwrap()).unwrap()
+    }
+
+    /// The boundary halfedge running from `from` to `to`.
+    fn find_boundary_halfedge(&self, from: Id, to: Id) -> Option<Id> {
+        self.vertex(from)?.halfedges.iter().cloned().find(|&id| {
+            self.halfedge_opposite_id(id).is_none() && self.halfedge_stop_vertex_id(id) == to
+        })
+    }
+
+    fn vertex_neighbors(&self, vertex_id: Id) -> Vec<Id> {
+        let mut neighbors = Vec::new();
+        for &halfedge_id in self.vertex(vertex_id).unwrap().halfedges.iter() {
+            let prev_id = self.halfedge_prev_id(halfedge_id).unwrap();
+            for &neighbor_id in [self.halfedge_stop_vertex_id(halfedge_id), self.halfedge_start_vertex_id(prev_id).unwrap()].iter() {
+                if !neighbors.contains(&neighbor_id) {
+                    neighbors.push(neighbor_id);
+                }
+            }
+        }
+        neighbors
+    }
+
+    /// Merges the boundary vertex `removed` into its boundary neighbor `kept`
+    /// along `halfedge_id`. Refused when the two vertices share neighbors
+    /// other than the tip of a triangle on that edge, since the collapse would
+    /// then pinch the surface.
+    fn collapse_boundary_halfedge(&mut self, halfedge_id: Id, removed: Id, kept: Id) -> bool {
+        let face_id = self.halfedge_face_id(halfedge_id).unwrap();
+        let corners = self.face_vertex_ids(face_id);
+        let tip = if corners.len() == 3 {
+            corners.iter().cloned().find(|&id| id != removed && id != kept)
+        } else {
+            None
+        };
+        let kept_neighbors = self.vertex_neighbors(kept);
+        if self.vertex_neighbors(removed).iter()
+                .any(|id| kept_neighbors.contains(id) && Some(*id) != tip) {
+            return false;
+        }
+        let mut old_faces = Vec::new();
+        for &around_id in self.vertex(removed).unwrap().halfedges.iter() {
+            let around_face = self.halfedge_face_id(around_id).unwrap();
+            if !old_faces.contains(&around_face) {
+                old_faces.push(around_face);
+            }
+        }
+        // Corners of `removed` with the UV it has on the collapsed edge are in
+        // the same chart and move to the UV of `kept` there, corners across a
+        // seam keep their own.
+        let edge_uv = |mesh: &Mesh, vertex_id: Id| FaceHalfedgeIterator::new(mesh, halfedge_id)
+            .find(|&id| mesh.halfedge_start_vertex_id(id) == Some(vertex_id))
+            .and_then(|id| mesh.halfedge_uv(id));
+        let (removed_uv, kept_uv) = (edge_uv(self, removed), edge_uv(self, kept));
+        let mut loops = Vec::new();
+        let mut materials = Vec::new();
+        let mut corner_uvs = Vec::new();
+        for &old_face in old_faces.iter() {
+            // Where the two corners merge, the corner of `kept` and its UV
+            // are the ones left.
+            let mut corners: Vec<(Id, Id, Option<Point2<f32>>)> = FaceHalfedgeIterator::new(self, self.face_first_halfedge_id(old_face).unwrap())
+                .map(|id| {
+                    let vertex_id = self.halfedge_start_vertex_id(id).unwrap();
+                    match self.halfedge_uv(id) {
+                        uv if vertex_id == removed && uv.is_some() && uv == removed_uv => (kept, vertex_id, kept_uv),
+                        uv => (if vertex_id == removed { kept } else { vertex_id }, vertex_id, uv),
+                    }
+                })
+                .collect();
+            corners.dedup_by(|later, earlier| {
+                if later.0 != earlier.0 {
+                    return false;
+                }
+                if later.1 == kept {
+                    *earlier = *later;
+                }
+                true
+            });
+            if corners.len() > 1 && corners[0].0 == corners[corners.len() - 1].0 {
+                let last = corners.pop().unwrap();
+                if last.1 == kept {
+                    corners[0] = last;
+                }
+            }
+            if corners.len() >= 3 {
+                loops.push(corners.iter().map(|&(vertex_id, _, _)| vertex_id).collect::<Vec<Id>>());
+                materials.push(self.face_material(old_face));
+                corner_uvs.push(corners.into_iter().map(|(_, _, uv)| uv).collect::<Vec<Option<Point2<f32>>>>());
+            }
+        }
+        let added_faces = self.replace_faces(&old_faces, &loops);
+        for ((&added_face, material), uvs) in added_faces.iter().zip(materials).zip(corner_uvs) {
+            self.set_face_material(added_face, material);
+            self.set_corner_uvs(added_face, &uvs);
+        }
+        true
+    }
+
+    /// Sets the UVs of the corners of a face in loop order, skipping the
+    /// corners without one.
+    fn set_corner_uvs(&mut self, face_id: Id, uvs: &[Option<Point2<f32>>]) {
+        let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+        for (halfedge_id, uv) in FaceHalfedgeIterator::new(self, face_halfedge).into_vec().into_iter().zip(uvs) {
+            if let Some(uv) = *uv {
+                self.set_halfedge_uv(halfedge_id, uv);
+            }
+        }
+    }
+
+    /// Inserts `count - 1` evenly spaced vertices into a boundary halfedge,
+    /// their UVs interpolated between the ends of the halfedge.
+    fn split_boundary_halfedge(&mut self, halfedge_id: Id, count: usize) {
+        let face_id = self.halfedge_face_id(halfedge_id).unwrap();
+        let start = self.halfedge_start_vertex(halfedge_id).unwrap().position;
+        let stop_id = self.halfedge_stop_vertex_id(halfedge_id);
+        let stop = self.vertex(stop_id).unwrap().position;
+        let start_uv = self.halfedge_uv(halfedge_id);
+        let stop_uv = self.halfedge_uv(self.halfedge_next_id(halfedge_id).unwrap());
+        let mut vertices = Vec::new();
+        let mut uvs = Vec::new();
+        for id in FaceHalfedgeIterator::new(self, halfedge_id).into_vec() {
+            vertices.push(self.halfedge_start_vertex_id(id).unwrap());
+            uvs.push(self.halfedge_uv(id));
+            if id == halfedge_id {
+                for i in 1..count {
+                    let t = i as f32 / count as f32;
+                    vertices.push(self.add_vertex(Point3::from_vec(start.to_vec() * (1.0 - t) + stop.to_vec() * t)));
+                    uvs.push(match (start_uv, stop_uv) {
+                        (Some(start_uv), Some(stop_uv)) => Some(Point2::from_vec(start_uv.to_vec() * (1.0 - t) + stop_uv.to_vec() * t)),
+                        _ => None,
+                    });
+                }
+            }
+        }
+        let material = self.face_material(face_id);
+        let added_faces = self.replace_faces(&[face_id], &[vertices]);
+        self.set_face_material(added_faces[0], material);
+        self.set_corner_uvs(added_faces[0], &uvs);
+    }
+
+    /// Boundary vertices where the outline turns sharply.
+    fn boundary_corners(&self) -> FnvHashSet<Id> {
+        let mut corners = FnvHashSet::default();
+        let min_cos = CORNER_TURN_DEGREES.to_radians().cos();
+        for boundary_loop in self.boundary_loops() {
+            for i in 0..boundary_loop.len() {
+                let incoming = boundary_loop[i];
+                let outgoing = boundary_loop[(i + 1) % boundary_loop.len()];
+                let vertex_id = self.halfedge_start_vertex_id(outgoing).unwrap();
+                let turn_cos = self.halfedge_direct(incoming).normalize().dot(self.halfedge_direct(outgoing).normalize());
+                if turn_cos < min_cos || turn_cos.is_nan() {
+                    corners.insert(vertex_id);
+                }
+            }
+        }
+        corners
+    }
+
+    /// Redistributes the vertices of the boundary loops to roughly
+    /// `target_spacing` apart. Edges shorter than half the spacing are
+    /// collapsed and longer edges are split evenly, so interior vertices never
+    /// move. Corners, where the outline turns by more than 40 degrees, stay in
+    /// place.
+    pub fn resample_boundary(&mut self, target_spacing: f32) {
+        let corners = self.boundary_corners();
+        let min_length = target_spacing * 0.5;
+        'collapse: loop {
+            for boundary_loop in self.boundary_loops().into_iter().filter(|boundary_loop| boundary_loop.len() > 3) {
+                for halfedge_id in boundary_loop {
+                    if self.halfedge_direct(halfedge_id).magnitude() >= min_length {
+                        continue;
+                    }
+                    let start_id = self.halfedge_start_vertex_id(halfedge_id).unwrap();
+                    let stop_id = self.halfedge_stop_vertex_id(halfedge_id);
+                    if (!corners.contains(&stop_id) && self.collapse_boundary_halfedge(halfedge_id, stop_id, start_id)) ||
+                            (!corners.contains(&start_id) && self.collapse_boundary_halfedge(halfedge_id, start_id, stop_id)) {
+                        continue 'collapse;
+                    }
+                }
+            }
+            break;
+        }
+        let mut long_edges = Vec::new();
+        for boundary_loop in self.boundary_loops() {
+            for halfedge_id in boundary_loop {
+                let start = self.halfedge_start_vertex(halfedge_id).unwrap();
+                let stop_id = self.halfedge_stop_vertex_id(halfedge_id);
+                let count = (start.position.distance(self.vertex(stop_id).unwrap().position) / target_spacing).round() as usize;
+                if count > 1 {
+                    long_edges.push((start.id, stop_id, count));
+                }
+            }
+        }
+        for (start_id, stop_id, count) in long_edges {
+            if let Some(halfedge_id) = self.find_boundary_halfedge(start_id, stop_id) {
+                self.split_boundary_halfedge(halfedge_id, count);
+            }
+        }
+    }
+}
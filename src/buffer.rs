@@ -0,0 +1,222 @@
+use cgmath::Point2;
+use cgmath::Point3;
+use cgmath::Vector4;
+use error::MeshError;
+use fnv::FnvHashMap;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use iterator::FaceTriangleIterator;
+use mesh::Id;
+use mesh::Mesh;
+
+/// Selects the attributes interleaved after the position of every vertex in
+/// the buffers produced by `Mesh::to_buffers_with_layout`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct VertexLayout {
+    /// Appends the vertex normal (x, y, z).
+    pub normals: bool,
+
+    /// Appends the texture coordinate (u, v) of the face corner. Vertices with
+    /// different coordinates on different faces are split.
+    pub uvs: bool,
+}
+
+impl VertexLayout {
+    /// Number of floats per vertex.
+    pub fn stride(&self) -> usize {
+        3 + if self.normals { 3 } else { 0 } + if self.uvs { 2 } else { 0 }
+    }
+}
+
+impl Mesh {
+    /// Flattens the mesh into a position buffer (x, y, z per vertex) and a
+    /// triangle index buffer, ready to upload to WebGL or wgpu.
+    pub fn to_buffers(&self) -> (Vec<f32>, Vec<u32>) {
+        self.to_buffers_with_layout(&VertexLayout::default())
+    }
+
+    /// Flattens the mesh into an interleaved vertex buffer following `layout`
+    /// and a triangle index buffer.
+    ///
+    /// Every living vertex gets one buffer vertex, in id order, so the index of
+    /// a vertex does not depend on the faces and stays the same between calls
+    /// on meshes with the same topology. When UVs are included, each extra UV
+    /// a vertex has on a seam adds a copy after all of those. Faces are fan
+    /// triangulated, so concave polygons should be triangulated first.
+    pub fn to_buffers_with_layout(&self, layout: &VertexLayout) -> (Vec<f32>, Vec<u32>) {
+        self.interleave(layout, false)
+    }
+
+    /// Flattens the mesh like `to_buffers_with_layout`, appending the tangent
+    /// with its handedness (x, y, z, w) from `Mesh::compute_tangents` to every
+    /// vertex, zero for vertices without one. Vertices are `layout.stride()`
+    /// plus 4 floats long.
+    pub fn to_buffers_with_tangents(&self, layout: &VertexLayout) -> (Vec<f32>, Vec<u32>) {
+        self.interleave(layout, true)
+    }
+
+    fn interleave(&self, layout: &VertexLayout, with_tangents: bool) -> (Vec<f32>, Vec<u32>) {
+        let mut vertex_indices: FnvHashMap<Id, u32> = FnvHashMap::default();
+        let mut vertex_order = Vec::with_capacity(self.vertex_count);
+        for vertex in self.vertices.iter().filter(|vertex| vertex.alive) {
+            vertex_indices.insert(vertex.id, vertex_order.len() as u32);
+            vertex_order.push((vertex.id, None));
+        }
+        let mut seam_indices: FnvHashMap<(Id, [u32; 2]), u32> = FnvHashMap::default();
+        let mut indices = Vec::with_capacity(self.face_count * 3);
+        for face_id in FaceIterator::new(self) {
+            let mut corners = Vec::new();
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for halfedge_id in FaceHalfedgeIterator::new(self, face_halfedge) {
+                let vertex_id = self.halfedge_start_vertex_id(halfedge_id).unwrap();
+                let mut index = vertex_indices[&vertex_id];
+                if layout.uvs {
+                    let uv = self.halfedge_uv(halfedge_id).unwrap_or(Point2::new(0.0, 0.0));
+                    let key = (vertex_id, [uv.x.to_bits(), uv.y.to_bits()]);
+                    match vertex_order[index as usize].1 {
+                        None => {
+                            vertex_order[index as usize].1 = Some(uv);
+                            seam_indices.insert(key, index);
+                        },
+                        Some(_) => {
+                            index = *seam_indices.entry(key).or_insert_with(|| {
+                                vertex_order.push((vertex_id, Some(uv)));
+                                (vertex_order.len() - 1) as u32
+                            });
+                        },
+                    }
+                }
+                corners.push(index);
+            }
+            for i in 1..corners.len().saturating_sub(1) {
+                indices.push(corners[0]);
+                indices.push(corners[i]);
+                indices.push(corners[i + 1]);
+            }
+        }
+        let tangents = if with_tangents { self.compute_tangents() } else { FnvHashMap::default() };
+        let stride = layout.stride() + if with_tangents { 4 } else { 0 };
+        let mut vertices = Vec::with_capacity(vertex_order.len() * stride);
+        for &(vertex_id, uv) in vertex_order.iter() {
+            let position = self.vertex(vertex_id).unwrap().position;
+            vertices.extend_from_slice(&[position.x, position.y, position.z]);
+            if layout.normals {
+                let normal = self.vertex_norm(vertex_id);
+                vertices.extend_from_slice(&[normal.x, normal.y, normal.z]);
+            }
+            if layout.uvs {
+                let uv = uv.unwrap_or(Point2::new(0.0, 0.0));
+                vertices.extend_from_slice(&[uv.x, uv.y]);
+            }
+            if with_tangents {
+                let tangent = tangents.get(&vertex_id).cloned().unwrap_or(Vector4::new(0.0, 0.0, 0.0, 0.0));
+                vertices.extend_from_slice(&[tangent.x, tangent.y, tangent.z, tangent.w]);
+            }
+        }
+        (vertices, indices)
+    }
+
+    /// Flattens the mesh for flat shading into a position buffer, a normal
+    /// buffer and a triangle index buffer. Every face gets its own copy of
+    /// its corners, in face id order, all carrying the face normal, so no
+    /// buffer vertex is shared between faces. Faces are fan triangulated as
+    /// in `to_buffers_with_layout`.
+    pub fn to_buffers_flat(&self) -> (Vec<f32>, Vec<f32>, Vec<u32>) {
+        let mut positions = Vec::with_capacity(self.halfedge_count * 3);
+        let mut normals = Vec::with_capacity(self.halfedge_count * 3);
+        let mut indices = Vec::with_capacity(self.face_count * 6);
+        for face_id in FaceIterator::new(self) {
+            let normal = self.face_norm(face_id);
+            let first = (positions.len() / 3) as u32;
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for halfedge_id in FaceHalfedgeIterator::new(self, face_halfedge) {
+                let position = self.halfedge_start_vertex(halfedge_id).unwrap().position;
+                positions.extend_from_slice(&[position.x, position.y, position.z]);
+                normals.extend_from_slice(&[normal.x, normal.y, normal.z]);
+            }
+            let last = (positions.len() / 3) as u32;
+            for i in first + 1..last.saturating_sub(1) {
+                indices.push(first);
+                indices.push(i);
+                indices.push(i + 1);
+            }
+        }
+        (positions, normals, indices)
+    }
+
+    /// Fan triangulates every face into a list of triangles with no
+    /// connectivity, as expected by collision and physics libraries.
+    pub fn triangle_soup(&self) -> Vec<[Point3<f32>; 3]> {
+        let mut triangles = Vec::with_capacity(self.face_count * 2);
+        for face_id in FaceIterator::new(self) {
+            for triangle in FaceTriangleIterator::new(self, face_id) {
+                let corner = |index: usize| self.vertex(triangle[index]).unwrap().position;
+                triangles.push([corner(0), corner(1), corner(2)]);
+            }
+        }
+        triangles
+    }
+
+    /// Same triangles as `triangle_soup`, sharing the positions of the living
+    /// vertices in id order, see `to_indexed`.
+    pub fn triangle_soup_indexed(&self) -> (Vec<Point3<f32>>, Vec<[u32; 3]>) {
+        self.to_indexed()
+    }
+
+    /// Flattens the mesh into the positions of the living vertices in id order
+    /// and fan triangles indexing them, the inverse of `Mesh::from_indexed`.
+    pub fn to_indexed(&self) -> (Vec<Point3<f32>>, Vec<[u32; 3]>) {
+        let (_, indices) = self.to_buffers();
+        let triangles = indices.chunks(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+        (self.positions(), triangles)
+    }
+
+    /// Builds a triangle mesh from shared positions and triangles indexing
+    /// them. Every position becomes one vertex, so triangles using the same
+    /// index share the vertex and get their edges paired through the edge map.
+    /// Triangles repeating an index have no area and no edges to pair, and are
+    /// skipped, so the mesh can have fewer faces than `triangles`. Returns
+    /// `MeshError::IndexOutOfRange` for the first triangle with an index past
+    /// the end of `positions`.
+    pub fn from_indexed(positions: &[Point3<f32>], triangles: &[[u32; 3]]) -> Result<Mesh, MeshError> {
+        for (triangle, indices) in triangles.iter().enumerate() {
+            if let Some(&index) = indices.iter().find(|&&index| index as usize >= positions.len()) {
+                return Err(MeshError::IndexOutOfRange { triangle, index });
+            }
+        }
+        let mut mesh = Mesh::with_capacity(positions.len(), triangles.len(), triangles.len() * 3);
+        let vertices: Vec<Id> = positions.iter()
+            .map(|&position| mesh.add_vertex(position))
+            .collect();
+        for triangle in triangles.iter() {
+            if triangle[0] == triangle[1] || triangle[1] == triangle[2] || triangle[2] == triangle[0] {
+                continue;
+            }
+            mesh.add_vertices(triangle.iter().map(|&index| vertices[index as usize]).collect());
+        }
+        Ok(mesh)
+    }
+
+    /// Builds a triangle mesh from a position buffer (x, y, z per vertex) and a
+    /// triangle index buffer, see `Mesh::from_indexed`, skipping the
+    /// triangles that repeat an index.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an index has no position, which `Mesh::from_indexed`
+    /// reports as an error instead.
+    pub fn from_buffers(positions: &[f32], indices: &[u32]) -> Mesh {
+        let positions: Vec<Point3<f32>> = positions.chunks(3)
+            .map(|p| Point3::new(p[0], p[1], p[2]))
+            .collect();
+        let triangles: Vec<[u32; 3]> = indices.chunks(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .collect();
+        match Mesh::from_indexed(&positions, &triangles) {
+            Ok(mesh) => mesh,
+            Err(error) => panic!("{}", error),
+        }
+    }
+}
@@ -0,0 +1,420 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use geom::tri_tri_coplanar_overlap;
+use geom::tri_tri_intersect;
+use iterator::FaceIterator;
+use iterator::FaceTriangleIterator;
+use mesh::Id;
+use mesh::Mesh;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use cgmath::prelude::*;
+use util::closest_point_on_triangle;
+use util::intersect_of_ray_and_triangle;
+
+/// Most triangles kept in a leaf before it gets split.
+const LEAF_TRIANGLES: usize = 4;
+
+/// Closest intersection of a ray with the mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub face_id: Id,
+
+    /// Distance along the ray in lengths of its direction, so the hit lies at
+    /// `origin + direction * distance`.
+    pub distance: f32,
+
+    pub position: Point3<f32>,
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    min: Point3<f32>,
+    max: Point3<f32>,
+
+    /// Leaves cover `count` triangles from `first`, inner nodes have a
+    /// `count` of zero, with the left child right after them and the right
+    /// one at `first`.
+    first: usize,
+    count: usize,
+}
+
+/// Face, corner positions and corner vertex ids of a triangle.
+type Triangle = (Id, [Point3<f32>; 3], [Id; 3]);
+
+/// Bounding volume hierarchy over the fan triangulated faces of a mesh, for
+/// casting many rays against the same mesh. It does not follow later changes
+/// to the mesh, so build a new one after editing.
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    triangles: Vec<Triangle>,
+    nodes: Vec<BvhNode>,
+}
+
+fn triangle_bounds(triangles: &[Triangle]) -> (Point3<f32>, Point3<f32>) {
+    let mut min = Point3::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = Point3::new(f32::MIN, f32::MIN, f32::MIN);
+    for (_, corners, _) in triangles.iter() {
+        for corner in corners.iter() {
+            min = Point3::new(min.x.min(corner.x), min.y.min(corner.y), min.z.min(corner.z));
+            max = Point3::new(max.x.max(corner.x), max.y.max(corner.y), max.z.max(corner.z));
+        }
+    }
+    (min, max)
+}
+
+fn triangle_center_sum(corners: &[Point3<f32>; 3], axis: usize) -> f32 {
+    corners[0][axis] + corners[1][axis] + corners[2][axis]
+}
+
+/// Distance along the ray to where it enters the box, if it does before
+/// `max_distance`.
+fn ray_box_distance(origin: Point3<f32>, inverse_direct: Vector3<f32>, min: Point3<f32>, max: Point3<f32>, max_distance: f32) -> Option<f32> {
+    let mut near = 0.0f32;
+    let mut far = max_distance;
+    for axis in 0..3 {
+        // Rays parallel to the slab would get NaN from origins on its planes.
+        if inverse_direct[axis].is_infinite() {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min[axis] - origin[axis]) * inverse_direct[axis];
+        let t2 = (max[axis] - origin[axis]) * inverse_direct[axis];
+        near = near.max(t1.min(t2));
+        far = far.min(t1.max(t2));
+    }
+    if near <= far {
+        Some(near)
+    } else {
+        None
+    }
+}
+
+/// Squared distance from the point to the box, zero inside it.
+fn point_box_distance2(point: Point3<f32>, min: Point3<f32>, max: Point3<f32>) -> f32 {
+    let mut distance2 = 0.0;
+    for axis in 0..3 {
+        let outside = (min[axis] - point[axis]).max(point[axis] - max[axis]).max(0.0);
+        distance2 += outside * outside;
+    }
+    distance2
+}
+
+/// Keeps the closer of two hits, the lower face id on ties, so the result
+/// does not depend on the order the triangles are tested in.
+fn closer_hit(best: Option<(f32, Id)>, distance: f32, face_id: Id) -> Option<(f32, Id)> {
+    match best {
+        Some((best_distance, best_face_id)) if (best_distance, best_face_id) <= (distance, face_id) => best,
+        _ => Some((distance, face_id)),
+    }
+}
+
+impl Bvh {
+    pub fn new(mesh: &Mesh) -> Self {
+        let mut triangles = Vec::with_capacity(mesh.face_count * 2);
+        for face_id in FaceIterator::new(mesh) {
+            for triangle in FaceTriangleIterator::new(mesh, face_id) {
+                let corner = |index: usize| mesh.vertex(triangle[index]).unwrap().position;
+                triangles.push((face_id, [corner(0), corner(1), corner(2)], triangle));
+            }
+        }
+        let mut bvh = Bvh {
+            triangles,
+            nodes: Vec::new(),
+        };
+        let count = bvh.triangles.len();
+        if count > 0 {
+            bvh.build(0, count);
+        }
+        bvh
+    }
+
+    /// Adds the node covering `count` triangles from `first`, splitting them
+    /// at the median along the longest axis of the box.
+    fn build(&mut self, first: usize, count: usize) {
+        let (min, max) = triangle_bounds(&self.triangles[first..first + count]);
+        let node_index = self.nodes.len();
+        self.nodes.push(BvhNode {
+            min,
+            max,
+            first,
+            count,
+        });
+        if count <= LEAF_TRIANGLES {
+            return;
+        }
+        let size = max - min;
+        let axis = if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        };
+        self.triangles[first..first + count].sort_by(|a, b| {
+            triangle_center_sum(&a.1, axis).partial_cmp(&triangle_center_sum(&b.1, axis)).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let left_count = count / 2;
+        self.build(first, left_count);
+        let right_index = self.nodes.len();
+        self.build(first + left_count, count - left_count);
+        self.nodes[node_index].first = right_index;
+        self.nodes[node_index].count = 0;
+    }
+
+    /// Moves the triangles of the given faces to the current positions of
+    /// their corners and recomputes the boxes, without sorting the triangles
+    /// again, for after moving a few vertices. The tree gets looser the
+    /// further the vertices move, rebuild it after large edits. Returns false
+    /// and leaves the tree as it was when the faces were added, removed or
+    /// rewired since it was built, in which case it has to be rebuilt.
+    pub fn refit_faces(&mut self, mesh: &Mesh, faces: &[Id]) -> bool {
+        let mut expected: FnvHashMap<Id, Vec<[Id; 3]>> = FnvHashMap::default();
+        for &face_id in faces {
+            let triangles = match mesh.face(face_id) {
+                Some(_) => FaceTriangleIterator::new(mesh, face_id).collect(),
+                None => Vec::new(),
+            };
+            expected.insert(face_id, triangles);
+        }
+        let mut found: FnvHashMap<Id, usize> = FnvHashMap::default();
+        for &(face_id, _, corner_ids) in self.triangles.iter() {
+            if let Some(triangles) = expected.get(&face_id) {
+                if !triangles.contains(&corner_ids) {
+                    return false;
+                }
+                *found.entry(face_id).or_insert(0) += 1;
+            }
+        }
+        if expected.iter().any(|(face_id, triangles)| found.get(face_id).cloned().unwrap_or(0) != triangles.len()) {
+            return false;
+        }
+        for triangle in self.triangles.iter_mut() {
+            if expected.contains_key(&triangle.0) {
+                for (corner, &vertex_id) in triangle.1.iter_mut().zip(triangle.2.iter()) {
+                    *corner = mesh.vertex(vertex_id).unwrap().position;
+                }
+            }
+        }
+        // Nodes are stored before their children, so going backwards every
+        // child is done before its parent.
+        for node_index in (0..self.nodes.len()).rev() {
+            let (min, max) = {
+                let node = &self.nodes[node_index];
+                if node.count > 0 {
+                    triangle_bounds(&self.triangles[node.first..node.first + node.count])
+                } else {
+                    let (left, right) = (&self.nodes[node_index + 1], &self.nodes[node.first]);
+                    (Point3::new(left.min.x.min(right.min.x), left.min.y.min(right.min.y), left.min.z.min(right.min.z)),
+                        Point3::new(left.max.x.max(right.max.x), left.max.y.max(right.max.y), left.max.z.max(right.max.z)))
+                }
+            };
+            self.nodes[node_index].min = min;
+            self.nodes[node_index].max = max;
+        }
+        true
+    }
+
+    /// Finds the closest face hit by the ray starting at `origin` going
+    /// along `direct`. Faces are hit from both sides.
+    pub fn raycast(&self, origin: Point3<f32>, direct: Vector3<f32>) -> Option<RayHit> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inverse_direct = Vector3::new(1.0 / direct.x, 1.0 / direct.y, 1.0 / direct.z);
+        let mut best: Option<(f32, Id)> = None;
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let max_distance = best.map_or(f32::INFINITY, |(distance, _)| distance);
+            if ray_box_distance(origin, inverse_direct, node.min, node.max, max_distance).is_none() {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first);
+                stack.push(node_index + 1);
+                continue;
+            }
+            for &(face_id, ref corners, _) in self.triangles[node.first..node.first + node.count].iter() {
+                if let Some(distance) = intersect_of_ray_and_triangle(origin, direct, corners[0], corners[1], corners[2]) {
+                    best = closer_hit(best, distance, face_id);
+                }
+            }
+        }
+        best.map(|(distance, face_id)| RayHit {
+            face_id,
+            distance,
+            position: origin + direct * distance,
+        })
+    }
+
+    /// Corners of the box around all faces, or None without faces.
+    pub fn bounds(&self) -> Option<(Point3<f32>, Point3<f32>)> {
+        self.nodes.first().map(|node| (node.min, node.max))
+    }
+
+    /// Whether the point is inside the surface, by counting how many times a
+    /// ray from it crosses the faces. Only meaningful for closed meshes, see
+    /// `Mesh::is_closed`.
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        // Skewed so the ray is unlikely to run along the edges or faces of
+        // axis aligned meshes.
+        let direct = Vector3::new(0.301, 0.557, 0.774);
+        let inverse_direct = Vector3::new(1.0 / direct.x, 1.0 / direct.y, 1.0 / direct.z);
+        let mut distances = Vec::new();
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if ray_box_distance(point, inverse_direct, node.min, node.max, f32::INFINITY).is_none() {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first);
+                stack.push(node_index + 1);
+                continue;
+            }
+            for (_, corners, _) in self.triangles[node.first..node.first + node.count].iter() {
+                if let Some(distance) = intersect_of_ray_and_triangle(point, direct, corners[0], corners[1], corners[2]) {
+                    distances.push(distance);
+                }
+            }
+        }
+        // A ray through an edge or vertex hits every triangle there at the
+        // same distance, that is a single crossing.
+        distances.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        distances.dedup_by(|a, b| (*a - *b).abs() <= 1e-6 * b.abs().max(1.0));
+        distances.len() % 2 == 1
+    }
+
+    /// Indices of the triangles whose boxes overlap the given box.
+    fn overlapping_triangles(&self, min: Point3<f32>, max: Point3<f32>) -> Vec<usize> {
+        let mut found = Vec::new();
+        if self.nodes.is_empty() {
+            return found;
+        }
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if (0..3).any(|axis| node.min[axis] > max[axis] || node.max[axis] < min[axis]) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first);
+                stack.push(node_index + 1);
+                continue;
+            }
+            found.extend(node.first..node.first + node.count);
+        }
+        found
+    }
+
+    /// Finds the point on the surface closest to `point`, with the face it
+    /// lies on.
+    pub fn closest_point(&self, point: Point3<f32>) -> Option<(Id, Point3<f32>)> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let mut best: Option<(f32, Id, Point3<f32>)> = None;
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            let max_distance2 = best.map_or(f32::INFINITY, |(distance2, _, _)| distance2);
+            if point_box_distance2(point, node.min, node.max) > max_distance2 {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.first);
+                stack.push(node_index + 1);
+                continue;
+            }
+            for &(face_id, ref corners, _) in self.triangles[node.first..node.first + node.count].iter() {
+                let closest = closest_point_on_triangle(point, corners[0], corners[1], corners[2]);
+                let distance2 = closest.distance2(point);
+                if best.is_none_or(|(best_distance2, best_face_id, _)| (distance2, face_id) < (best_distance2, best_face_id)) {
+                    best = Some((distance2, face_id, closest));
+                }
+            }
+        }
+        best.map(|(_, face_id, closest)| (face_id, closest))
+    }
+}
+
+impl Mesh {
+    pub fn build_bvh(&self) -> Bvh {
+        Bvh::new(self)
+    }
+
+    /// Finds the closest face hit by the ray by testing every face, which is
+    /// fine for a few rays. Use `raycast_batch` or a `Bvh` for many.
+    pub fn raycast(&self, origin: Point3<f32>, direct: Vector3<f32>) -> Option<RayHit> {
+        let mut best: Option<(f32, Id)> = None;
+        for face_id in FaceIterator::new(self) {
+            for triangle in FaceTriangleIterator::new(self, face_id) {
+                let corner = |index: usize| self.vertex(triangle[index]).unwrap().position;
+                if let Some(distance) = intersect_of_ray_and_triangle(origin, direct, corner(0), corner(1), corner(2)) {
+                    best = closer_hit(best, distance, face_id);
+                }
+            }
+        }
+        best.map(|(distance, face_id)| RayHit {
+            face_id,
+            distance,
+            position: origin + direct * distance,
+        })
+    }
+
+    /// Whether the point is inside the closed surface, see
+    /// `Bvh::contains_point`, which is faster for many points.
+    pub fn contains_point(&self, point: Point3<f32>) -> bool {
+        self.build_bvh().contains_point(point)
+    }
+
+    /// Pairs of faces whose triangles cross or overlap, with the lower face id
+    /// first, sorted. Faces sharing a corner are never reported, as they
+    /// meet there anyway. Coplanar faces count when they overlap with some
+    /// area, not when they only touch.
+    pub fn self_intersections(&self) -> Vec<(Id, Id)> {
+        let bvh = self.build_bvh();
+        let mut pairs = FnvHashSet::default();
+        for (index, &(face_id, ref corners, _)) in bvh.triangles.iter().enumerate() {
+            let (min, max) = triangle_bounds(&bvh.triangles[index..index + 1]);
+            for other_index in bvh.overlapping_triangles(min, max) {
+                let (other_face_id, ref other_corners, _) = bvh.triangles[other_index];
+                if other_face_id <= face_id || pairs.contains(&(face_id, other_face_id)) {
+                    continue;
+                }
+                let corner_ids = self.face_vertex_ids(face_id);
+                if self.face_vertex_ids(other_face_id).iter().any(|vertex_id| corner_ids.contains(vertex_id)) {
+                    continue;
+                }
+                let intersects = tri_tri_intersect(*corners, *other_corners).is_some() ||
+                    tri_tri_coplanar_overlap(*corners, *other_corners).is_some_and(|overlap| overlap.len() >= 3);
+                if intersects {
+                    pairs.insert((face_id, other_face_id));
+                }
+            }
+        }
+        let mut pairs: Vec<(Id, Id)> = pairs.into_iter().collect();
+        pairs.sort();
+        pairs
+    }
+
+    /// Casts every `(origin, direction)` ray against a `Bvh` built once for
+    /// the batch and returns the hits in the order of the rays. With the
+    /// `parallel` feature the rays are spread over all cores.
+    pub fn raycast_batch(&self, rays: &[(Point3<f32>, Vector3<f32>)]) -> Vec<Option<RayHit>> {
+        let bvh = self.build_bvh();
+        #[cfg(feature = "parallel")]
+        let hits = rays.par_iter().map(|&(origin, direct)| bvh.raycast(origin, direct)).collect();
+        #[cfg(not(feature = "parallel"))]
+        let hits = rays.iter().map(|&(origin, direct)| bvh.raycast(origin, direct)).collect();
+        hits
+    }
+}
@@ -0,0 +1,305 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use mesh::Id;
+use bvh::Bvh;
+use kdtree::KdTree;
+use mesh::Mesh;
+use std::mem;
+use std::sync::Arc;
+
+/// Face normals, face areas and vertex normals of a mesh, kept up to date
+/// after small edits by recomputing only around the faces marked dirty. The
+/// cache does not notice edits by itself, so mark the faces whose corners
+/// moved with `mark_dirty` or `mark_vertex_moved`. After changing the
+/// topology, also mark the added and removed faces, or build a new cache.
+/// `Mesh::mark_dirty` keeps one on the mesh instead, see
+/// `Mesh::cached_vertex_norm`.
+#[derive(Debug, Clone)]
+pub struct NormalCache {
+    /// Indexed by FACE ID - 1, zero for removed faces.
+    face_normals: Vec<Vector3<f32>>,
+    face_areas: Vec<f32>,
+
+    /// Corners of every face when it was last computed, indexed like
+    /// `face_normals`, so the corners of a removed face still get their
+    /// normals updated.
+    face_corners: Vec<Vec<Id>>,
+
+    /// Indexed by VERTEX ID - 1.
+    vertex_normals: Vec<Vector3<f32>>,
+
+    dirty_faces: FnvHashSet<Id>,
+}
+
+impl NormalCache {
+    pub fn new(mesh: &Mesh) -> Self {
+        let mut cache = NormalCache {
+            face_normals: Vec::new(),
+            face_areas: Vec::new(),
+            face_corners: Vec::new(),
+            vertex_normals: Vec::new(),
+            dirty_faces: FnvHashSet::default(),
+        };
+        cache.dirty_faces.extend(FaceIterator::new(mesh));
+        cache.refresh(mesh);
+        cache
+    }
+
+    /// Marks faces whose corners moved or that were added or removed.
+    pub fn mark_dirty(&mut self, faces: &[Id]) {
+        self.dirty_faces.extend(faces.iter().cloned());
+    }
+
+    /// Marks the faces around a vertex, for after moving it.
+    pub fn mark_vertex_moved(&mut self, mesh: &Mesh, vertex_id: Id) {
+        if let Some(vertex) = mesh.vertex(vertex_id) {
+            for &halfedge_id in vertex.halfedges.iter() {
+                if let Some(face_id) = mesh.halfedge_face_id(halfedge_id) {
+                    self.dirty_faces.insert(face_id);
+                }
+            }
+        }
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty_faces.is_empty()
+    }
+
+    /// Recomputes the dirty faces and the normals of their corners, giving
+    /// the same values as `Mesh::face_norm`, `Mesh::face_area` and
+    /// `Mesh::vertex_norm`.
+    pub fn refresh(&mut self, mesh: &Mesh) {
+        if self.dirty_faces.is_empty() {
+            return;
+        }
+        self.face_normals.resize(mesh.faces.len(), Vector3::zero());
+        self.face_areas.resize(mesh.faces.len(), 0.0);
+        self.face_corners.resize(mesh.faces.len(), Vec::new());
+        self.vertex_normals.resize(mesh.vertices.len(), Vector3::zero());
+        let mut dirty_vertices = FnvHashSet::default();
+        for &face_id in self.dirty_faces.iter() {
+            if face_id == 0 || face_id > mesh.faces.len() {
+                continue;
+            }
+            // The old corners lose the face, the new ones gain it.
+            dirty_vertices.extend(self.face_corners[face_id - 1].drain(..));
+            if mesh.face(face_id).is_none() {
+                self.face_normals[face_id - 1] = Vector3::zero();
+                self.face_areas[face_id - 1] = 0.0;
+                continue;
+            }
+            self.face_normals[face_id - 1] = mesh.face_norm(face_id);
+            self.face_areas[face_id - 1] = mesh.face_area(face_id);
+            self.face_corners[face_id - 1] = mesh.face_vertex_ids(face_id);
+            dirty_vertices.extend(self.face_corners[face_id - 1].iter().cloned());
+        }
+        for vertex_id in dirty_vertices {
+            let vertex = match mesh.vertex(vertex_id) {
+                Some(vertex) => vertex,
+                None => {
+                    self.vertex_normals[vertex_id - 1] = Vector3::zero();
+                    continue;
+                },
+            };
+            let mut total = Vector3::zero();
+            for &halfedge_id in vertex.halfedges.iter() {
+                total += self.face_normals[mesh.halfedge_face_id(halfedge_id).unwrap() - 1];
+            }
+            self.vertex_normals[vertex_id - 1] = if total.is_zero() { total } else { total.normalize() };
+        }
+        self.dirty_faces.clear();
+    }
+
+    pub fn face_norm(&mut self, mesh: &Mesh, face_id: Id) -> Vector3<f32> {
+        self.refresh(mesh);
+        self.face_normals.get(face_id.wrapping_sub(1)).cloned().unwrap_or_else(Vector3::zero)
+    }
+
+    pub fn face_area(&mut self, mesh: &Mesh, face_id: Id) -> f32 {
+        self.refresh(mesh);
+        self.face_areas.get(face_id.wrapping_sub(1)).cloned().unwrap_or(0.0)
+    }
+
+    pub fn vertex_norm(&mut self, mesh: &Mesh, vertex_id: Id) -> Vector3<f32> {
+        self.refresh(mesh);
+        self.vertex_normals.get(vertex_id.wrapping_sub(1)).cloned().unwrap_or_else(Vector3::zero)
+    }
+}
+
+/// Normals, BVH and kd-tree kept on a mesh by `Mesh::cached_vertex_norm`,
+/// `Mesh::cached_bvh` and `Mesh::cached_kd_tree`, with the `Mesh::generation` they are current for.
+#[derive(Debug, Default)]
+pub struct DerivedCache {
+    generation: u64,
+    normals: Option<NormalCache>,
+    bvh: Option<Arc<Bvh>>,
+    kd_tree: Option<Arc<KdTree>>,
+    face_center_tree: Option<Arc<KdTree>>,
+
+    /// Faces marked with `Mesh::mark_dirty` since `bvh` was refit.
+    bvh_dirty_faces: Vec<Id>,
+}
+
+/// Cotangent weights kept by `Mesh::cotangent_weights`, with the
+/// `Mesh::generation` they were computed at.
+#[derive(Debug, Default)]
+pub struct CotangentCache {
+    generation: u64,
+    weights: Option<Arc<FnvHashMap<Id, f32>>>,
+
+    /// How many times the weights were computed.
+    computations: usize,
+}
+
+/// Cotangent of the angle at `corner` between the lines to `first` and
+/// `second`, zero for degenerate corners.
+fn corner_cotangent(corner: Point3<f32>, first: Point3<f32>, second: Point3<f32>) -> f32 {
+    let (to_first, to_second) = (first - corner, second - corner);
+    let sine = to_first.cross(to_second).magnitude();
+    if sine <= 0.0 {
+        return 0.0;
+    }
+    to_first.dot(to_second) / sine
+}
+
+impl Mesh {
+    /// Tells the cached normals and BVH that the edits since they were last
+    /// brought up to date only touched the given faces, the ones whose
+    /// corners moved and the ones added or removed, so they are refreshed
+    /// around those faces on next access instead of from scratch. Edits not
+    /// followed by a call are noticed through the `generation`, and the
+    /// caches are then rebuilt on next access.
+    pub fn mark_dirty(&mut self, faces: &[Id]) {
+        let generation = self.generation();
+        let cache = self.derived_cache_mut();
+        if generation == cache.generation {
+            return;
+        }
+        if let Some(ref mut normals) = cache.normals {
+            normals.mark_dirty(faces);
+        }
+        if cache.bvh.is_some() {
+            cache.bvh_dirty_faces.extend_from_slice(faces);
+        }
+        cache.kd_tree = None;
+        cache.face_center_tree = None;
+        cache.generation = generation;
+    }
+
+    /// Brings the cached data up to date, rebuilding it when the mesh was
+    /// edited without `mark_dirty`.
+    fn with_derived_cache<T, F: FnOnce(&mut DerivedCache) -> T>(&self, f: F) -> T {
+        let mut cache = self.derived_cache().lock().unwrap();
+        if cache.generation != self.generation() {
+            *cache = DerivedCache {
+                generation: self.generation(),
+                ..DerivedCache::default()
+            };
+        }
+        f(&mut cache)
+    }
+
+    fn with_normal_cache<T, F: FnOnce(&mut NormalCache) -> T>(&self, f: F) -> T {
+        self.with_derived_cache(|cache| {
+            if cache.normals.is_none() {
+                cache.normals = Some(NormalCache::new(self));
+            }
+            let normals = cache.normals.as_mut().unwrap();
+            normals.refresh(self);
+            f(normals)
+        })
+    }
+
+    /// `face_norm` from a `NormalCache` kept on the mesh, see `mark_dirty`.
+    pub fn cached_face_norm(&self, face_id: Id) -> Vector3<f32> {
+        self.with_normal_cache(|normals| normals.face_norm(self, face_id))
+    }
+
+    /// `face_area` from a `NormalCache` kept on the mesh, see `mark_dirty`.
+    pub fn cached_face_area(&self, face_id: Id) -> f32 {
+        self.with_normal_cache(|normals| normals.face_area(self, face_id))
+    }
+
+    /// `vertex_norm` from a `NormalCache` kept on the mesh, see `mark_dirty`.
+    pub fn cached_vertex_norm(&self, vertex_id: Id) -> Vector3<f32> {
+        self.with_normal_cache(|normals| normals.vertex_norm(self, vertex_id))
+    }
+
+    /// A `Bvh` kept on the mesh, refit around the faces marked with
+    /// `mark_dirty` rather than rebuilt, see `Bvh::refit_faces`.
+    pub fn cached_bvh(&self) -> Arc<Bvh> {
+        self.with_derived_cache(|cache| {
+            let dirty_faces = mem::take(&mut cache.bvh_dirty_faces);
+            let refit = match cache.bvh {
+                Some(ref mut bvh) => dirty_faces.is_empty() || Arc::make_mut(bvh).refit_faces(self, &dirty_faces),
+                None => false,
+            };
+            if !refit {
+                cache.bvh = Some(Arc::new(self.build_bvh()));
+            }
+            cache.bvh.clone().unwrap()
+        })
+    }
+
+    /// A `KdTree` kept on the mesh until its next edit.
+    pub fn cached_kd_tree(&self) -> Arc<KdTree> {
+        self.with_derived_cache(|cache| {
+            if cache.kd_tree.is_none() {
+                cache.kd_tree = Some(Arc::new(self.build_kd_tree()));
+            }
+            cache.kd_tree.clone().unwrap()
+        })
+    }
+
+    /// A `KdTree` over the face centers, kept like `cached_kd_tree`.
+    pub(crate) fn cached_face_center_tree(&self) -> Arc<KdTree> {
+        self.with_derived_cache(|cache| {
+            if cache.face_center_tree.is_none() {
+                cache.face_center_tree = Some(Arc::new(KdTree::from_points(FaceIterator::new(self)
+                    .map(|face_id| (face_id, self.face_center(face_id)))
+                    .collect())));
+            }
+            cache.face_center_tree.clone().unwrap()
+        })
+    }
+
+    /// The cot α + cot β weight of every edge, keyed by the lower halfedge id
+    /// of the edge, see `peek_same_halfedge`, where α and β are the corners
+    /// facing the edge in the triangles on either side. Boundary edges only
+    /// have one corner, and faces with more than three corners add nothing.
+    ///
+    /// The weights are computed once and shared until the `generation`
+    /// changes, so solvers can ask for them every iteration.
+    pub fn cotangent_weights(&self) -> Arc<FnvHashMap<Id, f32>> {
+        let mut cache = self.cotangent_cache().lock().unwrap();
+        if cache.weights.is_none() || cache.generation != self.generation() {
+            let mut weights = FnvHashMap::default();
+            for face_id in FaceIterator::new(self) {
+                let halfedges: Vec<Id> = FaceHalfedgeIterator::new(self, self.face_first_halfedge_id(face_id).unwrap()).collect();
+                if halfedges.len() != 3 {
+                    continue;
+                }
+                for i in 0..3 {
+                    let position = |index: usize| self.halfedge_start_vertex(halfedges[index % 3]).unwrap().position;
+                    let cotangent = corner_cotangent(position(i + 2), position(i), position(i + 1));
+                    *weights.entry(self.peek_same_halfedge(halfedges[i])).or_insert(0.0) += cotangent;
+                }
+            }
+            cache.generation = self.generation();
+            cache.weights = Some(Arc::new(weights));
+            cache.computations += 1;
+        }
+        cache.weights.clone().unwrap()
+    }
+
+    /// How many times `cotangent_weights` had to compute the weights rather
+    /// than reuse them.
+    pub fn cotangent_weight_computations(&self) -> usize {
+        self.cotangent_cache().lock().unwrap().computations
+    }
+}
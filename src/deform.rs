@@ -0,0 +1,145 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use mesh::Id;
+use mesh::Mesh;
+use std::f32;
+
+/// One of the coordinate axes, the direction `Mesh::bend` and `Mesh::twist`
+/// work along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    /// The coordinate index of the axis, 0 for x.
+    pub fn index(&self) -> usize {
+        match *self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+}
+
+/// How the movement of `Mesh::soft_translate` fades from the center vertex
+/// to the edge of its radius.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Falloff {
+    /// Fades evenly with the distance.
+    Linear,
+
+    /// Fades slowly near the center and the edge, with a smoothstep.
+    Smooth,
+
+    /// Fades quickly near the center, with a square.
+    Sharp,
+}
+
+impl Falloff {
+    /// How much of the movement is kept at `fraction` of the radius from the
+    /// center, 1 at the center and 0 from the edge on.
+    pub fn weight(&self, fraction: f32) -> f32 {
+        let remaining = (1.0 - fraction).clamp(0.0, 1.0);
+        match *self {
+            Falloff::Linear => remaining,
+            Falloff::Smooth => remaining * remaining * (3.0 - 2.0 * remaining),
+            Falloff::Sharp => remaining * remaining,
+        }
+    }
+}
+
+impl Mesh {
+    /// Lowest and highest coordinate of the living vertices along every axis.
+    fn position_bounds(&self) -> Option<(Point3<f32>, Point3<f32>)> {
+        let mut positions = self.vertices.iter().filter(|vertex| vertex.alive).map(|vertex| vertex.position);
+        let first = positions.next()?;
+        Some(positions.fold((first, first), |(min, max), position| {
+            (Point3::new(min.x.min(position.x), min.y.min(position.y), min.z.min(position.z)),
+                Point3::new(max.x.max(position.x), max.y.max(position.y), max.z.max(position.z)))
+        }))
+    }
+
+    /// Bends the part of the mesh between `region.0` and `region.1` along
+    /// `axis`, given as fractions of the bounding box from its low side, into
+    /// a circular arc turning by `amount` radians towards the next axis (y for
+    /// x, z for y, x for z). Negative amounts bend the other way. The part
+    /// below the region stays in place and the part above it follows the end
+    /// of the arc rigidly. Lengths along the center line of the bounding box
+    /// are kept.
+    pub fn bend(&mut self, axis: Axis, amount: f32, region: (f32, f32)) -> &mut Self {
+        let (min, max) = match self.position_bounds() {
+            Some(bounds) => bounds,
+            None => return self,
+        };
+        let along = axis.index();
+        let across = (along + 1) % 3;
+        let start = min[along] + (max[along] - min[along]) * region.0;
+        let end = min[along] + (max[along] - min[along]) * region.1;
+        if amount == 0.0 || end <= start {
+            return self;
+        }
+        let center = (min[across] + max[across]) * 0.5;
+        let radius = (end - start) / amount;
+        self.map_positions_mut(|position| {
+            let clamped = position[along].max(start).min(end);
+            let angle = amount * (clamped - start) / (end - start);
+            let (sine, cosine) = angle.sin_cos();
+            let offset = radius - (position[across] - center);
+            let beyond = position[along] - clamped;
+            let mut bent = position;
+            bent[along] = start + offset * sine + beyond * cosine;
+            bent[across] = center + radius - offset * cosine + beyond * sine;
+            bent
+        })
+    }
+
+    /// Twists the mesh around the line through the center of its bounding box
+    /// along `axis`, turning every vertex by `turns_per_unit` full turns per
+    /// unit of distance from the low side of the box. The turn follows the
+    /// right hand around the axis, and the low side stays in place.
+    pub fn twist(&mut self, axis: Axis, turns_per_unit: f32) -> &mut Self {
+        let (min, max) = match self.position_bounds() {
+            Some(bounds) => bounds,
+            None => return self,
+        };
+        let along = axis.index();
+        let (first, second) = ((along + 1) % 3, (along + 2) % 3);
+        let center = Point3::new((min.x + max.x) * 0.5, (min.y + max.y) * 0.5, (min.z + max.z) * 0.5);
+        self.map_positions_mut(|position| {
+            let angle = (position[along] - min[along]) * turns_per_unit * 2.0 * f32::consts::PI;
+            let (sine, cosine) = angle.sin_cos();
+            let (u, v) = (position[first] - center[first], position[second] - center[second]);
+            let mut twisted = position;
+            twisted[first] = center[first] + u * cosine - v * sine;
+            twisted[second] = center[second] + u * sine + v * cosine;
+            twisted
+        })
+    }
+
+    /// Moves the `center` vertex by `delta` and the vertices around it by
+    /// less, as the proportional editing of modeling tools does. Vertices get
+    /// the part of `delta` the `falloff` keeps at their straight distance
+    /// from the center, whether or not the faces connect them, so vertices at
+    /// `radius` and beyond stay in place. Unknown centers leave the mesh as
+    /// it was.
+    pub fn soft_translate(&mut self, center: Id, delta: Vector3<f32>, radius: f32, falloff: Falloff) -> &mut Self {
+        let center_position = match self.vertex(center) {
+            Some(vertex) => vertex.position,
+            None => return self,
+        };
+        if radius <= 0.0 {
+            self.vertex_mut(center).unwrap().position += delta;
+            return self;
+        }
+        for vertex_id in self.vertices_within_radius(center_position, radius) {
+            let vertex = self.vertex_mut(vertex_id).unwrap();
+            let weight = falloff.weight(vertex.position.distance(center_position) / radius);
+            vertex.position += delta * weight;
+        }
+        self
+    }
+}
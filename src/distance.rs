@@ -0,0 +1,97 @@
+use bvh::Bvh;
+use cgmath::Point3;
+use cgmath::prelude::*;
+use iterator::FaceIterator;
+use iterator::FaceTriangleIterator;
+use mesh::Mesh;
+
+/// Mirrors the digits of `index` in `base` around the decimal point, giving
+/// the Halton sequence that spreads points evenly without randomness.
+fn radical_inverse(mut index: usize, base: usize) -> f32 {
+    let mut result = 0.0;
+    let mut scale = 1.0 / base as f32;
+    while index > 0 {
+        result += (index % base) as f32 * scale;
+        index /= base;
+        scale /= base as f32;
+    }
+    result
+}
+
+impl Mesh {
+    /// The living vertices followed by `samples` points spread over the
+    /// faces by area, placed by a Halton sequence so the same mesh always
+    /// gives the same points.
+    fn surface_samples(&self, samples: usize) -> Vec<Point3<f32>> {
+        let mut points: Vec<Point3<f32>> = self.vertices.iter()
+            .filter(|vertex| vertex.alive && !vertex.halfedges.is_empty())
+            .map(|vertex| vertex.position)
+            .collect();
+        let mut triangles = Vec::new();
+        let mut cumulative_areas = Vec::new();
+        let mut total_area = 0.0;
+        for face_id in FaceIterator::new(self) {
+            for triangle in FaceTriangleIterator::new(self, face_id) {
+                let corners = [self.vertex(triangle[0]).unwrap().position,
+                    self.vertex(triangle[1]).unwrap().position,
+                    self.vertex(triangle[2]).unwrap().position];
+                total_area += (corners[1] - corners[0]).cross(corners[2] - corners[0]).magnitude() * 0.5;
+                triangles.push(corners);
+                cumulative_areas.push(total_area);
+            }
+        }
+        if triangles.is_empty() || total_area <= 0.0 {
+            return points;
+        }
+        for i in 0..samples {
+            let target = (i as f32 + 0.5) / samples as f32 * total_area;
+            let index = cumulative_areas.partition_point(|&area| area < target).min(triangles.len() - 1);
+            let corners = triangles[index];
+            let s = radical_inverse(i + 1, 2).sqrt();
+            let t = radical_inverse(i + 1, 3);
+            points.push(Point3::from_vec(corners[0].to_vec() * (1.0 - s)
+                + corners[1].to_vec() * (s * (1.0 - t))
+                + corners[2].to_vec() * (s * t)));
+        }
+        points
+    }
+
+    /// Largest and mean distance from the surface samples of this mesh to
+    /// the surface in `bvh`.
+    fn directed_distances(&self, bvh: &Bvh, samples: usize) -> (f32, f32) {
+        let points = self.surface_samples(samples);
+        if points.is_empty() {
+            return (0.0, 0.0);
+        }
+        let mut max: f32 = 0.0;
+        let mut total = 0.0;
+        for point in points.iter() {
+            let distance = match bvh.closest_point(*point) {
+                Some((_, closest)) => closest.distance(*point),
+                None => f32::INFINITY,
+            };
+            max = max.max(distance);
+            total += distance;
+        }
+        (max, total / points.len() as f32)
+    }
+
+    /// Approximate symmetric Hausdorff distance, the largest distance from a
+    /// point on either surface to the other one. Each mesh is sampled at its
+    /// vertices and at `samples` more points spread by area, so the result
+    /// can only underestimate the exact distance. Infinite when just one
+    /// mesh has faces.
+    pub fn hausdorff_distance(&self, other: &Mesh, samples: usize) -> f32 {
+        let (forward, _) = self.directed_distances(&other.build_bvh(), samples);
+        let (backward, _) = other.directed_distances(&self.build_bvh(), samples);
+        forward.max(backward)
+    }
+
+    /// Mean distance from the sample points of each surface to the other
+    /// one, sampled like `hausdorff_distance`, averaged over both directions.
+    pub fn mean_distance(&self, other: &Mesh, samples: usize) -> f32 {
+        let (_, forward) = self.directed_distances(&other.build_bvh(), samples);
+        let (_, backward) = other.directed_distances(&self.build_bvh(), samples);
+        (forward + backward) * 0.5
+    }
+}
@@ -0,0 +1,107 @@
+use mesh::Id;
+use std::error;
+use std::fmt;
+use std::io;
+
+/// Errors reported by the fallible mesh operations, like subdividing and
+/// reading OBJ data. Topological edits report a `TopologyError` instead.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshError {
+    /// The side of a face along the halfedge is matched by several sides of
+    /// faces across it, split at vertices the halfedge skips, as adaptive
+    /// subdivision leaves behind. See `Mesh::find_t_junctions`.
+    TJunctionUnsupported { halfedge: Id },
+
+    /// The two faces along the halfedge run the same way over their shared
+    /// edge. See `Mesh::find_misoriented_edges`.
+    InconsistentOrientation { halfedge: Id },
+
+    /// Reading or writing failed, with the kind and message of the I/O
+    /// error, which is not `Clone` itself.
+    Io { kind: io::ErrorKind, message: String },
+
+    /// The line of an OBJ file, counting from 1, could not be parsed, like a
+    /// face using a vertex that does not exist.
+    InvalidObjLine { line: usize },
+
+    /// The triangle, counting from 0, uses an index past the end of the
+    /// positions it was given with.
+    IndexOutOfRange { triangle: usize, index: u32 },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MeshError::TJunctionUnsupported { halfedge } => {
+                write!(f, "halfedge {} has a T-junction", halfedge)
+            },
+            MeshError::InconsistentOrientation { halfedge } => {
+                write!(f, "the faces along halfedge {} disagree in orientation", halfedge)
+            },
+            MeshError::Io { ref message, .. } => write!(f, "{}", message),
+            MeshError::InvalidObjLine { line } => write!(f, "invalid OBJ data on line {}", line),
+            MeshError::IndexOutOfRange { triangle, index } => {
+                write!(f, "triangle {} uses index {}, which has no position", triangle, index)
+            },
+        }
+    }
+}
+
+impl error::Error for MeshError {}
+
+impl From<io::Error> for MeshError {
+    fn from(error: io::Error) -> Self {
+        MeshError::Io { kind: error.kind(), message: error.to_string() }
+    }
+}
+
+impl From<MeshError> for io::Error {
+    fn from(error: MeshError) -> Self {
+        match error {
+            MeshError::Io { kind, message } => io::Error::new(kind, message),
+            error => io::Error::new(io::ErrorKind::InvalidData, error.to_string()),
+        }
+    }
+}
+
+/// Reasons a topological edit like `split_face`, `bridge` or `bevel_edge`
+/// was refused, in which case the mesh is left as it was.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TopologyError {
+    /// The edit would leave an edge with more than two faces or a vertex
+    /// whose faces form more than one fan.
+    NonManifoldResult,
+
+    /// The edit would add an edge between two vertices that are already
+    /// connected.
+    WouldCreateDuplicateEdge { first: Id, second: Id },
+
+    /// The element does not exist, has been removed, or is not the kind the
+    /// edit needs, like an interior halfedge where a boundary one is
+    /// expected.
+    InvalidElement { id: Id },
+
+    /// The face has to be a triangle.
+    NotATriangle { face: Id },
+
+    /// The element is not one of the corners or sides of the face.
+    ElementNotOnFace { face: Id, element: Id },
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TopologyError::NonManifoldResult => write!(f, "the result would not be manifold"),
+            TopologyError::WouldCreateDuplicateEdge { first, second } => {
+                write!(f, "vertices {} and {} are already connected", first, second)
+            },
+            TopologyError::InvalidElement { id } => write!(f, "element {} is not valid here", id),
+            TopologyError::NotATriangle { face } => write!(f, "face {} is not a triangle", face),
+            TopologyError::ElementNotOnFace { face, element } => {
+                write!(f, "element {} is not on face {}", element, face)
+            },
+        }
+    }
+}
+
+impl error::Error for TopologyError {}
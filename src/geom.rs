@@ -0,0 +1,131 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+
+/// Distances of the corners of `triangle` to the plane of `other`, or None
+/// if `other` has no plane. Distances within `tolerance` are snapped to zero.
+fn triangle_plane_distances(triangle: &[Point3<f32>; 3], other: &[Point3<f32>; 3], tolerance: f32) -> Option<[f32; 3]> {
+    let normal = (other[1] - other[0]).cross(other[2] - other[0]);
+    if normal.magnitude2() <= 0.0 {
+        return None;
+    }
+    let normal = normal.normalize();
+    let mut distances = [0.0; 3];
+    for (distance, corner) in distances.iter_mut().zip(triangle.iter()) {
+        let signed = normal.dot(corner - other[0]);
+        *distance = if signed.abs() <= tolerance { 0.0 } else { signed };
+    }
+    Some(distances)
+}
+
+/// Interval of the line `direct` covered by the triangle where it crosses a
+/// plane, given the distances of its corners to the plane.
+fn triangle_plane_interval(triangle: &[Point3<f32>; 3], distances: &[f32; 3], direct: Vector3<f32>) -> Option<(f32, Point3<f32>, f32, Point3<f32>)> {
+    let mut points = Vec::with_capacity(3);
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        if distances[i] == 0.0 {
+            points.push(triangle[i]);
+        }
+        if distances[i] * distances[j] < 0.0 {
+            let t = distances[i] / (distances[i] - distances[j]);
+            points.push(triangle[i] + (triangle[j] - triangle[i]) * t);
+        }
+    }
+    let mut interval: Option<(f32, Point3<f32>, f32, Point3<f32>)> = None;
+    for point in points {
+        let along = direct.dot(point.to_vec());
+        interval = Some(match interval {
+            None => (along, point, along, point),
+            Some((min, min_point, max, max_point)) => {
+                let (min, min_point) = if along < min { (along, point) } else { (min, min_point) };
+                let (max, max_point) = if along > max { (along, point) } else { (max, max_point) };
+                (min, min_point, max, max_point)
+            },
+        });
+    }
+    interval
+}
+
+fn longest_edge(triangle: &[Point3<f32>; 3]) -> f32 {
+    triangle[0].distance(triangle[1]).max(triangle[1].distance(triangle[2])).max(triangle[2].distance(triangle[0]))
+}
+
+/// The segment where two triangles intersect, its ends equal when they only
+/// touch at a point, found from the intervals on the line where their planes
+/// meet, after Moller's "A Fast Triangle-Triangle Intersection Test". Corners
+/// within a small tolerance of the other plane count as on it, so triangles
+/// sharing an edge or corner touch there. Coplanar triangles give None, see
+/// `tri_tri_coplanar_overlap`, and so do degenerate ones.
+pub fn tri_tri_intersect(a: [Point3<f32>; 3], b: [Point3<f32>; 3]) -> Option<(Point3<f32>, Point3<f32>)> {
+    let tolerance = longest_edge(&a).max(longest_edge(&b)) * 1e-5;
+    let b_distances = triangle_plane_distances(&b, &a, tolerance)?;
+    if b_distances.iter().all(|&distance| distance > 0.0) || b_distances.iter().all(|&distance| distance < 0.0) ||
+            b_distances.iter().all(|&distance| distance == 0.0) {
+        return None;
+    }
+    let a_distances = triangle_plane_distances(&a, &b, tolerance)?;
+    if a_distances.iter().all(|&distance| distance > 0.0) || a_distances.iter().all(|&distance| distance < 0.0) {
+        return None;
+    }
+    let direct = (a[1] - a[0]).cross(a[2] - a[0]).cross((b[1] - b[0]).cross(b[2] - b[0]));
+    if direct.magnitude2() <= 0.0 {
+        return None;
+    }
+    let direct = direct.normalize();
+    let (a_min, a_min_point, a_max, a_max_point) = triangle_plane_interval(&a, &a_distances, direct)?;
+    let (b_min, b_min_point, b_max, b_max_point) = triangle_plane_interval(&b, &b_distances, direct)?;
+    // Both intervals lie on the same line, so touching ends may be apart by
+    // rounding only.
+    if a_min > b_max + tolerance || b_min > a_max + tolerance {
+        return None;
+    }
+    let start = if a_min >= b_min { a_min_point } else { b_min_point };
+    let end = if a_max <= b_max { a_max_point } else { b_max_point };
+    if direct.dot(end - start) < 0.0 {
+        return Some((start, start));
+    }
+    Some((start, end))
+}
+
+/// The polygon where two coplanar triangles overlap, clipped out of `b` by
+/// the sides of `a`, with the winding of `b`. None if the triangles are not
+/// coplanar, are degenerate or do not overlap. Triangles touching along an
+/// edge or at a corner give a polygon with no area.
+pub fn tri_tri_coplanar_overlap(a: [Point3<f32>; 3], b: [Point3<f32>; 3]) -> Option<Vec<Point3<f32>>> {
+    let tolerance = longest_edge(&a).max(longest_edge(&b)) * 1e-5;
+    let b_distances = triangle_plane_distances(&b, &a, tolerance)?;
+    if b_distances.iter().any(|&distance| distance != 0.0) {
+        return None;
+    }
+    let normal = (a[1] - a[0]).cross(a[2] - a[0]).normalize();
+    let mut polygon = b.to_vec();
+    for i in 0..3 {
+        let (start, end) = (a[i], a[(i + 1) % 3]);
+        let inward = normal.cross(end - start).normalize();
+        let side = |point: Point3<f32>| {
+            let distance = inward.dot(point - start);
+            if distance.abs() <= tolerance { 0.0 } else { distance }
+        };
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for j in 0..polygon.len() {
+            let (current, next) = (polygon[j], polygon[(j + 1) % polygon.len()]);
+            let (current_side, next_side) = (side(current), side(next));
+            if current_side >= 0.0 {
+                clipped.push(current);
+            }
+            if current_side * next_side < 0.0 {
+                clipped.push(current + (next - current) * (current_side / (current_side - next_side)));
+            }
+        }
+        clipped.dedup_by(|first, second| first.distance(*second) <= tolerance);
+        if clipped.len() > 1 && clipped[0].distance(clipped[clipped.len() - 1]) <= tolerance {
+            clipped.pop();
+        }
+        if clipped.is_empty() {
+            return None;
+        }
+        polygon = clipped;
+    }
+    Some(polygon)
+}
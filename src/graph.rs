@@ -0,0 +1,68 @@
+use cgmath::Point3;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use mesh::EdgeEndpoints;
+use mesh::Id;
+use mesh::Mesh;
+
+impl Mesh {
+    /// The edges of the mesh as pairs of indices into the returned vertex
+    /// positions, for graph tools that know nothing about halfedges. Living
+    /// vertices are indexed in id order, the same as in `to_buffers`, and
+    /// every edge is listed once with the lower index first, sorted.
+    pub fn to_graph(&self) -> (Vec<(usize, usize)>, Vec<Point3<f32>>) {
+        let mut indices: FnvHashMap<Id, usize> = FnvHashMap::default();
+        let mut positions = Vec::with_capacity(self.vertex_count);
+        for vertex in self.vertices.iter().filter(|vertex| vertex.alive) {
+            indices.insert(vertex.id, positions.len());
+            positions.push(vertex.position);
+        }
+        let mut edges = FnvHashSet::default();
+        for face_id in FaceIterator::new(self) {
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for halfedge_id in FaceHalfedgeIterator::new(self, face_halfedge) {
+                let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+                let endpoints = EdgeEndpoints::new(indices[&self.halfedge_start_vertex_id(halfedge_id).unwrap()],
+                    indices[&self.halfedge_start_vertex_id(next_id).unwrap()]);
+                edges.insert((endpoints.low, endpoints.high));
+            }
+        }
+        let mut edges: Vec<(usize, usize)> = edges.into_iter().collect();
+        edges.sort();
+        (edges, positions)
+    }
+
+    /// The face adjacency graph: faces are indexed in id order and placed at
+    /// their centers, and faces sharing at least one edge are connected
+    /// once, with the lower index first, sorted.
+    pub fn to_dual_graph(&self) -> (Vec<(usize, usize)>, Vec<Point3<f32>>) {
+        let mut indices: FnvHashMap<Id, usize> = FnvHashMap::default();
+        let mut centers = Vec::with_capacity(self.face_count);
+        let mut edge_faces: FnvHashMap<EdgeEndpoints, Vec<usize>> = FnvHashMap::default();
+        for face_id in FaceIterator::new(self) {
+            let index = centers.len();
+            indices.insert(face_id, index);
+            centers.push(self.face_center(face_id));
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for halfedge_id in FaceHalfedgeIterator::new(self, face_halfedge) {
+                let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+                let endpoints = EdgeEndpoints::new(self.halfedge_start_vertex_id(halfedge_id).unwrap(),
+                    self.halfedge_start_vertex_id(next_id).unwrap());
+                edge_faces.entry(endpoints).or_default().push(index);
+            }
+        }
+        let mut edges = FnvHashSet::default();
+        for faces in edge_faces.values() {
+            for (i, &first) in faces.iter().enumerate() {
+                for &second in faces[i + 1..].iter().filter(|&&second| second != first) {
+                    edges.insert((first.min(second), first.max(second)));
+                }
+            }
+        }
+        let mut edges: Vec<(usize, usize)> = edges.into_iter().collect();
+        edges.sort();
+        (edges, centers)
+    }
+}
@@ -0,0 +1,84 @@
+use fnv::FnvHasher;
+use iterator::FaceIterator;
+use mesh::Mesh;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+/// Rounds of neighbor label refinement done by `topology_hash`.
+const TOPOLOGY_HASH_ROUNDS: usize = 3;
+
+fn hash_of<T: Hash>(value: &T) -> u64 {
+    let mut hasher = FnvHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The lexicographically smallest rotation of a face loop, keeping the
+/// winding, so the result does not depend on which halfedge the face starts
+/// at, even when the smallest element appears on more than one corner.
+fn canonical_loop<T: Ord + Clone>(items: &[T]) -> Vec<T> {
+    let rotation = |start: usize| items[start..].iter().chain(items[..start].iter());
+    let start = (0..items.len()).min_by(|&a, &b| rotation(a).cmp(rotation(b))).unwrap_or(0);
+    rotation(start).cloned().collect()
+}
+
+impl Mesh {
+    /// Hash of the connectivity that does not depend on how the ids are
+    /// assigned, so a mesh and its compacted `clone` hash the same. Vertices
+    /// are labeled by their valence, refined a few times with the labels of
+    /// their neighbors, and the labels of all vertices and faces are summed.
+    /// Different meshes can collide, but the same mesh always gives the same
+    /// hash.
+    pub fn topology_hash(&self) -> u64 {
+        let mut labels = vec![0u64; self.vertices.len() + 1];
+        for vertex in self.vertices.iter().filter(|vertex| vertex.alive) {
+            labels[vertex.id] = hash_of(&vertex.halfedges.len());
+        }
+        for _ in 0..TOPOLOGY_HASH_ROUNDS {
+            let mut refined = labels.clone();
+            for vertex in self.vertices.iter().filter(|vertex| vertex.alive) {
+                let mut neighbor_labels = Vec::with_capacity(vertex.halfedges.len() * 2);
+                for &halfedge_id in vertex.halfedges.iter() {
+                    let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+                    let prev_id = self.halfedge_prev_id(halfedge_id).unwrap();
+                    neighbor_labels.push(labels[self.halfedge_start_vertex_id(next_id).unwrap()]);
+                    neighbor_labels.push(labels[self.halfedge_start_vertex_id(prev_id).unwrap()]);
+                }
+                neighbor_labels.sort();
+                refined[vertex.id] = hash_of(&(labels[vertex.id], neighbor_labels));
+            }
+            labels = refined;
+        }
+        let mut hash = hash_of(&(self.vertex_count, self.face_count, self.halfedge_count));
+        for vertex in self.vertices.iter().filter(|vertex| vertex.alive) {
+            hash = hash.wrapping_add(hash_of(&labels[vertex.id]));
+        }
+        for face_id in FaceIterator::new(self) {
+            let corners: Vec<u64> = self.face_vertex_ids(face_id).into_iter().map(|id| labels[id]).collect();
+            hash = hash.wrapping_add(hash_of(&canonical_loop(&corners)));
+        }
+        hash
+    }
+
+    /// Hash of the vertex positions and faces, with positions rounded to a
+    /// grid of `quantize` units, that does not depend on how the ids are
+    /// assigned. Positions that round to the same grid point are treated as
+    /// equal, so noise below `quantize` mostly goes unnoticed.
+    pub fn geometry_hash(&self, quantize: f32) -> u64 {
+        let grid_point = |id| {
+            let position = self.vertex(id).unwrap().position;
+            [(position.x / quantize).round() as i64,
+                (position.y / quantize).round() as i64,
+                (position.z / quantize).round() as i64]
+        };
+        let mut hash = hash_of(&(self.vertex_count, self.face_count));
+        for vertex in self.vertices.iter().filter(|vertex| vertex.alive) {
+            hash = hash.wrapping_add(hash_of(&grid_point(vertex.id)));
+        }
+        for face_id in FaceIterator::new(self) {
+            let corners: Vec<[i64; 3]> = self.face_vertex_ids(face_id).into_iter().map(grid_point).collect();
+            hash = hash.wrapping_add(hash_of(&canonical_loop(&corners)));
+        }
+        hash
+    }
+}
@@ -1,5 +1,9 @@
-use mesh::Mesh;
+use cgmath::Point3;
+use mesh::Face;
+use mesh::Halfedge;
 use mesh::Id;
+use mesh::Mesh;
+use mesh::Vertex;
 
 pub struct FaceIterator<'a> {
     index: usize,
@@ -129,3 +133,121 @@ impl<'a> VertexHalfedgeIterator<'a> {
         vec
     }
 }
+
+/// Fan triangulates a face from its first corner, the one convention shared
+/// by the exporters and the area, volume and raycast code. Faces with fewer
+/// than three corners, and dead faces, yield nothing.
+pub struct FaceTriangleIterator {
+    vertex_ids: Vec<Id>,
+    index: usize,
+}
+
+impl Iterator for FaceTriangleIterator {
+    type Item = [Id; 3];
+
+    fn next(&mut self) -> Option<[Id; 3]> {
+        if self.index + 2 >= self.vertex_ids.len() {
+            return None;
+        }
+        self.index += 1;
+        Some([self.vertex_ids[0], self.vertex_ids[self.index], self.vertex_ids[self.index + 1]])
+    }
+}
+
+impl FaceTriangleIterator {
+    pub fn new(mesh: &Mesh, face_id: Id) -> FaceTriangleIterator {
+        let vertex_ids = match mesh.face(face_id) {
+            Some(face) => FaceHalfedgeIterator::new(mesh, face.halfedge)
+                .map(|halfedge_id| mesh.halfedge_start_vertex_id(halfedge_id).unwrap())
+                .collect(),
+            None => Vec::new(),
+        };
+        FaceTriangleIterator {
+            vertex_ids,
+            index: 0,
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<[Id; 3]> {
+        self.collect()
+    }
+}
+
+/// Spreads the low 21 bits of `value` out to every third bit.
+fn spread_bits(value: u64) -> u64 {
+    let mut x = value & 0x1f_ffff;
+    x = (x | x << 32) & 0x1f_0000_0000_ffff;
+    x = (x | x << 16) & 0x1f_0000_ff00_00ff;
+    x = (x | x << 8) & 0x100f_00f0_0f00_f00f;
+    x = (x | x << 4) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x << 2) & 0x1249_2492_4924_9249;
+    x
+}
+
+/// Sorts ids by the Morton code of their points within the box around all
+/// of them, keeping the given order for equal codes.
+fn sort_by_morton_code(points: Vec<(Id, Point3<f32>)>) -> Vec<Id> {
+    let mut min = Point3::new(f32::MAX, f32::MAX, f32::MAX);
+    let mut max = Point3::new(f32::MIN, f32::MIN, f32::MIN);
+    for &(_, point) in points.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(point[axis]);
+            max[axis] = max[axis].max(point[axis]);
+        }
+    }
+    let cells = ((1u64 << 21) - 1) as f32;
+    let mut coded: Vec<(u64, Id)> = points.into_iter().map(|(id, point)| {
+        let mut code = 0;
+        for axis in 0..3 {
+            let extent = max[axis] - min[axis];
+            let cell = if extent > 0.0 { ((point[axis] - min[axis]) / extent * cells) as u64 } else { 0 };
+            code |= spread_bits(cell) << axis;
+        }
+        (code, id)
+    }).collect();
+    coded.sort_by_key(|&(code, _)| code);
+    coded.into_iter().map(|(_, id)| id).collect()
+}
+
+impl Mesh {
+    /// The living faces ordered along a Z curve through their centers, so
+    /// faces close in space are mostly close in the order. Walking faces in
+    /// this order touches their neighbors while they are still in cache.
+    pub fn faces_morton_order(&self) -> Vec<Id> {
+        sort_by_morton_code(FaceIterator::new(self).map(|face_id| (face_id, self.face_center(face_id))).collect())
+    }
+
+    /// The living vertices ordered along a Z curve through their positions,
+    /// see `faces_morton_order`.
+    pub fn vertices_morton_order(&self) -> Vec<Id> {
+        sort_by_morton_code(self.iter_vertices().map(|(vertex_id, vertex)| (vertex_id, vertex.position)).collect())
+    }
+
+    /// Iterates over the living vertices with their ids, in id order.
+    pub fn iter_vertices<'a>(&'a self) -> impl Iterator<Item = (Id, &'a Vertex)> + 'a {
+        self.vertices.iter().filter(|vertex| vertex.alive).map(|vertex| (vertex.id, vertex))
+    }
+
+    /// Iterates over the living faces with their ids, in id order.
+    pub fn iter_faces<'a>(&'a self) -> impl Iterator<Item = (Id, &'a Face)> + 'a {
+        self.faces.iter().filter(|face| face.alive).map(|face| (face.id, face))
+    }
+
+    /// Iterates over the living halfedges with their ids, in id order.
+    pub fn iter_halfedges<'a>(&'a self) -> impl Iterator<Item = (Id, &'a Halfedge)> + 'a {
+        self.halfedges.iter().filter(|halfedge| halfedge.alive).map(|halfedge| (halfedge.id, halfedge))
+    }
+
+    /// Calls `f` once for every undirected edge, in id order of the lower
+    /// halfedge of each pair, with that halfedge and the ids of the vertices
+    /// it runs from and to. Boundary edges are visited through their only
+    /// halfedge.
+    pub fn for_each_edge<F: FnMut(Id, Id, Id)>(&self, mut f: F) {
+        for (halfedge_id, halfedge) in self.iter_halfedges() {
+            if self.peek_same_halfedge(halfedge_id) != halfedge_id {
+                continue;
+            }
+            f(halfedge_id, halfedge.vertex, self.halfedge(halfedge.next).unwrap().vertex);
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use cgmath::Point3;
+use cgmath::prelude::*;
+use mesh::Id;
+use mesh::Mesh;
+use iterator::FaceIterator;
+use std::cmp::Ordering;
+
+/// Most points scanned one by one instead of being split further.
+const LEAF_POINTS: usize = 8;
+
+/// Meshes with at most this many vertices, or faces, are searched without a
+/// tree by `Mesh::vertices_within_radius`, `Mesh::nearest_vertex` and
+/// `Mesh::nearest_face`, as building one costs more than it saves.
+const BRUTE_FORCE_VERTICES: usize = 64;
+
+/// Kd-tree over the living vertices of a mesh, for neighborhood queries that
+/// do not follow the connectivity. It does not follow later changes to the
+/// mesh, so build a new one after editing, or use `Mesh::cached_kd_tree`.
+#[derive(Debug, Clone)]
+pub struct KdTree {
+    /// Points in tree order: the median of every range splits it along the
+    /// axis of its depth, with the lower half before it and the upper half
+    /// after it.
+    points: Vec<(Id, Point3<f32>)>,
+}
+
+fn build(points: &mut [(Id, Point3<f32>)], depth: usize) {
+    if points.len() <= LEAF_POINTS {
+        return;
+    }
+    let axis = depth % 3;
+    let middle = points.len() / 2;
+    points.select_nth_unstable_by(middle, |a, b| a.1[axis].partial_cmp(&b.1[axis]).unwrap_or(Ordering::Equal));
+    let (lower, upper) = points.split_at_mut(middle);
+    build(lower, depth + 1);
+    build(&mut upper[1..], depth + 1);
+}
+
+fn within_radius(points: &[(Id, Point3<f32>)], depth: usize, center: Point3<f32>, radius: f32, found: &mut Vec<Id>) {
+    if points.len() <= LEAF_POINTS {
+        found.extend(points.iter()
+            .filter(|&&(_, position)| position.distance2(center) <= radius * radius)
+            .map(|&(id, _)| id));
+        return;
+    }
+    let axis = depth % 3;
+    let middle = points.len() / 2;
+    let (id, position) = points[middle];
+    if position.distance2(center) <= radius * radius {
+        found.push(id);
+    }
+    let offset = center[axis] - position[axis];
+    if offset <= radius {
+        within_radius(&points[..middle], depth + 1, center, radius, found);
+    }
+    if offset >= -radius {
+        within_radius(&points[middle + 1..], depth + 1, center, radius, found);
+    }
+}
+
+fn nearest(points: &[(Id, Point3<f32>)], depth: usize, point: Point3<f32>, best: &mut Option<(f32, Id)>) {
+    let mut consider = |&(id, position): &(Id, Point3<f32>)| {
+        let distance2 = position.distance2(point);
+        let closer = match *best {
+            Some((best_distance2, best_id)) => distance2 < best_distance2 || (distance2 == best_distance2 && id < best_id),
+            None => true,
+        };
+        if closer {
+            *best = Some((distance2, id));
+        }
+    };
+    if points.len() <= LEAF_POINTS {
+        points.iter().for_each(consider);
+        return;
+    }
+    let axis = depth % 3;
+    let middle = points.len() / 2;
+    consider(&points[middle]);
+    let offset = point[axis] - points[middle].1[axis];
+    let (near, far) = if offset <= 0.0 {
+        (&points[..middle], &points[middle + 1..])
+    } else {
+        (&points[middle + 1..], &points[..middle])
+    };
+    nearest(near, depth + 1, point, best);
+    if best.is_none_or(|(best_distance2, _)| offset * offset <= best_distance2) {
+        nearest(far, depth + 1, point, best);
+    }
+}
+
+impl KdTree {
+    /// Builds the tree over the positions of the living vertices of `mesh`,
+    /// in O(n log n).
+    pub fn new(mesh: &Mesh) -> Self {
+        KdTree::from_points(mesh.iter_vertices()
+            .map(|(vertex_id, vertex)| (vertex_id, vertex.position))
+            .collect())
+    }
+
+    /// Builds the tree over any points, such as face centers, keyed by the
+    /// ids the queries return.
+    pub fn from_points(mut points: Vec<(Id, Point3<f32>)>) -> Self {
+        build(&mut points, 0);
+        KdTree { points }
+    }
+
+    /// The id of the point closest to `point`, the lowest id on ties, or None
+    /// for an empty tree.
+    pub fn nearest(&self, point: Point3<f32>) -> Option<Id> {
+        let mut best = None;
+        nearest(&self.points, 0, point, &mut best);
+        best.map(|(_, id)| id)
+    }
+
+    /// The ids of the points within `radius` of `center`, border included, in
+    /// id order.
+    pub fn within_radius(&self, center: Point3<f32>, radius: f32) -> Vec<Id> {
+        let mut found = Vec::new();
+        if radius >= 0.0 {
+            within_radius(&self.points, 0, center, radius, &mut found);
+        }
+        found.sort();
+        found
+    }
+}
+
+impl Mesh {
+    /// A new `KdTree` over the current vertex positions, see `KdTree::new`.
+    pub fn build_kd_tree(&self) -> KdTree {
+        KdTree::new(self)
+    }
+
+    /// The living vertices within `radius` of `center`, border included, in id
+    /// order, whether or not the faces connect them, as soft selection and
+    /// clustering need. Larger meshes are searched with the
+    /// `cached_kd_tree`, built on the first query after an edit.
+    pub fn vertices_within_radius(&self, center: Point3<f32>, radius: f32) -> Vec<Id> {
+        if radius < 0.0 {
+            return Vec::new();
+        }
+        if self.vertex_count > BRUTE_FORCE_VERTICES {
+            return self.cached_kd_tree().within_radius(center, radius);
+        }
+        self.iter_vertices()
+            .filter(|&(_, vertex)| vertex.position.distance2(center) <= radius * radius)
+            .map(|(vertex_id, _)| vertex_id)
+            .collect()
+    }
+
+    /// Returns the living vertex closest to `point`, the lowest id on ties, or
+    /// None for an empty mesh. Larger meshes are searched with the
+    /// `cached_kd_tree`.
+    pub fn nearest_vertex(&self, point: Point3<f32>) -> Option<Id> {
+        if self.vertex_count > BRUTE_FORCE_VERTICES {
+            return self.cached_kd_tree().nearest(point);
+        }
+        self.iter_vertices()
+            .min_by(|a, b| a.1.position.distance2(point).partial_cmp(&b.1.position.distance2(point)).unwrap_or(Ordering::Equal))
+            .map(|(vertex_id, _)| vertex_id)
+    }
+
+    /// Returns the face whose center is closest to `point`, the lowest id on
+    /// ties, or None for a mesh without faces. Faces are compared by
+    /// `face_center`, so this is not necessarily the face under the closest
+    /// point on the surface. Larger meshes are searched with a `KdTree` over
+    /// the face centers, kept on the mesh like the `cached_kd_tree`.
+    pub fn nearest_face(&self, point: Point3<f32>) -> Option<Id> {
+        if self.face_count > BRUTE_FORCE_VERTICES {
+            return self.cached_face_center_tree().nearest(point);
+        }
+        FaceIterator::new(self)
+            .map(|face_id| (face_id, self.face_center(face_id).distance2(point)))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
+            .map(|(face_id, _)| face_id)
+    }
+}
@@ -1,16 +1,46 @@
 extern crate cgmath;
 extern crate fnv;
+#[cfg(all(feature = "mmap", unix))]
+extern crate libc;
 extern crate petgraph;
+#[cfg(feature = "parallel")]
+extern crate rayon;
 extern crate smallvec;
 
+pub mod attribute;
 pub mod bmesh;
+pub mod boundary;
+pub mod buffer;
+pub mod cache;
+pub mod bvh;
 pub mod debug;
+pub mod deform;
+pub mod distance;
+pub mod error;
+pub mod geom;
+pub mod graph;
+pub mod hash;
 pub mod iterator;
+pub mod kdtree;
+pub mod limit;
+pub mod material;
 pub mod mesh;
+pub mod orient;
 pub mod primitives;
+pub mod quality;
+pub mod sample;
+pub mod scheme;
+pub mod selection;
+pub mod simplify;
 pub mod skeletonmesh;
+pub mod slice;
+pub mod smooth;
+pub mod sparse;
 pub mod subdivide;
+pub mod tangent;
 pub mod triangulate;
 pub mod util;
+pub mod validate;
+pub mod voxel;
 pub mod wavefront;
 pub mod wrap;
@@ -0,0 +1,408 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use iterator::FaceHalfedgeIterator;
+use mesh::Id;
+use mesh::Mesh;
+use subdivide::CatmullClarkSubdivider;
+use subdivide::Subdivide;
+use subdivide::VertexSource;
+use util::*;
+
+/// Number of local subdivisions `eval_limit` performs around extraordinary
+/// vertices before it falls back to interpolating the refined face.
+const EVAL_LIMIT_MAX_DEPTH: usize = 8;
+
+/// Control points of a regular bicubic B-spline patch, indexed `[u][v]`.
+type PatchPoints = [[Point3<f32>; 4]; 4];
+
+/// Uniform cubic B-spline basis functions and their derivatives at `t`.
+fn bspline_basis(t: f32) -> ([f32; 4], [f32; 4]) {
+    let s = 1.0 - t;
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (
+        [
+            s * s * s / 6.0,
+            (3.0 * t3 - 6.0 * t2 + 4.0) / 6.0,
+            (-3.0 * t3 + 3.0 * t2 + 3.0 * t + 1.0) / 6.0,
+            t3 / 6.0,
+        ],
+        [
+            -s * s / 2.0,
+            (3.0 * t2 - 4.0 * t) / 2.0,
+            (-3.0 * t2 + 2.0 * t + 1.0) / 2.0,
+            t2 / 2.0,
+        ],
+    )
+}
+
+fn eval_patch(points: &PatchPoints, u: f32, v: f32) -> (Point3<f32>, Vector3<f32>) {
+    let (bu, du) = bspline_basis(u);
+    let (bv, dv) = bspline_basis(v);
+    let mut position = Vector3::zero();
+    let mut tangent_u = Vector3::zero();
+    let mut tangent_v = Vector3::zero();
+    for i in 0..4 {
+        for j in 0..4 {
+            let point = points[i][j].to_vec();
+            position += point * (bu[i] * bv[j]);
+            tangent_u += point * (du[i] * bv[j]);
+            tangent_v += point * (bu[i] * dv[j]);
+        }
+    }
+    (Point3::from_vec(position), tangent_u.cross(tangent_v).normalize())
+}
+
+/// Corners of a quad in its own (u, v) parameter space, in loop order.
+const QUAD_CORNERS: [(f32, f32); 4] = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+
+impl Mesh {
+    /// Collects the control points of the B-spline patch equivalent to the
+    /// limit surface of a quad, or None if the quad or any face around it is
+    /// not a quad, if a corner does not have valence 4, or if the face touches
+    /// the boundary.
+    fn regular_patch(&self, face_id: Id) -> Option<PatchPoints> {
+        let first_halfedge = self.face_first_halfedge_id(face_id)?;
+        let face_halfedges = FaceHalfedgeIterator::new(self, first_halfedge).into_vec();
+        if face_halfedges.len() != 4 {
+            return None;
+        }
+        for &halfedge_id in face_halfedges.iter() {
+            let vertex = self.halfedge_start_vertex(halfedge_id)?;
+            if vertex.halfedges.len() != 4 ||
+                    vertex.halfedges.iter().any(|&id| self.halfedge_opposite_id(id).is_none()) {
+                return None;
+            }
+        }
+        // Walk the 3x3 block of quads centered on the face. Each face is
+        // entered through a halfedge with known grid coordinates at both ends,
+        // which determines the coordinates of its other two corners.
+        let mut grid = [[0 as Id; 4]; 4];
+        let mut visited = Vec::new();
+        let mut pending = vec![(first_halfedge, (1, 1), (2, 1))];
+        while let Some((halfedge_id, from, to)) = pending.pop() {
+            let cell_face = self.halfedge_face_id(halfedge_id)?;
+            if visited.contains(&cell_face) {
+                continue;
+            }
+            let direction = (to.0 - from.0, to.1 - from.1);
+            let corners = [
+                from,
+                to,
+                (to.0 - direction.1, to.1 + direction.0),
+                (from.0 - direction.1, from.1 + direction.0),
+            ];
+            if corners.iter().any(|&(i, j)| !(0..4).contains(&i) || !(0..4).contains(&j)) {
+                continue;
+            }
+            let cell_halfedges = FaceHalfedgeIterator::new(self, halfedge_id).into_vec();
+            if cell_halfedges.len() != 4 {
+                return None;
+            }
+            visited.push(cell_face);
+            for k in 0..4 {
+                let vertex_id = self.halfedge_start_vertex_id(cell_halfedges[k])?;
+                let slot = &mut grid[corners[k].0 as usize][corners[k].1 as usize];
+                if *slot == 0 {
+                    *slot = vertex_id;
+                } else if *slot != vertex_id {
+                    return None;
+                }
+                if let Some(opposite_id) = self.halfedge_opposite_id(cell_halfedges[k]) {
+                    pending.push((opposite_id, corners[(k + 1) % 4], corners[k]));
+                }
+            }
+        }
+        if visited.len() != 9 {
+            return None;
+        }
+        let mut points = [[Point3::origin(); 4]; 4];
+        for i in 0..4 {
+            for j in 0..4 {
+                points[i][j] = self.vertex(grid[i][j]).unwrap().position;
+            }
+        }
+        Some(points)
+    }
+
+    /// Copies the faces within two rings of a face into a new mesh, the face
+    /// itself first, which is enough to subdivide the face and its direct
+    /// neighbors exactly.
+    fn limit_patch(&self, face_id: Id) -> Mesh {
+        let mut faces = vec![face_id];
+        let mut face_set = FnvHashSet::default();
+        face_set.insert(face_id);
+        for _ in 0..2 {
+            for i in 0..faces.len() {
+                for vertex_id in self.face_vertex_ids(faces[i]) {
+                    for &halfedge_id in self.vertex(vertex_id).unwrap().halfedges.iter() {
+                        let around_face = self.halfedge_face_id(halfedge_id).unwrap();
+                        if face_set.insert(around_face) {
+                            faces.push(around_face);
+                        }
+                    }
+                }
+            }
+        }
+        let mut patch = Mesh::new();
+        let mut vertex_map: FnvHashMap<Id, Id> = FnvHashMap::default();
+        for face_id in faces {
+            let vertices = self.face_vertex_ids(face_id).iter().map(|&vertex_id| {
+                let position = self.vertex(vertex_id).unwrap().position;
+                *vertex_map.entry(vertex_id).or_insert_with(|| patch.add_vertex(position))
+            }).collect();
+            patch.add_vertices(vertices);
+        }
+        patch
+    }
+
+    /// Position of a vertex on the Catmull-Clark limit surface.
+    ///
+    /// Interior vertices use the limit stencil
+    /// `(n * n * v + 4 * sum(e) + sum(d)) / (n * (n + 5))` where `e` are the
+    /// edge neighbors and `d` the opposite corners of the surrounding quads.
+    /// Faces that are not quads contribute their center instead, so the result
+    /// is only exact for quad meshes, which includes every mesh that has been
+    /// subdivided once. Boundary vertices use the curve rule
+    /// `(prev + 4 * v + next) / 6`, and vertices where several boundaries meet
+    /// stay in place.
+    pub fn vertex_limit_position(&self, vertex_id: Id) -> Point3<f32> {
+        let vertex = self.vertex(vertex_id).unwrap();
+        let mut boundary_neighbors = Vec::new();
+        for &halfedge_id in vertex.halfedges.iter() {
+            if self.halfedge_opposite_id(halfedge_id).is_none() {
+                let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+                boundary_neighbors.push(self.halfedge_start_vertex(next_id).unwrap().position);
+            }
+            let prev_id = self.halfedge_prev_id(halfedge_id).unwrap();
+            if self.halfedge_opposite_id(prev_id).is_none() {
+                boundary_neighbors.push(self.halfedge_start_vertex(prev_id).unwrap().position);
+            }
+        }
+        if boundary_neighbors.len() == 2 {
+            return Point3::from_vec((boundary_neighbors[0].to_vec() + vertex.position.to_vec() * 4.0 +
+                boundary_neighbors[1].to_vec()) / 6.0);
+        } else if !boundary_neighbors.is_empty() || vertex.halfedges.is_empty() {
+            return vertex.position;
+        }
+        let n = vertex.halfedges.len() as f32;
+        let mut total = vertex.position.to_vec() * (n * n);
+        for &halfedge_id in vertex.halfedges.iter() {
+            let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+            total += self.halfedge_start_vertex(next_id).unwrap().position.to_vec() * 4.0;
+            let face_id = self.halfedge_face_id(halfedge_id).unwrap();
+            let face_halfedges = FaceHalfedgeIterator::new(self, halfedge_id).into_vec();
+            let diagonal = if face_halfedges.len() == 4 {
+                self.halfedge_start_vertex(face_halfedges[2]).unwrap().position
+            } else {
+                self.face_center(face_id)
+            };
+            total += diagonal.to_vec();
+        }
+        Point3::from_vec(total / (n * (n + 5.0)))
+    }
+
+    /// Unit normal of the Catmull-Clark limit surface at a vertex, the cross
+    /// product of the two limit tangents
+    /// `sum(A * cos(2 * pi * i / n) * e[i] + (cos(2 * pi * i / n) + cos(2 * pi * (i + 1) / n)) * d[i])`
+    /// and the same with the cosines turned back by one step, where
+    /// `A = 1 + cos(2 * pi / n) + cos(pi / n) * sqrt(2 * (9 + cos(2 * pi / n)))`,
+    /// `e` are the edge neighbors counterclockwise and `d` the opposite
+    /// corners of the quads between them. The masks hold for every valence,
+    /// but like `vertex_limit_position` they are only exact for quad meshes,
+    /// faces that are not quads contributing their center. Boundary vertices
+    /// have no such masks and get the averaged face normal of `vertex_norm`.
+    pub fn vertex_limit_normal(&self, vertex_id: Id) -> Vector3<f32> {
+        let vertex = match self.vertex(vertex_id) {
+            Some(vertex) => vertex,
+            None => return Vector3::zero(),
+        };
+        let ordered = self.ordered_vertex_halfedges(vertex_id);
+        if ordered.is_empty() || ordered.len() != vertex.halfedges.len() ||
+                ordered.iter().any(|&id| self.halfedge_opposite_id(id).is_none()) {
+            return self.vertex_norm(vertex_id);
+        }
+        let n = ordered.len() as f32;
+        let step = 2.0 * std::f32::consts::PI / n;
+        let a = 1.0 + step.cos() + (step * 0.5).cos() * (2.0 * (9.0 + step.cos())).sqrt();
+        let mut tangent_first = Vector3::zero();
+        let mut tangent_second = Vector3::zero();
+        for (i, &halfedge_id) in ordered.iter().enumerate() {
+            let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+            let edge = self.halfedge_start_vertex(next_id).unwrap().position.to_vec();
+            let face_halfedges = FaceHalfedgeIterator::new(self, halfedge_id).into_vec();
+            let diagonal = if face_halfedges.len() == 4 {
+                self.halfedge_start_vertex(face_halfedges[2]).unwrap().position
+            } else {
+                self.face_center(self.halfedge_face_id(halfedge_id).unwrap())
+            }.to_vec();
+            let angle = step * i as f32;
+            tangent_first += edge * (a * angle.cos()) + diagonal * (angle.cos() + (angle + step).cos());
+            tangent_second += edge * (a * (angle - step).cos()) + diagonal * ((angle - step).cos() + angle.cos());
+        }
+        let normal = tangent_first.cross(tangent_second);
+        if normal.magnitude2() > 0.0 {
+            normal.normalize()
+        } else {
+            self.vertex_norm(vertex_id)
+        }
+    }
+
+    /// Limit normals of every living vertex that has faces, see
+    /// `vertex_limit_normal`.
+    pub fn catmull_clark_limit_normals(&self) -> FnvHashMap<Id, Vector3<f32>> {
+        self.vertices.iter()
+            .filter(|vertex| vertex.alive && !vertex.halfedges.is_empty())
+            .map(|vertex| (vertex.id, self.vertex_limit_normal(vertex.id)))
+            .collect()
+    }
+
+    /// Returns a copy of the mesh with every vertex moved to its position on
+    /// the Catmull-Clark limit surface, see `vertex_limit_position`. The
+    /// topology and all ids are unchanged.
+    pub fn catmull_clark_limit(&self) -> Mesh {
+        let mut mesh = self.duplicate();
+        for vertex in mesh.vertices.iter_mut() {
+            if vertex.alive {
+                vertex.position = self.vertex_limit_position(vertex.id);
+            }
+        }
+        mesh.mark_changed();
+        mesh
+    }
+
+    /// Subdivides once and measures how far the new level moves the limit
+    /// surface away from the previous one: the largest distance, along the
+    /// vertex normal, between the limit position of every output vertex and
+    /// the average of the limit positions of the input elements it came from.
+    /// Sliding within the surface is not counted, so flat regions measure 0.
+    fn subdivide_with_displacement(&self) -> (Mesh, f32) {
+        let (output, map) = CatmullClarkSubdivider::new(self).generate_with_provenance();
+        let mut displacement: f32 = 0.0;
+        for (&vertex_id, &source) in map.vertices.iter() {
+            let corners = match source {
+                VertexSource::Vertex(id) => vec![id],
+                VertexSource::Edge(id) => {
+                    let next_id = self.halfedge_next_id(id).unwrap();
+                    vec![self.halfedge_start_vertex_id(id).unwrap(), self.halfedge_start_vertex_id(next_id).unwrap()]
+                },
+                VertexSource::Face(id) => self.face_vertex_ids(id),
+            };
+            let previous: Vec<Point3<f32>> = corners.into_iter().map(|id| self.vertex_limit_position(id)).collect();
+            let offset = output.vertex_limit_position(vertex_id) - Point3::centroid(&previous);
+            let normal = output.vertex_norm(vertex_id);
+            if is_valid_norm(normal) {
+                displacement = displacement.max(offset.dot(normal).abs());
+            }
+        }
+        (output, displacement)
+    }
+
+    /// Applies `levels` rounds of subdivision like `subdivide_n` and also
+    /// returns the displacement of the last round, the largest distance a
+    /// vertex of the limit surface moves off the limit surface of the level
+    /// before it. Zero levels measure 0.
+    pub fn subdivide_n_with_displacement(&self, levels: usize) -> (Mesh, f32) {
+        let mut mesh = self.duplicate();
+        let mut displacement = 0.0;
+        for _ in 0..levels {
+            let (output, output_displacement) = mesh.subdivide_with_displacement();
+            mesh = output;
+            displacement = output_displacement;
+        }
+        (mesh, displacement)
+    }
+
+    /// Subdivides until a round displaces the surface by at most `tolerance`
+    /// (see `subdivide_n_with_displacement`) or `max_levels` rounds are done,
+    /// returning the result and the number of rounds applied.
+    pub fn subdivide_until(&self, max_levels: usize, tolerance: f32) -> (Mesh, usize) {
+        let mut mesh = self.duplicate();
+        for level in 1..max_levels + 1 {
+            let (output, displacement) = mesh.subdivide_with_displacement();
+            mesh = output;
+            if displacement <= tolerance {
+                return (mesh, level);
+            }
+        }
+        (mesh, max_levels)
+    }
+
+    /// Evaluates the Catmull-Clark limit surface of a quad at the parameters
+    /// `(u, v)` in [0, 1], returning the position and the unit normal. The
+    /// first corner of the face (the start of `face.halfedge`) is at (0, 0) and
+    /// `u` runs along the first side.
+    ///
+    /// Quads whose corners all have valence 4 are evaluated directly from the
+    /// equivalent bicubic B-spline patch. Other faces are subdivided locally
+    /// until the quad containing the parameters is regular; near an
+    /// extraordinary vertex this stops after a fixed depth and the refined quad
+    /// is interpolated bilinearly, which is accurate to well below the size of
+    /// the refined face. Faces on the boundary are handled the same way.
+    ///
+    /// For faces that are not quads the parameters address the quad that
+    /// subdivision creates at the face's first corner. Returns None when
+    /// `face_id` is not a living face.
+    pub fn eval_limit(&self, face_id: Id, u: f32, v: f32) -> Option<(Point3<f32>, Vector3<f32>)> {
+        self.face(face_id)?;
+        let mut refined: Option<Mesh> = None;
+        let mut face_id = face_id;
+        let (mut u, mut v) = (u, v);
+        for _ in 0..EVAL_LIMIT_MAX_DEPTH {
+            let mesh = refined.as_ref().unwrap_or(self);
+            if let Some(points) = mesh.regular_patch(face_id) {
+                return Some(eval_patch(&points, u, v));
+            }
+            let corners = mesh.face_vertex_ids(face_id);
+            // The patch holds the face as face 1, so its children are the
+            // first faces of the subdivided patch. The child created for the
+            // i-th side holds the corner at the end of that side and starts at
+            // the face center, followed by the middle of the side.
+            let subdivided = mesh.limit_patch(face_id).subdivide();
+            if corners.len() == 4 {
+                let corner = match (u >= 0.5, v >= 0.5) {
+                    (false, false) => 0,
+                    (true, false) => 1,
+                    (true, true) => 2,
+                    (false, true) => 3,
+                };
+                let side = (corner + 3) % 4;
+                let center = (0.5, 0.5);
+                let side_mid = (
+                    (QUAD_CORNERS[side].0 + QUAD_CORNERS[corner].0) * 0.5,
+                    (QUAD_CORNERS[side].1 + QUAD_CORNERS[corner].1) * 0.5,
+                );
+                let next_mid = (
+                    (QUAD_CORNERS[corner].0 + QUAD_CORNERS[(corner + 1) % 4].0) * 0.5,
+                    (QUAD_CORNERS[corner].1 + QUAD_CORNERS[(corner + 1) % 4].1) * 0.5,
+                );
+                let offset = (u - center.0, v - center.1);
+                let axis_u = (side_mid.0 - center.0, side_mid.1 - center.1);
+                let axis_v = (next_mid.0 - center.0, next_mid.1 - center.1);
+                // The axes are orthogonal and half a unit long.
+                u = (offset.0 * axis_u.0 + offset.1 * axis_u.1) * 4.0;
+                v = (offset.0 * axis_v.0 + offset.1 * axis_v.1) * 4.0;
+                face_id = side + 1;
+            } else {
+                face_id = corners.len();
+            }
+            refined = Some(subdivided);
+        }
+        let mesh = refined.as_ref().unwrap_or(self);
+        if let Some(points) = mesh.regular_patch(face_id) {
+            return Some(eval_patch(&points, u, v));
+        }
+        let p: Vec<Vector3<f32>> = mesh.face_vertex_ids(face_id).iter()
+            .map(|&id| mesh.vertex(id).unwrap().position.to_vec())
+            .collect();
+        if p.len() != 4 {
+            return None;
+        }
+        let position = p[0] * ((1.0 - u) * (1.0 - v)) + p[1] * (u * (1.0 - v)) +
+            p[2] * (u * v) + p[3] * ((1.0 - u) * v);
+        Some((Point3::from_vec(position), mesh.face_norm(face_id)))
+    }
+}
@@ -0,0 +1,109 @@
+use std::io;
+use std::io::prelude::*;
+use std::str::FromStr;
+
+/// A surface material as described by a Wavefront `.mtl` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Material {
+    pub name: String,
+
+    /// Diffuse color (`Kd`).
+    pub diffuse: [f32; 3],
+
+    /// Specular color (`Ks`).
+    pub specular: [f32; 3],
+
+    /// Specular exponent (`Ns`).
+    pub shininess: f32,
+
+    /// Texture paths as written in the file (`map_Kd`, `map_Ks` and
+    /// `map_Bump`), relative to the `.mtl` file.
+    pub diffuse_texture: Option<String>,
+    pub specular_texture: Option<String>,
+    pub bump_texture: Option<String>,
+}
+
+impl Material {
+    pub fn new(name: &str) -> Self {
+        Material {
+            name: name.to_string(),
+            diffuse: [1.0, 1.0, 1.0],
+            specular: [0.0, 0.0, 0.0],
+            shininess: 0.0,
+            diffuse_texture: None,
+            specular_texture: None,
+            bump_texture: None,
+        }
+    }
+}
+
+fn parse_color<'a, I: Iterator<Item = &'a str>>(words: I) -> [f32; 3] {
+    let mut color = [0.0; 3];
+    for (channel, word) in color.iter_mut().zip(words) {
+        *channel = f32::from_str(word).unwrap_or(0.0);
+    }
+    color
+}
+
+/// Returns everything after the keyword so paths containing spaces survive.
+/// Texture options such as `-s` are not interpreted.
+fn parse_texture(line: &str) -> Option<String> {
+    let path = line.split_once(char::is_whitespace)?.1.trim();
+    if path.is_empty() {
+        None
+    } else {
+        Some(path.to_string())
+    }
+}
+
+/// Reads the materials of a `.mtl` file. Unknown statements are skipped.
+pub fn read_mtl<R: BufRead>(reader: R) -> io::Result<Vec<Material>> {
+    let mut materials: Vec<Material> = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        let mut words = line.split_whitespace();
+        let keyword = match words.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        if keyword == "newmtl" {
+            materials.push(Material::new(line["newmtl".len()..].trim()));
+            continue;
+        }
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => continue,
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_color(words),
+            "Ks" => material.specular = parse_color(words),
+            "Ns" => material.shininess = words.next().and_then(|w| f32::from_str(w).ok()).unwrap_or(0.0),
+            "map_Kd" => material.diffuse_texture = parse_texture(line),
+            "map_Ks" => material.specular_texture = parse_texture(line),
+            "map_Bump" | "map_bump" | "bump" => material.bump_texture = parse_texture(line),
+            _ => ()
+        }
+    }
+    Ok(materials)
+}
+
+pub fn write_mtl<W: Write>(writer: &mut W, materials: &[Material]) -> io::Result<()> {
+    writeln!(writer, "# Export by meshlite")?;
+    for material in materials {
+        writeln!(writer, "newmtl {}", material.name)?;
+        writeln!(writer, "Kd {} {} {}", material.diffuse[0], material.diffuse[1], material.diffuse[2])?;
+        writeln!(writer, "Ks {} {} {}", material.specular[0], material.specular[1], material.specular[2])?;
+        writeln!(writer, "Ns {}", material.shininess)?;
+        if let Some(ref path) = material.diffuse_texture {
+            writeln!(writer, "map_Kd {}", path)?;
+        }
+        if let Some(ref path) = material.specular_texture {
+            writeln!(writer, "map_Ks {}", path)?;
+        }
+        if let Some(ref path) = material.bump_texture {
+            writeln!(writer, "map_Bump {}", path)?;
+        }
+    }
+    Ok(())
+}
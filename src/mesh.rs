@@ -1,7 +1,9 @@
+use cgmath::Point2;
 use cgmath::Point3;
 use cgmath::Vector3;
 use cgmath::prelude::*;
 use cgmath::Matrix4;
+use cgmath::Quaternion;
 use std::option::Option;
 use std::io;
 use std::vec::Vec;
@@ -11,12 +13,25 @@ use fnv::FnvHashSet;
 use fnv::FnvHashMap;
 use iterator::FaceHalfedgeIterator;
 use iterator::FaceIterator;
+use iterator::FaceTriangleIterator;
 use util::*;
+use attribute::AttributeStore;
+use cache::CotangentCache;
+use cache::DerivedCache;
+use std::sync::Mutex;
+use error::TopologyError;
+use material::Material;
 use smallvec::SmallVec;
 use std::ops::Add;
 use std::ops::AddAssign;
 use std::f32;
+use std::cmp::Ordering;
+use std::mem;
 
+/// Ids are 1-based indices into the element vectors of a mesh, with 0
+/// meaning none. Removed elements keep their ids, so the largest id of a kind
+/// is the number of elements ever added, which fits a `usize` as long as the
+/// elements fit in memory.
 pub type Id = usize;
 
 // Optimized for quad meshes, since that is very common in high-poly models
@@ -29,7 +44,7 @@ pub type Id = usize;
 // generics and/or macros to specialize some parts of the implementation?
 const VERTEX_HALFEDGE_INLINE_COUNT: usize = 4;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Vertex {
     pub id: Id,
     pub position: Point3<f32>,
@@ -45,7 +60,7 @@ pub struct Vertex {
     pub source: i32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Face {
     pub id: Id,
     pub halfedge: Id,
@@ -54,7 +69,7 @@ pub struct Face {
     pub alive: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Halfedge {
     pub id: Id,
     pub vertex: Id,
@@ -108,13 +123,46 @@ pub type FacePair = EdgeEndpoints;
 
 #[derive(Debug)]
 pub struct Mesh {
+    /// Storage slots, index `id - 1`. Removed elements keep their slot with
+    /// `alive` cleared, so `len()` counts them too.
     pub vertices: Vec<Vertex>,
+
+    /// Number of living vertices, always at most `vertices.len()`. The same
+    /// goes for `face_count` and `halfedge_count`.
     pub vertex_count: usize,
     pub faces: Vec<Face>,
     pub face_count: usize,
     pub halfedges: Vec<Halfedge>,
     pub halfedge_count: usize,
-    pub edges: FnvHashMap<EdgeEndpoints, Id>
+    pub edges: FnvHashMap<EdgeEndpoints, Id>,
+
+    /// Texture coordinates of face corners, keyed by the halfedge starting at
+    /// the corner. Stored per corner so seams can have different coordinates
+    /// on each side.
+    pub halfedge_uvs: FnvHashMap<Id, Point2<f32>>,
+
+    pub materials: Vec<Material>,
+
+    /// Index into `materials` for the faces that have a material.
+    pub face_materials: FnvHashMap<Id, usize>,
+
+    /// User defined bitmask per vertex, such as selections or pinned flags.
+    /// Vertices without an entry have no tags.
+    pub vertex_tags: FnvHashMap<Id, u32>,
+
+    /// Named user attributes of the vertices, faces, halfedges and edges.
+    pub attributes: AttributeStore,
+
+    /// Bumped by every method changing the positions or the connectivity,
+    /// see `generation`.
+    generation: u64,
+
+    /// Normals and BVH reused by `cached_vertex_norm` and `cached_bvh`,
+    /// never copied with the mesh.
+    derived_cache: Mutex<DerivedCache>,
+
+    /// Weights reused by `cotangent_weights`, never copied with the mesh.
+    cotangent_cache: Mutex<CotangentCache>
 }
 
 impl Mesh {
@@ -126,12 +174,140 @@ impl Mesh {
             face_count: 0,
             halfedges: Vec::new(),
             halfedge_count: 0,
-            edges: FnvHashMap::default()
+            edges: FnvHashMap::default(),
+            halfedge_uvs: FnvHashMap::default(),
+            materials: Vec::new(),
+            face_materials: FnvHashMap::default(),
+            vertex_tags: FnvHashMap::default(),
+            attributes: AttributeStore::new(),
+            generation: 0,
+            derived_cache: Mutex::new(DerivedCache::default()),
+            cotangent_cache: Mutex::new(CotangentCache::default())
+        }
+    }
+
+    /// Changes whenever a method moves a vertex or changes the connectivity,
+    /// so data derived from the mesh can tell whether it is still current. The
+    /// element vectors are public, so code editing them directly has to call
+    /// `mark_changed` itself.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub(crate) fn cotangent_cache(&self) -> &Mutex<CotangentCache> {
+        &self.cotangent_cache
+    }
+
+    pub(crate) fn derived_cache(&self) -> &Mutex<DerivedCache> {
+        &self.derived_cache
+    }
+
+    pub(crate) fn derived_cache_mut(&mut self) -> &mut DerivedCache {
+        self.derived_cache.get_mut().unwrap()
+    }
+
+    /// Bumps the `generation`, for after editing the elements directly.
+    pub fn mark_changed(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Replaces the mesh by `other`, moving the `generation` on from the
+    /// current one rather than taking over the count of `other`, so data
+    /// derived from the old mesh does not look current.
+    pub(crate) fn replace_with(&mut self, other: Mesh) {
+        let generation = self.generation;
+        *self = other;
+        self.generation = generation.wrapping_add(1);
+    }
+
+    /// Whether the mesh has no vertices.
+    pub fn is_empty(&self) -> bool {
+        self.vertex_count == 0
+    }
+
+    /// Removes every element, side table and material, keeping the
+    /// allocations so the mesh can be filled again without reallocating.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.vertex_count = 0;
+        self.faces.clear();
+        self.face_count = 0;
+        self.halfedges.clear();
+        self.halfedge_count = 0;
+        self.edges.clear();
+        self.halfedge_uvs.clear();
+        self.materials.clear();
+        self.face_materials.clear();
+        self.vertex_tags.clear();
+        self.attributes.clear();
+        self.mark_changed();
+    }
+
+    /// Copies the mesh exactly, keeping every id and removed element, whereas
+    /// `clone` rebuilds the mesh with compact ids.
+    pub fn duplicate(&self) -> Self {
+        Mesh {
+            vertices: self.vertices.clone(),
+            vertex_count: self.vertex_count,
+            faces: self.faces.clone(),
+            face_count: self.face_count,
+            halfedges: self.halfedges.clone(),
+            halfedge_count: self.halfedge_count,
+            edges: self.edges.clone(),
+            halfedge_uvs: self.halfedge_uvs.clone(),
+            materials: self.materials.clone(),
+            face_materials: self.face_materials.clone(),
+            vertex_tags: self.vertex_tags.clone(),
+            attributes: self.attributes.clone(),
+            generation: self.generation,
+            derived_cache: Mutex::new(DerivedCache::default()),
+            cotangent_cache: Mutex::new(CotangentCache::default())
+        }
+    }
+
+    /// Copies the connectivity of the mesh with every position set to the
+    /// origin, keeping all ids. Positions can be streamed in afterwards with
+    /// `set_positions`.
+    pub fn clone_topology_without_positions(&self) -> Self {
+        let mut mesh = self.duplicate();
+        for vertex in mesh.vertices.iter_mut() {
+            vertex.position = Point3::new(0.0, 0.0, 0.0);
+        }
+        mesh.mark_changed();
+        mesh
+    }
+
+    /// Positions of the living vertices in id order.
+    pub fn positions(&self) -> Vec<Point3<f32>> {
+        self.vertices.iter().filter(|vertex| vertex.alive).map(|vertex| vertex.position).collect()
+    }
+
+    /// Sets the positions of the living vertices in id order, the same order
+    /// `positions` returns them in.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is not exactly one position per living vertex.
+    pub fn set_positions(&mut self, positions: &[Point3<f32>]) {
+        assert_eq!(self.vertex_count, positions.len());
+        for (vertex, &position) in self.vertices.iter_mut().filter(|vertex| vertex.alive).zip(positions.iter()) {
+            vertex.position = position;
         }
+        self.mark_changed();
+    }
+
+    /// Constructs an empty mesh with room for the given number of elements.
+    pub fn with_capacity(vertex_count: usize, face_count: usize, halfedge_count: usize) -> Self {
+        let mut mesh = Mesh::new();
+        mesh.vertices.reserve(vertex_count);
+        mesh.faces.reserve(face_count);
+        mesh.halfedges.reserve(halfedge_count);
+        mesh.edges.reserve(halfedge_count / 2);
+        mesh
     }
 
     pub fn vertex(&self, id: Id) -> Option<&Vertex> {
-        if 0 == id {
+        if 0 == id || id > self.vertices.len() {
             return None;
         }
         {
@@ -144,7 +320,7 @@ impl Mesh {
     }
 
     pub fn vertex_mut(&mut self, id: Id) -> Option<&mut Vertex> {
-        if 0 == id {
+        if 0 == id || id > self.vertices.len() {
             return None;
         }
         {
@@ -153,6 +329,7 @@ impl Mesh {
                 return None;
             }
         }
+        self.mark_changed();
         Some(&mut self.vertices[id - 1])
     }
 
@@ -171,6 +348,8 @@ impl Mesh {
             self.vertex(next.vertex).unwrap().position)
     }
 
+    /// Average of the corners of a face. Well defined for degenerate faces as
+    /// well.
     pub fn face_center(&self, id: Id) -> Point3<f32> {
         let face = self.face(id).unwrap();
         let mut points = SmallVec::<[Point3<f32>; 4]>::new();
@@ -182,6 +361,8 @@ impl Mesh {
         Point3::centroid(&points)
     }
 
+    /// Unit normal of a face, or a zero vector for a degenerate face (zero
+    /// area or collinear corners) instead of NaN.
     pub fn face_norm(&self, id: Id) -> Vector3<f32> {
         let face = self.face(id).unwrap();
         let mut points = Vec::new();
@@ -193,18 +374,68 @@ impl Mesh {
         if points.len() < 3 {
             return Vector3::zero();
         } else if points.len() == 3 {
-            return norm(points[0], points[1], points[2]);
+            let n = norm(points[0], points[1], points[2]);
+            return if is_valid_norm(n) { n } else { Vector3::zero() };
         }
         let mut total = Vector3::zero();
         for i in 0..points.len() {
             let n = norm(points[i], points[(i + 1) % points.len()], points[(i + 2) % points.len()]);
-            total += n;
+            if is_valid_norm(n) {
+                total += n;
+            }
+        }
+        if total.magnitude2() < SMALL_NUM {
+            return Vector3::zero();
         }
         total.normalize()
     }
 
+    /// Area of a face, summed over the triangles of its fan.
+    pub fn face_area(&self, id: Id) -> f32 {
+        let mut total = Vector3::zero();
+        for triangle in FaceTriangleIterator::new(self, id) {
+            let corner = |index: usize| self.vertex(triangle[index]).unwrap().position;
+            total += (corner(1) - corner(0)).cross(corner(2) - corner(0));
+        }
+        total.magnitude() * 0.5
+    }
+
+    /// Average of the normals of the faces around a vertex, or a zero vector for
+    /// a vertex without faces.
+    pub fn vertex_norm(&self, id: Id) -> Vector3<f32> {
+        let mut total = Vector3::zero();
+        for &halfedge_id in self.vertex(id).unwrap().halfedges.iter() {
+            let normal = self.face_norm(self.halfedge_face_id(halfedge_id).unwrap());
+            if is_valid_norm(normal) {
+                total += normal;
+            }
+        }
+        if total.is_zero() {
+            return total;
+        }
+        total.normalize()
+    }
+
+    /// Returns one halfedge for every edge where the normals of the two faces
+    /// sharing it differ by more than `angle_degrees`. Boundary edges are never
+    /// sharp.
+    pub fn sharp_edges(&self, angle_degrees: f32) -> Vec<Id> {
+        let min_cos = angle_degrees.to_radians().cos();
+        let mut sharp = Vec::new();
+        for halfedge in self.halfedges.iter() {
+            if !halfedge.alive || halfedge.opposite == 0 || halfedge.opposite < halfedge.id {
+                continue;
+            }
+            let opposite_face = self.halfedge_face_id(halfedge.opposite).unwrap();
+            if self.face_norm(halfedge.face).dot(self.face_norm(opposite_face)) < min_cos {
+                sharp.push(halfedge.id);
+            }
+        }
+        sharp
+    }
+
     pub fn face(&self, id: Id) -> Option<&Face> {
-        if 0 == id {
+        if 0 == id || id > self.faces.len() {
             return None;
         }
         {
@@ -326,6 +557,45 @@ impl Mesh {
         None
     }
 
+    /// Returns the outgoing halfedges of a vertex in counter-clockwise order,
+    /// seen from the side the faces are facing. The walk follows
+    /// `prev.opposite` from one halfedge to the next; `opposite.next` would
+    /// visit the same fan clockwise.
+    ///
+    /// On a boundary vertex the walk starts from the outgoing boundary
+    /// halfedge (the one without an opposite) so the whole fan is covered. On a
+    /// non-manifold vertex only the fan containing the first boundary halfedge
+    /// (or the first halfedge) is returned, so the result is shorter than
+    /// `vertex.halfedges`.
+    pub fn ordered_vertex_halfedges(&self, vertex_id: Id) -> Vec<Id> {
+        let mut ordered = Vec::new();
+        let vertex = match self.vertex(vertex_id) {
+            Some(vertex) => vertex,
+            None => return ordered,
+        };
+        let start_id = match vertex.halfedges.iter().find(|&&id| self.halfedge_opposite_id(id).is_none()) {
+            Some(&id) => id,
+            None => match vertex.halfedges.first() {
+                Some(&id) => id,
+                None => return ordered,
+            },
+        };
+        let mut halfedge_id = start_id;
+        loop {
+            ordered.push(halfedge_id);
+            if ordered.len() >= vertex.halfedges.len() {
+                break;
+            }
+            match self.halfedge_prev_id(halfedge_id).and_then(|prev_id| self.halfedge_opposite_id(prev_id)) {
+                Some(next_id) if next_id != start_id && self.halfedge_start_vertex_id(next_id) == Some(vertex_id) => {
+                    halfedge_id = next_id;
+                },
+                _ => break,
+            }
+        }
+        ordered
+    }
+
     pub fn remove_face(&mut self, id: Id) {
         let halfedge_collection = FaceHalfedgeIterator::new(self, self.face_first_halfedge_id(id).unwrap()).into_vec();
         self.remove_halfedges_from_edges(&halfedge_collection);
@@ -361,8 +631,88 @@ impl Mesh {
         self.face_count -= 1;
     }
 
+    /// Connects two non-adjacent corners of a face with a new edge, splitting
+    /// the face in two. The original face keeps the side starting at `v0`, the
+    /// returned new face keeps the side starting at `v1`. The new face gets
+    /// the material and face attributes of the original, and the halfedges of
+    /// the new edge the UVs and halfedge attributes of the corners they start
+    /// at.
+    ///
+    /// Fails with `InvalidElement` for a removed face or when `v0` and `v1`
+    /// are the same, with `ElementNotOnFace` when either is not a corner of
+    /// the face, and with `WouldCreateDuplicateEdge` when they are already
+    /// connected, by a side of the face or elsewhere.
+    pub fn split_face(&mut self, face_id: Id, v0: Id, v1: Id) -> Result<Id, TopologyError> {
+        let face_halfedge = match self.face(face_id) {
+            Some(face) => face.halfedge,
+            None => return Err(TopologyError::InvalidElement { id: face_id }),
+        };
+        let halfedges = FaceHalfedgeIterator::new(self, face_halfedge).into_vec();
+        let find_halfedge = |vertex_id: Id| {
+            halfedges.iter().position(|&id| self.halfedge_start_vertex_id(id) == Some(vertex_id))
+                .ok_or(TopologyError::ElementNotOnFace { face: face_id, element: vertex_id })
+        };
+        let first_index = find_halfedge(v0)?;
+        let second_index = find_halfedge(v1)?;
+        let count = halfedges.len();
+        if first_index == second_index {
+            return Err(TopologyError::InvalidElement { id: v1 });
+        }
+        if (first_index + 1) % count == second_index || (second_index + 1) % count == first_index ||
+                self.edges.contains_key(&EdgeEndpoints::new(v0, v1)) {
+            return Err(TopologyError::WouldCreateDuplicateEdge { first: v0, second: v1 });
+        }
+        let first_halfedge = halfedges[first_index];
+        let second_halfedge = halfedges[second_index];
+        let first_prev = self.halfedge_prev_id(first_halfedge).unwrap();
+        let second_prev = self.halfedge_prev_id(second_halfedge).unwrap();
+        let new_face_id = self.add_face();
+        // Closes the loop of the original face, from v1 back to v0.
+        let old_side = self.add_halfedge();
+        // Closes the loop of the new face, from v0 to v1.
+        let new_side = self.add_halfedge();
+        {
+            let halfedge = self.halfedge_mut(old_side).unwrap();
+            halfedge.vertex = v1;
+            halfedge.face = face_id;
+            halfedge.prev = second_prev;
+            halfedge.next = first_halfedge;
+        }
+        {
+            let halfedge = self.halfedge_mut(new_side).unwrap();
+            halfedge.vertex = v0;
+            halfedge.face = new_face_id;
+            halfedge.prev = first_prev;
+            halfedge.next = second_halfedge;
+        }
+        self.halfedge_mut(second_prev).unwrap().next = old_side;
+        self.halfedge_mut(first_halfedge).unwrap().prev = old_side;
+        self.halfedge_mut(first_prev).unwrap().next = new_side;
+        self.halfedge_mut(second_halfedge).unwrap().prev = new_side;
+        self.pair_halfedges(old_side, new_side);
+        self.vertex_mut(v1).unwrap().halfedges.push(old_side);
+        self.vertex_mut(v0).unwrap().halfedges.push(new_side);
+        self.edges.insert(EdgeEndpoints::new(v0, v1), new_side);
+        for halfedge_id in FaceHalfedgeIterator::new(self, new_side).into_vec() {
+            self.halfedge_mut(halfedge_id).unwrap().face = new_face_id;
+        }
+        self.face_mut(face_id).unwrap().halfedge = first_halfedge;
+        self.face_mut(new_face_id).unwrap().halfedge = new_side;
+        let material = self.face_material(face_id);
+        self.set_face_material(new_face_id, material);
+        // The new sides start at the corners of v1 and v0.
+        for &(corner, side) in [(second_halfedge, old_side), (first_halfedge, new_side)].iter() {
+            if let Some(uv) = self.halfedge_uv(corner) {
+                self.set_halfedge_uv(side, uv);
+            }
+        }
+        self.attributes.copy_faces(&[(face_id, new_face_id)]);
+        self.attributes.copy_halfedges(&[(second_halfedge, old_side), (first_halfedge, new_side)]);
+        Ok(new_face_id)
+    }
+
     pub fn face_mut(&mut self, id: Id) -> Option<&mut Face> {
-        if 0 == id {
+        if 0 == id || id > self.faces.len() {
             return None;
         }
         {
@@ -371,11 +721,12 @@ impl Mesh {
                 return None;
             }
         }
+        self.mark_changed();
         Some(&mut self.faces[id - 1])
     }
 
     pub fn halfedge(&self, id: Id) -> Option<&Halfedge> {
-        if 0 == id {
+        if 0 == id || id > self.halfedges.len() {
             return None;
         }
         {
@@ -387,8 +738,49 @@ impl Mesh {
         Some(&self.halfedges[id - 1])
     }
 
+    pub fn halfedge_uv(&self, id: Id) -> Option<Point2<f32>> {
+        self.halfedge_uvs.get(&id).cloned()
+    }
+
+    pub fn set_halfedge_uv(&mut self, id: Id, uv: Point2<f32>) {
+        self.halfedge_uvs.insert(id, uv);
+    }
+
+    pub fn vertex_tags(&self, id: Id) -> u32 {
+        self.vertex_tags.get(&id).cloned().unwrap_or(0)
+    }
+
+    pub fn set_vertex_tags(&mut self, id: Id, tags: u32) {
+        if tags == 0 {
+            self.vertex_tags.remove(&id);
+        } else {
+            self.vertex_tags.insert(id, tags);
+        }
+    }
+
+    pub fn material(&self, index: usize) -> Option<&Material> {
+        self.materials.get(index)
+    }
+
+    /// Appends a material and returns its index.
+    pub fn add_material(&mut self, material: Material) -> usize {
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    pub fn face_material(&self, face_id: Id) -> Option<usize> {
+        self.face_materials.get(&face_id).cloned()
+    }
+
+    pub fn set_face_material(&mut self, face_id: Id, material: Option<usize>) {
+        match material {
+            Some(material) => self.face_materials.insert(face_id, material),
+            None => self.face_materials.remove(&face_id),
+        };
+    }
+
     pub fn halfedge_mut(&mut self, id: Id) -> Option<&mut Halfedge> {
-        if 0 == id {
+        if 0 == id || id > self.halfedges.len() {
             return None;
         }
         {
@@ -397,6 +789,7 @@ impl Mesh {
                 return None;
             }
         }
+        self.mark_changed();
         Some(&mut self.halfedges[id - 1])
     }
 
@@ -412,6 +805,7 @@ impl Mesh {
             source: -1,
         });
         self.vertex_count += 1;
+        self.mark_changed();
         new_id
     }
 
@@ -427,6 +821,7 @@ impl Mesh {
             alive: true,
         });
         self.halfedge_count += 1;
+        self.mark_changed();
         new_id
     }
 
@@ -463,6 +858,7 @@ impl Mesh {
             alive: true,
         });
         self.face_count += 1;
+        self.mark_changed();
         new_id
     }
 
@@ -628,6 +1024,520 @@ impl Mesh {
         self
     }
 
+    /// Returns the corners of a face in loop order, starting from the start
+    /// vertex of `face.halfedge`. Empty for a dead face.
+    pub fn face_vertex_ids(&self, face_id: Id) -> Vec<Id> {
+        match self.face(face_id) {
+            Some(face) => FaceHalfedgeIterator::new(self, face.halfedge)
+                .map(|id| self.halfedge_start_vertex_id(id).unwrap())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn revive_vertex(&mut self, id: Id) {
+        let vertex = &mut self.vertices[id - 1];
+        if !vertex.alive {
+            vertex.alive = true;
+            self.vertex_count += 1;
+            self.mark_changed();
+        }
+    }
+
+    /// Points the edge map entry of an edge at one of its living halfedges, or
+    /// drops the entry if no face uses the edge anymore.
+    fn repair_edge(&mut self, endpoints: EdgeEndpoints) {
+        let mut found = None;
+        for &(from, to) in [(endpoints.low, endpoints.high), (endpoints.high, endpoints.low)].iter() {
+            if let Some(vertex) = self.vertex(from) {
+                found = vertex.halfedges.iter().cloned().find(|&id| {
+                    self.halfedge_next_id(id).and_then(|next_id| self.halfedge_start_vertex_id(next_id)) == Some(to)
+                });
+            }
+            if found.is_some() {
+                break;
+            }
+        }
+        match found {
+            Some(halfedge_id) => {
+                self.edges.insert(endpoints, halfedge_id);
+            },
+            None => {
+                self.edges.remove(&endpoints);
+            },
+        }
+    }
+
+    /// Removes the `old` faces and adds the `new` vertex loops in their place,
+    /// returning the ids of the added faces. Edges shared with the remaining
+    /// faces are paired with the added faces again, and vertices used by the
+    /// new loops stay alive even if all of their old faces were removed.
+    pub fn replace_faces(&mut self, old: &[Id], new: &[Vec<Id>]) -> Vec<Id> {
+        let mut endpoints_set = FnvHashSet::default();
+        for &face_id in old {
+            let halfedge_id = match self.face(face_id) {
+                Some(face) => face.halfedge,
+                None => continue,
+            };
+            for id in FaceHalfedgeIterator::new(self, halfedge_id).into_vec() {
+                let next_id = self.halfedge_next_id(id).unwrap();
+                endpoints_set.insert(EdgeEndpoints::new(self.halfedge_start_vertex_id(id).unwrap(),
+                    self.halfedge_start_vertex_id(next_id).unwrap()));
+            }
+            self.remove_face(face_id);
+        }
+        for endpoints in endpoints_set {
+            self.repair_edge(endpoints);
+        }
+        let mut added_faces = Vec::new();
+        for vertices in new {
+            for &vertex_id in vertices {
+                self.revive_vertex(vertex_id);
+            }
+            added_faces.push(self.add_vertices(vertices.clone()));
+        }
+        added_faces
+    }
+
+    /// Removes the vertices no face uses, as left behind by imports with stray
+    /// `v` lines, and returns how many were removed.
+    pub fn remove_isolated_vertices(&mut self) -> usize {
+        let mut removed = 0;
+        for vertex in self.vertices.iter_mut() {
+            if vertex.alive && vertex.halfedges.is_empty() {
+                vertex.alive = false;
+                self.vertex_tags.remove(&vertex.id);
+                removed += 1;
+            }
+        }
+        self.vertex_count -= removed;
+        if removed > 0 {
+            self.mark_changed();
+        }
+        removed
+    }
+
+    /// Merges vertex `remove` into `keep`: the halfedges of `remove` start
+    /// from `keep` afterwards, edges that now join the same two vertices are
+    /// paired, and `remove` is deleted. Both vertices keep their faces, so
+    /// stitching a seam closes it one vertex pair at a time; the vertices in
+    /// between may briefly have two fans.
+    ///
+    /// Fails without changing the mesh when the vertices share a face, or when
+    /// two halfedges would run the same way along one edge, which means more
+    /// than two faces or opposite windings meeting there.
+    pub fn merge_vertices(&mut self, keep: Id, remove: Id) -> Result<(), TopologyError> {
+        for &id in [keep, remove].iter() {
+            if self.vertex(id).is_none() {
+                return Err(TopologyError::InvalidElement { id });
+            }
+        }
+        if keep == remove {
+            return Err(TopologyError::InvalidElement { id: remove });
+        }
+        let outgoing = self.vertex(remove).unwrap().halfedges.clone();
+        let incoming: Vec<Id> = outgoing.iter().map(|&halfedge_id| self.halfedge_prev_id(halfedge_id).unwrap()).collect();
+        for &halfedge_id in outgoing.iter() {
+            for id in FaceHalfedgeIterator::new(self, halfedge_id) {
+                if self.halfedge_start_vertex_id(id).unwrap() == keep {
+                    return Err(TopologyError::NonManifoldResult);
+                }
+            }
+        }
+        let mut directed = HashMap::new();
+        let renamed = |id: Id| if id == remove { keep } else { id };
+        for &vertex_id in [keep, remove].iter() {
+            for &halfedge_id in self.vertex(vertex_id).unwrap().halfedges.iter() {
+                let prev_id = self.halfedge_prev_id(halfedge_id).unwrap();
+                let ends = [(halfedge_id, self.halfedge_next_id(halfedge_id).unwrap()), (prev_id, halfedge_id)];
+                for &(from, to) in ends.iter() {
+                    let key = (renamed(self.halfedge_start_vertex_id(from).unwrap()),
+                        renamed(self.halfedge_start_vertex_id(to).unwrap()));
+                    if directed.insert(key, from).is_some_and(|other| other != from) {
+                        return Err(TopologyError::NonManifoldResult);
+                    }
+                }
+            }
+        }
+        let affected: Vec<Id> = outgoing.iter().chain(incoming.iter()).cloned().collect();
+        for &halfedge_id in affected.iter() {
+            let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+            self.edges.remove(&EdgeEndpoints::new(self.halfedge_start_vertex_id(halfedge_id).unwrap(),
+                self.halfedge_start_vertex_id(next_id).unwrap()));
+            if let Some(opposite) = self.halfedge_opposite_id(halfedge_id) {
+                self.unpair_halfedges(halfedge_id, opposite);
+            }
+        }
+        for &halfedge_id in outgoing.iter() {
+            self.halfedge_mut(halfedge_id).unwrap().vertex = keep;
+        }
+        self.vertex_mut(keep).unwrap().halfedges.extend(outgoing);
+        {
+            let vertex = self.vertex_mut(remove).unwrap();
+            vertex.halfedges.clear();
+            vertex.alive = false;
+        }
+        self.vertex_count -= 1;
+        self.vertex_tags.remove(&remove);
+        for &halfedge_id in affected.iter() {
+            let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+            let endpoints = EdgeEndpoints::new(self.halfedge_start_vertex_id(halfedge_id).unwrap(),
+                self.halfedge_start_vertex_id(next_id).unwrap());
+            match self.edges.get(&endpoints) {
+                Some(&other) => {
+                    if other != halfedge_id && self.halfedge_opposite_id(other).is_none() {
+                        self.pair_halfedges(halfedge_id, other);
+                    }
+                },
+                None => {
+                    self.edges.insert(endpoints, halfedge_id);
+                },
+            }
+        }
+        self.mark_changed();
+        Ok(())
+    }
+
+    /// Whether the ids of the vertices, faces and halfedges run from 1 to
+    /// their count with nothing removed in between, as in a freshly built
+    /// mesh such as the output of subdivision. Removing elements leaves
+    /// their ids unused until `compact`.
+    pub fn is_compact(&self) -> bool {
+        self.vertices.iter().all(|vertex| vertex.alive) &&
+            self.faces.iter().all(|face| face.alive) &&
+            self.halfedges.iter().all(|halfedge| halfedge.alive)
+    }
+
+    /// Drops the removed vertices, faces and halfedges and renumbers the rest
+    /// in their current order, updating every reference and side table.
+    /// Unlike `clone`, vertices without faces are kept.
+    pub fn compact(&mut self) {
+        if self.is_compact() {
+            return;
+        }
+        fn new_ids(alive: &[bool]) -> Vec<Id> {
+            let mut next_id = 0;
+            let mut ids = vec![0];
+            for &alive in alive.iter() {
+                ids.push(if alive {
+                    next_id += 1;
+                    next_id
+                } else {
+                    0
+                });
+            }
+            ids
+        }
+        let vertex_ids = new_ids(&self.vertices.iter().map(|vertex| vertex.alive).collect::<Vec<bool>>());
+        let face_ids = new_ids(&self.faces.iter().map(|face| face.alive).collect::<Vec<bool>>());
+        let halfedge_ids = new_ids(&self.halfedges.iter().map(|halfedge| halfedge.alive).collect::<Vec<bool>>());
+        let old_attributes = mem::take(&mut self.attributes);
+        let vertex_targets: Vec<(Id, Vec<(Id, f32)>)> = (1..vertex_ids.len())
+            .filter(|&id| vertex_ids[id] > 0)
+            .map(|id| (vertex_ids[id], vec![(id, 1.0)]))
+            .collect();
+        let face_targets: Vec<(Id, Vec<(Id, f32)>)> = (1..face_ids.len())
+            .filter(|&id| face_ids[id] > 0)
+            .map(|id| (face_ids[id], vec![(id, 1.0)]))
+            .collect();
+        let halfedge_targets: Vec<(Id, Vec<(Id, f32)>)> = (1..halfedge_ids.len())
+            .filter(|&id| halfedge_ids[id] > 0)
+            .map(|id| (halfedge_ids[id], vec![(id, 1.0)]))
+            .collect();
+        let edge_targets: Vec<(EdgeEndpoints, Vec<(EdgeEndpoints, f32)>)> = self.edges.keys()
+            .filter(|endpoints| vertex_ids[endpoints.low] > 0 && vertex_ids[endpoints.high] > 0)
+            .map(|endpoints| (EdgeEndpoints::new(vertex_ids[endpoints.low], vertex_ids[endpoints.high]), vec![(endpoints.clone(), 1.0)]))
+            .collect();
+        self.attributes.resample_vertices(&old_attributes, &vertex_targets);
+        self.attributes.resample_faces(&old_attributes, &face_targets);
+        self.attributes.resample_halfedges(&old_attributes, &halfedge_targets);
+        self.attributes.resample_edges(&old_attributes, &edge_targets);
+
+        self.vertices.retain(|vertex| vertex.alive);
+        for vertex in self.vertices.iter_mut() {
+            vertex.id = vertex_ids[vertex.id];
+            vertex.prev = vertex_ids[vertex.prev];
+            vertex.next = vertex_ids[vertex.next];
+            vertex.halfedges.retain(|halfedge_id| halfedge_ids[*halfedge_id] > 0);
+            for halfedge_id in vertex.halfedges.iter_mut() {
+                *halfedge_id = halfedge_ids[*halfedge_id];
+            }
+        }
+        self.faces.retain(|face| face.alive);
+        for face in self.faces.iter_mut() {
+            face.id = face_ids[face.id];
+            face.halfedge = halfedge_ids[face.halfedge];
+            face.prev = face_ids[face.prev];
+            face.next = face_ids[face.next];
+        }
+        self.halfedges.retain(|halfedge| halfedge.alive);
+        for halfedge in self.halfedges.iter_mut() {
+            halfedge.id = halfedge_ids[halfedge.id];
+            halfedge.vertex = vertex_ids[halfedge.vertex];
+            halfedge.face = face_ids[halfedge.face];
+            halfedge.prev = halfedge_ids[halfedge.prev];
+            halfedge.next = halfedge_ids[halfedge.next];
+            halfedge.opposite = halfedge_ids[halfedge.opposite];
+        }
+        self.vertex_count = self.vertices.len();
+        self.face_count = self.faces.len();
+        self.halfedge_count = self.halfedges.len();
+        self.edges = self.edges.drain()
+            .filter(|&(ref endpoints, halfedge_id)| vertex_ids[endpoints.low] > 0 && vertex_ids[endpoints.high] > 0 && halfedge_ids[halfedge_id] > 0)
+            .map(|(endpoints, halfedge_id)| (EdgeEndpoints::new(vertex_ids[endpoints.low], vertex_ids[endpoints.high]), halfedge_ids[halfedge_id]))
+            .collect();
+        self.halfedge_uvs = self.halfedge_uvs.drain()
+            .filter(|&(halfedge_id, _)| halfedge_ids.get(halfedge_id).is_some_and(|&id| id > 0))
+            .map(|(halfedge_id, uv)| (halfedge_ids[halfedge_id], uv))
+            .collect();
+        self.face_materials = self.face_materials.drain()
+            .filter(|&(face_id, _)| face_ids.get(face_id).is_some_and(|&id| id > 0))
+            .map(|(face_id, material)| (face_ids[face_id], material))
+            .collect();
+        self.vertex_tags = self.vertex_tags.drain()
+            .filter(|&(vertex_id, _)| vertex_ids.get(vertex_id).is_some_and(|&id| id > 0))
+            .map(|(vertex_id, tags)| (vertex_ids[vertex_id], tags))
+            .collect();
+        self.mark_changed();
+    }
+
+    /// Removes the faces whose area is at most `area_epsilon` and returns how
+    /// many were removed.
+    ///
+    /// A degenerate triangle is usually a sliver whose third corner lies on
+    /// its longest side. That corner is inserted into the face across the
+    /// longest side, so the mesh stays closed. Other degenerate faces are
+    /// removed and leave a hole.
+    pub fn remove_degenerate_faces(&mut self, area_epsilon: f32) -> usize {
+        let degenerate: Vec<Id> = FaceIterator::new(self)
+            .filter(|&face_id| self.face_area(face_id) <= area_epsilon)
+            .collect();
+        let mut removed = 0;
+        for face_id in degenerate {
+            let halfedges = match self.face(face_id) {
+                Some(face) => FaceHalfedgeIterator::new(self, face.halfedge).into_vec(),
+                None => continue,
+            };
+            let mut replaced = vec![face_id];
+            let mut rebuilt = Vec::new();
+            if halfedges.len() == 3 {
+                let longest = *halfedges.iter().max_by(|&&a, &&b| {
+                    let length = |id: Id| self.halfedge_direct(id).magnitude2();
+                    length(a).partial_cmp(&length(b)).unwrap_or(Ordering::Equal)
+                }).unwrap();
+                let apex = self.halfedge_start_vertex_id(self.halfedge_prev_id(longest).unwrap()).unwrap();
+                if let Some(neighbor_face) = self.halfedge_opposite_face_id(longest) {
+                    if neighbor_face != face_id {
+                        let opposite = self.halfedge_opposite_id(longest).unwrap();
+                        let mut vertices = Vec::new();
+                        for id in FaceHalfedgeIterator::new(self, opposite) {
+                            vertices.push(self.halfedge_start_vertex_id(id).unwrap());
+                            if id == opposite {
+                                vertices.push(apex);
+                            }
+                        }
+                        let material = self.face_material(neighbor_face);
+                        replaced.push(neighbor_face);
+                        rebuilt.push((vertices, material));
+                    }
+                }
+            }
+            let loops: Vec<Vec<Id>> = rebuilt.iter().map(|(vertices, _)| vertices.clone()).collect();
+            let added = self.replace_faces(&replaced, &loops);
+            for (&added_face_id, &(_, material)) in added.iter().zip(rebuilt.iter()) {
+                self.set_face_material(added_face_id, material);
+            }
+            removed += 1;
+        }
+        removed
+    }
+
+    /// Replaces the edge of a halfedge by a chamfer of `segments` faces. The
+    /// edge's end points are offset by `amount` along the sides of the two
+    /// faces sharing the edge, and intermediate rows follow a quadratic curve
+    /// through the original edge, so more segments give a rounder bevel.
+    ///
+    /// An end vertex shared by exactly three faces is replaced by the chamfer
+    /// profile. Any other end vertex is kept and the gap is closed with a cap
+    /// face; merging the bevels of several edges meeting at a corner is not
+    /// supported yet.
+    ///
+    /// Returns the chamfer faces followed by the cap faces. The faces around
+    /// the edge are rebuilt and get new ids, keeping their materials and UVs,
+    /// with the UVs of the new points interpolated along the sides they lie
+    /// on. The chamfer and cap faces take the material of the face of the
+    /// halfedge and UVs blended from the two faces sharing the edge. A
+    /// `segments` of 0 is treated as 1. Fails with `InvalidElement` for a
+    /// removed or boundary edge, and with `NonManifoldResult` when the faces
+    /// around the edge wrap around to each other.
+    pub fn bevel_edge(&mut self, edge_id: Id, amount: f32, segments: usize) -> Result<Vec<Id>, TopologyError> {
+        let segments = segments.max(1);
+        let opposite_id = match self.halfedge(edge_id).and_then(|_| self.halfedge_opposite_id(edge_id)) {
+            Some(id) => id,
+            None => return Err(TopologyError::InvalidElement { id: edge_id }),
+        };
+        let first_face = self.halfedge_face_id(edge_id).unwrap();
+        let second_face = self.halfedge_face_id(opposite_id).unwrap();
+        let a = self.halfedge_start_vertex_id(edge_id).unwrap();
+        let b = self.halfedge_start_vertex_id(opposite_id).unwrap();
+        let edge_next = self.halfedge_next_id(edge_id).unwrap();
+        let edge_prev = self.halfedge_prev_id(edge_id).unwrap();
+        let opposite_next = self.halfedge_next_id(opposite_id).unwrap();
+        let opposite_prev = self.halfedge_prev_id(opposite_id).unwrap();
+        let side_faces = [
+            self.halfedge_opposite_face_id(edge_prev),
+            self.halfedge_opposite_face_id(opposite_next),
+            self.halfedge_opposite_face_id(edge_next),
+            self.halfedge_opposite_face_id(opposite_prev),
+        ];
+        if side_faces.iter().any(|&face| face == Some(first_face) || face == Some(second_face)) {
+            return Err(TopologyError::NonManifoldResult);
+        }
+        let offset = |mesh: &Mesh, from: Id, towards: Id| {
+            let from = mesh.vertex(from).unwrap().position;
+            let towards = mesh.vertex(towards).unwrap().position;
+            from + (towards - from).normalize() * amount
+        };
+        let profile = |mesh: &mut Mesh, vertex: Id, start: Point3<f32>, end: Point3<f32>| {
+            let corner = mesh.vertex(vertex).unwrap().position.to_vec();
+            (0..segments + 1).map(|i| {
+                let t = i as f32 / segments as f32;
+                let position = start.to_vec() * ((1.0 - t) * (1.0 - t)) +
+                    corner * (2.0 * t * (1.0 - t)) + end.to_vec() * (t * t);
+                mesh.add_vertex(Point3::from_vec(position))
+            }).collect::<Vec<Id>>()
+        };
+        let a_first = offset(self, a, self.halfedge_start_vertex_id(edge_prev).unwrap());
+        let a_second = offset(self, a, self.halfedge_start_vertex_id(self.halfedge_next_id(opposite_next).unwrap()).unwrap());
+        let b_first = offset(self, b, self.halfedge_start_vertex_id(self.halfedge_next_id(edge_next).unwrap()).unwrap());
+        let b_second = offset(self, b, self.halfedge_start_vertex_id(opposite_prev).unwrap());
+        let a_row = profile(self, a, a_first, a_second);
+        let b_row = profile(self, b, b_first, b_second);
+
+        // UVs of the new points, in the chart of each face they end up in.
+        let corner_uv = |mesh: &Mesh, face_id: Id, vertex: Id| {
+            FaceHalfedgeIterator::new(mesh, mesh.face_first_halfedge_id(face_id).unwrap())
+                .find(|&id| mesh.halfedge_start_vertex_id(id) == Some(vertex))
+                .and_then(|id| mesh.halfedge_uv(id))
+        };
+        let offset_uv = |mesh: &Mesh, face_id: Id, from: Id, towards: Id| {
+            let length = mesh.vertex(from).unwrap().position.distance(mesh.vertex(towards).unwrap().position);
+            match (corner_uv(mesh, face_id, from), corner_uv(mesh, face_id, towards)) {
+                (Some(from_uv), Some(towards_uv)) => Some(from_uv + (towards_uv - from_uv) * (amount / length)),
+                _ => None,
+            }
+        };
+        let profile_uvs = |start: Option<Point2<f32>>, corner: Option<Point2<f32>>, end: Option<Point2<f32>>| {
+            (0..segments + 1).map(|i| match (start, corner, end) {
+                (Some(start), Some(corner), Some(end)) => {
+                    let t = i as f32 / segments as f32;
+                    Some(Point2::from_vec(start.to_vec() * ((1.0 - t) * (1.0 - t)) +
+                        corner.to_vec() * (2.0 * t * (1.0 - t)) + end.to_vec() * (t * t)))
+                },
+                _ => None,
+            }).collect::<Vec<Option<Point2<f32>>>>()
+        };
+        let a_before = self.halfedge_start_vertex_id(edge_prev).unwrap();
+        let a_after = self.halfedge_start_vertex_id(self.halfedge_next_id(opposite_next).unwrap()).unwrap();
+        let b_before = self.halfedge_start_vertex_id(opposite_prev).unwrap();
+        let b_after = self.halfedge_start_vertex_id(self.halfedge_next_id(edge_next).unwrap()).unwrap();
+        let mut new_uvs: Vec<(Id, Id, Option<Point2<f32>>)> = vec![
+            (first_face, a_row[0], offset_uv(self, first_face, a, a_before)),
+            (first_face, b_row[0], offset_uv(self, first_face, b, b_after)),
+            (second_face, a_row[segments], offset_uv(self, second_face, a, a_after)),
+            (second_face, b_row[segments], offset_uv(self, second_face, b, b_before)),
+        ];
+        let a_uvs = profile_uvs(new_uvs[0].2, corner_uv(self, first_face, a), new_uvs[2].2);
+        let b_uvs = profile_uvs(new_uvs[1].2, corner_uv(self, first_face, b), new_uvs[3].2);
+
+        let mut loops: Vec<(Id, Vec<Id>)> = Vec::new();
+        let mut replace_corner = |mesh: &Mesh, face_id: Id, vertex: Id, replacement: Vec<Id>| {
+            let index = match loops.iter().position(|&(id, _)| id == face_id) {
+                Some(index) => index,
+                None => {
+                    loops.push((face_id, mesh.face_vertex_ids(face_id)));
+                    loops.len() - 1
+                },
+            };
+            let vertices = &mut loops[index].1;
+            if let Some(position) = vertices.iter().position(|&id| id == vertex) {
+                vertices.splice(position..position + 1, replacement);
+            }
+        };
+        replace_corner(self, first_face, a, vec![a_row[0]]);
+        replace_corner(self, first_face, b, vec![b_row[0]]);
+        replace_corner(self, second_face, b, vec![b_row[segments]]);
+        replace_corner(self, second_face, a, vec![a_row[segments]]);
+        let mut caps = Vec::new();
+        let mut cap_uvs = Vec::new();
+        let ends = [
+            (a, side_faces[0], side_faces[1], a_before, a_after, a_row.clone(), a_uvs.clone()),
+            (b, side_faces[3], side_faces[2], b_before, b_after,
+                b_row.iter().rev().cloned().collect::<Vec<Id>>(), b_uvs.iter().rev().cloned().collect()),
+        ];
+        for &(vertex, first_side, second_side, before, after, ref row, ref row_uvs) in ends.iter() {
+            // The row starts next to first_side and ends next to second_side.
+            let reversed: Vec<Id> = row.iter().rev().cloned().collect();
+            if let Some(face_id) = first_side {
+                if first_side == second_side {
+                    let side_uvs = profile_uvs(offset_uv(self, face_id, vertex, before),
+                        corner_uv(self, face_id, vertex), offset_uv(self, face_id, vertex, after));
+                    new_uvs.extend(row.iter().zip(side_uvs).map(|(&id, uv)| (face_id, id, uv)));
+                    replace_corner(self, face_id, vertex, reversed);
+                    continue;
+                }
+            }
+            if let Some(face_id) = first_side {
+                new_uvs.push((face_id, row[0], offset_uv(self, face_id, vertex, before)));
+                replace_corner(self, face_id, vertex, vec![vertex, row[0]]);
+            }
+            if let Some(face_id) = second_side {
+                new_uvs.push((face_id, row[segments], offset_uv(self, face_id, vertex, after)));
+                replace_corner(self, face_id, vertex, vec![*reversed.first().unwrap(), vertex]);
+            }
+            let mut cap = vec![vertex];
+            cap.extend(reversed);
+            let mut uvs = vec![corner_uv(self, first_face, vertex)];
+            uvs.extend(row_uvs.iter().rev().cloned());
+            caps.push(cap);
+            cap_uvs.push(uvs);
+        }
+        let mut new_loops: Vec<Vec<Id>> = (0..segments).map(|i| {
+            vec![b_row[i], a_row[i], a_row[i + 1], b_row[i + 1]]
+        }).collect();
+        let mut loop_uvs: Vec<Vec<Option<Point2<f32>>>> = (0..segments).map(|i| {
+            vec![b_uvs[i], a_uvs[i], a_uvs[i + 1], b_uvs[i + 1]]
+        }).collect();
+        new_loops.extend(caps);
+        let added_count = new_loops.len();
+        let mut materials = Vec::new();
+        let mut corner_uvs: Vec<Vec<Option<Point2<f32>>>> = Vec::new();
+        for &(face_id, ref vertices) in loops.iter() {
+            materials.push(self.face_material(face_id));
+            corner_uvs.push(vertices.iter().map(|&vertex| match new_uvs.iter().find(|&&(id, new_vertex, _)| id == face_id && new_vertex == vertex) {
+                Some(&(_, _, uv)) => uv,
+                None => corner_uv(self, face_id, vertex),
+            }).collect());
+        }
+        materials.extend(vec![self.face_material(first_face); added_count]);
+        loop_uvs.extend(cap_uvs);
+        corner_uvs.extend(loop_uvs);
+        let old_faces: Vec<Id> = loops.iter().map(|&(id, _)| id).collect();
+        let mut rebuilt: Vec<Vec<Id>> = loops.into_iter().map(|(_, vertices)| vertices).collect();
+        rebuilt.extend(new_loops);
+        let added_faces = self.replace_faces(&old_faces, &rebuilt);
+        for ((&face_id, material), uvs) in added_faces.iter().zip(materials).zip(corner_uvs) {
+            self.set_face_material(face_id, material);
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for (halfedge_id, uv) in FaceHalfedgeIterator::new(self, face_halfedge).into_vec().into_iter().zip(uvs) {
+                if let Some(uv) = uv {
+                    self.set_halfedge_uv(halfedge_id, uv);
+                }
+            }
+        }
+        Ok(added_faces[added_faces.len() - added_count..].to_vec())
+    }
+
     pub fn add_plane(&mut self, width: f32, depth: f32) -> Id {
         let x = width / 2.0;
         let y = depth / 2.0;
@@ -646,6 +1556,24 @@ impl Mesh {
         for vertex in self.vertices.iter_mut() {
             vertex.position = mat.transform_point(vertex.position);
         }
+        self.mark_changed();
+        self
+    }
+
+    /// Copy of the mesh with every vertex moved to `f(position)`, keeping all
+    /// ids, the connectivity and the side tables, see `duplicate`.
+    pub fn map_positions<F: Fn(Point3<f32>) -> Point3<f32>>(&self, f: F) -> Mesh {
+        let mut mesh = self.duplicate();
+        mesh.map_positions_mut(f);
+        mesh
+    }
+
+    /// Moves every vertex to `f(position)` in place.
+    pub fn map_positions_mut<F: Fn(Point3<f32>) -> Point3<f32>>(&mut self, f: F) -> &mut Self {
+        for vertex in self.vertices.iter_mut().filter(|vertex| vertex.alive) {
+            vertex.position = f(vertex.position);
+        }
+        self.mark_changed();
         self
     }
 
@@ -659,6 +1587,41 @@ impl Mesh {
         self.transform(&mat)
     }
 
+    /// Scales by separate factors along x, y and z, keeping `pivot` in place.
+    /// An odd number of negative factors mirrors the mesh, which leaves the
+    /// faces facing inwards.
+    pub fn scale_about(&mut self, pivot: Point3<f32>, factors: Vector3<f32>) -> &mut Self {
+        let mat = Matrix4::from_translation(pivot.to_vec())
+            * Matrix4::from_nonuniform_scale(factors.x, factors.y, factors.z)
+            * Matrix4::from_translation(-pivot.to_vec());
+        self.transform(&mat)
+    }
+
+    /// Rotates by `rotation`, a unit quaternion, keeping `pivot` in place.
+    pub fn rotate_about(&mut self, pivot: Point3<f32>, rotation: Quaternion<f32>) -> &mut Self {
+        let mat = Matrix4::from_translation(pivot.to_vec())
+            * Matrix4::from(rotation)
+            * Matrix4::from_translation(-pivot.to_vec());
+        self.transform(&mat)
+    }
+
+    /// Moves every vertex along its normal by `f(position, normal)`. The
+    /// normals are all computed before any vertex moves, so the result does
+    /// not depend on the vertex order. Vertices without faces have no normal
+    /// and stay in place.
+    pub fn displace<F: Fn(Point3<f32>, Vector3<f32>) -> f32>(&mut self, f: F) -> &mut Self {
+        let normals: Vec<Vector3<f32>> = self.vertices.iter()
+            .map(|vertex| if vertex.alive { self.vertex_norm(vertex.id) } else { Vector3::zero() })
+            .collect();
+        for (vertex, normal) in self.vertices.iter_mut().zip(normals) {
+            if vertex.alive && normal != Vector3::zero() {
+                vertex.position += normal * f(vertex.position, normal);
+            }
+        }
+        self.mark_changed();
+        self
+    }
+
     pub fn weld(&self) -> Self {
         let mut new_mesh = Mesh::new();
         let mut vertices_set : HashMap<Point3Key, Id> = HashMap::new();
@@ -695,6 +1658,10 @@ impl Mesh {
 
     pub fn add_mesh(&mut self, other: &Mesh) {
         let mut vertices_set : HashMap<Id, Id> = HashMap::new();
+        let mut halfedge_targets = Vec::new();
+        let mut face_targets = Vec::new();
+        let material_offset = self.materials.len();
+        self.materials.extend(other.materials.iter().cloned());
         for face_id in FaceIterator::new(&other) {
             let face = other.face(face_id).unwrap();
             let mut added_halfedges : Vec<(Id, Id)> = Vec::new();
@@ -706,12 +1673,40 @@ impl Mesh {
                 } else {
                     let new_vertex_id = self.add_vertex(vertex.position);
                     self.vertex_mut(new_vertex_id).unwrap().source = vertex.source;
+                    if let Some(&tags) = other.vertex_tags.get(&key) {
+                        self.vertex_tags.insert(new_vertex_id, tags);
+                    }
                     vertices_set.insert(key, new_vertex_id);
                     added_halfedges.push((self.add_halfedge(), new_vertex_id));
                 }
+                if let Some(&uv) = other.halfedge_uvs.get(&halfedge_id) {
+                    self.halfedge_uvs.insert(added_halfedges.last().unwrap().0, uv);
+                }
+                halfedge_targets.push((added_halfedges.last().unwrap().0, vec![(halfedge_id, 1.0)]));
             }
-            self.add_halfedges_and_vertices(&added_halfedges);
+            let added_face_id = self.add_halfedges_and_vertices(&added_halfedges);
+            if let Some(&material) = other.face_materials.get(&face_id) {
+                self.face_materials.insert(added_face_id, material_offset + material);
+            }
+            face_targets.push((added_face_id, vec![(face_id, 1.0)]));
+        }
+        if other.attributes.is_empty() {
+            return;
         }
+        let mut vertex_targets: Vec<(Id, Vec<(Id, f32)>)> = vertices_set.iter()
+            .map(|(&old_id, &new_id)| (new_id, vec![(old_id, 1.0)]))
+            .collect();
+        vertex_targets.sort_by_key(|&(id, _)| id);
+        let edge_targets: Vec<(EdgeEndpoints, Vec<(EdgeEndpoints, f32)>)> = other.edges.keys()
+            .filter_map(|endpoints| match (vertices_set.get(&endpoints.low), vertices_set.get(&endpoints.high)) {
+                (Some(&low), Some(&high)) => Some((EdgeEndpoints::new(low, high), vec![(endpoints.clone(), 1.0)])),
+                _ => None,
+            })
+            .collect();
+        self.attributes.resample_vertices(&other.attributes, &vertex_targets);
+        self.attributes.resample_faces(&other.attributes, &face_targets);
+        self.attributes.resample_halfedges(&other.attributes, &halfedge_targets);
+        self.attributes.resample_edges(&other.attributes, &edge_targets);
     }
 
     pub fn flip_mesh(&self) -> Mesh {
@@ -751,6 +1746,8 @@ impl Mesh {
         (outter_mesh, inner_mesh)
     }
 
+    /// Union of two meshes that must be convex, closed and manifold, see
+    /// `is_closed` and `is_manifold`.
     pub fn union_convex_mesh(&self, other: &Mesh) -> Mesh {
         let (other_outter, _) = other.split_mesh_by_other(self);
         let (my_outter, _) = self.split_mesh_by_other(other);
@@ -758,6 +1755,8 @@ impl Mesh {
         mesh.weld().fix_tjunction().combine_adj_faces()
     }
 
+    /// This mesh minus `other`. Both meshes must be convex, closed and
+    /// manifold, see `is_closed` and `is_manifold`.
     pub fn diff_convex_mesh(&self, other: &Mesh) -> Mesh {
         let (_, other_inner) =  other.split_mesh_by_other(self);
         let (my_outter, _) = self.split_mesh_by_other(other);
@@ -765,6 +1764,8 @@ impl Mesh {
         mesh.weld().fix_tjunction().combine_adj_faces()
     }
 
+    /// Intersection of two meshes that must be convex, closed and manifold, see
+    /// `is_closed` and `is_manifold`.
     pub fn intersect_convex_mesh(&self, other: &Mesh) -> Mesh {
         let (_, other_inner) =  other.split_mesh_by_other(self);
         let (_, my_inner) = self.split_mesh_by_other(other);
@@ -1400,6 +2401,7 @@ impl Mesh {
                 _ => {}
             }
         }
+        self.mark_changed();
         let mut change_back_pairs : Vec<(Id, Point3<f32>)> = Vec::new();
         for (face_id, face_normal) in face_norm_map {
             if face_normal.dot(self.face_norm(face_id)) <= 0.0 {
@@ -1454,3 +2456,9 @@ impl Clone for Mesh {
         mesh
     }
 }
+
+impl Default for Mesh {
+    fn default() -> Self {
+        Mesh::new()
+    }
+}
@@ -0,0 +1,105 @@
+use cgmath::prelude::*;
+use fnv::FnvHashSet;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use iterator::FaceTriangleIterator;
+use mesh::Id;
+use mesh::Mesh;
+use std::collections::VecDeque;
+
+impl Mesh {
+    /// Signed volume enclosed by the faces, positive when they wind
+    /// counterclockwise seen from outside. Only meaningful for closed meshes
+    /// with consistent winding.
+    pub fn volume(&self) -> f32 {
+        let mut volume = 0.0;
+        for face_id in FaceIterator::new(self) {
+            for triangle in FaceTriangleIterator::new(self, face_id) {
+                let a = self.vertex(triangle[0]).unwrap().position.to_vec();
+                let b = self.vertex(triangle[1]).unwrap().position.to_vec();
+                let c = self.vertex(triangle[2]).unwrap().position.to_vec();
+                volume += a.dot(b.cross(c));
+            }
+        }
+        volume / 6.0
+    }
+
+    /// Reverses the winding of the faces in place, keeping their uvs and
+    /// materials.
+    fn reverse_faces(&mut self, faces: &[Id]) {
+        let mut loops = Vec::with_capacity(faces.len());
+        let mut corners = Vec::with_capacity(faces.len());
+        for &face_id in faces {
+            let halfedges = FaceHalfedgeIterator::new(self, self.face_first_halfedge_id(face_id).unwrap()).into_vec();
+            let mut vertices: Vec<Id> = halfedges.iter().map(|&id| self.halfedge_start_vertex_id(id).unwrap()).collect();
+            vertices.reverse();
+            corners.push((self.face_material(face_id), halfedges.iter()
+                .map(|&id| (self.halfedge_start_vertex_id(id).unwrap(), self.halfedge_uv(id)))
+                .collect::<Vec<_>>()));
+            loops.push(vertices);
+        }
+        let added_faces = self.replace_faces(faces, &loops);
+        for (face_id, (material, uvs)) in added_faces.into_iter().zip(corners) {
+            self.set_face_material(face_id, material);
+            for halfedge_id in FaceHalfedgeIterator::new(self, self.face_first_halfedge_id(face_id).unwrap()).into_vec() {
+                let vertex_id = self.halfedge_start_vertex_id(halfedge_id).unwrap();
+                if let Some(&(_, Some(uv))) = uvs.iter().find(|&&(id, _)| id == vertex_id) {
+                    self.set_halfedge_uv(halfedge_id, uv);
+                }
+            }
+        }
+    }
+
+    /// Makes neighboring faces run opposite ways along their shared edges,
+    /// flipping faces to agree with the lowest face id of each connected
+    /// part, and returns how many faces were flipped. Non-orientable parts,
+    /// like a Möbius strip, keep a seam of disagreeing faces.
+    pub fn fix_orientation(&mut self) -> usize {
+        let mut visited: FnvHashSet<Id> = FnvHashSet::default();
+        let mut flipped = Vec::new();
+        for seed in FaceIterator::new(self) {
+            if !visited.insert(seed) {
+                continue;
+            }
+            let mut queue = VecDeque::new();
+            queue.push_back((seed, false));
+            while let Some((face_id, flip)) = queue.pop_front() {
+                if flip {
+                    flipped.push(face_id);
+                }
+                for halfedge_id in FaceHalfedgeIterator::new(self, self.face_first_halfedge_id(face_id).unwrap()) {
+                    let opposite_id = match self.halfedge_opposite_id(halfedge_id) {
+                        Some(id) => id,
+                        None => continue,
+                    };
+                    let neighbor_id = self.halfedge_face_id(opposite_id).unwrap();
+                    if !visited.insert(neighbor_id) {
+                        continue;
+                    }
+                    // Agreeing faces cross the edge in opposite directions,
+                    // so the opposite halfedge starts at a different vertex.
+                    let disagrees = self.halfedge_start_vertex_id(opposite_id) == self.halfedge_start_vertex_id(halfedge_id);
+                    queue.push_back((neighbor_id, flip != disagrees));
+                }
+            }
+        }
+        self.reverse_faces(&flipped);
+        flipped.len()
+    }
+
+    /// Makes the winding consistent with `fix_orientation`, then flips the
+    /// whole mesh if its `volume` is negative, so the faces point outward.
+    /// Returns how many faces were flipped. Only meaningful for closed
+    /// manifolds, see `is_closed` and `is_manifold`, as open surfaces have
+    /// no inside.
+    pub fn orient_outward(&mut self) -> usize {
+        let fixed = self.fix_orientation();
+        if self.volume() >= 0.0 {
+            return fixed;
+        }
+        let faces: Vec<Id> = FaceIterator::new(self).collect();
+        self.reverse_faces(&faces);
+        // Faces flipped twice end up as they started.
+        faces.len() - fixed
+    }
+}
@@ -1,5 +1,11 @@
+use cgmath::MetricSpace;
+use cgmath::Point3;
+use mesh::Id;
 use mesh::Mesh;
 
+/// Rows of grid samples closer together than this are welded into a pole.
+const POLE_EPSILON: f32 = 0.00001;
+
 pub fn cube() -> Mesh {
     let mut m = Mesh::new();
     let face_id = m.add_plane(1.0, 1.0);
@@ -8,3 +14,49 @@ pub fn cube() -> Mesh {
     m
 }
 
+impl Mesh {
+    /// Samples the parametric surface `f` over u, v in [0, 1] on a grid of
+    /// `u_steps` by `v_steps` quads. Faces wind counterclockwise around the
+    /// direction of df/du x df/dv.
+    ///
+    /// `wrap_u` and `wrap_v` weld the last column or row to the first one,
+    /// the seam of a cylinder or torus, and the closure is not evaluated at
+    /// the end of that parameter. A row collapsing into a single point, like
+    /// the poles of a sphere, is welded into one vertex and the quads touching
+    /// it become triangles.
+    pub fn from_grid<F: Fn(f32, f32) -> Point3<f32>>(u_steps: usize, v_steps: usize, f: F, wrap_u: bool, wrap_v: bool) -> Mesh {
+        let columns = if wrap_u { u_steps } else { u_steps + 1 };
+        let rows = if wrap_v { v_steps } else { v_steps + 1 };
+        let mut mesh = Mesh::with_capacity(columns * rows, u_steps * v_steps, u_steps * v_steps * 4);
+        let mut grid: Vec<Id> = Vec::with_capacity(columns * rows);
+        for j in 0..rows {
+            let v = j as f32 / v_steps as f32;
+            let row: Vec<Point3<f32>> = (0..columns)
+                .map(|i| f(i as f32 / u_steps as f32, v))
+                .collect();
+            if row.iter().all(|point| point.distance(row[0]) < POLE_EPSILON) {
+                let pole = mesh.add_vertex(row[0]);
+                grid.extend((0..columns).map(|_| pole));
+            } else {
+                for point in row {
+                    let vertex_id = mesh.add_vertex(point);
+                    grid.push(vertex_id);
+                }
+            }
+        }
+        for j in 0..v_steps {
+            for i in 0..u_steps {
+                let corner = |i: usize, j: usize| grid[(j % rows) * columns + i % columns];
+                let mut face = vec![corner(i, j), corner(i + 1, j), corner(i + 1, j + 1), corner(i, j + 1)];
+                face.dedup();
+                if face.len() > 1 && face[0] == face[face.len() - 1] {
+                    face.pop();
+                }
+                if face.len() >= 3 {
+                    mesh.add_vertices(face);
+                }
+            }
+        }
+        mesh
+    }
+}
@@ -0,0 +1,146 @@
+use cgmath::Point3;
+use cgmath::prelude::*;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use iterator::FaceTriangleIterator;
+use mesh::Id;
+use mesh::Mesh;
+use std::f32;
+
+/// Triangles scoring below this are counted as slivers by `quality_report`.
+pub const DEFAULT_SLIVER_THRESHOLD: f32 = 0.1;
+
+/// Summary of the triangle quality of a mesh. Polygons with more than three
+/// sides are measured per triangle of their fan triangulation.
+#[derive(Debug, Clone)]
+pub struct QualityReport {
+    pub triangle_count: usize,
+    pub min_quality: f32,
+    pub mean_quality: f32,
+
+    /// The face containing the lowest scoring triangle, 0 for an empty mesh.
+    pub worst_face: Id,
+
+    /// Number of triangles whose quality is below the sliver threshold.
+    pub sliver_count: usize,
+
+    /// Dihedral angles in degrees, measured across every paired edge. Two
+    /// coplanar faces meet at 180 degrees.
+    pub min_dihedral_angle: f32,
+    pub max_dihedral_angle: f32,
+}
+
+/// Radius ratio of a triangle, normalized so an equilateral triangle scores 1
+/// and a degenerate one scores 0.
+pub fn triangle_radius_ratio(a: Point3<f32>, b: Point3<f32>, c: Point3<f32>) -> f32 {
+    let la = b.distance(c);
+    let lb = c.distance(a);
+    let lc = a.distance(b);
+    let denominator = (la + lb + lc) * la * lb * lc;
+    if denominator <= 0.0 {
+        return 0.0;
+    }
+    let double_area = (b - a).cross(c - a).magnitude();
+    // 2r/R = 16 * area^2 / (perimeter * la * lb * lc)
+    (4.0 * double_area * double_area / denominator).min(1.0)
+}
+
+impl Mesh {
+    fn face_fan_qualities(&self, face_id: Id) -> Vec<f32> {
+        FaceTriangleIterator::new(self, face_id).map(|triangle| {
+            let corner = |index: usize| self.vertex(triangle[index]).unwrap().position;
+            triangle_radius_ratio(corner(0), corner(1), corner(2))
+        }).collect()
+    }
+
+    /// Returns the radius ratio quality of a face, from 0 (degenerate) to 1
+    /// (equilateral). Polygons are fan triangulated and score their worst
+    /// triangle. Dead faces score 0.
+    pub fn triangle_quality(&self, face_id: Id) -> f32 {
+        self.face_fan_qualities(face_id).into_iter().fold(None, |worst: Option<f32>, quality| {
+            Some(worst.map_or(quality, |worst| worst.min(quality)))
+        }).unwrap_or(0.0)
+    }
+
+    /// Sum of the lengths of the sides of a face, 0 for dead faces.
+    pub fn face_perimeter(&self, face_id: Id) -> f32 {
+        let face_halfedge = match self.face_first_halfedge_id(face_id) {
+            Some(face_halfedge) => face_halfedge,
+            None => return 0.0,
+        };
+        FaceHalfedgeIterator::new(self, face_halfedge).map(|halfedge_id| {
+            let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+            self.halfedge_start_vertex(halfedge_id).unwrap().position
+                .distance(self.halfedge_start_vertex(next_id).unwrap().position)
+        }).sum()
+    }
+
+    /// Isoperimetric ratio 4π·area / perimeter² of a face, 1 for a circle,
+    /// π/4 for a square, about 0.6 for an equilateral triangle and close to 0
+    /// for slivers. Degenerate and dead faces score 0.
+    pub fn face_compactness(&self, face_id: Id) -> f32 {
+        let perimeter = self.face_perimeter(face_id);
+        if perimeter <= 0.0 {
+            return 0.0;
+        }
+        4.0 * f32::consts::PI * self.face_area(face_id) / (perimeter * perimeter)
+    }
+
+    pub fn quality_report(&self) -> QualityReport {
+        self.quality_report_with_threshold(DEFAULT_SLIVER_THRESHOLD)
+    }
+
+    pub fn quality_report_with_threshold(&self, sliver_threshold: f32) -> QualityReport {
+        let mut report = QualityReport {
+            triangle_count: 0,
+            min_quality: 0.0,
+            mean_quality: 0.0,
+            worst_face: 0,
+            sliver_count: 0,
+            min_dihedral_angle: 0.0,
+            max_dihedral_angle: 0.0,
+        };
+        let mut min_quality = f32::MAX;
+        let mut quality_sum = 0.0;
+        for face_id in FaceIterator::new(self) {
+            for quality in self.face_fan_qualities(face_id) {
+                report.triangle_count += 1;
+                quality_sum += quality;
+                if quality < sliver_threshold {
+                    report.sliver_count += 1;
+                }
+                if quality < min_quality {
+                    min_quality = quality;
+                    report.worst_face = face_id;
+                }
+            }
+        }
+        if report.triangle_count > 0 {
+            report.min_quality = min_quality;
+            report.mean_quality = quality_sum / report.triangle_count as f32;
+        }
+        let mut min_angle = f32::MAX;
+        let mut max_angle = f32::MIN;
+        for halfedge in self.halfedges.iter() {
+            if !halfedge.alive || halfedge.opposite == 0 || halfedge.opposite < halfedge.id {
+                continue;
+            }
+            let opposite_face = match self.halfedge_face_id(halfedge.opposite) {
+                Some(face_id) => face_id,
+                None => continue,
+            };
+            let cos = self.face_norm(halfedge.face).dot(self.face_norm(opposite_face));
+            if !cos.is_finite() {
+                continue;
+            }
+            let angle = 180.0 - cos.clamp(-1.0, 1.0).acos().to_degrees();
+            min_angle = min_angle.min(angle);
+            max_angle = max_angle.max(angle);
+        }
+        if min_angle <= max_angle {
+            report.min_dihedral_angle = min_angle;
+            report.max_dihedral_angle = max_angle;
+        }
+        report
+    }
+}
@@ -0,0 +1,233 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use fnv::FnvHashMap;
+use bvh::Bvh;
+use iterator::FaceIterator;
+use iterator::FaceTriangleIterator;
+use mesh::Id;
+use mesh::Mesh;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::f32;
+
+/// Darts thrown per disk of `radius` that fits in the surface area, enough
+/// for the accepted points to stop growing much.
+const POISSON_DARTS_PER_DISK: f32 = 30.0;
+
+/// Most darts `Mesh::sample_surface_poisson` throws, however small the radius
+/// is for the surface.
+pub const POISSON_MAX_DARTS: usize = 1_000_000;
+
+/// Xorshift generator, so the same seed always gives the same samples.
+struct SampleRandom(u64);
+
+impl SampleRandom {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck on zero, so spread the seed bits first.
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        SampleRandom((state ^ (state >> 31)) | 1)
+    }
+
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// Fan triangles of the faces with the running total of their areas, for
+/// picking triangles with a chance proportional to their area.
+struct AreaTable {
+    triangles: Vec<(Id, [Point3<f32>; 3])>,
+    cumulative_areas: Vec<f32>,
+    total_area: f32,
+}
+
+impl AreaTable {
+    fn new(mesh: &Mesh) -> Self {
+        let mut triangles = Vec::new();
+        let mut cumulative_areas = Vec::new();
+        let mut total_area = 0.0;
+        for face_id in FaceIterator::new(mesh) {
+            for triangle in FaceTriangleIterator::new(mesh, face_id) {
+                let corners = [mesh.vertex(triangle[0]).unwrap().position,
+                    mesh.vertex(triangle[1]).unwrap().position,
+                    mesh.vertex(triangle[2]).unwrap().position];
+                total_area += (corners[1] - corners[0]).cross(corners[2] - corners[0]).magnitude() * 0.5;
+                triangles.push((face_id, corners));
+                cumulative_areas.push(total_area);
+            }
+        }
+        AreaTable {
+            triangles,
+            cumulative_areas,
+            total_area,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.triangles.is_empty() || self.total_area <= 0.0
+    }
+
+    /// Picks a triangle by area and a point on it uniformly, returning the
+    /// face, the point and its barycentric coordinates on the triangle.
+    fn sample(&self, random: &mut SampleRandom) -> (Id, Point3<f32>, [f32; 3]) {
+        let target = random.next() * self.total_area;
+        let index = self.cumulative_areas.partition_point(|&area| area < target).min(self.triangles.len() - 1);
+        let (face_id, corners) = self.triangles[index];
+        let s = random.next().sqrt();
+        let t = random.next();
+        let weights = [1.0 - s, s * (1.0 - t), s * t];
+        let point = Point3::from_vec(corners[0].to_vec() * weights[0]
+            + corners[1].to_vec() * weights[1]
+            + corners[2].to_vec() * weights[2]);
+        (face_id, point, weights)
+    }
+}
+
+/// Fraction of `samples` cosine weighted rays over the hemisphere around
+/// `normal` that leave `position` without hitting the mesh.
+fn unoccluded_fraction(bvh: &Bvh, position: Point3<f32>, normal: Vector3<f32>, offset: f32,
+        samples: usize, random: &mut SampleRandom) -> f32 {
+    let helper = if normal.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+    let tangent = normal.cross(helper).normalize();
+    let bitangent = normal.cross(tangent);
+    let origin = position + normal * offset;
+    let mut unoccluded = 0;
+    for _ in 0..samples {
+        let (sine, cosine) = (random.next() * 2.0 * f32::consts::PI).sin_cos();
+        let radius = random.next().sqrt();
+        let up = (1.0 - radius * radius).max(0.0).sqrt();
+        let direct = tangent * (radius * cosine) + bitangent * (radius * sine) + normal * up;
+        if bvh.raycast(origin, direct).is_none() {
+            unoccluded += 1;
+        }
+    }
+    unoccluded as f32 / samples as f32
+}
+
+fn grid_cell(point: Point3<f32>, radius: f32) -> (i64, i64, i64) {
+    ((point.x / radius).floor() as i64,
+        (point.y / radius).floor() as i64,
+        (point.z / radius).floor() as i64)
+}
+
+impl Mesh {
+    /// Spreads points over the faces with no two closer than `radius`, by
+    /// throwing darts at the faces weighted by area and rejecting those too
+    /// close to an accepted point. Returns each point with the face it lies
+    /// on. The darts come from a fixed seed, so the same mesh always gives
+    /// the same points. At most `POISSON_MAX_DARTS` darts are thrown, so a
+    /// radius far too small for the area gives fewer and less evenly spread
+    /// points instead of taking unbounded time.
+    pub fn sample_surface_poisson(&self, radius: f32) -> Vec<(Point3<f32>, Id)> {
+        self.sample_surface_poisson_with_max_darts(radius, POISSON_MAX_DARTS)
+    }
+
+    /// Samples like `sample_surface_poisson`, throwing at most `max_darts`
+    /// darts, to bound the time it takes.
+    pub fn sample_surface_poisson_with_max_darts(&self, radius: f32, max_darts: usize) -> Vec<(Point3<f32>, Id)> {
+        let mut samples = Vec::new();
+        if radius <= 0.0 {
+            return samples;
+        }
+        let table = AreaTable::new(self);
+        if table.is_empty() {
+            return samples;
+        }
+        let darts = ((POISSON_DARTS_PER_DISK * table.total_area / (radius * radius)).ceil() as usize).min(max_darts);
+        let radius2 = radius * radius;
+        let mut grid: FnvHashMap<(i64, i64, i64), Vec<usize>> = FnvHashMap::default();
+        let mut random = SampleRandom::new(0);
+        for _ in 0..darts {
+            let (face_id, point, _) = table.sample(&mut random);
+            let cell = grid_cell(point, radius);
+            let mut too_close = false;
+            'search: for x in cell.0 - 1..=cell.0 + 1 {
+                for y in cell.1 - 1..=cell.1 + 1 {
+                    for z in cell.2 - 1..=cell.2 + 1 {
+                        if let Some(indices) = grid.get(&(x, y, z)) {
+                            if indices.iter().any(|&i| samples[i].0.distance2(point) < radius2) {
+                                too_close = true;
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+            if !too_close {
+                grid.entry(cell).or_default().push(samples.len());
+                samples.push((point, face_id));
+            }
+        }
+        samples
+    }
+
+    /// Picks `count` points uniformly over the faces by area, returning each
+    /// point with the face it lies on and its barycentric coordinates. Faces
+    /// with more than three corners are fan triangulated like
+    /// `FaceTriangleIterator`, and the coordinates are on the fan triangle
+    /// the point fell in. The same `seed` always gives the same points.
+    pub fn sample_surface_uniform(&self, count: usize, seed: u64) -> Vec<(Point3<f32>, Id, [f32; 3])> {
+        let table = AreaTable::new(self);
+        if table.is_empty() {
+            return Vec::new();
+        }
+        let mut random = SampleRandom::new(seed);
+        (0..count).map(|_| {
+            let (face_id, point, weights) = table.sample(&mut random);
+            (point, face_id, weights)
+        }).collect()
+    }
+
+    /// Ambient occlusion of every vertex used by a face: the fraction of
+    /// `samples` rays over the hemisphere around the vertex normal that
+    /// leave without hitting the mesh, 1 for a vertex nothing hides and
+    /// close to 0 at the bottom of a deep crease. The rays are cosine
+    /// weighted, as light falling on the surface is, and start slightly off
+    /// the surface so they do not hit the faces around the vertex. Every
+    /// vertex gets its own random sequence from `seed` and its id, so the
+    /// result does not depend on the order of the work, which is spread over
+    /// all cores with the `parallel` feature.
+    pub fn bake_ambient_occlusion(&self, samples: usize, seed: u64) -> FnvHashMap<Id, f32> {
+        let bvh = self.build_bvh();
+        let offset = match bvh.bounds() {
+            Some((min, max)) => min.distance(max) * 1e-4,
+            None => return FnvHashMap::default(),
+        };
+        // Gathered first, the mesh itself cannot be shared between threads.
+        let vertices: Vec<(Id, Point3<f32>, Vector3<f32>)> = self.iter_vertices()
+            .filter(|&(_, vertex)| !vertex.halfedges.is_empty())
+            .map(|(vertex_id, vertex)| (vertex_id, vertex.position, self.vertex_norm(vertex_id)))
+            .collect();
+        let occlusion = |&(vertex_id, position, normal): &(Id, Point3<f32>, Vector3<f32>)| {
+            if samples == 0 || normal.magnitude2() == 0.0 {
+                return (vertex_id, 1.0);
+            }
+            let mut random = SampleRandom::new(seed ^ (vertex_id as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            (vertex_id, unoccluded_fraction(&bvh, position, normal.normalize(), offset, samples, &mut random))
+        };
+        #[cfg(feature = "parallel")]
+        let values = vertices.par_iter().map(occlusion).collect();
+        #[cfg(not(feature = "parallel"))]
+        let values = vertices.iter().map(occlusion).collect();
+        values
+    }
+
+    /// Bakes `bake_ambient_occlusion` into the f32 vertex attribute `name`,
+    /// adding it if needed, for renderers to read with the other
+    /// attributes.
+    pub fn bake_ambient_occlusion_attribute(&mut self, name: &str, samples: usize, seed: u64) {
+        let values = self.bake_ambient_occlusion(samples, seed);
+        if self.attributes.vertex_attribute_values::<f32>(name).is_none() {
+            self.attributes.add_vertex_attribute::<f32>(name);
+        }
+        for (vertex_id, value) in values {
+            self.attributes.set_vertex_attribute(name, vertex_id, value);
+        }
+    }
+}
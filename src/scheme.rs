@@ -0,0 +1,291 @@
+use cgmath::Point3;
+use cgmath::prelude::*;
+use error::TopologyError;
+use fnv::FnvHashMap;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use mesh::EdgeEndpoints;
+use mesh::Id;
+use mesh::Mesh;
+use std::f32::consts::PI;
+use triangulate::Triangulate;
+
+/// How the faces are changed before subdividing, see `SubdivideOptions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prepass {
+    None,
+
+    /// Splits every face into triangles, see `Triangulate`.
+    Triangulate,
+
+    /// Merges pairs of triangles into quads, see `Mesh::quadrangulate`.
+    Quadrangulate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubdivisionScheme {
+    /// Splits every face into quads, see `CatmullClarkSubdivider`.
+    CatmullClark,
+
+    /// Splits every triangle into four, see `Mesh::loop_subdivide`. Only
+    /// takes triangles.
+    Loop,
+
+    /// Cuts the corners off every face, see `Mesh::doo_sabin_subdivide`.
+    DooSabin,
+}
+
+/// What `Mesh::subdivide_with` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubdivideOptions {
+    pub prepass: Prepass,
+    pub scheme: SubdivisionScheme,
+    pub levels: usize,
+}
+
+impl Default for SubdivideOptions {
+    fn default() -> Self {
+        SubdivideOptions {
+            prepass: Prepass::None,
+            scheme: SubdivisionScheme::CatmullClark,
+            levels: 1,
+        }
+    }
+}
+
+/// Weight of the vertex itself in the Loop vertex point of an interior
+/// vertex with `valence` neighbors, each of which gets the rest shared out.
+fn loop_vertex_weight(valence: usize) -> f32 {
+    let n = valence as f32;
+    let cosine = 3.0 / 8.0 + (2.0 * PI / n).cos() / 4.0;
+    let beta = (5.0 / 8.0 - cosine * cosine) / n;
+    1.0 - n * beta
+}
+
+fn face_halfedges(mesh: &Mesh, face_id: Id) -> Vec<Id> {
+    FaceHalfedgeIterator::new(mesh, mesh.face_first_halfedge_id(face_id).unwrap()).collect()
+}
+
+impl Mesh {
+    /// Merges pairs of neighboring triangles into quads, the most square
+    /// quads first, as long as the quad is convex and the triangles are
+    /// within 30 degrees of being coplanar. Other faces are kept and
+    /// triangles without a good partner are left as they are.
+    pub fn quadrangulate(&self) -> Mesh {
+        let min_normal_dot = (30.0f32).to_radians().cos();
+        let mut candidates = Vec::new();
+        for face_id in FaceIterator::new(self) {
+            let corners = self.face_vertex_ids(face_id);
+            if corners.len() != 3 {
+                continue;
+            }
+            for halfedge_id in face_halfedges(self, face_id) {
+                let opposite_id = match self.halfedge_opposite_id(halfedge_id) {
+                    Some(opposite_id) if halfedge_id < opposite_id => opposite_id,
+                    _ => continue,
+                };
+                let other_face_id = self.halfedge_face_id(opposite_id).unwrap();
+                if other_face_id == face_id || self.face_vertex_ids(other_face_id).len() != 3 ||
+                        self.face_norm(face_id).dot(self.face_norm(other_face_id)) < min_normal_dot {
+                    continue;
+                }
+                // The triangles a, b, c and b, a, d make the quad a, d, b, c.
+                let a = self.halfedge_start_vertex_id(halfedge_id).unwrap();
+                let b = self.halfedge_start_vertex_id(opposite_id).unwrap();
+                let c = self.halfedge_start_vertex_id(self.halfedge_prev_id(halfedge_id).unwrap()).unwrap();
+                let d = self.halfedge_start_vertex_id(self.halfedge_prev_id(opposite_id).unwrap()).unwrap();
+                let quad = [a, d, b, c];
+                let normal = self.face_norm(face_id) + self.face_norm(other_face_id);
+                let mut worst = 0.0f32;
+                let mut convex = true;
+                for i in 0..4 {
+                    let position = |index: usize| self.vertex(quad[index % 4]).unwrap().position;
+                    let to_prev = position(i + 3) - position(i);
+                    let to_next = position(i + 1) - position(i);
+                    if to_next.cross(to_prev).dot(normal) <= 0.0 {
+                        convex = false;
+                        break;
+                    }
+                    worst = worst.max((to_prev.angle(to_next).0 - PI / 2.0).abs());
+                }
+                if convex {
+                    candidates.push((worst, halfedge_id, face_id, other_face_id, quad));
+                }
+            }
+        }
+        candidates.sort_by(|a, b| (a.0, a.1).partial_cmp(&(b.0, b.1)).unwrap_or(std::cmp::Ordering::Equal));
+        let mut merged: FnvHashMap<Id, Option<[Id; 4]>> = FnvHashMap::default();
+        for &(_, _, face_id, other_face_id, quad) in candidates.iter() {
+            if merged.contains_key(&face_id) || merged.contains_key(&other_face_id) {
+                continue;
+            }
+            merged.insert(face_id, Some(quad));
+            merged.insert(other_face_id, None);
+        }
+        let mut output = Mesh::new();
+        output.materials = self.materials.clone();
+        let mut new_vertices: FnvHashMap<Id, Id> = FnvHashMap::default();
+        for face_id in FaceIterator::new(self) {
+            let corners = match merged.get(&face_id) {
+                Some(&Some(quad)) => quad.to_vec(),
+                Some(&None) => continue,
+                None => self.face_vertex_ids(face_id),
+            };
+            let corners = corners.iter().map(|&vertex_id| {
+                let position = self.vertex(vertex_id).unwrap().position;
+                *new_vertices.entry(vertex_id).or_insert_with(|| output.add_vertex(position))
+            }).collect();
+            let new_face_id = output.add_vertices(corners);
+            output.set_face_material(new_face_id, self.face_material(face_id));
+        }
+        output
+    }
+
+    /// One level of Loop subdivision, which splits every triangle into four
+    /// and smooths towards a surface with continuous curvature almost
+    /// everywhere. Boundaries follow the cubic B-spline of their vertices.
+    /// Face materials are kept.
+    pub fn loop_subdivide(&self) -> Result<Mesh, TopologyError> {
+        if let Some(face_id) = FaceIterator::new(self).find(|&face_id| self.face_vertex_ids(face_id).len() != 3) {
+            return Err(TopologyError::NotATriangle { face: face_id });
+        }
+        let mut output = Mesh::new();
+        output.materials = self.materials.clone();
+        let position = |vertex_id: Id| self.vertex(vertex_id).unwrap().position.to_vec();
+        let end_vertex_id = |halfedge_id: Id| self.halfedge_start_vertex_id(self.halfedge_next_id(halfedge_id).unwrap()).unwrap();
+        let mut vertex_points: FnvHashMap<Id, Id> = FnvHashMap::default();
+        for vertex in self.vertices.iter().filter(|vertex| vertex.alive && !vertex.halfedges.is_empty()) {
+            let boundary_next = vertex.halfedges.iter()
+                .find(|&&halfedge_id| self.halfedge_opposite_id(halfedge_id).is_none())
+                .map(|&halfedge_id| end_vertex_id(halfedge_id));
+            let boundary_prev = vertex.halfedges.iter()
+                .map(|&halfedge_id| self.halfedge_prev_id(halfedge_id).unwrap())
+                .find(|&halfedge_id| self.halfedge_opposite_id(halfedge_id).is_none())
+                .map(|halfedge_id| self.halfedge_start_vertex_id(halfedge_id).unwrap());
+            let point = match (boundary_next, boundary_prev) {
+                (Some(next), Some(prev)) => position(vertex.id) * 0.75 + (position(next) + position(prev)) * 0.125,
+                _ => {
+                    let valence = vertex.halfedges.len();
+                    let weight = loop_vertex_weight(valence);
+                    let neighbor_sum = vertex.halfedges.iter()
+                        .fold(cgmath::Vector3::zero(), |sum, &halfedge_id| sum + position(end_vertex_id(halfedge_id)));
+                    position(vertex.id) * weight + neighbor_sum * ((1.0 - weight) / valence as f32)
+                },
+            };
+            vertex_points.insert(vertex.id, output.add_vertex(Point3::from_vec(point)));
+        }
+        let mut edge_points: FnvHashMap<EdgeEndpoints, Id> = FnvHashMap::default();
+        for face_id in FaceIterator::new(self) {
+            let halfedges = face_halfedges(self, face_id);
+            let mut corners = Vec::with_capacity(3);
+            let mut middles = Vec::with_capacity(3);
+            for &halfedge_id in halfedges.iter() {
+                let from = self.halfedge_start_vertex_id(halfedge_id).unwrap();
+                let to = end_vertex_id(halfedge_id);
+                corners.push(vertex_points[&from]);
+                let key = EdgeEndpoints::new(from, to);
+                if let Some(&edge_point) = edge_points.get(&key) {
+                    middles.push(edge_point);
+                    continue;
+                }
+                let across = |halfedge_id: Id| position(self.halfedge_start_vertex_id(self.halfedge_prev_id(halfedge_id).unwrap()).unwrap());
+                let point = match self.halfedge_opposite_id(halfedge_id) {
+                    Some(opposite_id) => (position(from) + position(to)) * 0.375 + (across(halfedge_id) + across(opposite_id)) * 0.125,
+                    None => (position(from) + position(to)) * 0.5,
+                };
+                let edge_point = output.add_vertex(Point3::from_vec(point));
+                edge_points.insert(key, edge_point);
+                middles.push(edge_point);
+            }
+            let material = self.face_material(face_id);
+            for new_face in [vec![corners[0], middles[0], middles[2]],
+                    vec![middles[0], corners[1], middles[1]],
+                    vec![middles[2], middles[1], corners[2]],
+                    vec![middles[0], middles[1], middles[2]]].iter() {
+                let new_face_id = output.add_vertices(new_face.clone());
+                output.set_face_material(new_face_id, material);
+            }
+        }
+        Ok(output)
+    }
+
+    /// One level of Doo-Sabin subdivision, which shrinks every face towards
+    /// its center and fills the gaps with a quad for every interior edge and
+    /// a face for every interior vertex. Faces around boundary edges and
+    /// vertices are not filled, so boundaries stay open. The shrunk faces
+    /// keep their materials.
+    pub fn doo_sabin_subdivide(&self) -> Mesh {
+        let mut output = Mesh::new();
+        output.materials = self.materials.clone();
+        let mut corner_points: FnvHashMap<Id, Id> = FnvHashMap::default();
+        for face_id in FaceIterator::new(self) {
+            let halfedges = face_halfedges(self, face_id);
+            let positions: Vec<_> = halfedges.iter()
+                .map(|&halfedge_id| self.halfedge_start_vertex(halfedge_id).unwrap().position.to_vec())
+                .collect();
+            let n = positions.len();
+            let mut corners = Vec::with_capacity(n);
+            for i in 0..n {
+                let mut point = cgmath::Vector3::zero();
+                for (j, offset) in (0..n).map(|j| (j, positions[(i + j) % n])) {
+                    let weight = if j == 0 {
+                        0.25 + 5.0 / (4.0 * n as f32)
+                    } else {
+                        (3.0 + 2.0 * (2.0 * PI * j as f32 / n as f32).cos()) / (4.0 * n as f32)
+                    };
+                    point += offset * weight;
+                }
+                let corner = output.add_vertex(Point3::from_vec(point));
+                corner_points.insert(halfedges[i], corner);
+                corners.push(corner);
+            }
+            let new_face_id = output.add_vertices(corners);
+            output.set_face_material(new_face_id, self.face_material(face_id));
+        }
+        for face_id in FaceIterator::new(self) {
+            for halfedge_id in face_halfedges(self, face_id) {
+                let opposite_id = match self.halfedge_opposite_id(halfedge_id) {
+                    Some(opposite_id) if halfedge_id < opposite_id => opposite_id,
+                    _ => continue,
+                };
+                let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+                let opposite_next_id = self.halfedge_next_id(opposite_id).unwrap();
+                output.add_vertices(vec![corner_points[&next_id], corner_points[&halfedge_id],
+                    corner_points[&opposite_next_id], corner_points[&opposite_id]]);
+            }
+        }
+        for vertex in self.vertices.iter().filter(|vertex| vertex.alive) {
+            let ordered = self.ordered_vertex_halfedges(vertex.id);
+            if ordered.len() < 3 || ordered.len() != vertex.halfedges.len() ||
+                    ordered.iter().any(|&halfedge_id| self.halfedge_opposite_id(halfedge_id).is_none()) {
+                continue;
+            }
+            output.add_vertices(ordered.iter().map(|halfedge_id| corner_points[halfedge_id]).collect());
+        }
+        output
+    }
+
+    /// Runs the prepass once, then the scheme `levels` times. Fails before
+    /// doing anything if the scheme cannot take the faces the prepass leaves,
+    /// like Loop subdivision of quads.
+    pub fn subdivide_with(&self, options: &SubdivideOptions) -> Result<Mesh, TopologyError> {
+        let mut mesh = match options.prepass {
+            Prepass::None => self.duplicate(),
+            Prepass::Triangulate => self.triangulate(),
+            Prepass::Quadrangulate => self.quadrangulate(),
+        };
+        if options.scheme == SubdivisionScheme::Loop {
+            if let Some(face_id) = FaceIterator::new(&mesh).find(|&face_id| mesh.face_vertex_ids(face_id).len() != 3) {
+                return Err(TopologyError::NotATriangle { face: face_id });
+            }
+        }
+        for _ in 0..options.levels {
+            mesh = match options.scheme {
+                SubdivisionScheme::CatmullClark => mesh.subdivide_n(1),
+                SubdivisionScheme::Loop => mesh.loop_subdivide()?,
+                SubdivisionScheme::DooSabin => mesh.doo_sabin_subdivide(),
+            };
+        }
+        Ok(mesh)
+    }
+}
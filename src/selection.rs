@@ -0,0 +1,72 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use fnv::FnvHashSet;
+use iterator::FaceIterator;
+use mesh::Id;
+use mesh::Mesh;
+
+/// A set of faces that editing operations such as `Mesh::subdivide_region`
+/// are restricted to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    pub faces: FnvHashSet<Id>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Selection::default()
+    }
+
+    pub fn from_faces<I: IntoIterator<Item = Id>>(face_ids: I) -> Self {
+        Selection {
+            faces: face_ids.into_iter().collect(),
+        }
+    }
+
+    pub fn contains(&self, face_id: Id) -> bool {
+        self.faces.contains(&face_id)
+    }
+
+    pub fn insert(&mut self, face_id: Id) {
+        self.faces.insert(face_id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.faces.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+}
+
+impl Mesh {
+    /// Faces whose `face_center` passes the test, in one pass over the
+    /// faces, which is cheaper than building a `Bvh` for a single query.
+    fn select_faces_by_center<F: Fn(Point3<f32>) -> bool>(&self, test: F) -> Selection {
+        Selection::from_faces(FaceIterator::new(self).filter(|&face_id| test(self.face_center(face_id))))
+    }
+
+    /// Faces whose centers lie within `radius` of `center`, the sphere
+    /// surface included.
+    pub fn select_faces_in_sphere(&self, center: Point3<f32>, radius: f32) -> Selection {
+        let radius2 = radius * radius;
+        self.select_faces_by_center(|face_center| face_center.distance2(center) <= radius2)
+    }
+
+    /// Faces whose centers lie in the axis aligned box from `min` to `max`,
+    /// its sides included.
+    pub fn select_faces_in_box(&self, min: Point3<f32>, max: Point3<f32>) -> Selection {
+        self.select_faces_by_center(|face_center| {
+            (0..3).all(|axis| face_center[axis] >= min[axis] && face_center[axis] <= max[axis])
+        })
+    }
+
+    /// Faces whose centers lie on the side of the plane through `point` that
+    /// `normal` points to. Faces centered on the plane are left out, so the
+    /// two sides never share a face.
+    pub fn select_faces_by_plane_side(&self, normal: Vector3<f32>, point: Point3<f32>) -> Selection {
+        self.select_faces_by_center(|face_center| (face_center - point).dot(normal) > 0.0)
+    }
+}
@@ -0,0 +1,701 @@
+use cgmath::Point2;
+use cgmath::Point3;
+use cgmath::Vector3;
+use attribute::AttributeStore;
+use cgmath::prelude::*;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use material::Material;
+use mesh::EdgeEndpoints;
+use mesh::Id;
+use mesh::Mesh;
+
+/// Weight of the planes holding boundary vertices on the boundary, relative
+/// to the planes of the faces.
+const BOUNDARY_WEIGHT: f64 = 1000.0;
+
+/// Controls how `Mesh::simplify_to` treats what is attached to the vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SimplifyOptions {
+    /// Samples the UVs of every merged vertex from the faces around the
+    /// collapsed edge, at the position the vertex ends up at, and keeps
+    /// vertices on UV seams in place. Otherwise the corners keep the UVs they
+    /// had, which smears textures.
+    pub preserve_attributes: bool,
+}
+
+/// Sum of squared distances to a set of planes, as the upper triangle of the
+/// symmetric 4x4 matrix. Face planes are weighted by the area of their face,
+/// so slivers count for little, except for `Mesh::simplify_to_error`, where
+/// the error has to bound the squared distance to every plane summed into
+/// it.
+#[derive(Debug, Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: Vector3<f32>, point: Point3<f32>, weight: f64) -> Self {
+        let (a, b, c) = (normal.x as f64, normal.y as f64, normal.z as f64);
+        let d = -(a * point.x as f64 + b * point.y as f64 + c * point.z as f64);
+        Quadric([a * a * weight, a * b * weight, a * c * weight, a * d * weight,
+            b * b * weight, b * c * weight, b * d * weight,
+            c * c * weight, c * d * weight,
+            d * d * weight])
+    }
+
+    fn add(&self, other: &Quadric) -> Quadric {
+        let mut sum = *self;
+        for (value, other_value) in sum.0.iter_mut().zip(other.0.iter()) {
+            *value += other_value;
+        }
+        sum
+    }
+
+    fn error(&self, point: Point3<f32>) -> f64 {
+        let q = &self.0;
+        let (x, y, z) = (point.x as f64, point.y as f64, point.z as f64);
+        q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x
+            + q[4] * y * y + 2.0 * q[5] * y * z + 2.0 * q[6] * y
+            + q[7] * z * z + 2.0 * q[8] * z
+            + q[9]
+    }
+}
+
+/// Newell normal of a polygon, its length being twice the area.
+fn loop_normal(points: &[Point3<f32>]) -> Vector3<f32> {
+    let mut normal = Vector3::zero();
+    for (i, point) in points.iter().enumerate() {
+        let next = points[(i + 1) % points.len()];
+        normal += point.to_vec().cross(next.to_vec());
+    }
+    normal
+}
+
+/// Barycentric coordinates of the point of the triangle closest to `point`,
+/// approximated by clamping those of its projection on the plane.
+fn clamped_barycentric(point: Point3<f32>, corners: [Point3<f32>; 3]) -> Option<[f32; 3]> {
+    let edge1 = corners[1] - corners[0];
+    let edge2 = corners[2] - corners[0];
+    let offset = point - corners[0];
+    let (d11, d12, d22) = (edge1.dot(edge1), edge1.dot(edge2), edge2.dot(edge2));
+    let denominator = d11 * d22 - d12 * d12;
+    if denominator.abs() < 1e-12 {
+        return None;
+    }
+    let (d31, d32) = (offset.dot(edge1), offset.dot(edge2));
+    let v = ((d22 * d31 - d12 * d32) / denominator).max(0.0);
+    let w = ((d11 * d32 - d12 * d31) / denominator).max(0.0);
+    let u = (1.0 - v - w).max(0.0);
+    let sum = u + v + w;
+    Some([u / sum, v / sum, w / sum])
+}
+
+/// Undoes one edge collapse of `Mesh::to_progressive`, bringing back a
+/// vertex and the faces around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexSplit {
+    /// Vertex the new one splits off from, and where it moves back to.
+    pub vertex: Id,
+    pub position: Point3<f32>,
+
+    pub new_vertex: Id,
+    pub new_position: Point3<f32>,
+
+    /// Every face around the two vertices as it is after the split. Faces
+    /// the collapse removed come back here.
+    pub faces: Vec<SplitFace>,
+}
+
+/// Face restored by a `VertexSplit`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitFace {
+    /// Position of the face in the original mesh.
+    pub index: usize,
+    pub vertices: Vec<Id>,
+    pub uvs: Vec<Option<Point2<f32>>>,
+}
+
+/// Base mesh and the vertex splits that refine it back to the mesh it was
+/// made from, as made by `Mesh::to_progressive`. Faces are stored by their
+/// position in the original mesh, empty while collapsed, and vertices by
+/// their original id.
+#[derive(Debug, Clone)]
+pub struct ProgressiveMesh {
+    pub positions: Vec<Point3<f32>>,
+    pub faces: Vec<Vec<Id>>,
+    pub corner_uvs: Vec<Vec<Option<Point2<f32>>>>,
+    pub face_materials: Vec<Option<usize>>,
+    pub vertex_sources: Vec<i32>,
+    pub vertex_tags: Vec<u32>,
+    pub materials: Vec<Material>,
+
+    /// Coarsest first, the reverse of the order the edges were collapsed in.
+    pub splits: Vec<VertexSplit>,
+
+    attributes: SourceAttributes,
+}
+
+impl ProgressiveMesh {
+    /// Applies the longest prefix of the splits that keeps the face count at
+    /// most `faces` and builds the result. Asking for fewer faces than the
+    /// base mesh has gives the base mesh, asking for at least as many as the
+    /// original had gives the original back.
+    pub fn at_detail(&self, faces: usize) -> Mesh {
+        let mut positions = self.positions.clone();
+        let mut loops = self.faces.clone();
+        let mut corner_uvs = self.corner_uvs.clone();
+        let mut face_count = loops.iter().filter(|face| !face.is_empty()).count();
+        for split in self.splits.iter() {
+            let restored = split.faces.iter().filter(|face| loops[face.index].is_empty()).count();
+            if face_count + restored > faces {
+                break;
+            }
+            positions[split.vertex] = split.position;
+            positions[split.new_vertex] = split.new_position;
+            for face in split.faces.iter() {
+                loops[face.index] = face.vertices.clone();
+                corner_uvs[face.index] = face.uvs.clone();
+            }
+            face_count += restored;
+        }
+        build_mesh(&positions, &loops, &corner_uvs, &self.face_materials, &self.materials, &self.attributes,
+            |vertex_id| (self.vertex_sources[vertex_id], self.vertex_tags[vertex_id]))
+    }
+}
+
+/// The user attributes of the mesh a `Simplifier` or `ProgressiveMesh` was
+/// made from, with the id every face and every corner had there. Vertices
+/// keep their ids while simplifying, faces their position and corners their
+/// vertex, so merged vertices keep the values of the one that stays, and
+/// corners moved to another vertex lose their halfedge values.
+#[derive(Debug, Clone, Default)]
+struct SourceAttributes {
+    attributes: AttributeStore,
+    face_ids: Vec<Id>,
+    corner_halfedges: Vec<FnvHashMap<Id, Id>>,
+}
+
+impl SourceAttributes {
+    fn new(mesh: &Mesh) -> Self {
+        if mesh.attributes.is_empty() {
+            return SourceAttributes::default();
+        }
+        let mut source = SourceAttributes {
+            attributes: mesh.attributes.clone(),
+            face_ids: Vec::with_capacity(mesh.face_count),
+            corner_halfedges: Vec::with_capacity(mesh.face_count),
+        };
+        for face_id in FaceIterator::new(mesh) {
+            let face_halfedge = mesh.face_first_halfedge_id(face_id).unwrap();
+            source.face_ids.push(face_id);
+            source.corner_halfedges.push(FaceHalfedgeIterator::new(mesh, face_halfedge).into_vec().into_iter()
+                .map(|halfedge_id| (mesh.halfedge_start_vertex_id(halfedge_id).unwrap(), halfedge_id))
+                .collect());
+        }
+        source
+    }
+}
+
+/// Builds a mesh from faces given as vertex loops, adding each vertex once
+/// with the source and tags `vertex_data` gives for it, and copying the
+/// values of `attributes` to the elements that are left.
+fn build_mesh<F>(positions: &[Point3<f32>], faces: &[Vec<Id>], corner_uvs: &[Vec<Option<Point2<f32>>>],
+        face_materials: &[Option<usize>], materials: &[Material], attributes: &SourceAttributes, vertex_data: F) -> Mesh
+        where F: Fn(Id) -> (i32, u32) {
+    let mut mesh = Mesh::new();
+    mesh.materials = materials.to_vec();
+    let mut new_vertices: FnvHashMap<Id, Id> = FnvHashMap::default();
+    let carry_attributes = !attributes.attributes.is_empty();
+    let mut face_targets: Vec<(Id, Vec<(Id, f32)>)> = Vec::new();
+    let mut halfedge_targets: Vec<(Id, Vec<(Id, f32)>)> = Vec::new();
+    let mut edge_sources: FnvHashMap<EdgeEndpoints, EdgeEndpoints> = FnvHashMap::default();
+    for (face_index, face) in faces.iter().enumerate() {
+        if face.is_empty() {
+            continue;
+        }
+        let mut added_halfedges = Vec::with_capacity(face.len());
+        for (&vertex_id, uv) in face.iter().zip(corner_uvs[face_index].iter()) {
+            let new_vertex_id = match new_vertices.get(&vertex_id) {
+                Some(&new_vertex_id) => new_vertex_id,
+                None => {
+                    let new_vertex_id = mesh.add_vertex(positions[vertex_id]);
+                    let (source, tags) = vertex_data(vertex_id);
+                    mesh.vertex_mut(new_vertex_id).unwrap().source = source;
+                    mesh.set_vertex_tags(new_vertex_id, tags);
+                    new_vertices.insert(vertex_id, new_vertex_id);
+                    new_vertex_id
+                },
+            };
+            let halfedge_id = mesh.add_halfedge();
+            if let Some(uv) = *uv {
+                mesh.set_halfedge_uv(halfedge_id, uv);
+            }
+            added_halfedges.push((halfedge_id, new_vertex_id));
+            if carry_attributes {
+                if let Some(&source_halfedge_id) = attributes.corner_halfedges[face_index].get(&vertex_id) {
+                    halfedge_targets.push((halfedge_id, vec![(source_halfedge_id, 1.0)]));
+                }
+            }
+        }
+        let face_id = mesh.add_halfedges_and_vertices(&added_halfedges);
+        mesh.set_face_material(face_id, face_materials[face_index]);
+        if carry_attributes {
+            face_targets.push((face_id, vec![(attributes.face_ids[face_index], 1.0)]));
+            for i in 0..face.len() {
+                let (low, high) = (face[i], face[(i + 1) % face.len()]);
+                edge_sources.insert(EdgeEndpoints::new(new_vertices[&low], new_vertices[&high]), EdgeEndpoints::new(low, high));
+            }
+        }
+    }
+    if carry_attributes {
+        let mut vertex_targets: Vec<(Id, Vec<(Id, f32)>)> = new_vertices.iter()
+            .map(|(&old_id, &new_id)| (new_id, vec![(old_id, 1.0)]))
+            .collect();
+        vertex_targets.sort_by_key(|&(id, _)| id);
+        let edge_targets: Vec<(EdgeEndpoints, Vec<(EdgeEndpoints, f32)>)> = edge_sources.into_iter()
+            .map(|(endpoints, source)| (endpoints, vec![(source, 1.0)]))
+            .collect();
+        mesh.attributes.resample_vertices(&attributes.attributes, &vertex_targets);
+        mesh.attributes.resample_faces(&attributes.attributes, &face_targets);
+        mesh.attributes.resample_halfedges(&attributes.attributes, &halfedge_targets);
+        mesh.attributes.resample_edges(&attributes.attributes, &edge_targets);
+    }
+    mesh
+}
+
+/// Faces as vertex loops with a UV per corner, collapsed in place.
+struct Simplifier {
+    positions: Vec<Point3<f32>>,
+    quadrics: Vec<Quadric>,
+    faces: Vec<Vec<Id>>,
+    corner_uvs: Vec<Vec<Option<Point2<f32>>>>,
+    vertex_faces: Vec<Vec<usize>>,
+    boundary_vertices: FnvHashSet<Id>,
+    face_count: usize,
+
+    /// Inverse of every collapse so far, when recording them.
+    splits: Option<Vec<VertexSplit>>,
+}
+
+impl Simplifier {
+    fn new(mesh: &Mesh) -> Self {
+        Simplifier::with_area_weights(mesh, true)
+    }
+
+    /// Sets up the quadrics with the face planes weighted by area or not,
+    /// see `Quadric`.
+    fn with_area_weights(mesh: &Mesh, area_weights: bool) -> Self {
+        let mut simplifier = Simplifier {
+            positions: vec![Point3::new(0.0, 0.0, 0.0); mesh.vertices.len() + 1],
+            quadrics: vec![Quadric::default(); mesh.vertices.len() + 1],
+            faces: Vec::with_capacity(mesh.face_count),
+            corner_uvs: Vec::with_capacity(mesh.face_count),
+            vertex_faces: vec![Vec::new(); mesh.vertices.len() + 1],
+            boundary_vertices: FnvHashSet::default(),
+            face_count: 0,
+            splits: None,
+        };
+        for vertex in mesh.vertices.iter().filter(|vertex| vertex.alive) {
+            simplifier.positions[vertex.id] = vertex.position;
+        }
+        for face_id in FaceIterator::new(mesh) {
+            let face_halfedge = mesh.face_first_halfedge_id(face_id).unwrap();
+            let halfedges = FaceHalfedgeIterator::new(mesh, face_halfedge).into_vec();
+            let face_index = simplifier.faces.len();
+            let vertex_ids: Vec<Id> = halfedges.iter().map(|&id| mesh.halfedge_start_vertex_id(id).unwrap()).collect();
+            for &vertex_id in vertex_ids.iter() {
+                simplifier.vertex_faces[vertex_id].push(face_index);
+            }
+            simplifier.faces.push(vertex_ids);
+            simplifier.corner_uvs.push(halfedges.iter().map(|&id| mesh.halfedge_uv(id)).collect());
+            simplifier.face_count += 1;
+        }
+        let mut edge_faces: FnvHashMap<EdgeEndpoints, usize> = FnvHashMap::default();
+        for face in simplifier.faces.iter() {
+            let points: Vec<Point3<f32>> = face.iter().map(|&id| simplifier.positions[id]).collect();
+            for i in 0..face.len() {
+                *edge_faces.entry(EdgeEndpoints::new(face[i], face[(i + 1) % face.len()])).or_insert(0) += 1;
+            }
+            let normal = loop_normal(&points);
+            let area = normal.magnitude() * 0.5;
+            if area <= 0.0 {
+                continue;
+            }
+            let weight = if area_weights { area as f64 } else { 1.0 };
+            let plane = Quadric::from_plane(normal / (area * 2.0), points[0], weight);
+            for &vertex_id in face.iter() {
+                simplifier.quadrics[vertex_id] = simplifier.quadrics[vertex_id].add(&plane);
+            }
+        }
+        // Boundary edges also get a plane through them, perpendicular to
+        // their face, so collapses cannot pull the boundary inwards.
+        for face in simplifier.faces.iter() {
+            let points: Vec<Point3<f32>> = face.iter().map(|&id| simplifier.positions[id]).collect();
+            let face_normal = loop_normal(&points);
+            for i in 0..face.len() {
+                let (from, to) = (face[i], face[(i + 1) % face.len()]);
+                if edge_faces.get(&EdgeEndpoints::new(from, to)) != Some(&1) {
+                    continue;
+                }
+                let edge = simplifier.positions[to] - simplifier.positions[from];
+                let normal = edge.cross(face_normal);
+                if normal.magnitude2() <= 0.0 {
+                    continue;
+                }
+                let plane = Quadric::from_plane(normal.normalize(), simplifier.positions[from],
+                    BOUNDARY_WEIGHT * edge.magnitude2() as f64);
+                simplifier.quadrics[from] = simplifier.quadrics[from].add(&plane);
+                simplifier.quadrics[to] = simplifier.quadrics[to].add(&plane);
+                simplifier.boundary_vertices.insert(from);
+                simplifier.boundary_vertices.insert(to);
+            }
+        }
+        simplifier
+    }
+
+    fn neighbors(&self, vertex_id: Id) -> FnvHashSet<Id> {
+        let mut neighbors = FnvHashSet::default();
+        for &face_index in self.vertex_faces[vertex_id].iter() {
+            let face = &self.faces[face_index];
+            let i = face.iter().position(|&id| id == vertex_id).unwrap();
+            neighbors.insert(face[(i + 1) % face.len()]);
+            neighbors.insert(face[(i + face.len() - 1) % face.len()]);
+        }
+        neighbors
+    }
+
+    /// Whether the corners of the vertex disagree on its UV.
+    fn is_seam(&self, vertex_id: Id) -> bool {
+        let mut uvs = self.vertex_faces[vertex_id].iter().map(|&face_index| {
+            let i = self.faces[face_index].iter().position(|&id| id == vertex_id).unwrap();
+            self.corner_uvs[face_index][i].map(|uv| [uv.x.to_bits(), uv.y.to_bits()])
+        });
+        match uvs.next() {
+            Some(first) => uvs.any(|uv| uv != first),
+            None => false,
+        }
+    }
+
+    /// Checks that merging `b` into `a` at `position` keeps the surface
+    /// manifold and does not fold any face over.
+    fn can_collapse(&self, a: Id, b: Id, position: Point3<f32>, edge_is_boundary: bool) -> bool {
+        if !edge_is_boundary && self.boundary_vertices.contains(&a) && self.boundary_vertices.contains(&b) {
+            return false;
+        }
+        let mut opposite_vertices = FnvHashSet::default();
+        for &face_index in self.vertex_faces[b].iter() {
+            let face = &self.faces[face_index];
+            if !face.contains(&a) {
+                continue;
+            }
+            let i = face.iter().position(|&id| id == b).unwrap();
+            let next = face[(i + 1) % face.len()];
+            let prev = face[(i + face.len() - 1) % face.len()];
+            if next != a && prev != a {
+                return false;
+            }
+            if face.len() == 3 {
+                opposite_vertices.insert(face.iter().cloned().find(|&id| id != a && id != b).unwrap());
+            }
+        }
+        let a_neighbors = self.neighbors(a);
+        if self.neighbors(b).iter().any(|id| *id != a && a_neighbors.contains(id) && !opposite_vertices.contains(id)) {
+            return false;
+        }
+        for &vertex_id in [a, b].iter() {
+            for &face_index in self.vertex_faces[vertex_id].iter() {
+                let face = &self.faces[face_index];
+                if face.contains(&a) && face.contains(&b) {
+                    continue;
+                }
+                let before: Vec<Point3<f32>> = face.iter().map(|&id| self.positions[id]).collect();
+                let after: Vec<Point3<f32>> = face.iter()
+                    .map(|&id| if id == vertex_id { position } else { self.positions[id] })
+                    .collect();
+                if loop_normal(&before).dot(loop_normal(&after)) <= 0.0 {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// UV at `position` on the closest triangle of the faces around `a` and
+    /// `b`.
+    fn sample_uv(&self, a: Id, b: Id, position: Point3<f32>) -> Option<Point2<f32>> {
+        let mut best: Option<(f32, Point2<f32>)> = None;
+        let mut face_indices = self.vertex_faces[a].clone();
+        face_indices.extend(self.vertex_faces[b].iter().cloned());
+        for face_index in face_indices {
+            let face = &self.faces[face_index];
+            let uvs = &self.corner_uvs[face_index];
+            for i in 1..face.len().saturating_sub(1) {
+                let corners = [0, i, i + 1];
+                let (uv0, uv1, uv2) = match (uvs[corners[0]], uvs[corners[1]], uvs[corners[2]]) {
+                    (Some(uv0), Some(uv1), Some(uv2)) => (uv0, uv1, uv2),
+                    _ => continue,
+                };
+                let points = [self.positions[face[0]], self.positions[face[i]], self.positions[face[i + 1]]];
+                let weights = match clamped_barycentric(position, points) {
+                    Some(weights) => weights,
+                    None => continue,
+                };
+                let closest = Point3::from_vec(points[0].to_vec() * weights[0]
+                    + points[1].to_vec() * weights[1] + points[2].to_vec() * weights[2]);
+                let distance = closest.distance2(position);
+                if best.is_none_or(|(best_distance, _)| distance < best_distance) {
+                    let uv = Point2::from_vec(uv0.to_vec() * weights[0] + uv1.to_vec() * weights[1] + uv2.to_vec() * weights[2]);
+                    best = Some((distance, uv));
+                }
+            }
+        }
+        best.map(|(_, uv)| uv)
+    }
+
+    /// Merges `b` into `a`, moving `a` to `position`.
+    fn collapse(&mut self, a: Id, b: Id, position: Point3<f32>, preserve_attributes: bool) {
+        let sampled_uv = if preserve_attributes {
+            self.sample_uv(a, b, position)
+        } else {
+            None
+        };
+        let split = if self.splits.is_some() {
+            let mut face_indices = self.vertex_faces[a].clone();
+            face_indices.extend(self.vertex_faces[b].iter().filter(|index| !self.vertex_faces[a].contains(index)));
+            Some(VertexSplit {
+                vertex: a,
+                position: self.positions[a],
+                new_vertex: b,
+                new_position: self.positions[b],
+                faces: face_indices.into_iter().map(|index| SplitFace {
+                    index,
+                    vertices: self.faces[index].clone(),
+                    uvs: self.corner_uvs[index].clone(),
+                }).collect(),
+            })
+        } else {
+            None
+        };
+        if let (Some(splits), Some(split)) = (self.splits.as_mut(), split) {
+            splits.push(split);
+        }
+        self.positions[a] = position;
+        self.quadrics[a] = self.quadrics[a].add(&self.quadrics[b]);
+        if self.boundary_vertices.remove(&b) {
+            self.boundary_vertices.insert(a);
+        }
+        for face_index in self.vertex_faces[b].split_off(0) {
+            let i = self.faces[face_index].iter().position(|&id| id == b).unwrap();
+            if self.faces[face_index].contains(&a) {
+                self.faces[face_index].remove(i);
+                self.corner_uvs[face_index].remove(i);
+            } else {
+                self.faces[face_index][i] = a;
+                self.vertex_faces[a].push(face_index);
+            }
+            if self.faces[face_index].len() < 3 {
+                for vertex_id in self.faces[face_index].split_off(0) {
+                    self.vertex_faces[vertex_id].retain(|&index| index != face_index);
+                }
+                self.corner_uvs[face_index].clear();
+                self.face_count -= 1;
+            }
+        }
+        if let Some(uv) = sampled_uv {
+            for &face_index in self.vertex_faces[a].iter() {
+                let i = self.faces[face_index].iter().position(|&id| id == a).unwrap();
+                self.corner_uvs[face_index][i] = Some(uv);
+            }
+        }
+    }
+
+    /// Whether the faces around the vertex face the same way and their
+    /// corners lie within `tolerance` of the plane of its first face.
+    fn is_flat(&self, vertex_id: Id, tolerance: f32) -> bool {
+        let normals: Vec<Vector3<f32>> = self.vertex_faces[vertex_id].iter().map(|&face_index| {
+            let points: Vec<Point3<f32>> = self.faces[face_index].iter().map(|&id| self.positions[id]).collect();
+            loop_normal(&points)
+        }).collect();
+        let plane_normal = match normals.first() {
+            Some(normal) if normal.magnitude2() > 0.0 => normal.normalize(),
+            _ => return false,
+        };
+        let origin = self.positions[vertex_id];
+        normals.iter().all(|normal| normal.dot(plane_normal) > 0.0)
+            && self.vertex_faces[vertex_id].iter().all(|&face_index| {
+                self.faces[face_index].iter().all(|&id| (self.positions[id] - origin).dot(plane_normal).abs() <= tolerance)
+            })
+    }
+
+    /// Merges every interior vertex whose faces are flat within `tolerance`
+    /// into a neighbor, which keeps its position, and returns how many were
+    /// merged.
+    fn dissolve_flat_vertices(&mut self, tolerance: f32) -> usize {
+        let mut dissolved = 0;
+        loop {
+            let mut pass_dissolved = 0;
+            for vertex_id in 1..self.positions.len() {
+                if self.vertex_faces[vertex_id].is_empty() || self.boundary_vertices.contains(&vertex_id)
+                        || self.is_seam(vertex_id) || !self.is_flat(vertex_id, tolerance) {
+                    continue;
+                }
+                let mut neighbors: Vec<Id> = self.neighbors(vertex_id).into_iter().collect();
+                neighbors.sort();
+                let target = neighbors.into_iter()
+                    .find(|&neighbor_id| self.can_collapse(neighbor_id, vertex_id, self.positions[neighbor_id], false));
+                if let Some(neighbor_id) = target {
+                    let position = self.positions[neighbor_id];
+                    self.collapse(neighbor_id, vertex_id, position, false);
+                    pass_dissolved += 1;
+                }
+            }
+            if pass_dissolved == 0 {
+                break;
+            }
+            dissolved += pass_dissolved;
+        }
+        dissolved
+    }
+
+    /// Builds the faces left as a new mesh carrying over the materials, tags,
+    /// corner UVs and user attributes of `source`, the mesh the simplifier
+    /// was made from.
+    fn to_mesh(&self, source: &Mesh) -> Mesh {
+        let face_materials: Vec<Option<usize>> = FaceIterator::new(source)
+            .map(|face_id| source.face_material(face_id))
+            .collect();
+        build_mesh(&self.positions, &self.faces, &self.corner_uvs, &face_materials, &source.materials, &SourceAttributes::new(source),
+            |vertex_id| (source.vertex(vertex_id).unwrap().source, source.vertex_tags(vertex_id)))
+    }
+
+    /// Collapses the cheapest edges costing at most `max_error`, each vertex
+    /// at most once, and returns how many were collapsed.
+    fn collapse_pass(&mut self, target_face_count: usize, max_error: f64, options: &SimplifyOptions) -> usize {
+        let mut edge_faces: FnvHashMap<EdgeEndpoints, usize> = FnvHashMap::default();
+        for face in self.faces.iter() {
+            for i in 0..face.len() {
+                *edge_faces.entry(EdgeEndpoints::new(face[i], face[(i + 1) % face.len()])).or_insert(0) += 1;
+            }
+        }
+        let mut candidates = Vec::with_capacity(edge_faces.len());
+        for (endpoints, &count) in edge_faces.iter() {
+            let (a, b) = (endpoints.low, endpoints.high);
+            let quadric = self.quadrics[a].add(&self.quadrics[b]);
+            let middle = self.positions[a].midpoint(self.positions[b]);
+            let (cost, position) = [self.positions[a], self.positions[b], middle].iter()
+                .map(|&position| (quadric.error(position), position))
+                .fold((f64::INFINITY, middle), |best, candidate| if candidate.0 < best.0 { candidate } else { best });
+            candidates.push((cost, a, b, position, count == 1));
+        }
+        candidates.sort_by(|first, second| {
+            first.0.partial_cmp(&second.0).unwrap_or(::std::cmp::Ordering::Equal)
+                .then((first.1, first.2).cmp(&(second.1, second.2)))
+        });
+        let mut touched = FnvHashSet::default();
+        let mut collapsed = 0;
+        for (cost, a, b, position, edge_is_boundary) in candidates {
+            if self.face_count <= target_face_count || cost > max_error {
+                break;
+            }
+            if touched.contains(&a) || touched.contains(&b) {
+                continue;
+            }
+            if options.preserve_attributes && (self.is_seam(a) || self.is_seam(b)) {
+                continue;
+            }
+            if !self.can_collapse(a, b, position, edge_is_boundary) {
+                continue;
+            }
+            touched.extend(self.neighbors(a));
+            touched.extend(self.neighbors(b));
+            self.collapse(a, b, position, options.preserve_attributes);
+            touched.insert(a);
+            collapsed += 1;
+        }
+        collapsed
+    }
+}
+
+impl Mesh {
+    /// Collapses edges in the order of the quadric error they add, until at
+    /// most `target_face_count` faces are left or no edge can be collapsed
+    /// without folding faces over or breaking the surface. Open boundaries
+    /// are kept in shape. Materials, tags, user attributes and, depending on
+    /// `options`, UVs are carried over, a merged vertex keeping the
+    /// attributes of the vertex it was merged into.
+    pub fn simplify_to(&self, target_face_count: usize, options: &SimplifyOptions) -> Mesh {
+        self.simplify(Simplifier::new(self), target_face_count, f64::INFINITY, options)
+    }
+
+    /// Collapses edges like `simplify_to`, as long as the quadric error of the
+    /// next collapse does not exceed `max_error`, a squared distance. Every
+    /// remaining vertex then lies within the square root of `max_error` of
+    /// the planes of the original faces merged into it. Returns the result
+    /// with the face count the bound allowed.
+    pub fn simplify_to_error(&self, max_error: f32) -> (Mesh, usize) {
+        let simplified = self.simplify(Simplifier::with_area_weights(self, false), 0, max_error as f64, &SimplifyOptions::default());
+        let face_count = simplified.face_count;
+        (simplified, face_count)
+    }
+
+    /// Removes the interior vertices whose faces all lie in one plane, within
+    /// `coplanarity` as a distance, by merging each into a neighbor, and
+    /// returns how many were removed. The merged faces cover the same part of
+    /// the plane as before, so flat regions lose no shape at all, while
+    /// boundaries, vertices on UV seams and vertices on curved parts or
+    /// feature edges stay as they are. Large flat faces of architectural or
+    /// CAD models end up as a few polygons spanning their outlines.
+    /// Materials, tags, UVs and the user attributes of the vertices, faces
+    /// and edges that are left are kept, but when anything was removed the
+    /// mesh is rebuilt and every vertex, face and halfedge gets a new id.
+    pub fn decimate_flat_regions(&mut self, coplanarity: f32) -> usize {
+        let mut simplifier = Simplifier::new(self);
+        let dissolved = simplifier.dissolve_flat_vertices(coplanarity);
+        if dissolved > 0 {
+            let simplified = simplifier.to_mesh(self);
+            self.replace_with(simplified);
+        }
+        dissolved
+    }
+
+    /// Collapses edges like `simplify_to` down to a base mesh of four faces,
+    /// or as far as the surface allows, recording the inverse of every
+    /// collapse. `ProgressiveMesh::at_detail` then rebuilds the mesh at any
+    /// face count in between, and at full detail gives back this mesh with
+    /// the same faces in the same order, UVs, materials, sources, tags and
+    /// user attributes included. Vertices no face uses are left out.
+    pub fn to_progressive(&self) -> ProgressiveMesh {
+        let mut simplifier = Simplifier::new(self);
+        simplifier.splits = Some(Vec::new());
+        while simplifier.collapse_pass(4, f64::INFINITY, &SimplifyOptions::default()) > 0 {}
+        let mut splits = simplifier.splits.take().unwrap();
+        splits.reverse();
+        let mut vertex_sources = vec![0; simplifier.positions.len()];
+        let mut vertex_tags = vec![0; simplifier.positions.len()];
+        for (vertex_id, vertex) in self.iter_vertices() {
+            vertex_sources[vertex_id] = vertex.source;
+            vertex_tags[vertex_id] = self.vertex_tags(vertex_id);
+        }
+        ProgressiveMesh {
+            positions: simplifier.positions,
+            faces: simplifier.faces,
+            corner_uvs: simplifier.corner_uvs,
+            face_materials: FaceIterator::new(self).map(|face_id| self.face_material(face_id)).collect(),
+            vertex_sources,
+            vertex_tags,
+            materials: self.materials.clone(),
+            splits,
+            attributes: SourceAttributes::new(self),
+        }
+    }
+
+    fn simplify(&self, mut simplifier: Simplifier, target_face_count: usize, max_error: f64, options: &SimplifyOptions) -> Mesh {
+        while simplifier.face_count > target_face_count {
+            if simplifier.collapse_pass(target_face_count, max_error, options) == 0 {
+                break;
+            }
+        }
+        simplifier.to_mesh(self)
+    }
+}
@@ -0,0 +1,86 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use mesh::EdgeEndpoints;
+use mesh::Mesh;
+
+impl Mesh {
+    /// Intersects the mesh with a plane and returns the cross-section as
+    /// polylines. Loops through closed parts of the mesh are closed, without
+    /// repeating the first point, and run counterclockwise seen from the side
+    /// `plane_normal` points to. Open surfaces can also produce open
+    /// polylines, those start and end on the boundary.
+    ///
+    /// Vertices exactly on the plane count as being in front of it, so a plane
+    /// through vertices or along edges still crosses every face cleanly.
+    pub fn slice(&self, plane_normal: Vector3<f32>, plane_point: Point3<f32>) -> Vec<Vec<Point3<f32>>> {
+        let distances: Vec<f32> = self.vertices.iter()
+            .map(|vertex| plane_normal.dot(vertex.position - plane_point))
+            .collect();
+        let in_front = |vertex_id: usize| distances[vertex_id - 1] >= 0.0;
+        let mut points: FnvHashMap<EdgeEndpoints, Point3<f32>> = FnvHashMap::default();
+        let mut segments: FnvHashMap<EdgeEndpoints, EdgeEndpoints> = FnvHashMap::default();
+        for face_id in FaceIterator::new(self) {
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            // Crossings in loop order, marking whether the loop leaves the
+            // front side there.
+            let mut crossings = Vec::new();
+            for halfedge_id in FaceHalfedgeIterator::new(self, face_halfedge) {
+                let from = self.halfedge_start_vertex_id(halfedge_id).unwrap();
+                let to = self.halfedge_start_vertex_id(self.halfedge_next_id(halfedge_id).unwrap()).unwrap();
+                if in_front(from) == in_front(to) {
+                    continue;
+                }
+                let endpoints = EdgeEndpoints::new(from, to);
+                if !points.contains_key(&endpoints) {
+                    let (from_distance, to_distance) = (distances[from - 1], distances[to - 1]);
+                    let t = from_distance / (from_distance - to_distance);
+                    let from_position = self.vertex(from).unwrap().position;
+                    let to_position = self.vertex(to).unwrap().position;
+                    points.insert(endpoints.clone(), from_position + (to_position - from_position) * t);
+                }
+                crossings.push((endpoints, in_front(from)));
+            }
+            if crossings.len() < 2 {
+                continue;
+            }
+            // Each segment runs from where the face loop leaves the front
+            // side to where it comes back, so neighboring faces chain up.
+            let first_leaving = crossings.iter().position(|&(_, leaving)| leaving).unwrap();
+            crossings.rotate_left(first_leaving);
+            for pair in crossings.chunks(2) {
+                if pair.len() == 2 {
+                    segments.insert(pair[0].0.clone(), pair[1].0.clone());
+                }
+            }
+        }
+        let mut polylines = Vec::new();
+        let mut starts: Vec<EdgeEndpoints> = segments.keys().cloned().collect();
+        starts.sort_by_key(|endpoints| (endpoints.low, endpoints.high));
+        let ends: FnvHashSet<EdgeEndpoints> = segments.values().cloned().collect();
+        // Open polylines first, from the crossings no segment ends at, then
+        // the remaining closed loops.
+        let (open_starts, loop_starts): (Vec<EdgeEndpoints>, Vec<EdgeEndpoints>) = starts.into_iter()
+            .partition(|endpoints| !ends.contains(endpoints));
+        for start in open_starts.into_iter().chain(loop_starts) {
+            if !segments.contains_key(&start) {
+                continue;
+            }
+            let mut polyline = vec![points[&start]];
+            let mut current = start.clone();
+            while let Some(next) = segments.remove(&current) {
+                if next == start {
+                    break;
+                }
+                polyline.push(points[&next]);
+                current = next;
+            }
+            polylines.push(polyline);
+        }
+        polylines
+    }
+}
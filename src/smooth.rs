@@ -0,0 +1,130 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use iterator::FaceIterator;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceTriangleIterator;
+use mesh::Id;
+use mesh::Mesh;
+use util::*;
+
+impl Mesh {
+    /// Faces sharing at least one vertex with the face, the face included.
+    fn face_one_ring(&self, face_id: Id) -> Vec<Id> {
+        let mut ring = Vec::new();
+        for halfedge_id in FaceHalfedgeIterator::new(self, self.face_first_halfedge_id(face_id).unwrap()) {
+            for &around_id in self.halfedge_start_vertex(halfedge_id).unwrap().halfedges.iter() {
+                let around_face = self.halfedge_face_id(around_id).unwrap();
+                if !ring.contains(&around_face) {
+                    ring.push(around_face);
+                }
+            }
+        }
+        ring
+    }
+
+    /// Feature preserving denoising by bilateral filtering of the face normals.
+    ///
+    /// Every iteration replaces each face normal by a weighted average of the
+    /// normals of the faces around it. The weight combines the distance between
+    /// the face centers (`sigma_c`), the difference between the normals
+    /// (`sigma_s`) and the face area, so faces across a sharp edge barely
+    /// influence each other. The vertices are then moved towards the planes of
+    /// their faces with the filtered normals, which keeps edges and corners
+    /// sharp where Laplacian smoothing would round them.
+    pub fn bilateral_smooth(&self, iterations: usize, sigma_c: f32, sigma_s: f32) -> Mesh {
+        let mut mesh = self.clone();
+        let face_ids = FaceIterator::new(&mesh).into_vec();
+        let rings: Vec<Vec<Id>> = face_ids.iter().map(|&face_id| mesh.face_one_ring(face_id)).collect();
+        let spatial_factor = -1.0 / (2.0 * sigma_c * sigma_c);
+        let range_factor = -1.0 / (2.0 * sigma_s * sigma_s);
+        let mut normals = vec![Vector3::zero(); mesh.faces.len() + 1];
+        let mut centers = vec![Point3::origin(); mesh.faces.len() + 1];
+        let mut areas = vec![0.0; mesh.faces.len() + 1];
+        let mut filtered = vec![Vector3::zero(); mesh.faces.len() + 1];
+        for _ in 0..iterations {
+            for &face_id in face_ids.iter() {
+                let normal = mesh.face_norm(face_id);
+                normals[face_id] = if is_valid_norm(normal) { normal } else { Vector3::zero() };
+                centers[face_id] = mesh.face_center(face_id);
+                areas[face_id] = mesh.face_area(face_id);
+            }
+            for (&face_id, ring) in face_ids.iter().zip(rings.iter()) {
+                let mut total = Vector3::zero();
+                for &other_id in ring {
+                    let distance2 = centers[face_id].distance2(centers[other_id]);
+                    let difference2 = (normals[face_id] - normals[other_id]).magnitude2();
+                    let weight = areas[other_id] *
+                        (distance2 * spatial_factor).exp() * (difference2 * range_factor).exp();
+                    total += normals[other_id] * weight;
+                }
+                filtered[face_id] = if total.is_zero() { normals[face_id] } else { total.normalize() };
+            }
+            for vertex in mesh.vertices.iter_mut() {
+                if !vertex.alive || vertex.halfedges.is_empty() {
+                    continue;
+                }
+                let mut offset = Vector3::zero();
+                for &halfedge_id in vertex.halfedges.iter() {
+                    let face_id = mesh.halfedges[halfedge_id - 1].face;
+                    let normal = filtered[face_id];
+                    offset += normal * normal.dot(centers[face_id] - vertex.position);
+                }
+                vertex.position += offset / vertex.halfedges.len() as f32;
+            }
+            mesh.mark_changed();
+        }
+        mesh
+    }
+
+    /// Center of mass of the solid the faces enclose, from the tetrahedra
+    /// the fan triangles span with the origin, like `volume`. None when the
+    /// volume is zero.
+    fn volume_centroid(&self) -> Option<Point3<f32>> {
+        let mut volume = 0.0;
+        let mut moment = Vector3::zero();
+        for face_id in FaceIterator::new(self) {
+            for triangle in FaceTriangleIterator::new(self, face_id) {
+                let a = self.vertex(triangle[0]).unwrap().position.to_vec();
+                let b = self.vertex(triangle[1]).unwrap().position.to_vec();
+                let c = self.vertex(triangle[2]).unwrap().position.to_vec();
+                let tetrahedron = a.dot(b.cross(c));
+                volume += tetrahedron;
+                moment += (a + b + c) * (tetrahedron / 4.0);
+            }
+        }
+        if volume == 0.0 {
+            None
+        } else {
+            Some(Point3::from_vec(moment / volume))
+        }
+    }
+
+    /// Laplacian smoothing that keeps the enclosed volume: after every
+    /// iteration of `smooth`, pulling each vertex halfway to the average of
+    /// its neighbors, the mesh is scaled uniformly about its center of mass
+    /// back to the `volume` it started with. Unlike Taubin smoothing this
+    /// restores the volume exactly, up to rounding, at the price of growing
+    /// the mesh as a whole to make up for the shrinking of its bumps. The
+    /// mesh has to be a closed manifold with consistent winding for its
+    /// volume to mean anything, others only get the plain smoothing.
+    pub fn volume_preserving_smooth(&self, iterations: usize) -> Mesh {
+        let mut mesh = self.clone();
+        let target = self.volume();
+        let closed = self.is_closed();
+        for _ in 0..iterations {
+            mesh.smooth(0.5, None);
+            let volume = mesh.volume();
+            if !closed || volume == 0.0 || (volume > 0.0) != (target > 0.0) {
+                continue;
+            }
+            let center = match mesh.volume_centroid() {
+                Some(center) => center,
+                None => continue,
+            };
+            let scale = (target / volume).cbrt();
+            mesh.map_positions_mut(|position| center + (position - center) * scale);
+        }
+        mesh
+    }
+}
@@ -0,0 +1,156 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use fnv::FnvHashMap;
+use mesh::Id;
+use mesh::Mesh;
+use std::sync::Arc;
+
+/// Sparse matrix in compressed rows, as returned by
+/// `Mesh::subdivision_matrix` and `Mesh::laplacian_matrix`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseMatrix {
+    columns: usize,
+
+    /// Row `i` is `entries[row_offsets[i]..row_offsets[i + 1]]`.
+    row_offsets: Vec<usize>,
+
+    /// Column and value of the nonzero entries, sorted by column in every
+    /// row.
+    entries: Vec<(usize, f32)>,
+}
+
+/// Edge weights of the Laplacian built by `Mesh::laplacian_matrix`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LaplacianKind {
+    /// Every edge weighs one, the combinatorial graph Laplacian.
+    Uniform,
+
+    /// Every edge weighs half its `cotangent_weights` weight, the discrete
+    /// Laplace-Beltrami operator of a triangle mesh up to the vertex areas.
+    Cotangent,
+}
+
+impl SparseMatrix {
+    /// Builds the matrix from its rows of `(column, value)` entries, in any
+    /// order. Entries in the same row and column are added up.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an entry has a column of `columns` or more.
+    pub fn from_rows(columns: usize, rows: Vec<Vec<(usize, f32)>>) -> Self {
+        let mut row_offsets = Vec::with_capacity(rows.len() + 1);
+        let mut entries: Vec<(usize, f32)> = Vec::new();
+        row_offsets.push(0);
+        for mut row in rows {
+            row.sort_by_key(|&(column, _)| column);
+            let row_start = entries.len();
+            for (column, value) in row {
+                assert!(column < columns, "column {} out of range for {} columns", column, columns);
+                if entries.len() > row_start && entries[entries.len() - 1].0 == column {
+                    entries.last_mut().unwrap().1 += value;
+                } else {
+                    entries.push((column, value));
+                }
+            }
+            row_offsets.push(entries.len());
+        }
+        SparseMatrix {
+            columns,
+            row_offsets,
+            entries,
+        }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        SparseMatrix::from_rows(size, (0..size).map(|i| vec![(i, 1.0)]).collect())
+    }
+
+    pub fn rows(&self) -> usize {
+        self.row_offsets.len() - 1
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// The nonzero `(column, value)` entries of a row, sorted by column.
+    pub fn row(&self, index: usize) -> &[(usize, f32)] {
+        &self.entries[self.row_offsets[index]..self.row_offsets[index + 1]]
+    }
+
+    /// The entry at `row` and `column`, zero when it is not stored.
+    pub fn get(&self, row: usize, column: usize) -> f32 {
+        let entries = self.row(row);
+        match entries.binary_search_by_key(&column, |&(entry_column, _)| entry_column) {
+            Ok(index) => entries[index].1,
+            Err(_) => 0.0,
+        }
+    }
+
+    /// Number of nonzero entries.
+    pub fn nonzeros(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Multiplies the matrix with a column of points, one per matrix column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of points is not the number of columns.
+    pub fn transform_points(&self, points: &[Point3<f32>]) -> Vec<Point3<f32>> {
+        assert_eq!(self.columns, points.len(), "one point per column");
+        (0..self.rows()).map(|index| {
+            Point3::from_vec(self.row(index).iter()
+                .fold(Vector3::zero(), |sum, &(column, value)| sum + points[column].to_vec() * value))
+        }).collect()
+    }
+
+    /// The product `self * other`, applying `other` first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `other` does not have as many rows as `self` has columns.
+    pub fn multiply(&self, other: &SparseMatrix) -> SparseMatrix {
+        assert_eq!(self.columns, other.rows());
+        let rows = (0..self.rows()).map(|index| {
+            let mut row: FnvHashMap<usize, f32> = FnvHashMap::default();
+            for &(middle, value) in self.row(index) {
+                for &(column, other_value) in other.row(middle) {
+                    *row.entry(column).or_insert(0.0) += value * other_value;
+                }
+            }
+            row.into_iter().collect()
+        }).collect();
+        SparseMatrix::from_rows(other.columns, rows)
+    }
+}
+
+impl Mesh {
+    /// The Laplacian of the living vertices, one row and column per vertex in
+    /// the order of the returned ids, the same order `positions` uses. Row `i`
+    /// holds `-w` for every neighbor over an edge of weight `w` and the sum of
+    /// those weights on the diagonal, so rows add up to zero and the matrix is
+    /// symmetric and positive semidefinite for nonnegative weights.
+    pub fn laplacian_matrix(&self, kind: LaplacianKind) -> (SparseMatrix, Vec<Id>) {
+        let ids: Vec<Id> = self.vertices.iter().filter(|vertex| vertex.alive).map(|vertex| vertex.id).collect();
+        let indices: FnvHashMap<Id, usize> = ids.iter().enumerate().map(|(index, &id)| (id, index)).collect();
+        let cotangents = match kind {
+            LaplacianKind::Uniform => Arc::new(FnvHashMap::default()),
+            LaplacianKind::Cotangent => self.cotangent_weights(),
+        };
+        let mut rows: Vec<Vec<(usize, f32)>> = vec![Vec::new(); ids.len()];
+        self.for_each_edge(|halfedge_id, from, to| {
+            let weight = match kind {
+                LaplacianKind::Uniform => 1.0,
+                LaplacianKind::Cotangent => cotangents.get(&halfedge_id).cloned().unwrap_or(0.0) * 0.5,
+            };
+            let (from, to) = (indices[&from], indices[&to]);
+            rows[from].push((from, weight));
+            rows[from].push((to, -weight));
+            rows[to].push((to, weight));
+            rows[to].push((from, -weight));
+        });
+        (SparseMatrix::from_rows(ids.len(), rows), ids)
+    }
+}
@@ -1,9 +1,22 @@
 use cgmath::EuclideanSpace;
+use cgmath::InnerSpace;
+use cgmath::Point2;
 use cgmath::Point3;
+use cgmath::Vector2;
+use cgmath::Vector3;
+use cgmath::Zero;
+use error::MeshError;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
 use iterator::FaceHalfedgeIterator;
 use iterator::FaceIterator;
+use mesh::EdgeEndpoints;
 use mesh::Id;
 use mesh::Mesh;
+use selection::Selection;
+use smallvec::SmallVec;
+use sparse::SparseMatrix;
+use std::mem;
 
 /// Derives Clone to allow initializing a vec with the vec![value; length]
 /// macro.
@@ -39,16 +52,43 @@ impl VertexData {
     }
 }
 
+/// Sum of the points in f64, so coordinates far from the origin keep their
+/// low bits.
+fn sum_f64(points: &[Point3<f32>]) -> Vector3<f64> {
+    points.iter().fold(Vector3::zero(), |sum, point| sum + point.to_vec().cast().unwrap())
+}
+
+/// Orders the points by their coordinates, so sums over them round the same
+/// whichever order the mesh lists them in.
+fn sort_points(points: &mut [Point3<f32>]) {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)).then(a.z.total_cmp(&b.z)));
+}
+
+/// Average of the points in the order of `sort_points`, accumulated in f64
+/// and rounded to f32 once when `precise`, otherwise the same as
+/// `Point3::centroid`.
+fn centroid(points: &mut [Point3<f32>], precise: bool) -> Point3<f32> {
+    sort_points(points);
+    if !precise {
+        return Point3::centroid(points);
+    }
+    Point3::from_vec(sum_f64(points) / points.len() as f64).cast().unwrap()
+}
+
 fn face_data_mut<'a>(
     input: &Mesh,
     id: Id,
     face_data_set: &'a mut Vec<Option<FaceData>>,
     output: &mut Mesh,
+    precise: bool,
 ) -> &'a mut FaceData {
     if face_data_set[id].is_some() {
         return face_data_set[id].as_mut().unwrap();
     }
-    let average_of_points = input.face_center(id);
+    let mut points: SmallVec<[Point3<f32>; 4]> = FaceHalfedgeIterator::new(input, input.face(id).unwrap().halfedge)
+        .map(|halfedge_id| input.halfedge_start_vertex(halfedge_id).unwrap().position)
+        .collect();
+    let average_of_points = centroid(&mut points, precise);
     face_data_set[id] = Some(FaceData {
         average_of_points,
         generated_vertex_id: output.add_vertex(average_of_points),
@@ -62,12 +102,16 @@ fn edge_data_mut<'a>(
     face_data_set: &mut Vec<Option<FaceData>>,
     edge_data_set: &'a mut Vec<Option<EdgeData>>,
     output: &mut Mesh,
+    precise: bool,
+    boundary: BoundaryInterpolation,
 ) -> &'a mut EdgeData {
     let id = input.peek_same_halfedge(id);
     if edge_data_set[id].is_some() {
         return edge_data_set[id].as_mut().unwrap();
     }
-    let mid_point = input.edge_center(id);
+    let next_id = input.halfedge_next_id(id).unwrap();
+    let mid_point = centroid(&mut [input.halfedge_start_vertex(id).unwrap().position,
+        input.halfedge_start_vertex(next_id).unwrap().position], precise);
     let (
         halfedge_face_id,
         opposite_face_id,
@@ -77,25 +121,42 @@ fn edge_data_mut<'a>(
         let halfedge = input.halfedge(id).unwrap();
         (
             halfedge.face,
-            input.halfedge(halfedge.opposite).unwrap().face,
+            input.halfedge_face_id(halfedge.opposite),
             input.halfedge(halfedge.next).unwrap().vertex,
             input.vertex(halfedge.vertex).unwrap().position,
         )
     };
-    let stop_vertex_position =
-        input.vertex(next_halfedge_vertex_id).unwrap().position;
-    let f1_data_average =
-        face_data_mut(input, halfedge_face_id, face_data_set, output)
-            .average_of_points;
-    let f2_data_average =
-        face_data_mut(input, opposite_face_id, face_data_set, output)
-            .average_of_points;
-    let center = Point3::centroid(&[
-        f1_data_average,
-        f2_data_average,
-        start_vertex_position,
-        stop_vertex_position,
-    ]);
+    // Boundary edges follow the curve through their end points, or average
+    // their one face point in when there are no boundary rules.
+    let center = match opposite_face_id {
+        Some(opposite_face_id) => {
+            let stop_vertex_position =
+                input.vertex(next_halfedge_vertex_id).unwrap().position;
+            let f1_data_average =
+                face_data_mut(input, halfedge_face_id, face_data_set, output, precise)
+                    .average_of_points;
+            let f2_data_average =
+                face_data_mut(input, opposite_face_id, face_data_set, output, precise)
+                    .average_of_points;
+            centroid(&mut [
+                f1_data_average,
+                f2_data_average,
+                start_vertex_position,
+                stop_vertex_position,
+            ], precise)
+        }
+        None if boundary == BoundaryInterpolation::None => {
+            let face_average =
+                face_data_mut(input, halfedge_face_id, face_data_set, output, precise)
+                    .average_of_points;
+            centroid(&mut [
+                face_average,
+                start_vertex_position,
+                input.vertex(next_halfedge_vertex_id).unwrap().position,
+            ], precise)
+        }
+        None => mid_point,
+    };
     edge_data_set[id] = Some(EdgeData {
         mid_point,
         generated_vertex_id: output.add_vertex(center),
@@ -103,6 +164,150 @@ fn edge_data_mut<'a>(
     edge_data_set[id].as_mut().unwrap()
 }
 
+/// The element of the input mesh an output vertex was generated from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VertexSource {
+    /// The vertex point of an input vertex.
+    Vertex(Id),
+
+    /// The edge point of an input edge, given by one of its halfedges.
+    Edge(Id),
+
+    /// The face point of an input face.
+    Face(Id),
+}
+
+/// How `CatmullClarkSubdivider` treats the boundary of an open mesh, after
+/// the boundary interpolation modes of OpenSubdiv.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundaryInterpolation {
+    /// No boundary rules, the boundary is subdivided by the interior rules
+    /// over the faces it has and shrinks away from the cage. OpenSubdiv
+    /// leaves such faces out of the limit surface instead, here they stay.
+    None,
+
+    /// Boundary edges are creases following the cubic B-spline curve through
+    /// the boundary vertices, and the corners of a single face are smoothed
+    /// along it. The default.
+    EdgesOnly,
+
+    /// Like `EdgesOnly`, but boundary vertices with a single face stay in
+    /// place, so the corners of an open patch remain sharp.
+    EdgesAndCorners,
+}
+
+/// Relates the elements of a subdivided mesh to the input mesh, for
+/// transferring UVs, colors or selections from the cage to the result.
+#[derive(Debug, Clone, Default)]
+pub struct SubdivisionMap {
+    /// Maps VERTEX ID in the OUTPUT mesh to its source.
+    pub vertices: FnvHashMap<Id, VertexSource>,
+
+    /// Maps FACE ID in the OUTPUT mesh to the FACE ID in the INPUT mesh it
+    /// subdivides.
+    pub faces: FnvHashMap<Id, Id>,
+
+    /// Maps FACE ID in the OUTPUT mesh to the HALFEDGE ID in the INPUT mesh
+    /// ending at the corner the face covers.
+    pub face_corners: FnvHashMap<Id, Id>,
+}
+
+/// The levels of subdivision of a cage with where every output vertex came
+/// from, made by `Mesh::subdivision_jacobian`, to look up rows of the
+/// subdivision matrix one output vertex at a time.
+#[derive(Debug, Clone)]
+pub struct SubdivisionJacobian {
+    /// The input of every level with the provenance of its output.
+    steps: Vec<(Mesh, SubdivisionMap)>,
+
+    /// The result of the last level.
+    output: Mesh,
+}
+
+impl SubdivisionJacobian {
+    /// The row of the subdivision matrix for one output vertex, see
+    /// `Mesh::subdivision_jacobian_row`. Only the weights reaching the vertex
+    /// are followed back through the levels, so the cost depends on the
+    /// size of its support rather than of the mesh.
+    pub fn row(&self, output_vertex: Id) -> Vec<(Id, f32)> {
+        if self.output.vertex(output_vertex).is_none() {
+            return Vec::new();
+        }
+        let mut weights: FnvHashMap<Id, f32> = FnvHashMap::default();
+        weights.insert(output_vertex, 1.0);
+        for (input, map) in self.steps.iter().rev() {
+            let mut input_weights: FnvHashMap<Id, f32> = FnvHashMap::default();
+            for (vertex_id, weight) in weights {
+                for (source_id, source_weight) in source_weights(input, map.vertices[&vertex_id]) {
+                    *input_weights.entry(source_id).or_insert(0.0) += weight * source_weight;
+                }
+            }
+            weights = input_weights;
+        }
+        let mut row: Vec<(Id, f32)> = weights.into_iter().collect();
+        row.sort_by_key(|&(vertex_id, _)| vertex_id);
+        row
+    }
+
+    /// The mesh `subdivide_n` gives for the same cage and levels.
+    pub fn output(&self) -> &Mesh {
+        &self.output
+    }
+}
+
+/// Rings of cage faces around a moved vertex subdivided again by
+/// `Mesh::update_subdivision_local`. Its reach is two rings, and the border of
+/// the patch disturbs less than a ring inside it however many levels deep.
+const LOCAL_UPDATE_RINGS: usize = 4;
+
+/// What the builder methods of `CatmullClarkSubdivider` set, kept apart
+/// from the per input state so it can move over to another input.
+#[derive(Debug, Clone)]
+struct SubdividerSettings {
+    /// Whether edge and face points get the tags of their input vertices.
+    propagate_tags: bool,
+
+    /// Whether the averages are accumulated in f64.
+    precise: bool,
+
+    /// VERTEX IDs in the INPUT mesh whose vertex points stay in place.
+    pinned: FnvHashSet<Id>,
+
+    /// Maps VERTEX ID in the INPUT mesh to how far its vertex point moves
+    /// from the input position, 1 for vertices without an entry.
+    vertex_weights: FnvHashMap<Id, f32>,
+
+    /// The rules for boundary edges and vertices.
+    boundary_interpolation: BoundaryInterpolation,
+
+    /// Whether `try_generate` fixes inconsistent orientation instead of
+    /// failing.
+    repair_orientation: bool,
+
+    /// How far the faces around a vertex may be from a regular grid for the
+    /// vertex to be subdivided linearly, see `with_flat_optimization`.
+    flat_tolerance: Option<f32>,
+
+    /// Whether quads are walked into a stack array, see
+    /// `with_quad_fast_path`.
+    quad_fast_path: bool,
+}
+
+impl Default for SubdividerSettings {
+    fn default() -> Self {
+        SubdividerSettings {
+            propagate_tags: false,
+            precise: false,
+            pinned: FnvHashSet::default(),
+            vertex_weights: FnvHashMap::default(),
+            boundary_interpolation: BoundaryInterpolation::EdgesOnly,
+            repair_orientation: false,
+            flat_tolerance: None,
+            quad_fast_path: true,
+        }
+    }
+}
+
 /// A context for subdivision, providing temporary memory buffers.
 pub struct CatmullClarkSubdivider<'a> {
     /// Maps HALFEDGE ID in the input mesh to EdgeData.
@@ -125,6 +330,20 @@ pub struct CatmullClarkSubdivider<'a> {
 
     /// Maps VERTEX ID in the INPUT mesh to VertexData.
     vertex_data_set: Vec<Option<VertexData>>,
+
+    /// Maps FACE ID in the INPUT mesh to the FACE IDs in the OUTPUT mesh
+    /// subdividing it, only collected for `generate_with_child_map`.
+    face_children: Option<FnvHashMap<Id, Vec<Id>>>,
+
+    /// Whether the input has been subdivided into the output.
+    subdivided: bool,
+
+    /// Everything the builder methods set.
+    settings: SubdividerSettings,
+
+    /// Maps VERTEX ID in the INPUT mesh to whether it is subdivided
+    /// linearly, empty without the flat optimization.
+    flat_vertices: Vec<bool>,
 }
 
 impl<'a> CatmullClarkSubdivider<'a> {
@@ -133,8 +352,17 @@ impl<'a> CatmullClarkSubdivider<'a> {
     /// This function will preallocate as much memory as it can predict is
     /// necessary for the subdivision.
     pub fn new(input: &'a Mesh) -> Self {
-        let mut output = Mesh::new();
+        Self::with_output(input, Mesh::new())
+    }
+
+    /// Like `new`, but writes the result into `output`, whose previous
+    /// content is dropped while its allocations are kept for reuse.
+    pub fn with_output(input: &'a Mesh, mut output: Mesh) -> Self {
+        output.clear();
 
+        // The predictions use the living counts, only living elements of the
+        // input end up in the output no matter how many slots were removed.
+        //
         // Each halfedge produce 3 new
         let halfedge_prediction = input.halfedge_count * 4;
         output.halfedges.reserve(halfedge_prediction);
@@ -145,7 +373,7 @@ impl<'a> CatmullClarkSubdivider<'a> {
             + input.face_count, // Each face produce a new point
         );
         output.faces.reserve(
-            input.face_count * 4, // Optimize for quads
+            input.halfedge_count, // Each face side produce a new quad
         );
 
         // Is this prediction true for all meshes? If false, this is probably
@@ -178,84 +406,551 @@ impl<'a> CatmullClarkSubdivider<'a> {
             face_data_set,
             edge_data_set,
             vertex_data_set,
+            face_children: None,
+            subdivided: false,
+            settings: SubdividerSettings::default(),
+            flat_vertices: Vec::new(),
         }
     }
 
+    /// The same settings and output over another input.
+    fn with_input<'b>(self, input: &'b Mesh) -> CatmullClarkSubdivider<'b> {
+        let mut subdivider = CatmullClarkSubdivider::with_output(input, self.output);
+        subdivider.face_children = self.face_children;
+        subdivider.settings = self.settings;
+        subdivider
+    }
+
+    /// Vertex points always keep the tags of their input vertex. With
+    /// propagation on, edge and face points also get the tags of all the
+    /// input vertices around them combined.
+    pub fn propagate_tags(mut self, propagate: bool) -> Self {
+        self.settings.propagate_tags = propagate;
+        self
+    }
+
+    /// Accumulates the face, edge and vertex point averages in f64 and only
+    /// rounds the results to f32, which keeps meshes far from the origin from
+    /// drifting over many levels. Off by default, as it is slower.
+    pub fn accumulate_in_f64(mut self, precise: bool) -> Self {
+        self.settings.precise = precise;
+        self
+    }
+
+    /// Keeps the vertex points of the given input vertices at their input
+    /// position instead of smoothing them, for handles and constraints. The
+    /// edge and face points around them still move as usual.
+    pub fn with_pinned(mut self, pinned: FnvHashSet<Id>) -> Self {
+        self.settings.pinned = pinned;
+        self
+    }
+
+    /// Blends the vertex point of each input vertex in the map with its input
+    /// position, so a weight of 0 keeps the vertex in place and 1, the weight
+    /// of vertices left out, moves it as usual. Painting weights that fade
+    /// out feathers the smoothing into the rest of the mesh. Pinned vertices
+    /// stay in place whatever their weight.
+    pub fn with_vertex_weights(mut self, weights: FnvHashMap<Id, f32>) -> Self {
+        self.settings.vertex_weights = weights;
+        self
+    }
+
+    /// Chooses the rules for the boundary of open meshes, `EdgesOnly` unless
+    /// set. Vertex attributes follow the same rules.
+    pub fn with_boundary_interpolation(mut self, mode: BoundaryInterpolation) -> Self {
+        self.settings.boundary_interpolation = mode;
+        self
+    }
+
+    /// Makes `try_generate` subdivide a copy of the input whose faces were
+    /// made to agree with `Mesh::fix_orientation` when some of them run the
+    /// same way along a shared edge, instead of failing. Off by default.
+    pub fn with_repair_orientation(mut self, repair: bool) -> Self {
+        self.settings.repair_orientation = repair;
+        self
+    }
+
+    /// Subdivides the flat parts of the input linearly, skipping the
+    /// averages there. An interior vertex is flat when it has four quads
+    /// around it and its neighbors lie within `tolerance` of an evenly spaced
+    /// grid through it, a parallelogram grid in its plane. The full rules
+    /// keep such a vertex in place and put the points of its edges at their
+    /// midpoints, so flat vertices keep their position and their edges are
+    /// split at the middle, giving the same mesh as the full rules within
+    /// `tolerance`. Flat panels with uneven spacing are not flat in this
+    /// sense and take the full rules, as those slide their points within
+    /// the plane. Meant for cages dominated by regular flat panels, like
+    /// architectural models, though building the output still takes most of
+    /// the time, see the `benchmark_subdivide_flat` example. Off by default.
+    pub fn with_flat_optimization(mut self, tolerance: f32) -> Self {
+        self.settings.flat_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Walks the halfedges of quad faces into a stack array instead of a Vec
+    /// collected for every face. The output is the same either way, turning
+    /// it off only serves to compare the two paths. On by default.
+    pub fn with_quad_fast_path(mut self, enabled: bool) -> Self {
+        self.settings.quad_fast_path = enabled;
+        self
+    }
+
+    /// Subdivides the input. The output is built from the faces, so vertices
+    /// without faces are left out of it.
+    ///
+    /// The output faces come in input face order, one quad per halfedge of
+    /// the input face going around it from its first halfedge, so the quads
+    /// of a face form a fan turning the same way as the face. Every quad
+    /// starts at the face point and goes over the edge point of the halfedge,
+    /// the vertex point of the corner it ends at and the edge point of the
+    /// next halfedge, keeping the winding of the input face. Consecutive quads
+    /// of a face share their last and first sides. Renderers building strips
+    /// can rely on this order. Every output face gets the material of the
+    /// input face it subdivides. The output positions do not depend on the
+    /// order the vertices, faces and corners of the input were added in, to
+    /// the bit, as every average sums its points sorted.
     pub fn generate(mut self) -> Mesh {
+        self.subdivide_faces();
+        self.output
+    }
+
+    /// Like `generate`, but first checks the input for T-junctions, which
+    /// the rules do not cover: `generate` subdivides the two sides of one as
+    /// separate boundaries, leaving a crack. Fails with the first halfedge
+    /// `Mesh::find_t_junctions` reports.
+    ///
+    /// Faces running the same way along a shared edge are refused too, with
+    /// the first halfedge `Mesh::find_misoriented_edges` reports, as the
+    /// vertex points around them would be averaged over the wrong
+    /// neighbors. With `with_repair_orientation`, a fixed copy is subdivided
+    /// instead, and only non-orientable inputs fail.
+    pub fn try_generate(self) -> Result<Mesh, MeshError> {
+        if let Some(&halfedge_id) = self.input.find_t_junctions().first() {
+            return Err(MeshError::TJunctionUnsupported { halfedge: halfedge_id });
+        }
+        let misoriented = self.input.find_misoriented_edges();
+        if misoriented.is_empty() {
+            return Ok(self.generate());
+        }
+        if !self.settings.repair_orientation {
+            return Err(MeshError::InconsistentOrientation { halfedge: misoriented[0] });
+        }
+        let mut repaired = self.input.duplicate();
+        repaired.fix_orientation();
+        if let Some(&halfedge_id) = repaired.find_misoriented_edges().first() {
+            return Err(MeshError::InconsistentOrientation { halfedge: halfedge_id });
+        }
+        Ok(self.with_input(&repaired).generate())
+    }
+
+    /// Subdivides the input like `generate`, but keeps the subdivider so the
+    /// output vertices made for input elements can be looked up, see
+    /// `output_vertex_for_input_vertex`. Take the result with `into_output`.
+    pub fn run(&mut self) -> &Mesh {
+        self.subdivide_faces();
+        &self.output
+    }
+
+    pub fn into_output(self) -> Mesh {
+        self.output
+    }
+
+    /// The vertex point made for an input vertex, once `run` has subdivided
+    /// the input. Vertices without faces get none.
+    ///
+    /// Output vertices are numbered in the order the input faces are walked,
+    /// by id and then from the first halfedge of each face, so they only
+    /// depend on the ids and connectivity of the input, not on its positions
+    /// or on hashing. Subdividing a mesh again after moving its vertices
+    /// gives every input vertex, edge and face the same output vertex.
+    pub fn output_vertex_for_input_vertex(&self, vertex_id: Id) -> Option<Id> {
+        self.vertex_data_set.get(vertex_id).and_then(|data| data.map(|data| data.generated_vertex_id))
+    }
+
+    /// The edge point made for the edge of either of its input halfedges,
+    /// see `output_vertex_for_input_vertex`.
+    pub fn output_vertex_for_edge(&self, halfedge_id: Id) -> Option<Id> {
+        self.input.halfedge(halfedge_id)?;
+        self.edge_data_set.get(self.input.peek_same_halfedge(halfedge_id))
+            .and_then(|data| data.as_ref().map(|data| data.generated_vertex_id))
+    }
+
+    /// The face point made for an input face, see
+    /// `output_vertex_for_input_vertex`.
+    pub fn output_vertex_for_face(&self, face_id: Id) -> Option<Id> {
+        self.face_data_set.get(face_id).and_then(|data| data.as_ref().map(|data| data.generated_vertex_id))
+    }
+
+    /// Like `generate`, but also returns the faces every input face was
+    /// subdivided into, in the order of its halfedges, for blending between
+    /// levels of detail or collapsing a region back. Each list is a fan around
+    /// the face point in the rotational order described at `generate`.
+    pub fn generate_with_child_map(mut self) -> (Mesh, FnvHashMap<Id, Vec<Id>>) {
+        self.face_children = Some(FnvHashMap::default());
+        self.subdivide_faces();
+        (self.output, self.face_children.unwrap())
+    }
+
+    /// Like `generate`, but also returns where every output vertex and face
+    /// came from.
+    pub fn generate_with_provenance(mut self) -> (Mesh, SubdivisionMap) {
+        self.subdivide_faces();
+        let mut map = SubdivisionMap::default();
+        for (id, data) in self.face_data_set.iter().enumerate() {
+            if let Some(ref data) = *data {
+                map.vertices.insert(data.generated_vertex_id, VertexSource::Face(id));
+            }
+        }
+        for (id, data) in self.edge_data_set.iter().enumerate() {
+            if let Some(ref data) = *data {
+                map.vertices.insert(data.generated_vertex_id, VertexSource::Edge(id));
+            }
+        }
+        for (id, data) in self.vertex_data_set.iter().enumerate() {
+            if let Some(data) = *data {
+                map.vertices.insert(data.generated_vertex_id, VertexSource::Vertex(id));
+            }
+        }
+        // Output faces are added in order, one per side of every input face.
+        let mut added_face_id = 0;
+        for face_id in FaceIterator::new(self.input) {
+            let face_halfedge = self.input.face(face_id).unwrap().halfedge;
+            for halfedge_id in FaceHalfedgeIterator::new(self.input, face_halfedge) {
+                added_face_id += 1;
+                map.faces.insert(added_face_id, face_id);
+                map.face_corners.insert(added_face_id, halfedge_id);
+            }
+        }
+        (self.output, map)
+    }
+
+    fn transfer_tags(&mut self) {
+        if self.input.vertex_tags.is_empty() {
+            return;
+        }
+        for (id, data) in self.vertex_data_set.iter().enumerate() {
+            if let Some(data) = *data {
+                self.output.set_vertex_tags(data.generated_vertex_id, self.input.vertex_tags(id));
+            }
+        }
+        if !self.settings.propagate_tags {
+            return;
+        }
+        for (id, data) in self.edge_data_set.iter().enumerate() {
+            if let Some(ref data) = *data {
+                let next_id = self.input.halfedge_next_id(id).unwrap();
+                let tags = self.input.vertex_tags(self.input.halfedge_start_vertex_id(id).unwrap()) |
+                    self.input.vertex_tags(self.input.halfedge_start_vertex_id(next_id).unwrap());
+                self.output.set_vertex_tags(data.generated_vertex_id, tags);
+            }
+        }
+        for (id, data) in self.face_data_set.iter().enumerate() {
+            if let Some(ref data) = *data {
+                let tags = self.input.face_vertex_ids(id).into_iter()
+                    .fold(0, |tags, vertex_id| tags | self.input.vertex_tags(vertex_id));
+                self.output.set_vertex_tags(data.generated_vertex_id, tags);
+            }
+        }
+    }
+
+    fn subdivide_faces(&mut self) {
+        if self.subdivided {
+            return;
+        }
+        self.subdivided = true;
+        if let Some(tolerance) = self.settings.flat_tolerance {
+            self.flat_vertices = self.find_flat_vertices(tolerance);
+        }
         for face_id in FaceIterator::new(self.input) {
             let face_vertex_id = face_data_mut(
                 &self.input,
                 face_id,
                 &mut self.face_data_set,
                 &mut self.output,
+                self.settings.precise,
             ).generated_vertex_id;
             let face_halfedge = self.input.face(face_id).unwrap().halfedge;
-            let face_halfedge_id_vec =
-                FaceHalfedgeIterator::new(self.input, face_halfedge).into_vec();
-            for halfedge_id in face_halfedge_id_vec {
-                let (next_halfedge_id, vertex_id) = {
-                    let halfedge = self.input.halfedge(halfedge_id).unwrap();
-                    let next_halfedge_id = halfedge.next;
-                    let next_halfedge_start =
-                        self.input.halfedge(next_halfedge_id).unwrap().vertex;
-                    (next_halfedge_id, next_halfedge_start)
-                };
-                let e1_vertex_id =
-                    self.edge_data_mut(halfedge_id).generated_vertex_id;
-                let e2_vertex_id =
-                    self.edge_data_mut(next_halfedge_id).generated_vertex_id;
-                let vertex_generated_id =
-                    self.vertex_data_mut(vertex_id).generated_vertex_id;
-                let added_face_id = self.output.add_face();
-                let mut added_halfedges = [
-                    (self.output.add_halfedge(), face_vertex_id),
-                    (self.output.add_halfedge(), e1_vertex_id),
-                    (self.output.add_halfedge(), vertex_generated_id),
-                    (self.output.add_halfedge(), e2_vertex_id),
-                ];
-                for &(added_halfedge_id, added_vertex_id) in
-                    added_halfedges.iter()
-                {
-                    {
-                        let vert = self.output
-                            .vertex_mut(added_vertex_id)
-                            .unwrap();
-                        if !vert.halfedges.contains(&added_halfedge_id) {
-                            vert.halfedges.push(added_halfedge_id);
-                        }
+            // Quads are by far the most common input, walk them into a stack
+            // array instead of allocating a Vec for every face.
+            let first_added_face_id = self.output.faces.len() + 1;
+            let quad_halfedges = if self.settings.quad_fast_path { self.quad_halfedges(face_halfedge) } else { None };
+            match quad_halfedges {
+                Some(quad_halfedges) => {
+                    for &halfedge_id in quad_halfedges.iter() {
+                        self.add_corner_face(face_vertex_id, halfedge_id);
                     }
-                    self.output.halfedge_mut(added_halfedge_id).unwrap().face =
-                        added_face_id;
-                    self.output
-                        .halfedge_mut(added_halfedge_id)
-                        .unwrap()
-                        .vertex = added_vertex_id;
                 }
-                self.output.face_mut(added_face_id).unwrap().halfedge =
-                    added_halfedges[0].0;
-                for i in 0..added_halfedges.len() {
-                    let first = added_halfedges[i].0;
-                    let second =
-                        added_halfedges[(i + 1) % added_halfedges.len()].0;
-                    self.output.link_halfedges(first, second);
+                None => {
+                    let face_halfedge_id_vec =
+                        FaceHalfedgeIterator::new(self.input, face_halfedge)
+                            .into_vec();
+                    for halfedge_id in face_halfedge_id_vec {
+                        self.add_corner_face(face_vertex_id, halfedge_id);
+                    }
+                }
+            }
+            if let Some(material) = self.input.face_material(face_id) {
+                for child_id in first_added_face_id..self.output.faces.len() + 1 {
+                    self.output.set_face_material(child_id, Some(material));
+                }
+            }
+            if let Some(ref mut face_children) = self.face_children {
+                let children = (first_added_face_id..self.output.faces.len() + 1).collect();
+                face_children.insert(face_id, children);
+            }
+        }
+        self.output.materials = self.input.materials.clone();
+        self.transfer_tags();
+        self.transfer_attributes();
+        self.transfer_uvs();
+    }
+
+    /// Blends the vertex attributes of the input by the weights of the rules
+    /// that placed every output vertex, and copies the face attributes to
+    /// the faces subdividing each input face.
+    fn transfer_attributes(&mut self) {
+        if self.input.attributes.is_empty() {
+            return;
+        }
+        let input = self.input;
+        let mut vertex_targets = Vec::new();
+        for (id, data) in self.face_data_set.iter().enumerate() {
+            if let Some(ref data) = *data {
+                vertex_targets.push((data.generated_vertex_id, face_point_weights(input, id)));
+            }
+        }
+        for (id, data) in self.edge_data_set.iter().enumerate() {
+            if let Some(ref data) = *data {
+                vertex_targets.push((data.generated_vertex_id, edge_point_weights(input, id, self.settings.boundary_interpolation)));
+            }
+        }
+        for (id, data) in self.vertex_data_set.iter().enumerate() {
+            if let Some(data) = *data {
+                let weights = if self.settings.pinned.contains(&id) {
+                    vec![(id, 1.0)]
+                } else {
+                    match self.settings.vertex_weights.get(&id) {
+                        Some(&weight) => {
+                            let mut weights: Vec<(Id, f32)> = vertex_point_weights(input, id, self.settings.boundary_interpolation).into_iter()
+                                .map(|(source_id, source_weight)| (source_id, source_weight * weight))
+                                .collect();
+                            weights.push((id, 1.0 - weight));
+                            weights
+                        },
+                        None => vertex_point_weights(input, id, self.settings.boundary_interpolation),
+                    }
+                };
+                vertex_targets.push((data.generated_vertex_id, weights));
+            }
+        }
+        // Output faces are added in order, one per side of every input face.
+        let mut face_targets = Vec::with_capacity(self.output.faces.len());
+        for face_id in FaceIterator::new(input) {
+            let face_halfedge = input.face(face_id).unwrap().halfedge;
+            for _ in FaceHalfedgeIterator::new(input, face_halfedge) {
+                face_targets.push((face_targets.len() + 1, vec![(face_id, 1.0)]));
+            }
+        }
+        self.output.attributes.resample_vertices(&input.attributes, &vertex_targets);
+        self.output.attributes.resample_faces(&input.attributes, &face_targets);
+    }
+
+    /// Subdivides the halfedge UVs of the input within its UV charts, the
+    /// faces joined by edges whose end points have the same UVs on both
+    /// sides. Away from seams the UVs follow the weights `transfer_attributes`
+    /// uses, boundary rules, pinned vertices and vertex weights included.
+    /// Seams between charts follow the boundary rules on each side, so the
+    /// UVs never blend across a seam and it stays sharp, while positions are
+    /// smoothed over it as usual.
+    fn transfer_uvs(&mut self) {
+        if self.input.halfedge_uvs.is_empty() {
+            return;
+        }
+        let input = self.input;
+        // Output faces are added in order, one per side of every input face,
+        // starting at the face point.
+        let mut output_face_id = 0;
+        for face_id in FaceIterator::new(input) {
+            let face_halfedge = input.face(face_id).unwrap().halfedge;
+            let face_uv = stencil_uv(input, &[face_id], &face_point_weights(input, face_id));
+            for halfedge_id in FaceHalfedgeIterator::new(input, face_halfedge) {
+                output_face_id += 1;
+                let next_id = input.halfedge_next_id(halfedge_id).unwrap();
+                let boundary = self.settings.boundary_interpolation;
+                let uvs = [face_uv, edge_uv(input, boundary, halfedge_id),
+                    vertex_uv(input, &self.settings, next_id), edge_uv(input, boundary, next_id)];
+                let mut output_halfedge_id = self.output.face_first_halfedge_id(output_face_id).unwrap();
+                for &uv in uvs.iter() {
+                    self.output.set_halfedge_uv(output_halfedge_id, Point2::from_vec(uv));
+                    output_halfedge_id = self.output.halfedge_next_id(output_halfedge_id).unwrap();
                 }
             }
         }
-        self.output
+    }
+
+    /// Returns the four halfedges of a face in loop order, or None if the
+    /// face is not a quad.
+    fn quad_halfedges(&self, face_halfedge: Id) -> Option<[Id; 4]> {
+        let mut quad_halfedges = [0; 4];
+        let mut halfedge_id = face_halfedge;
+        for slot in quad_halfedges.iter_mut() {
+            *slot = halfedge_id;
+            halfedge_id = self.input.halfedge(halfedge_id).unwrap().next;
+            if halfedge_id == face_halfedge {
+                break;
+            }
+        }
+        if halfedge_id == face_halfedge && quad_halfedges[3] != 0 {
+            Some(quad_halfedges)
+        } else {
+            None
+        }
+    }
+
+    /// Adds the output quad for the corner at the end of the given halfedge,
+    /// spanning from the face point over the two edge points to the vertex
+    /// point. The order of the corners is part of the output guarantees, see
+    /// `generate`.
+    fn add_corner_face(&mut self, face_vertex_id: Id, halfedge_id: Id) {
+        let (next_halfedge_id, vertex_id) = {
+            let halfedge = self.input.halfedge(halfedge_id).unwrap();
+            let next_halfedge_id = halfedge.next;
+            let next_halfedge_start =
+                self.input.halfedge(next_halfedge_id).unwrap().vertex;
+            (next_halfedge_id, next_halfedge_start)
+        };
+        let e1_vertex_id = self.edge_data_mut(halfedge_id).generated_vertex_id;
+        let e2_vertex_id =
+            self.edge_data_mut(next_halfedge_id).generated_vertex_id;
+        let vertex_generated_id =
+            self.vertex_data_mut(vertex_id).generated_vertex_id;
+        let added_face_id = self.output.add_face();
+        let added_halfedges = [
+            (self.output.add_halfedge(), face_vertex_id),
+            (self.output.add_halfedge(), e1_vertex_id),
+            (self.output.add_halfedge(), vertex_generated_id),
+            (self.output.add_halfedge(), e2_vertex_id),
+        ];
+        for &(added_halfedge_id, added_vertex_id) in added_halfedges.iter() {
+            {
+                let vert = self.output.vertex_mut(added_vertex_id).unwrap();
+                if !vert.halfedges.contains(&added_halfedge_id) {
+                    vert.halfedges.push(added_halfedge_id);
+                }
+            }
+            self.output.halfedge_mut(added_halfedge_id).unwrap().face =
+                added_face_id;
+            self.output.halfedge_mut(added_halfedge_id).unwrap().vertex =
+                added_vertex_id;
+        }
+        self.output.face_mut(added_face_id).unwrap().halfedge =
+            added_halfedges[0].0;
+        for i in 0..added_halfedges.len() {
+            let first = added_halfedges[i].0;
+            let second = added_halfedges[(i + 1) % added_halfedges.len()].0;
+            self.output.link_halfedges(first, second);
+        }
     }
 
     /// Helps to reduce the syntax noise when a Self is available. Splits Self
     /// into multiple mutable borrows.
     fn edge_data_mut(&mut self, halfedge_id: Id) -> &EdgeData {
+        if !self.flat_vertices.is_empty() {
+            self.add_flat_edge_data(halfedge_id);
+        }
         edge_data_mut(
             &self.input,
             halfedge_id,
             &mut self.face_data_set,
             &mut self.edge_data_set,
             &mut self.output,
+            self.settings.precise,
+            self.settings.boundary_interpolation,
         )
     }
 
+    /// Splits the edge at its midpoint if one of its vertices is flat, as
+    /// the full rules would within the tolerance. Both faces of the edge are
+    /// then quads of the grid around that vertex. Their face points are
+    /// added first, like `edge_data_mut` does, so the output vertices come in
+    /// the same order as without the flat optimization.
+    fn add_flat_edge_data(&mut self, halfedge_id: Id) {
+        let input = self.input;
+        let id = input.peek_same_halfedge(halfedge_id);
+        if self.edge_data_set[id].is_some() {
+            return;
+        }
+        let start_id = input.halfedge_start_vertex_id(id).unwrap();
+        let end_id = input.halfedge_start_vertex_id(input.halfedge_next_id(id).unwrap()).unwrap();
+        if !self.flat_vertices[start_id] && !self.flat_vertices[end_id] {
+            return;
+        }
+        for &face_id in [input.halfedge_face_id(id).unwrap(), input.halfedge_opposite_face_id(id).unwrap()].iter() {
+            face_data_mut(input, face_id, &mut self.face_data_set, &mut self.output, self.settings.precise);
+        }
+        let mid_point = centroid(&mut [input.vertex(start_id).unwrap().position,
+            input.vertex(end_id).unwrap().position], self.settings.precise);
+        self.edge_data_set[id] = Some(EdgeData {
+            mid_point,
+            generated_vertex_id: self.output.add_vertex(mid_point),
+        });
+    }
+
+    /// Maps VERTEX ID in the INPUT mesh to whether it is an interior vertex
+    /// of four quads whose corners lie within `tolerance` of an evenly spaced
+    /// grid, see `with_flat_optimization`.
+    fn find_flat_vertices(&self, tolerance: f32) -> Vec<bool> {
+        let input = self.input;
+        let mut flat_vertices = vec![false; input.vertices.len() + 1];
+        for (vertex_id, vertex) in input.iter_vertices() {
+            if vertex.halfedges.len() != 4 {
+                continue;
+            }
+            // Walks the outgoing halfedges around the vertex, checking that
+            // every face is a parallelogram on the way.
+            let mut neighbors = Vec::with_capacity(4);
+            let mut halfedge_id = vertex.halfedges[0];
+            for _ in 0..4 {
+                let halfedge = input.halfedge(halfedge_id).unwrap();
+                let corners: SmallVec<[Vector3<f32>; 4]> = FaceHalfedgeIterator::new(input, halfedge_id)
+                    .map(|corner_id| input.halfedge_start_vertex(corner_id).unwrap().position.to_vec())
+                    .collect();
+                let prev_opposite = input.halfedge(halfedge.prev).unwrap().opposite;
+                if halfedge.opposite == 0 || prev_opposite == 0 || corners.len() != 4
+                        || (corners[0] + corners[2] - corners[1] - corners[3]).magnitude() > tolerance {
+                    break;
+                }
+                neighbors.push(corners[1]);
+                halfedge_id = prev_opposite;
+            }
+            let center = vertex.position.to_vec() * 2.0;
+            flat_vertices[vertex_id] = neighbors.len() == 4
+                && halfedge_id == vertex.halfedges[0]
+                && (neighbors[0] + neighbors[2] - center).magnitude() <= tolerance
+                && (neighbors[1] + neighbors[3] - center).magnitude() <= tolerance;
+        }
+        flat_vertices
+    }
+
+    /// Positions of the vertices connected to the given input vertex by a
+    /// boundary edge.
+    fn boundary_neighbors(&self, vertex_id: Id) -> Vec<Point3<f32>> {
+        let input = self.input;
+        let mut neighbors = Vec::new();
+        for &halfedge_id in input.vertex(vertex_id).unwrap().halfedges.iter() {
+            let halfedge = input.halfedge(halfedge_id).unwrap();
+            if input.halfedge_opposite_id(halfedge_id).is_none() {
+                let next = input.halfedge(halfedge.next).unwrap();
+                neighbors.push(input.vertex(next.vertex).unwrap().position);
+            }
+            if input.halfedge_opposite_id(halfedge.prev).is_none() {
+                let prev = input.halfedge(halfedge.prev).unwrap();
+                neighbors.push(input.vertex(prev.vertex).unwrap().position);
+            }
+        }
+        neighbors
+    }
+
     /// Get or create a vertex in the new mesh.
     /// The vertex_id paremeter refers to a vertex in the input mesh.
     /// The returned VertexData (vertex id) refers to the output mesh.
@@ -263,6 +958,20 @@ impl<'a> CatmullClarkSubdivider<'a> {
         if let Some(data) = self.vertex_data_set[vertex_id] {
             return data;
         }
+        if !self.flat_vertices.is_empty() && self.flat_vertices[vertex_id] {
+            // Adds the face and edge points around it first, in the order
+            // the full rules below do.
+            let input = self.input;
+            for &halfedge_id in input.vertex(vertex_id).unwrap().halfedges.iter() {
+                face_data_mut(input, input.halfedge(halfedge_id).unwrap().face, &mut self.face_data_set, &mut self.output, self.settings.precise);
+                self.edge_data_mut(halfedge_id);
+            }
+            let data = VertexData {
+                generated_vertex_id: self.output.add_vertex(self.input.vertex(vertex_id).unwrap().position),
+            };
+            self.vertex_data_set[vertex_id] = Some(data);
+            return data;
+        }
         self.tmp_avg_of_faces.clear();
         self.tmp_avg_of_edge_mids.clear();
         let vertex = self.input.vertex(vertex_id).unwrap();
@@ -275,6 +984,7 @@ impl<'a> CatmullClarkSubdivider<'a> {
                     halfedge_face_id,
                     &mut self.face_data_set,
                     &mut self.output,
+                    self.settings.precise,
                 ).average_of_points,
             );
             self.tmp_avg_of_edge_mids.push(
@@ -284,15 +994,64 @@ impl<'a> CatmullClarkSubdivider<'a> {
                     &mut self.face_data_set,
                     &mut self.edge_data_set,
                     &mut self.output,
+                    self.settings.precise,
+                    self.settings.boundary_interpolation,
                 ).mid_point,
             );
         }
-        let barycenter = Point3::centroid(&self.tmp_avg_of_faces);
-        let average_of_edge = Point3::centroid(&self.tmp_avg_of_edge_mids);
-        let position = (((average_of_edge * 2.0) + barycenter.to_vec())
-            + (vertex.position.to_vec()
-                * ((self.tmp_avg_of_faces.len() as i32 - 3).abs() as f32)))
-            / (self.tmp_avg_of_faces.len() as f32);
+        let mut boundary_neighbors = match self.settings.boundary_interpolation {
+            BoundaryInterpolation::None => Vec::new(),
+            _ => self.boundary_neighbors(vertex_id),
+        };
+        // The halfedges of a vertex are listed in the order the faces were
+        // added, sorting makes the sums below independent of it.
+        sort_points(&mut boundary_neighbors);
+        sort_points(&mut self.tmp_avg_of_faces);
+        sort_points(&mut self.tmp_avg_of_edge_mids);
+        let corner = self.settings.boundary_interpolation == BoundaryInterpolation::EdgesAndCorners
+            && boundary_neighbors.len() == 2 && vertex.halfedges.len() == 1;
+        // Boundary vertices follow the cubic B-spline curve rule.
+        let position = if self.settings.pinned.contains(&vertex_id) || corner {
+            vertex.position
+        } else if boundary_neighbors.len() == 2 && self.settings.precise {
+            let sum = sum_f64(&boundary_neighbors) + vertex.position.to_vec().cast::<f64>().unwrap() * 6.0;
+            Point3::from_vec(sum / 8.0).cast().unwrap()
+        } else if boundary_neighbors.len() == 2 {
+            Point3::from_vec(
+                (boundary_neighbors[0].to_vec()
+                    + vertex.position.to_vec() * 6.0
+                    + boundary_neighbors[1].to_vec())
+                    / 8.0,
+            )
+        } else if !boundary_neighbors.is_empty() {
+            // Several boundaries meet at this vertex, keep it in place.
+            vertex.position
+        } else if self.settings.precise {
+            let valence = self.tmp_avg_of_faces.len() as f64;
+            let barycenter = sum_f64(&self.tmp_avg_of_faces) / valence;
+            let average_of_edge = sum_f64(&self.tmp_avg_of_edge_mids) / self.tmp_avg_of_edge_mids.len() as f64;
+            let position = vertex.position.to_vec().cast::<f64>().unwrap();
+            Point3::from_vec((average_of_edge * 2.0 + barycenter + position * (valence - 3.0)) / valence)
+                .cast().unwrap()
+        } else {
+            let barycenter = Point3::centroid(&self.tmp_avg_of_faces);
+            let average_of_edge =
+                Point3::centroid(&self.tmp_avg_of_edge_mids);
+            // The standard rule (F + 2R + (n - 3)P) / n. The weights sum to 1
+            // for every valence, so below valence 3 the vertex is pulled past
+            // the averages, which flattens a two sided pillow onto its
+            // center. At valence 3 the vertex itself has no weight.
+            // Valences are counted in floats, so no cast can overflow however
+            // many faces meet at the vertex.
+            let valence = self.tmp_avg_of_faces.len() as f32;
+            (((average_of_edge * 2.0) + barycenter.to_vec())
+                + (vertex.position.to_vec() * (valence - 3.0)))
+                / valence
+        };
+        let position = match self.settings.vertex_weights.get(&vertex_id) {
+            Some(&weight) => vertex.position + (position - vertex.position) * weight,
+            None => position,
+        };
         let mut data = VertexData::new();
         data.generated_vertex_id = self.output.add_vertex(position);
         self.vertex_data_set[vertex_id] = Some(data);
@@ -300,6 +1059,188 @@ impl<'a> CatmullClarkSubdivider<'a> {
     }
 }
 
+/// Input vertices and their weights in the face point of `face_id`.
+fn face_point_weights(input: &Mesh, face_id: Id) -> Vec<(Id, f32)> {
+    let corners = input.face_vertex_ids(face_id);
+    let weight = 1.0 / corners.len() as f32;
+    corners.into_iter().map(|vertex_id| (vertex_id, weight)).collect()
+}
+
+/// Input vertices and their weights in the edge point of `halfedge_id`,
+/// following `edge_data_mut`.
+fn edge_point_weights(input: &Mesh, halfedge_id: Id, boundary: BoundaryInterpolation) -> Vec<(Id, f32)> {
+    let next_id = input.halfedge_next_id(halfedge_id).unwrap();
+    let mut weights = vec![(input.halfedge_start_vertex_id(halfedge_id).unwrap(), 0.5),
+        (input.halfedge_start_vertex_id(next_id).unwrap(), 0.5)];
+    if input.halfedge_opposite_face_id(halfedge_id).is_none() && boundary == BoundaryInterpolation::None {
+        for weight in weights.iter_mut() {
+            weight.1 = 1.0 / 3.0;
+        }
+        for (vertex_id, weight) in face_point_weights(input, input.halfedge_face_id(halfedge_id).unwrap()) {
+            weights.push((vertex_id, weight / 3.0));
+        }
+    }
+    if let Some(opposite_face_id) = input.halfedge_opposite_face_id(halfedge_id) {
+        for weight in weights.iter_mut() {
+            weight.1 = 0.25;
+        }
+        for &face_id in [input.halfedge_face_id(halfedge_id).unwrap(), opposite_face_id].iter() {
+            for (vertex_id, weight) in face_point_weights(input, face_id) {
+                weights.push((vertex_id, weight * 0.25));
+            }
+        }
+    }
+    weights
+}
+
+/// Input vertices and their weights in an output vertex coming from `source`,
+/// with the default boundary rules.
+fn source_weights(input: &Mesh, source: VertexSource) -> Vec<(Id, f32)> {
+    match source {
+        VertexSource::Face(face_id) => face_point_weights(input, face_id),
+        VertexSource::Edge(halfedge_id) => edge_point_weights(input, halfedge_id, BoundaryInterpolation::EdgesOnly),
+        VertexSource::Vertex(vertex_id) => vertex_point_weights(input, vertex_id, BoundaryInterpolation::EdgesOnly),
+    }
+}
+
+/// Input vertices and their weights in the vertex point of `vertex_id`,
+/// following `vertex_data_mut`.
+fn vertex_point_weights(input: &Mesh, vertex_id: Id, boundary: BoundaryInterpolation) -> Vec<(Id, f32)> {
+    let vertex = input.vertex(vertex_id).unwrap();
+    let mut boundary_neighbors = Vec::new();
+    if boundary != BoundaryInterpolation::None {
+        for &halfedge_id in vertex.halfedges.iter() {
+            let halfedge = input.halfedge(halfedge_id).unwrap();
+            if input.halfedge_opposite_id(halfedge_id).is_none() {
+                boundary_neighbors.push(input.halfedge_start_vertex_id(halfedge.next).unwrap());
+            }
+            if input.halfedge_opposite_id(halfedge.prev).is_none() {
+                boundary_neighbors.push(input.halfedge_start_vertex_id(halfedge.prev).unwrap());
+            }
+        }
+    }
+    let corner = boundary == BoundaryInterpolation::EdgesAndCorners && vertex.halfedges.len() == 1;
+    if boundary_neighbors.len() == 2 && !corner {
+        return vec![(vertex_id, 0.75), (boundary_neighbors[0], 0.125), (boundary_neighbors[1], 0.125)];
+    }
+    if !boundary_neighbors.is_empty() {
+        return vec![(vertex_id, 1.0)];
+    }
+    // (F + 2R + (n - 3)P) / n, with F the average of the face points and R
+    // the average of the edge midpoints.
+    let valence = vertex.halfedges.len() as f32;
+    let mut weights = vec![(vertex_id, (valence - 3.0) / valence)];
+    for &halfedge_id in vertex.halfedges.iter() {
+        for (id, weight) in face_point_weights(input, input.halfedge_face_id(halfedge_id).unwrap()) {
+            weights.push((id, weight / (valence * valence)));
+        }
+        let next_id = input.halfedge_next_id(halfedge_id).unwrap();
+        weights.push((vertex_id, 1.0 / (valence * valence)));
+        weights.push((input.halfedge_start_vertex_id(next_id).unwrap(), 1.0 / (valence * valence)));
+    }
+    weights
+}
+
+/// UV of the corner at the start of `halfedge_id`, zero when it has none.
+fn corner_uv(input: &Mesh, halfedge_id: Id) -> Vector2<f32> {
+    input.halfedge_uv(halfedge_id).map_or(Vector2::zero(), |uv| uv.to_vec())
+}
+
+/// Whether the faces on both sides of the edge of `halfedge_id` give its end
+/// points the same UVs, so UVs are interpolated across it. Boundary edges
+/// and seams are not.
+fn uv_continuous(input: &Mesh, halfedge_id: Id) -> bool {
+    let opposite_id = match input.halfedge_opposite_id(halfedge_id) {
+        Some(opposite_id) => opposite_id,
+        None => return false,
+    };
+    let next_id = input.halfedge_next_id(halfedge_id).unwrap();
+    let opposite_next_id = input.halfedge_next_id(opposite_id).unwrap();
+    input.halfedge_uv(halfedge_id) == input.halfedge_uv(opposite_next_id)
+        && input.halfedge_uv(next_id) == input.halfedge_uv(opposite_id)
+}
+
+/// Blends the UVs of the input vertices by the weights of a position rule,
+/// taking the UV of every vertex from its corner on one of `faces`, which
+/// have to lie in one chart so that every vertex has a single UV there.
+fn stencil_uv(input: &Mesh, faces: &[Id], weights: &[(Id, f32)]) -> Vector2<f32> {
+    let mut corners: SmallVec<[(Id, Vector2<f32>); 16]> = SmallVec::new();
+    for &face_id in faces {
+        let face_halfedge = input.face(face_id).unwrap().halfedge;
+        for halfedge_id in FaceHalfedgeIterator::new(input, face_halfedge) {
+            corners.push((input.halfedge_start_vertex_id(halfedge_id).unwrap(), corner_uv(input, halfedge_id)));
+        }
+    }
+    weights.iter().fold(Vector2::zero(), |sum, &(vertex_id, weight)| {
+        sum + corners.iter().find(|corner| corner.0 == vertex_id).map_or(Vector2::zero(), |corner| corner.1) * weight
+    })
+}
+
+/// UV of the edge point of `halfedge_id` in its face: the edge point rule of
+/// `boundary` in UV space when the edge is continuous or on the boundary,
+/// and the midpoint on a seam.
+fn edge_uv(input: &Mesh, boundary: BoundaryInterpolation, halfedge_id: Id) -> Vector2<f32> {
+    let face_id = input.halfedge_face_id(halfedge_id).unwrap();
+    let weights = edge_point_weights(input, halfedge_id, boundary);
+    match input.halfedge_opposite_face_id(halfedge_id) {
+        None => stencil_uv(input, &[face_id], &weights),
+        Some(opposite_face_id) if uv_continuous(input, halfedge_id) => stencil_uv(input, &[face_id, opposite_face_id], &weights),
+        Some(_) => {
+            let next_id = input.halfedge_next_id(halfedge_id).unwrap();
+            (corner_uv(input, halfedge_id) + corner_uv(input, next_id)) * 0.5
+        },
+    }
+}
+
+/// UV of the vertex point at the start of `corner_id` in its face. Corners
+/// whose chart has every face around the vertex follow the vertex point
+/// rule, corners on a seam the boundary rule along it, and a corner that is
+/// the only face of its chart at a seam keeps its UV, like the corner of a
+/// texture island. Pinned vertices keep their UVs and weighted ones blend
+/// them as their positions do.
+fn vertex_uv(input: &Mesh, settings: &SubdividerSettings, corner_id: Id) -> Vector2<f32> {
+    let uv = corner_uv(input, corner_id);
+    let vertex_id = input.halfedge_start_vertex_id(corner_id).unwrap();
+    if settings.pinned.contains(&vertex_id) {
+        return uv;
+    }
+    let valence = input.vertex(vertex_id).unwrap().halfedges.len();
+    let mut fan = vec![corner_id];
+    let mut first_id = corner_id;
+    let mut closed = false;
+    let mut prev_id = input.halfedge_prev_id(first_id).unwrap();
+    while fan.len() <= valence && uv_continuous(input, prev_id) {
+        first_id = input.halfedge_opposite_id(prev_id).unwrap();
+        if first_id == corner_id {
+            closed = true;
+            break;
+        }
+        fan.push(first_id);
+        prev_id = input.halfedge_prev_id(first_id).unwrap();
+    }
+    let mut last_id = corner_id;
+    while !closed && fan.len() <= valence && uv_continuous(input, last_id) {
+        last_id = input.halfedge_next_id(input.halfedge_opposite_id(last_id).unwrap()).unwrap();
+        fan.push(last_id);
+    }
+    let whole_chart = closed
+        || (input.halfedge_opposite_id(prev_id).is_none() && input.halfedge_opposite_id(last_id).is_none());
+    let smoothed = if whole_chart {
+        let faces: SmallVec<[Id; 8]> = fan.iter().map(|&id| input.halfedge_face_id(id).unwrap()).collect();
+        stencil_uv(input, &faces, &vertex_point_weights(input, vertex_id, settings.boundary_interpolation))
+    } else if fan.len() == 1 {
+        uv
+    } else {
+        let before = corner_uv(input, input.halfedge_prev_id(first_id).unwrap());
+        let after = corner_uv(input, input.halfedge_next_id(last_id).unwrap());
+        (before + uv * 6.0 + after) / 8.0
+    };
+    match settings.vertex_weights.get(&vertex_id) {
+        Some(&weight) => smoothed * weight + uv * (1.0 - weight),
+        None => smoothed,
+    }
+}
+
 pub trait Subdivide {
     fn subdivide(&self) -> Self;
 }
@@ -309,3 +1250,291 @@ impl Subdivide for Mesh {
         CatmullClarkSubdivider::new(self).generate()
     }
 }
+
+impl Mesh {
+    /// Subdivides into `out`, replacing whatever it held but keeping its
+    /// allocations, which saves allocating a new mesh on every call when the
+    /// same buffer is subdivided into repeatedly.
+    pub fn subdivide_into(&self, out: &mut Mesh) {
+        let buffer = mem::take(out);
+        *out = CatmullClarkSubdivider::with_output(self, buffer).generate();
+    }
+
+    /// Applies `levels` rounds of Catmull-Clark subdivision.
+    pub fn subdivide_n(&self, levels: usize) -> Mesh {
+        let mut mesh = self.duplicate();
+        for _ in 0..levels {
+            mesh = mesh.subdivide();
+        }
+        mesh
+    }
+
+    /// Matrix taking the positions of the living vertices, in the order of
+    /// `positions`, to those of the mesh `subdivide_n(levels)` returns. The
+    /// rules are linear in the positions, so a deforming cage of the same
+    /// topology can be subdivided again by `transform_points` alone.
+    pub fn subdivision_matrix(&self, levels: usize) -> SparseMatrix {
+        let mut columns: FnvHashMap<Id, usize> = self.vertices.iter()
+            .filter(|vertex| vertex.alive)
+            .enumerate()
+            .map(|(column, vertex)| (vertex.id, column))
+            .collect();
+        let mut matrix = SparseMatrix::identity(columns.len());
+        let jacobian = self.subdivision_jacobian(levels);
+        for (level, (input, map)) in jacobian.steps.iter().enumerate() {
+            let output = jacobian.steps.get(level + 1).map_or(&jacobian.output, |step| &step.0);
+            let rows = output.vertices.iter().map(|vertex| {
+                source_weights(input, map.vertices[&vertex.id]).into_iter()
+                    .map(|(vertex_id, weight)| (columns[&vertex_id], weight))
+                    .collect()
+            }).collect();
+            matrix = SparseMatrix::from_rows(columns.len(), rows).multiply(&matrix);
+            // Every vertex of a subdivided mesh is alive, so the next level
+            // indexes them by id.
+            columns = output.vertices.iter().map(|vertex| (vertex.id, vertex.id - 1)).collect();
+        }
+        matrix
+    }
+
+    /// Subdivides `levels` times like `subdivide_n`, keeping every level to
+    /// look up rows of `subdivision_matrix(levels)` with
+    /// `SubdivisionJacobian::row`.
+    pub fn subdivision_jacobian(&self, levels: usize) -> SubdivisionJacobian {
+        let mut steps = Vec::with_capacity(levels);
+        let mut mesh = self.duplicate();
+        for _ in 0..levels {
+            let (output, map) = CatmullClarkSubdivider::new(&mesh).generate_with_provenance();
+            steps.push((mesh, map));
+            mesh = output;
+        }
+        SubdivisionJacobian { steps, output: mesh }
+    }
+
+    /// The row of `subdivision_matrix(levels)` for one vertex of the mesh
+    /// `subdivide_n(levels)` returns, as the input vertices it depends on
+    /// with their weights, sorted by id. These are the derivatives of the
+    /// output position by the input positions along every axis, for fitting
+    /// a cage by gradient descent, and sum to 1 as every rule is an affine
+    /// combination. Empty if the output has no such vertex. Subdivides the
+    /// whole cage on every call, so build a `subdivision_jacobian` once to
+    /// look up many rows.
+    pub fn subdivision_jacobian_row(&self, output_vertex: Id, levels: usize) -> Vec<(Id, f32)> {
+        self.subdivision_jacobian(levels).row(output_vertex)
+    }
+
+    /// Updates `subdivided`, made by `subdivide_n(levels)` from this cage
+    /// before `moved_vertex` was moved, to match the cage again, without
+    /// subdividing all of it. The moved vertex only reaches the faces coming
+    /// from the cage faces at most two rings of faces away, so only those are
+    /// written. They are taken from a patch of the faces up to
+    /// `LOCAL_UPDATE_RINGS` rings away subdivided on its own, whose open
+    /// border is far enough away not to disturb them. The rest of the cage
+    /// is only walked once to number the faces.
+    ///
+    /// Output faces are numbered by the input face and corner they cover,
+    /// four per face from the first level on, so the faces of the patch are
+    /// matched to those of `subdivided` by their ids alone. The cage must
+    /// still have the topology it was subdivided with.
+    pub fn update_subdivision_local(&self, subdivided: &mut Mesh, moved_vertex: Id, levels: usize) {
+        if levels == 0 {
+            if let (Some(vertex), Some(target)) = (self.vertex(moved_vertex), subdivided.vertex_mut(moved_vertex)) {
+                target.position = vertex.position;
+            }
+            return;
+        }
+        let vertex = match self.vertex(moved_vertex) {
+            Some(vertex) => vertex,
+            None => return,
+        };
+        // Rings of faces around the vertex, the faces touching it being the
+        // first.
+        let mut face_rings: FnvHashMap<Id, usize> = FnvHashMap::default();
+        let mut front: Vec<Id> = vertex.halfedges.iter().filter_map(|&halfedge_id| self.halfedge_face_id(halfedge_id)).collect();
+        for ring in 1..LOCAL_UPDATE_RINGS + 1 {
+            let mut next_front = Vec::new();
+            for face_id in front {
+                if face_rings.contains_key(&face_id) {
+                    continue;
+                }
+                face_rings.insert(face_id, ring);
+                for corner in self.face_vertex_ids(face_id) {
+                    for &halfedge_id in self.vertex(corner).unwrap().halfedges.iter() {
+                        let around_id = self.halfedge_face_id(halfedge_id).unwrap();
+                        if !face_rings.contains_key(&around_id) {
+                            next_front.push(around_id);
+                        }
+                    }
+                }
+            }
+            front = next_front;
+        }
+        let mut patch = Mesh::new();
+        let mut patch_vertices: FnvHashMap<Id, Id> = FnvHashMap::default();
+        // Pairs the first level one face of every cage face in the support
+        // with the one in the patch.
+        let mut first_children = Vec::new();
+        let (mut cage_face_count, mut patch_face_count) = (0, 0);
+        for face_id in FaceIterator::new(self) {
+            let corners = self.face_vertex_ids(face_id);
+            let ring = face_rings.get(&face_id).cloned();
+            if ring.is_some_and(|ring| ring <= 2) {
+                first_children.push((patch_face_count + 1, cage_face_count + 1, corners.len()));
+            }
+            cage_face_count += corners.len();
+            if ring.is_none() {
+                continue;
+            }
+            let patch_corners = corners.iter().map(|&corner| {
+                let position = self.vertex(corner).unwrap().position;
+                *patch_vertices.entry(corner).or_insert_with(|| patch.add_vertex(position))
+            }).collect();
+            patch.add_vertices(patch_corners);
+            patch_face_count += corners.len();
+        }
+        let patch = patch.subdivide_n(levels);
+        let mut pairs: Vec<(Id, Id)> = first_children.into_iter()
+            .flat_map(|(patch_id, global_id, count)| (0..count).map(move |corner| (patch_id + corner, global_id + corner)))
+            .collect();
+        for _ in 1..levels {
+            pairs = pairs.into_iter()
+                .flat_map(|(patch_id, global_id)| (0..4).map(move |corner| ((patch_id - 1) * 4 + corner + 1, (global_id - 1) * 4 + corner + 1)))
+                .collect();
+        }
+        for (patch_id, global_id) in pairs {
+            let (patch_halfedge, global_halfedge) = match (patch.face(patch_id), subdivided.face(global_id)) {
+                (Some(patch_face), Some(global_face)) => (patch_face.halfedge, global_face.halfedge),
+                _ => continue,
+            };
+            let patch_corners = FaceHalfedgeIterator::new(&patch, patch_halfedge).into_vec();
+            let global_corners = FaceHalfedgeIterator::new(subdivided, global_halfedge).into_vec();
+            for (patch_halfedge_id, global_halfedge_id) in patch_corners.into_iter().zip(global_corners) {
+                let position = patch.halfedge_start_vertex(patch_halfedge_id).unwrap().position;
+                subdivided.halfedge_start_vertex_mut(global_halfedge_id).unwrap().position = position;
+            }
+        }
+    }
+
+    /// Applies one round of Catmull-Clark subdivision to the selected faces
+    /// only, in place. Vertices touching an unselected face keep their
+    /// position, and the edges between selected and unselected faces are
+    /// split at their middle, the new vertex also going into the unselected
+    /// face so no crack or T-vertex is left. Those neighbors gain a side for
+    /// every split edge. Face materials are kept.
+    pub fn subdivide_region(&mut self, faces: &Selection) {
+        let selected: Vec<Id> = FaceIterator::new(self).filter(|&face_id| faces.contains(face_id)).collect();
+        // Indexed by FACE ID and by HALFEDGE ID, from the start of the
+        // halfedge, and filled in one pass, so the edge and vertex points read
+        // the centers and midpoints around them instead of computing them
+        // again.
+        let mut face_centers: Vec<Option<Point3<f32>>> = vec![None; self.faces.len() + 1];
+        let mut edge_mids: Vec<Option<Point3<f32>>> = vec![None; self.halfedges.len() + 1];
+        for &face_id in selected.iter() {
+            face_centers[face_id] = Some(self.face_center(face_id));
+            for halfedge_id in FaceHalfedgeIterator::new(self, self.face_first_halfedge_id(face_id).unwrap()) {
+                edge_mids[halfedge_id] = Some(self.edge_center(halfedge_id));
+            }
+        }
+        let mut edge_positions: FnvHashMap<EdgeEndpoints, Point3<f32>> = FnvHashMap::default();
+        let mut vertex_positions: FnvHashMap<Id, Point3<f32>> = FnvHashMap::default();
+        let mut neighbors = Vec::new();
+        for &face_id in selected.iter() {
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for halfedge_id in FaceHalfedgeIterator::new(self, face_halfedge) {
+                let from = self.halfedge_start_vertex_id(halfedge_id).unwrap();
+                let to = self.halfedge_start_vertex_id(self.halfedge_next_id(halfedge_id).unwrap()).unwrap();
+                let opposite_face_id = self.halfedge_opposite_face_id(halfedge_id);
+                let mid_point = edge_mids[halfedge_id].unwrap();
+                let position = match opposite_face_id.and_then(|id| face_centers[id]) {
+                    Some(opposite_center) => {
+                        let (from_position, to_position) = (self.vertex(from).unwrap().position, self.vertex(to).unwrap().position);
+                        Point3::centroid(&[from_position, to_position, face_centers[face_id].unwrap(), opposite_center])
+                    },
+                    None => mid_point,
+                };
+                edge_positions.insert(EdgeEndpoints::new(from, to), position);
+                if let Some(opposite_face_id) = opposite_face_id {
+                    if !faces.contains(opposite_face_id) && !neighbors.contains(&opposite_face_id) {
+                        neighbors.push(opposite_face_id);
+                    }
+                }
+                vertex_positions.entry(from).or_insert_with(|| self.region_vertex_point(from, &face_centers, &edge_mids));
+            }
+        }
+        let mut edge_points: FnvHashMap<EdgeEndpoints, Id> = FnvHashMap::default();
+        let mut sorted_edges: Vec<(&EdgeEndpoints, &Point3<f32>)> = edge_positions.iter().collect();
+        sorted_edges.sort_by_key(|&(endpoints, _)| (endpoints.low, endpoints.high));
+        for (endpoints, &position) in sorted_edges {
+            let vertex_id = self.add_vertex(position);
+            edge_points.insert(endpoints.clone(), vertex_id);
+        }
+        let mut loops = Vec::new();
+        let mut materials = Vec::new();
+        for &face_id in selected.iter() {
+            let face_point = self.add_vertex(face_centers[face_id].unwrap());
+            let corners = self.face_vertex_ids(face_id);
+            for (i, &corner) in corners.iter().enumerate() {
+                let next = corners[(i + 1) % corners.len()];
+                let prev = corners[(i + corners.len() - 1) % corners.len()];
+                loops.push(vec![corner,
+                    edge_points[&EdgeEndpoints::new(corner, next)],
+                    face_point,
+                    edge_points[&EdgeEndpoints::new(prev, corner)]]);
+                materials.push(self.face_material(face_id));
+            }
+        }
+        for &face_id in neighbors.iter() {
+            let corners = self.face_vertex_ids(face_id);
+            let mut vertex_loop = Vec::with_capacity(corners.len() + 1);
+            for (i, &corner) in corners.iter().enumerate() {
+                vertex_loop.push(corner);
+                if let Some(&edge_point) = edge_points.get(&EdgeEndpoints::new(corner, corners[(i + 1) % corners.len()])) {
+                    vertex_loop.push(edge_point);
+                }
+            }
+            loops.push(vertex_loop);
+            materials.push(self.face_material(face_id));
+        }
+        let old_faces: Vec<Id> = selected.iter().chain(neighbors.iter()).cloned().collect();
+        let added_faces = self.replace_faces(&old_faces, &loops);
+        for (&face_id, material) in added_faces.iter().zip(materials) {
+            self.set_face_material(face_id, material);
+        }
+        for (&vertex_id, &position) in vertex_positions.iter() {
+            self.vertex_mut(vertex_id).unwrap().position = position;
+        }
+    }
+
+    /// The new position of a vertex of the selected faces in
+    /// `subdivide_region`, following the rules of `CatmullClarkSubdivider`
+    /// when all of its faces are selected and staying in place otherwise.
+    fn region_vertex_point(&self, vertex_id: Id, face_centers: &[Option<Point3<f32>>], edge_mids: &[Option<Point3<f32>>]) -> Point3<f32> {
+        let vertex = self.vertex(vertex_id).unwrap();
+        let mut face_points = Vec::with_capacity(vertex.halfedges.len());
+        let mut mids = Vec::with_capacity(vertex.halfedges.len());
+        let mut boundary_neighbors = Vec::new();
+        for &halfedge_id in vertex.halfedges.iter() {
+            match face_centers[self.halfedge_face_id(halfedge_id).unwrap()] {
+                Some(center) => face_points.push(center),
+                None => return vertex.position,
+            }
+            mids.push(edge_mids[halfedge_id].unwrap());
+            if self.halfedge_opposite_id(halfedge_id).is_none() {
+                let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+                boundary_neighbors.push(self.halfedge_start_vertex(next_id).unwrap().position);
+            }
+            let prev_id = self.halfedge_prev_id(halfedge_id).unwrap();
+            if self.halfedge_opposite_id(prev_id).is_none() {
+                boundary_neighbors.push(self.halfedge_start_vertex(prev_id).unwrap().position);
+            }
+        }
+        if boundary_neighbors.len() == 2 {
+            Point3::from_vec((boundary_neighbors[0].to_vec() + vertex.position.to_vec() * 6.0 + boundary_neighbors[1].to_vec()) / 8.0)
+        } else if !boundary_neighbors.is_empty() {
+            vertex.position
+        } else {
+            let valence = face_points.len() as f32;
+            (Point3::centroid(&mids) * 2.0 + Point3::centroid(&face_points).to_vec()
+                + vertex.position.to_vec() * (valence - 3.0)) / valence
+        }
+    }
+}
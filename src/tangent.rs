@@ -0,0 +1,63 @@
+use cgmath::Point2;
+use cgmath::Vector3;
+use cgmath::Vector4;
+use cgmath::prelude::*;
+use fnv::FnvHashMap;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use iterator::FaceTriangleIterator;
+use mesh::Id;
+use mesh::Mesh;
+
+impl Mesh {
+    /// Per vertex tangents for normal mapping, by Lengyel's method: the U
+    /// and V directions of every triangle with UVs on all of its corners are
+    /// summed at its vertices, and the U direction is made orthogonal to the
+    /// vertex normal. The w component is the handedness, -1 where the V
+    /// direction runs against normal x tangent, as on mirrored UVs. Vertices
+    /// without UV mapped faces, or whose UVs are degenerate, are left out.
+    pub fn compute_tangents(&self) -> FnvHashMap<Id, Vector4<f32>> {
+        let mut u_directions: FnvHashMap<Id, Vector3<f32>> = FnvHashMap::default();
+        let mut v_directions: FnvHashMap<Id, Vector3<f32>> = FnvHashMap::default();
+        for face_id in FaceIterator::new(self) {
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            let corner_uvs: FnvHashMap<Id, Option<Point2<f32>>> = FaceHalfedgeIterator::new(self, face_halfedge)
+                .map(|halfedge_id| (self.halfedge_start_vertex_id(halfedge_id).unwrap(), self.halfedge_uv(halfedge_id)))
+                .collect();
+            for triangle in FaceTriangleIterator::new(self, face_id) {
+                let (uv0, uv1, uv2) = match (corner_uvs[&triangle[0]], corner_uvs[&triangle[1]], corner_uvs[&triangle[2]]) {
+                    (Some(uv0), Some(uv1), Some(uv2)) => (uv0, uv1, uv2),
+                    _ => continue,
+                };
+                let p0 = self.vertex(triangle[0]).unwrap().position;
+                let edge1 = self.vertex(triangle[1]).unwrap().position - p0;
+                let edge2 = self.vertex(triangle[2]).unwrap().position - p0;
+                let (du1, dv1) = (uv1.x - uv0.x, uv1.y - uv0.y);
+                let (du2, dv2) = (uv2.x - uv0.x, uv2.y - uv0.y);
+                let determinant = du1 * dv2 - du2 * dv1;
+                if determinant.abs() < 1e-12 {
+                    continue;
+                }
+                let u_direction = (edge1 * dv2 - edge2 * dv1) / determinant;
+                let v_direction = (edge2 * du1 - edge1 * du2) / determinant;
+                for &vertex_id in triangle.iter() {
+                    *u_directions.entry(vertex_id).or_insert_with(Vector3::zero) += u_direction;
+                    *v_directions.entry(vertex_id).or_insert_with(Vector3::zero) += v_direction;
+                }
+            }
+        }
+        let mut tangents = FnvHashMap::default();
+        for (&vertex_id, &u_direction) in u_directions.iter() {
+            let normal = self.vertex_norm(vertex_id);
+            let tangent = u_direction - normal * normal.dot(u_direction);
+            let length2 = tangent.magnitude2();
+            if length2.is_nan() || length2 <= 1e-12 {
+                continue;
+            }
+            let tangent = tangent.normalize();
+            let handedness = if normal.cross(tangent).dot(v_directions[&vertex_id]) < 0.0 { -1.0 } else { 1.0 };
+            tangents.insert(vertex_id, tangent.extend(handedness));
+        }
+        tangents
+    }
+}
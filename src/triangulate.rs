@@ -1,3 +1,7 @@
+use cgmath::Point2;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use error::TopologyError;
 use iterator::FaceHalfedgeIterator;
 use iterator::FaceIterator;
 use mesh::Id;
@@ -155,3 +159,192 @@ impl Triangulate for Mesh {
         tri_mesh
     }
 }
+
+/// Twice the signed area of the triangle, positive when counterclockwise.
+fn cross_2d(a: Point2<f32>, b: Point2<f32>, c: Point2<f32>) -> f32 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+fn signed_area_2d(points: &[Point2<f32>]) -> f32 {
+    (0..points.len()).map(|i| {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        a.x * b.y - b.x * a.y
+    }).sum::<f32>() * 0.5
+}
+
+/// Whether the segments cross at a point inside both of them.
+fn segments_cross_2d(a: Point2<f32>, b: Point2<f32>, c: Point2<f32>, d: Point2<f32>) -> bool {
+    let (d1, d2) = (cross_2d(a, b, c), cross_2d(a, b, d));
+    let (d3, d4) = (cross_2d(c, d, a), cross_2d(c, d, b));
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+/// Whether `target` lies in the interior angle at `vertex` of a polygon
+/// whose interior is on the left of `prev`, `vertex`, `next`.
+fn locally_inside_2d(prev: Point2<f32>, vertex: Point2<f32>, next: Point2<f32>, target: Point2<f32>) -> bool {
+    if cross_2d(prev, vertex, next) >= 0.0 {
+        cross_2d(prev, vertex, target) >= 0.0 && cross_2d(vertex, next, target) >= 0.0
+    } else {
+        cross_2d(prev, vertex, target) >= 0.0 || cross_2d(vertex, next, target) >= 0.0
+    }
+}
+
+/// Connects the clockwise `hole` to the counterclockwise `polygon` by a pair
+/// of edges from its rightmost vertex to the closest polygon vertex it can
+/// see, giving a single loop that runs around the hole. Returns false if no
+/// vertex is visible. `obstacles` are the other loops the bridge must not
+/// cross.
+fn bridge_hole_2d(points: &[Point2<f32>], polygon: &mut Vec<usize>, hole: &[usize], obstacles: &[Vec<usize>]) -> bool {
+    let m = (0..hole.len()).max_by(|&i, &j| points[hole[i]].x.partial_cmp(&points[hole[j]].x).unwrap_or(std::cmp::Ordering::Equal)).unwrap();
+    let from = points[hole[m]];
+    let hole_prev = points[hole[(m + hole.len() - 1) % hole.len()]];
+    let hole_next = points[hole[(m + 1) % hole.len()]];
+    let mut candidates: Vec<usize> = (0..polygon.len()).collect();
+    candidates.sort_by(|&i, &j| {
+        let distance = |index: usize| points[polygon[index]].distance2(from);
+        distance(i).partial_cmp(&distance(j)).unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let loops: Vec<&[usize]> = [&polygon[..], hole].iter().cloned()
+        .chain(obstacles.iter().map(|obstacle| &obstacle[..]))
+        .collect();
+    let found = candidates.into_iter().find(|&i| {
+        let to = points[polygon[i]];
+        let prev = points[polygon[(i + polygon.len() - 1) % polygon.len()]];
+        let next = points[polygon[(i + 1) % polygon.len()]];
+        if !locally_inside_2d(prev, to, next, from) || !locally_inside_2d(hole_prev, from, hole_next, to) {
+            return false;
+        }
+        !loops.iter().any(|indices| (0..indices.len()).any(|k| {
+            let (a, b) = (points[indices[k]], points[indices[(k + 1) % indices.len()]]);
+            segments_cross_2d(from, to, a, b)
+        }))
+    });
+    let i = match found {
+        Some(i) => i,
+        None => return false,
+    };
+    let mut bridged: Vec<usize> = (0..hole.len() + 1).map(|k| hole[(m + k) % hole.len()]).collect();
+    bridged.push(polygon[i]);
+    let tail = polygon.split_off(i + 1);
+    polygon.extend(bridged);
+    polygon.extend(tail);
+    true
+}
+
+/// Cuts a counterclockwise polygon, which may touch itself along bridge
+/// edges, into counterclockwise triangles of point indices.
+fn ear_clip_2d(points: &[Point2<f32>], mut polygon: Vec<usize>) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::new();
+    while polygon.len() > 3 {
+        let count = polygon.len();
+        let corner = |i: usize| (polygon[(i + count - 1) % count], polygon[i], polygon[(i + 1) % count]);
+        let ear = (0..count).find(|&i| {
+            let (a, b, c) = corner(i);
+            if cross_2d(points[a], points[b], points[c]) <= 0.0 {
+                return false;
+            }
+            // Bridge ends repeat positions, those never block an ear.
+            !polygon.iter().any(|&other| {
+                let p = points[other];
+                p != points[a] && p != points[b] && p != points[c] &&
+                    cross_2d(points[a], points[b], p) >= 0.0 &&
+                    cross_2d(points[b], points[c], p) >= 0.0 &&
+                    cross_2d(points[c], points[a], p) >= 0.0
+            })
+        });
+        match ear {
+            Some(i) => {
+                let (a, b, c) = corner(i);
+                triangles.push([a, b, c]);
+                polygon.remove(i);
+            },
+            // Only flat corners are left, dropping one loses no area.
+            None => match (0..count).find(|&i| {
+                let (a, b, c) = corner(i);
+                cross_2d(points[a], points[b], points[c]).abs() <= 1e-12
+            }) {
+                Some(i) => {
+                    polygon.remove(i);
+                },
+                None => break,
+            },
+        }
+    }
+    if polygon.len() == 3 && cross_2d(points[polygon[0]], points[polygon[1]], points[polygon[2]]) > 0.0 {
+        triangles.push([polygon[0], polygon[1], polygon[2]]);
+    }
+    triangles
+}
+
+impl Mesh {
+    /// Adds a flat polygon with holes, given as loops of existing vertices,
+    /// as triangle faces and returns them. The triangles wind like `outer`
+    /// and share the edges along the holes, which stay open, so the result
+    /// is not a single face with holes. The holes may wind either way. The
+    /// loops are projected onto the plane of `outer` and connected by edges
+    /// from each hole to a vertex it can see before the ear clipping, so
+    /// holes must lie inside `outer` and not overlap each other. Holes of
+    /// fewer than three vertices are left out.
+    ///
+    /// Fails with `TopologyError::InvalidElement` for the first id in the
+    /// loops that is not a live vertex, for the first vertex of `outer` when
+    /// it has no area, and for the first vertex of a hole no vertex can be
+    /// seen from, as one outside `outer` or crossing another hole. Nothing
+    /// is added then.
+    pub fn add_face_with_holes(&mut self, outer: &[Id], holes: &[Vec<Id>]) -> Result<Vec<Id>, TopologyError> {
+        if outer.len() < 3 {
+            return Err(TopologyError::InvalidElement { id: outer.first().cloned().unwrap_or(0) });
+        }
+        let mut ids: Vec<Id> = outer.to_vec();
+        for hole in holes.iter() {
+            ids.extend(hole.iter().cloned());
+        }
+        if let Some(&id) = ids.iter().find(|&&id| self.vertex(id).is_none()) {
+            return Err(TopologyError::InvalidElement { id });
+        }
+        let positions: Vec<_> = ids.iter().map(|&id| self.vertex(id).unwrap().position).collect();
+        let mut normal = Vector3::zero();
+        for i in 0..outer.len() {
+            let (a, b) = (positions[i], positions[(i + 1) % outer.len()]);
+            normal += Vector3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y));
+        }
+        if normal.magnitude2() <= 0.0 {
+            return Err(TopologyError::InvalidElement { id: outer[0] });
+        }
+        let normal = normal.normalize();
+        let axis_u = world_perp(normal).normalize();
+        let axis_v = normal.cross(axis_u);
+        let points: Vec<Point2<f32>> = positions.iter()
+            .map(|position| Point2::new(position.to_vec().dot(axis_u), position.to_vec().dot(axis_v)))
+            .collect();
+        let mut polygon: Vec<usize> = (0..outer.len()).collect();
+        let mut hole_loops = Vec::new();
+        let mut start = outer.len();
+        for hole in holes.iter() {
+            let mut indices: Vec<usize> = (start..start + hole.len()).collect();
+            start += hole.len();
+            if indices.len() < 3 {
+                continue;
+            }
+            let hole_points: Vec<Point2<f32>> = indices.iter().map(|&i| points[i]).collect();
+            if signed_area_2d(&hole_points) > 0.0 {
+                indices.reverse();
+            }
+            hole_loops.push(indices);
+        }
+        // Bridging the rightmost holes first keeps the bridges short.
+        hole_loops.sort_by(|a, b| {
+            let max_x = |indices: &Vec<usize>| indices.iter().map(|&i| points[i].x).fold(f32::MIN, f32::max);
+            max_x(b).partial_cmp(&max_x(a)).unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for i in 0..hole_loops.len() {
+            let (hole, obstacles) = (&hole_loops[i], &hole_loops[i + 1..]);
+            if !bridge_hole_2d(&points, &mut polygon, hole, obstacles) {
+                return Err(TopologyError::InvalidElement { id: ids[*hole.iter().min().unwrap()] });
+            }
+        }
+        Ok(ear_clip_2d(&points, polygon).into_iter()
+            .map(|triangle| self.add_vertices(vec![ids[triangle[0]], ids[triangle[1]], ids[triangle[2]]]))
+            .collect())
+    }
+}
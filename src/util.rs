@@ -321,3 +321,71 @@ pub fn pick_most_not_obvious_vertex(vertices: Vec<Point3<f32>>) -> usize {
     }
     choosen_index
 }
+
+// Moller-Trumbore ray triangle intersection
+// https://en.wikipedia.org/wiki/M%C3%B6ller%E2%80%93Trumbore_intersection_algorithm
+pub fn intersect_of_ray_and_triangle(origin: Point3<f32>, direct: Vector3<f32>, p1: Point3<f32>, p2: Point3<f32>, p3: Point3<f32>) -> Option<f32> {
+    let edge1 = p2 - p1;
+    let edge2 = p3 - p1;
+    let h = direct.cross(edge2);
+    let a = edge1.dot(h);
+    if a.abs() < SMALL_NUM {
+        return None;
+    }
+    let f = 1.0 / a;
+    let s = origin - p1;
+    let u = f * s.dot(h);
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(edge1);
+    let v = f * direct.dot(q);
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = f * edge2.dot(q);
+    if t > 0.0 {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+// Closest point on a triangle by its Voronoi regions, from Real-Time
+// Collision Detection by Christer Ericson, 5.1.5
+pub fn closest_point_on_triangle(p: Point3<f32>, a: Point3<f32>, b: Point3<f32>, c: Point3<f32>) -> Point3<f32> {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = p - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+    let cp = p - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+    let denom = 1.0 / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
@@ -0,0 +1,528 @@
+use cgmath::Point2;
+use cgmath::prelude::*;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use iterator::FaceHalfedgeIterator;
+use iterator::FaceIterator;
+use mesh::EdgeEndpoints;
+use mesh::Id;
+use mesh::Mesh;
+
+/// Topological problems found by `Mesh::validate`.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    /// Number of edges used by a single face.
+    pub boundary_edges: usize,
+
+    /// Edges used by more than two faces.
+    pub nonmanifold_edges: Vec<EdgeEndpoints>,
+
+    /// Vertices whose faces form more than one fan, like the tip shared by
+    /// two cones.
+    pub nonmanifold_vertices: Vec<Id>,
+
+    t_junctions: Vec<Id>,
+    misoriented_edges: Vec<Id>,
+}
+
+impl ValidationReport {
+    pub fn is_manifold(&self) -> bool {
+        self.nonmanifold_edges.is_empty() && self.nonmanifold_vertices.is_empty()
+    }
+
+    /// Boundary halfedges with a T-junction, see `Mesh::find_t_junctions`.
+    pub fn t_junctions(&self) -> &[Id] {
+        &self.t_junctions
+    }
+
+    /// Halfedges whose opposite runs the same way, see
+    /// `Mesh::find_misoriented_edges`.
+    pub fn misoriented_edges(&self) -> &[Id] {
+        &self.misoriented_edges
+    }
+}
+
+/// What `Mesh::remove_duplicate_faces_with` does with faces using the same
+/// vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateFacePolicy {
+    /// Keeps the face with the lowest id and removes the others.
+    KeepOne,
+
+    /// Removes a face together with one of opposite winding, as two sides of
+    /// a zero-thickness sheet cancel out. Duplicates left over after that
+    /// keep one face.
+    CancelOpposite,
+}
+
+fn find_root(parents: &mut FnvHashMap<Id, Id>, id: Id) -> Id {
+    let mut root = id;
+    while parents[&root] != root {
+        root = parents[&root];
+    }
+    let mut id = id;
+    while parents[&id] != root {
+        let parent = parents[&id];
+        parents.insert(id, root);
+        id = parent;
+    }
+    root
+}
+
+impl Mesh {
+    /// The halfedges of every edge, found from the face loops so the result
+    /// does not depend on the opposite links, which cannot express edges
+    /// shared by more than two faces.
+    fn edge_halfedges(&self) -> FnvHashMap<EdgeEndpoints, Vec<Id>> {
+        let mut edge_halfedges: FnvHashMap<EdgeEndpoints, Vec<Id>> = FnvHashMap::default();
+        for face_id in FaceIterator::new(self) {
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for halfedge_id in FaceHalfedgeIterator::new(self, face_halfedge) {
+                let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+                let endpoints = EdgeEndpoints::new(self.halfedge_start_vertex_id(halfedge_id).unwrap(),
+                    self.halfedge_start_vertex_id(next_id).unwrap());
+                edge_halfedges.entry(endpoints).or_default().push(halfedge_id);
+            }
+        }
+        edge_halfedges
+    }
+
+    /// The halfedge joined to `halfedge_id` across the edge. Edges with more
+    /// than two faces only join the halfedges chosen in `pairs`.
+    fn joined_halfedge(&self, edge_halfedges: &FnvHashMap<EdgeEndpoints, Vec<Id>>, pairs: &FnvHashMap<Id, Id>, halfedge_id: Id) -> Option<Id> {
+        let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+        let endpoints = EdgeEndpoints::new(self.halfedge_start_vertex_id(halfedge_id).unwrap(),
+            self.halfedge_start_vertex_id(next_id).unwrap());
+        let halfedges = &edge_halfedges[&endpoints];
+        if halfedges.len() == 2 {
+            halfedges.iter().cloned().find(|&id| id != halfedge_id)
+        } else {
+            pairs.get(&halfedge_id).cloned()
+        }
+    }
+
+    /// Picks the two faces kept together on every edge shared by more than two
+    /// faces: one running each way, preferably from the same fan around the
+    /// lower end point so that joining them does not merge separate sheets.
+    fn nonmanifold_edge_pairs(&self, edge_halfedges: &FnvHashMap<EdgeEndpoints, Vec<Id>>) -> FnvHashMap<Id, Id> {
+        let mut pairs = FnvHashMap::default();
+        let loose = FnvHashMap::default();
+        for (endpoints, halfedges) in edge_halfedges.iter().filter(|&(_, halfedges)| halfedges.len() > 2) {
+            let fans = self.vertex_fans(edge_halfedges, &loose, endpoints.low);
+            let fan_index = |halfedge_id: Id| {
+                let around_id = if self.halfedge_start_vertex_id(halfedge_id) == Some(endpoints.low) {
+                    halfedge_id
+                } else {
+                    self.halfedge_next_id(halfedge_id).unwrap()
+                };
+                fans.iter().position(|fan| fan.contains(&around_id))
+            };
+            let (forward, backward): (Vec<Id>, Vec<Id>) = halfedges.iter()
+                .partition(|&&id| self.halfedge_start_vertex_id(id) == Some(endpoints.low));
+            let mut chosen = None;
+            for &forward_id in forward.iter() {
+                if let Some(&backward_id) = backward.iter().find(|&&id| fan_index(id) == fan_index(forward_id)) {
+                    chosen = Some((forward_id, backward_id));
+                    break;
+                }
+            }
+            if chosen.is_none() && !forward.is_empty() && !backward.is_empty() {
+                chosen = Some((forward[0], backward[0]));
+            }
+            if let Some((forward_id, backward_id)) = chosen {
+                pairs.insert(forward_id, backward_id);
+                pairs.insert(backward_id, forward_id);
+            }
+        }
+        pairs
+    }
+
+    /// Groups the outgoing halfedges of a vertex by the fan of faces they
+    /// belong to, the fan keeping the first halfedge of the vertex first.
+    fn vertex_fans(&self, edge_halfedges: &FnvHashMap<EdgeEndpoints, Vec<Id>>, pairs: &FnvHashMap<Id, Id>, vertex_id: Id) -> Vec<Vec<Id>> {
+        let outgoing = self.vertex(vertex_id).unwrap().halfedges.to_vec();
+        let mut parents: FnvHashMap<Id, Id> = outgoing.iter().map(|&id| (id, id)).collect();
+        for &halfedge_id in outgoing.iter() {
+            let prev_id = self.halfedge_prev_id(halfedge_id).unwrap();
+            for &edge_id in [halfedge_id, prev_id].iter() {
+                let joined_id = match self.joined_halfedge(edge_halfedges, pairs, edge_id) {
+                    Some(joined_id) => joined_id,
+                    None => continue,
+                };
+                // The joined halfedge either leaves this vertex or ends at it.
+                let around_id = if self.halfedge_start_vertex_id(joined_id) == Some(vertex_id) {
+                    joined_id
+                } else {
+                    self.halfedge_next_id(joined_id).unwrap()
+                };
+                if !parents.contains_key(&around_id) {
+                    continue;
+                }
+                let first_root = find_root(&mut parents, halfedge_id);
+                let second_root = find_root(&mut parents, around_id);
+                parents.insert(first_root, second_root);
+            }
+        }
+        let mut fans: Vec<Vec<Id>> = Vec::new();
+        let mut fan_of_root: FnvHashMap<Id, usize> = FnvHashMap::default();
+        for &halfedge_id in outgoing.iter() {
+            let root = find_root(&mut parents, halfedge_id);
+            let index = *fan_of_root.entry(root).or_insert(fans.len());
+            if index == fans.len() {
+                fans.push(Vec::new());
+            }
+            fans[index].push(halfedge_id);
+        }
+        fans
+    }
+
+    /// Checks the mesh for boundaries and non-manifold edges and vertices.
+    pub fn validate(&self) -> ValidationReport {
+        let edge_halfedges = self.edge_halfedges();
+        let pairs = self.nonmanifold_edge_pairs(&edge_halfedges);
+        let mut report = ValidationReport::default();
+        for (endpoints, halfedges) in edge_halfedges.iter() {
+            if halfedges.len() == 1 {
+                report.boundary_edges += 1;
+            } else if halfedges.len() > 2 {
+                report.nonmanifold_edges.push(endpoints.clone());
+            }
+        }
+        report.nonmanifold_edges.sort_by_key(|endpoints| (endpoints.low, endpoints.high));
+        for vertex in self.vertices.iter().filter(|vertex| vertex.alive && !vertex.halfedges.is_empty()) {
+            if self.vertex_fans(&edge_halfedges, &pairs, vertex.id).len() > 1 {
+                report.nonmanifold_vertices.push(vertex.id);
+            }
+        }
+        report.t_junctions = self.find_t_junctions();
+        report.misoriented_edges = self.find_misoriented_edges();
+        report
+    }
+
+    /// One halfedge, the one with the lower id, of every edge whose two faces
+    /// run the same way along it, as imports with flipped faces have. The
+    /// edge map pairs such halfedges all the same, but walking around their
+    /// vertices goes wrong. `fix_orientation` repairs orientable meshes.
+    pub fn find_misoriented_edges(&self) -> Vec<Id> {
+        self.iter_halfedges()
+            .filter(|&(halfedge_id, halfedge)| halfedge.opposite > halfedge_id
+                && self.halfedge_start_vertex_id(halfedge.opposite) == Some(halfedge.vertex))
+            .map(|(halfedge_id, _)| halfedge_id)
+            .collect()
+    }
+
+    /// Boundary halfedges whose side is covered by a chain of two or more
+    /// boundary halfedges of other faces running back along it, through
+    /// vertices lying on the side. Such T-junctions look closed but leave the
+    /// edges on the two sides unpaired, so subdivision treats them as
+    /// boundaries and tears the surface open there.
+    pub fn find_t_junctions(&self) -> Vec<Id> {
+        let end_vertex_id = |halfedge_id: Id| self.halfedge_start_vertex_id(self.halfedge_next_id(halfedge_id).unwrap()).unwrap();
+        let mut t_junctions = Vec::new();
+        for (halfedge_id, _) in self.iter_halfedges().filter(|&(_, halfedge)| halfedge.opposite == 0) {
+            let start_id = self.halfedge_start_vertex_id(halfedge_id).unwrap();
+            let start = self.vertex(start_id).unwrap().position;
+            let side = self.vertex(end_vertex_id(halfedge_id)).unwrap().position - start;
+            let length2 = side.magnitude2();
+            if length2 == 0.0 {
+                continue;
+            }
+            let on_side = |vertex_id: Id| {
+                let offset = self.vertex(vertex_id).unwrap().position - start;
+                let along = offset.dot(side) / length2;
+                along > 0.0 && along < 1.0 && (offset - side * along).magnitude2() <= length2 * 1e-8
+            };
+            // Follows the boundary from the end of the side, at every vertex
+            // taking the boundary halfedge that stays on the side.
+            let mut vertex_id = end_vertex_id(halfedge_id);
+            let mut skipped = 0;
+            loop {
+                let next_id = self.vertex(vertex_id).unwrap().halfedges.iter()
+                    .filter(|&&next_id| self.halfedge_opposite_id(next_id).is_none())
+                    .map(|&next_id| end_vertex_id(next_id))
+                    .find(|&next_id| next_id == start_id || on_side(next_id));
+                match next_id {
+                    Some(next_id) if next_id == start_id => {
+                        if skipped > 0 {
+                            t_junctions.push(halfedge_id);
+                        }
+                        break;
+                    },
+                    Some(next_id) if skipped < self.vertex_count => {
+                        vertex_id = next_id;
+                        skipped += 1;
+                    },
+                    _ => break,
+                }
+            }
+        }
+        t_junctions
+    }
+
+    /// Whether every halfedge has an opposite one, so the mesh has no
+    /// boundary. Stops at the first boundary halfedge.
+    pub fn is_closed(&self) -> bool {
+        self.halfedges.iter()
+            .filter(|halfedge| halfedge.alive)
+            .all(|halfedge| self.halfedge_opposite_id(halfedge.id).is_some())
+    }
+
+    /// Whether every edge has at most two faces and the faces around every
+    /// vertex form a single fan, closed or not, with neighboring faces
+    /// running opposite ways along their shared edge. Cheaper than
+    /// `validate`, as it stops at the first problem.
+    pub fn is_manifold(&self) -> bool {
+        let mut edge_faces: FnvHashMap<EdgeEndpoints, usize> = FnvHashMap::default();
+        for face_id in FaceIterator::new(self) {
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for halfedge_id in FaceHalfedgeIterator::new(self, face_halfedge) {
+                let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+                let endpoints = EdgeEndpoints::new(self.halfedge_start_vertex_id(halfedge_id).unwrap(),
+                    self.halfedge_start_vertex_id(next_id).unwrap());
+                let count = edge_faces.entry(endpoints).or_insert(0);
+                *count += 1;
+                if *count > 2 {
+                    return false;
+                }
+            }
+        }
+        self.vertices.iter()
+            .filter(|vertex| vertex.alive && !vertex.halfedges.is_empty())
+            .all(|vertex| self.is_single_fan(vertex.id))
+    }
+
+    /// Walks the outgoing halfedges of the vertex through the opposite links,
+    /// both ways from the first one, and checks that the walk reaches all of
+    /// them. Only meaningful once every edge is known to have at most two
+    /// faces.
+    fn is_single_fan(&self, vertex_id: Id) -> bool {
+        let outgoing = &self.vertex(vertex_id).unwrap().halfedges;
+        let first_id = outgoing[0];
+        let mut reached = 1;
+        let mut halfedge_id = first_id;
+        let mut closed = false;
+        while reached <= outgoing.len() {
+            let prev_id = self.halfedge_prev_id(halfedge_id).unwrap();
+            match self.halfedge_opposite_id(prev_id) {
+                Some(opposite_id) => {
+                    if self.halfedge_start_vertex_id(opposite_id) != Some(vertex_id) {
+                        return false;
+                    }
+                    if opposite_id == first_id {
+                        closed = true;
+                        break;
+                    }
+                    halfedge_id = opposite_id;
+                    reached += 1;
+                },
+                None => break,
+            }
+        }
+        if !closed {
+            halfedge_id = first_id;
+            while reached <= outgoing.len() {
+                match self.halfedge_opposite_id(halfedge_id) {
+                    Some(opposite_id) => {
+                        let next_id = self.halfedge_next_id(opposite_id).unwrap();
+                        if self.halfedge_start_vertex_id(next_id) != Some(vertex_id) {
+                            return false;
+                        }
+                        halfedge_id = next_id;
+                        reached += 1;
+                    },
+                    None => break,
+                }
+            }
+        }
+        reached == outgoing.len()
+    }
+
+    /// Gives every fan but the first of the vertices its own copy of the
+    /// vertex, rebuilding the faces around them. Materials and UVs of the
+    /// rebuilt faces are kept.
+    fn split_vertex_fans(&mut self, vertex_ids: &[Id]) -> usize {
+        let edge_halfedges = self.edge_halfedges();
+        let pairs = self.nonmanifold_edge_pairs(&edge_halfedges);
+        let mut replacements: FnvHashMap<Id, Id> = FnvHashMap::default();
+        let mut split_count = 0;
+        for &vertex_id in vertex_ids {
+            let fans = self.vertex_fans(&edge_halfedges, &pairs, vertex_id);
+            if fans.len() < 2 {
+                continue;
+            }
+            split_count += 1;
+            let position = self.vertex(vertex_id).unwrap().position;
+            let tags = self.vertex_tags(vertex_id);
+            for fan in fans.iter().skip(1) {
+                let copy_id = self.add_vertex(position);
+                self.set_vertex_tags(copy_id, tags);
+                for &halfedge_id in fan.iter() {
+                    replacements.insert(halfedge_id, copy_id);
+                }
+            }
+        }
+        let mut old_faces = Vec::new();
+        let mut seen_faces = FnvHashSet::default();
+        for &vertex_id in vertex_ids {
+            for &halfedge_id in self.vertex(vertex_id).unwrap().halfedges.iter() {
+                let face_id = self.halfedge_face_id(halfedge_id).unwrap();
+                if seen_faces.insert(face_id) {
+                    old_faces.push(face_id);
+                }
+            }
+        }
+        let mut loops = Vec::new();
+        let mut materials = Vec::new();
+        let mut corner_uvs: Vec<Vec<Option<Point2<f32>>>> = Vec::new();
+        for &face_id in old_faces.iter() {
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            let halfedges = FaceHalfedgeIterator::new(self, face_halfedge).into_vec();
+            loops.push(halfedges.iter().map(|id| match replacements.get(id) {
+                Some(&copy_id) => copy_id,
+                None => self.halfedge_start_vertex_id(*id).unwrap(),
+            }).collect());
+            materials.push(self.face_material(face_id));
+            corner_uvs.push(halfedges.iter().map(|&id| self.halfedge_uv(id)).collect());
+        }
+        let added_faces = self.replace_faces(&old_faces, &loops);
+        for ((&face_id, material), uvs) in added_faces.iter().zip(materials).zip(corner_uvs) {
+            self.set_face_material(face_id, material);
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for (halfedge_id, uv) in FaceHalfedgeIterator::new(self, face_halfedge).into_vec().into_iter().zip(uvs) {
+                if let Some(uv) = uv {
+                    self.set_halfedge_uv(halfedge_id, uv);
+                }
+            }
+        }
+        split_count
+    }
+
+    /// Detaches the faces of edges shared by more than two faces, keeping one
+    /// face on each side of the edge, and returns how many edges were split.
+    /// The detached faces get their own copies of the edge end points, so new
+    /// boundaries may appear.
+    pub fn split_nonmanifold_edges(&mut self) -> usize {
+        let nonmanifold_edges = self.validate().nonmanifold_edges;
+        let mut vertex_ids = Vec::new();
+        for endpoints in nonmanifold_edges.iter() {
+            for &vertex_id in [endpoints.low, endpoints.high].iter() {
+                if !vertex_ids.contains(&vertex_id) {
+                    vertex_ids.push(vertex_id);
+                }
+            }
+        }
+        self.split_vertex_fans(&vertex_ids);
+        nonmanifold_edges.len()
+    }
+
+    /// Splits every vertex whose faces form several fans into one vertex per
+    /// fan and returns how many vertices were split.
+    pub fn remove_nonmanifold_vertices(&mut self) -> usize {
+        let nonmanifold_vertices = self.validate().nonmanifold_vertices;
+        self.split_vertex_fans(&nonmanifold_vertices)
+    }
+
+    /// Removes faces using the same vertices as a face with a lower id, as
+    /// left by imports that repeat faces, and returns how many were removed.
+    /// See `remove_duplicate_faces_with` for removing faces of opposite
+    /// winding as a pair.
+    pub fn remove_duplicate_faces(&mut self) -> usize {
+        self.remove_duplicate_faces_with(DuplicateFacePolicy::KeepOne)
+    }
+
+    /// Removes faces using the same set of vertices as another face following
+    /// `policy` and returns how many were removed. The edges of the removed
+    /// faces are joined again between the faces that are left.
+    pub fn remove_duplicate_faces_with(&mut self, policy: DuplicateFacePolicy) -> usize {
+        let mut groups: FnvHashMap<Vec<Id>, Vec<(Id, Vec<Id>)>> = FnvHashMap::default();
+        let mut group_order = Vec::new();
+        for face_id in FaceIterator::new(self) {
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            let corners: Vec<Id> = FaceHalfedgeIterator::new(self, face_halfedge)
+                .map(|halfedge_id| self.halfedge_start_vertex_id(halfedge_id).unwrap())
+                .collect();
+            let mut key = corners.clone();
+            key.sort();
+            let group = groups.entry(key.clone()).or_default();
+            if group.is_empty() {
+                group_order.push(key);
+            }
+            group.push((face_id, corners));
+        }
+        let mut removed_faces = Vec::new();
+        for key in group_order {
+            let group = &groups[&key];
+            if group.len() < 2 {
+                continue;
+            }
+            let (first_id, ref first_corners) = group[0];
+            let (mut forward, mut backward): (Vec<Id>, Vec<Id>) = (vec![first_id], Vec::new());
+            for &(face_id, ref corners) in group.iter().skip(1) {
+                if policy == DuplicateFacePolicy::CancelOpposite && is_reversed_loop(first_corners, corners) {
+                    backward.push(face_id);
+                } else {
+                    forward.push(face_id);
+                }
+            }
+            let cancelled = forward.len().min(backward.len());
+            removed_faces.extend(forward.drain(..cancelled));
+            removed_faces.extend(backward.drain(..cancelled));
+            removed_faces.extend(forward.into_iter().chain(backward).skip(1));
+        }
+        let mut endpoints_set = FnvHashSet::default();
+        for &face_id in removed_faces.iter() {
+            let face_halfedge = self.face_first_halfedge_id(face_id).unwrap();
+            for halfedge_id in FaceHalfedgeIterator::new(self, face_halfedge) {
+                let next_id = self.halfedge_next_id(halfedge_id).unwrap();
+                endpoints_set.insert(EdgeEndpoints::new(self.halfedge_start_vertex_id(halfedge_id).unwrap(),
+                    self.halfedge_start_vertex_id(next_id).unwrap()));
+            }
+        }
+        for &face_id in removed_faces.iter() {
+            self.remove_face(face_id);
+        }
+        for endpoints in endpoints_set {
+            self.rejoin_edge(endpoints);
+        }
+        removed_faces.len()
+    }
+
+    /// Pairs the halfedges along an edge again after faces on it were
+    /// removed: one halfedge running each way are joined, any other count
+    /// leaves them all unpaired.
+    fn rejoin_edge(&mut self, endpoints: EdgeEndpoints) {
+        let mut along = [Vec::new(), Vec::new()];
+        for (direction, &(from, to)) in [(endpoints.low, endpoints.high), (endpoints.high, endpoints.low)].iter().enumerate() {
+            if let Some(vertex) = self.vertex(from) {
+                along[direction] = vertex.halfedges.iter().cloned().filter(|&id| {
+                    self.halfedge_next_id(id).and_then(|next_id| self.halfedge_start_vertex_id(next_id)) == Some(to)
+                }).collect();
+            }
+        }
+        // Links to removed halfedges may be left over, so every halfedge on the
+        // edge is cleared rather than unpaired from its opposite.
+        for &halfedge_id in along[0].iter().chain(along[1].iter()) {
+            self.set_halfedge_opposite_id(halfedge_id, 0);
+        }
+        if along[0].len() == 1 && along[1].len() == 1 {
+            self.pair_halfedges(along[0][0], along[1][0]);
+        }
+        match along[0].first().or(along[1].first()) {
+            Some(&halfedge_id) => {
+                self.edges.insert(endpoints, halfedge_id);
+            },
+            None => {
+                self.edges.remove(&endpoints);
+            },
+        }
+    }
+}
+
+/// Whether `second` runs through the corners of `first` the other way round.
+fn is_reversed_loop(first: &[Id], second: &[Id]) -> bool {
+    let start = match second.iter().position(|&id| id == first[0]) {
+        Some(start) => start,
+        None => return false,
+    };
+    (0..first.len()).all(|i| first[i] == second[(start + second.len() - i) % second.len()])
+}
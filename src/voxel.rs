@@ -0,0 +1,144 @@
+use cgmath::Point3;
+use cgmath::Vector3;
+use cgmath::prelude::*;
+use iterator::FaceIterator;
+use iterator::FaceTriangleIterator;
+use mesh::Mesh;
+
+/// Dense grid of cells over the bounding box of a mesh, as made by
+/// `Mesh::voxelize` and `Mesh::voxelize_surface`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxelGrid {
+    /// Number of cells along x, y and z.
+    pub resolution: [usize; 3],
+
+    /// Corner of the first cell, the lowest corner of the grid.
+    pub origin: Point3<f32>,
+
+    pub cell_size: Vector3<f32>,
+
+    /// Whether each cell is filled, with x changing fastest, see `index`.
+    pub cells: Vec<bool>,
+}
+
+impl VoxelGrid {
+    fn new(mesh: &Mesh, resolution: [usize; 3]) -> Self {
+        let (origin, cell_size) = match mesh.build_bvh().bounds() {
+            Some((min, max)) => (min, Vector3::new((max.x - min.x) / resolution[0].max(1) as f32,
+                (max.y - min.y) / resolution[1].max(1) as f32,
+                (max.z - min.z) / resolution[2].max(1) as f32)),
+            None => (Point3::origin(), Vector3::zero()),
+        };
+        VoxelGrid {
+            resolution,
+            origin,
+            cell_size,
+            cells: vec![false; resolution[0] * resolution[1] * resolution[2]],
+        }
+    }
+
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        x + self.resolution[0] * (y + self.resolution[1] * z)
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> bool {
+        self.cells[self.index(x, y, z)]
+    }
+
+    pub fn cell_center(&self, x: usize, y: usize, z: usize) -> Point3<f32> {
+        self.origin + Vector3::new((x as f32 + 0.5) * self.cell_size.x,
+            (y as f32 + 0.5) * self.cell_size.y,
+            (z as f32 + 0.5) * self.cell_size.z)
+    }
+
+    /// Number of filled cells.
+    pub fn filled_count(&self) -> usize {
+        self.cells.iter().filter(|&&filled| filled).count()
+    }
+}
+
+/// Whether the triangle overlaps the box, by looking for a separating axis
+/// among the box axes, the triangle normal and their edge cross products.
+fn triangle_overlaps_box(corners: &[Point3<f32>; 3], center: Point3<f32>, half: Vector3<f32>) -> bool {
+    let points = [corners[0] - center, corners[1] - center, corners[2] - center];
+    let edges = [points[1] - points[0], points[2] - points[1], points[0] - points[2]];
+    let box_axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()];
+    let mut axes = Vec::with_capacity(13);
+    axes.extend_from_slice(&box_axes);
+    axes.push(edges[0].cross(edges[1]));
+    for edge in edges.iter() {
+        for box_axis in box_axes.iter() {
+            axes.push(edge.cross(*box_axis));
+        }
+    }
+    axes.iter().all(|axis| {
+        let projections = [points[0].dot(*axis), points[1].dot(*axis), points[2].dot(*axis)];
+        let radius = half.x * axis.x.abs() + half.y * axis.y.abs() + half.z * axis.z.abs();
+        let min = projections[0].min(projections[1]).min(projections[2]);
+        let max = projections[0].max(projections[1]).max(projections[2]);
+        min <= radius && max >= -radius
+    })
+}
+
+impl Mesh {
+    /// Marks the cells of a grid over the bounding box whose center is
+    /// inside the mesh, see `Bvh::contains_point`. Only meaningful for closed
+    /// meshes.
+    pub fn voxelize(&self, resolution: [usize; 3]) -> VoxelGrid {
+        let mut grid = VoxelGrid::new(self, resolution);
+        let bvh = self.build_bvh();
+        for z in 0..resolution[2] {
+            for y in 0..resolution[1] {
+                for x in 0..resolution[0] {
+                    let index = grid.index(x, y, z);
+                    grid.cells[index] = bvh.contains_point(grid.cell_center(x, y, z));
+                }
+            }
+        }
+        grid
+    }
+
+    /// Marks the cells of a grid over the bounding box that the faces pass
+    /// through, including cells they only touch.
+    pub fn voxelize_surface(&self, resolution: [usize; 3]) -> VoxelGrid {
+        let mut grid = VoxelGrid::new(self, resolution);
+        if grid.cells.is_empty() {
+            return grid;
+        }
+        // Slightly larger than the cells, so faces on a cell border count as
+        // touching it despite rounding.
+        let half = grid.cell_size * (0.5 + 1e-4);
+        let cell_range = |grid: &VoxelGrid, axis: usize, min: f32, max: f32| {
+            let last = grid.resolution[axis] - 1;
+            if grid.cell_size[axis] <= 0.0 {
+                return (0, last);
+            }
+            let cell = |value: f32| (((value - grid.origin[axis]) / grid.cell_size[axis]).floor().max(0.0) as usize).min(last);
+            (cell(min), cell(max))
+        };
+        for face_id in FaceIterator::new(self) {
+            for triangle in FaceTriangleIterator::new(self, face_id) {
+                let corners = [self.vertex(triangle[0]).unwrap().position,
+                    self.vertex(triangle[1]).unwrap().position,
+                    self.vertex(triangle[2]).unwrap().position];
+                let mut ranges = [(0, 0); 3];
+                for (axis, range) in ranges.iter_mut().enumerate() {
+                    let min = corners[0][axis].min(corners[1][axis]).min(corners[2][axis]);
+                    let max = corners[0][axis].max(corners[1][axis]).max(corners[2][axis]);
+                    *range = cell_range(&grid, axis, min, max);
+                }
+                for z in ranges[2].0..=ranges[2].1 {
+                    for y in ranges[1].0..=ranges[1].1 {
+                        for x in ranges[0].0..=ranges[0].1 {
+                            let index = grid.index(x, y, z);
+                            if !grid.cells[index] && triangle_overlaps_box(&corners, grid.cell_center(x, y, z), half) {
+                                grid.cells[index] = true;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        grid
+    }
+}
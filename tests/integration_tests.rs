@@ -1,8 +1,52 @@
+extern crate cgmath;
+extern crate fnv;
 extern crate meshlite;
 
+use cgmath::prelude::*;
+use cgmath::Deg;
+use cgmath::Point2;
+use cgmath::Point3;
+use cgmath::Quaternion;
+use cgmath::Vector3;
+use fnv::FnvHashMap;
+use fnv::FnvHashSet;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use meshlite::buffer::VertexLayout;
+use meshlite::cache::NormalCache;
+use meshlite::deform::Axis;
+use meshlite::deform::Falloff;
+use meshlite::error::MeshError;
+use meshlite::error::TopologyError;
+use meshlite::geom::tri_tri_coplanar_overlap;
+use meshlite::geom::tri_tri_intersect;
+use meshlite::iterator::FaceHalfedgeIterator;
+use meshlite::iterator::FaceIterator;
+use meshlite::iterator::FaceTriangleIterator;
+use meshlite::material::Material;
+use meshlite::mesh::EdgeEndpoints;
+use meshlite::mesh::Export;
+use meshlite::mesh::Id;
+use meshlite::mesh::Import;
+use meshlite::mesh::Mesh;
 use meshlite::primitives::cube;
+use meshlite::sample::POISSON_MAX_DARTS;
+use meshlite::scheme::Prepass;
+use meshlite::scheme::SubdivideOptions;
+use meshlite::scheme::SubdivisionScheme;
+use meshlite::selection::Selection;
+use meshlite::simplify::SimplifyOptions;
+use meshlite::sparse::LaplacianKind;
+use meshlite::subdivide::BoundaryInterpolation;
+use meshlite::subdivide::CatmullClarkSubdivider;
 use meshlite::subdivide::Subdivide;
+use meshlite::subdivide::VertexSource;
 use meshlite::triangulate::Triangulate;
+use meshlite::validate::DuplicateFacePolicy;
+use meshlite::wavefront::NormalMode;
+use meshlite::wavefront::ObjImportOptions;
+use meshlite::wavefront::ObjExportOptions;
 
 /// Test added for https://github.com/huxingyi/meshlite/pull/2
 #[test]
@@ -20,3 +64,3223 @@ fn verify_cube_triangulation() {
     assert_eq!(8, tri.vertex_count);
     assert_eq!(12, tri.face_count);
 }
+
+#[test]
+fn verify_cube_corner_halfedge_order() {
+    let mesh = cube();
+    for vertex_id in 1..=8 {
+        let ordered = mesh.ordered_vertex_halfedges(vertex_id);
+        assert_eq!(3, ordered.len());
+        let position = mesh.vertex(vertex_id).unwrap().position;
+        let outward = position - Point3::new(0.0, 0.0, 0.0);
+        for i in 0..ordered.len() {
+            let current = ordered[i];
+            let next = ordered[(i + 1) % ordered.len()];
+            let prev = mesh.halfedge_prev_id(current).unwrap();
+            assert_eq!(Some(next), mesh.halfedge_opposite_id(prev));
+            let turn = mesh.halfedge_direct(current).cross(mesh.halfedge_direct(next));
+            assert!(turn.dot(outward) > 0.0);
+        }
+    }
+}
+
+#[test]
+fn verify_triangle_quality() {
+    let mut mesh = Mesh::new();
+    let equilateral = mesh.add_positions(vec![
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(1.0, 0.0, 0.0),
+        Point3::new(0.5, 3.0f32.sqrt() / 2.0, 0.0),
+    ]);
+    let sliver = mesh.add_positions(vec![
+        Point3::new(0.0, 0.0, 5.0),
+        Point3::new(1.0, 0.0, 5.0),
+        Point3::new(0.5, 0.001, 5.0),
+    ]);
+    assert!((mesh.triangle_quality(equilateral) - 1.0).abs() < 1e-4);
+    assert!(mesh.triangle_quality(sliver) < 0.01);
+    let report = mesh.quality_report();
+    assert_eq!(2, report.triangle_count);
+    assert_eq!(1, report.sliver_count);
+    assert_eq!(sliver, report.worst_face);
+
+    let report = cube().quality_report();
+    assert_eq!(12, report.triangle_count);
+    assert!((report.min_dihedral_angle - 90.0).abs() < 1e-3);
+    assert!((report.max_dihedral_angle - 90.0).abs() < 1e-3);
+}
+
+fn assert_closed_quad_mesh(mesh: &Mesh) {
+    for face_id in FaceIterator::new(mesh) {
+        let halfedge_id = mesh.face_first_halfedge_id(face_id).unwrap();
+        assert_eq!(4, FaceHalfedgeIterator::new(mesh, halfedge_id).into_vec().len());
+    }
+    assert_closed_mesh(mesh);
+}
+
+fn assert_closed_mesh(mesh: &Mesh) {
+    for halfedge in mesh.halfedges.iter().filter(|halfedge| halfedge.alive) {
+        let opposite = mesh.halfedge(halfedge.opposite).unwrap();
+        assert_eq!(halfedge.id, opposite.opposite);
+        assert_eq!(Some(halfedge.vertex), mesh.halfedge_start_vertex_id(opposite.next));
+    }
+}
+
+#[test]
+fn verify_tetrahedron_subdivision() {
+    let mut mesh = Mesh::new();
+    let v1 = mesh.add_vertex(Point3::new(1.0, 1.0, 1.0));
+    let v2 = mesh.add_vertex(Point3::new(1.0, -1.0, -1.0));
+    let v3 = mesh.add_vertex(Point3::new(-1.0, 1.0, -1.0));
+    let v4 = mesh.add_vertex(Point3::new(-1.0, -1.0, 1.0));
+    mesh.add_vertices(vec![v1, v2, v3]);
+    mesh.add_vertices(vec![v1, v4, v2]);
+    mesh.add_vertices(vec![v1, v3, v4]);
+    mesh.add_vertices(vec![v2, v4, v3]);
+    let sub = mesh.subdivide();
+    assert_eq!(4 + 6 + 4, sub.vertex_count);
+    assert_eq!(12, sub.face_count);
+    assert_closed_quad_mesh(&sub);
+}
+
+/// Checks every vertex of one level of subdivision against `expected`, which
+/// gets the source of the vertex with the input positions of its face, edge
+/// or vertex.
+fn assert_subdivision_reference<F: Fn(VertexSource, &[Point3<f32>]) -> Point3<f32>>(input: &Mesh, expected: F) {
+    let (output, map) = CatmullClarkSubdivider::new(input).generate_with_provenance();
+    for vertex in output.vertices.iter() {
+        let source = map.vertices[&vertex.id];
+        let positions: Vec<Point3<f32>> = match source {
+            VertexSource::Face(id) => input.face_vertex_ids(id),
+            VertexSource::Edge(id) => vec![input.halfedge_start_vertex_id(id).unwrap(),
+                input.halfedge_start_vertex_id(input.halfedge_next_id(id).unwrap()).unwrap()],
+            VertexSource::Vertex(id) => vec![id],
+        }.into_iter().map(|id| input.vertex(id).unwrap().position).collect();
+        let expected_position = expected(source, &positions);
+        assert!(vertex.position.distance(expected_position) < 1e-6,
+            "{:?} at {:?}, expected {:?}", source, vertex.position, expected_position);
+    }
+}
+
+#[test]
+fn verify_catmull_clark_reference_values() {
+    // A lone unit quad is all boundary: edge points are midpoints and the
+    // corners move to 3/4 of themselves plus 1/8 of both neighbors.
+    let mut quad = Mesh::new();
+    quad.add_positions(vec![
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(1.0, 0.0, 0.0),
+        Point3::new(1.0, 1.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0),
+    ]);
+    assert_subdivision_reference(&quad, |source, positions| match source {
+        VertexSource::Face(_) => Point3::new(0.5, 0.5, 0.0),
+        VertexSource::Edge(_) => positions[0].midpoint(positions[1]),
+        VertexSource::Vertex(_) => Point3::new(0.125 + 0.75 * positions[0].x, 0.125 + 0.75 * positions[0].y, 0.0),
+    });
+
+    // Unit cube: a corner (1/2, 1/2, 1/2) goes to (F + 2R) / 3 with
+    // F = (1/6, 1/6, 1/6) and R = (1/3, 1/3, 1/3), which is 5/9 of it. An
+    // edge point averages its end points and face points, 3/4 of the edge
+    // midpoint.
+    assert_subdivision_reference(&cube(), |source, positions| match source {
+        VertexSource::Face(_) => Point3::centroid(positions),
+        VertexSource::Edge(_) => Point3::from_vec(positions[0].midpoint(positions[1]).to_vec() * 0.75),
+        VertexSource::Vertex(_) => Point3::from_vec(positions[0].to_vec() * (5.0 / 9.0)),
+    });
+
+    // Regular tetrahedron centered at the origin: the face points are minus a
+    // third of the opposite vertex, so an edge point is (a + b) / 3, and a
+    // vertex goes to (F + 2R) / 3 = (v / 9 + 2v / 3) / 3 = 7v / 27.
+    let mut tetrahedron = Mesh::new();
+    let v1 = tetrahedron.add_vertex(Point3::new(1.0, 1.0, 1.0));
+    let v2 = tetrahedron.add_vertex(Point3::new(1.0, -1.0, -1.0));
+    let v3 = tetrahedron.add_vertex(Point3::new(-1.0, 1.0, -1.0));
+    let v4 = tetrahedron.add_vertex(Point3::new(-1.0, -1.0, 1.0));
+    tetrahedron.add_vertices(vec![v1, v2, v3]);
+    tetrahedron.add_vertices(vec![v1, v4, v2]);
+    tetrahedron.add_vertices(vec![v1, v3, v4]);
+    tetrahedron.add_vertices(vec![v2, v4, v3]);
+    assert_subdivision_reference(&tetrahedron, |source, positions| match source {
+        VertexSource::Face(_) => Point3::from_vec((positions[0].to_vec() + positions[1].to_vec() + positions[2].to_vec()) / 3.0),
+        VertexSource::Edge(_) => Point3::from_vec((positions[0].to_vec() + positions[1].to_vec()) / 3.0),
+        VertexSource::Vertex(_) => Point3::from_vec(positions[0].to_vec() * (7.0 / 27.0)),
+    });
+
+    // An open strip of two quads: the shared edge is interior, its end points
+    // lie on the boundary and follow the curve along it.
+    let mut strip = Mesh::new();
+    let ids: Vec<Id> = [(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (0.0, 1.0), (1.0, 1.0), (2.0, 1.0)].iter()
+        .map(|&(x, y)| strip.add_vertex(Point3::new(x, y, 0.0)))
+        .collect();
+    strip.add_vertices(vec![ids[0], ids[1], ids[4], ids[3]]);
+    strip.add_vertices(vec![ids[1], ids[2], ids[5], ids[4]]);
+    assert_subdivision_reference(&strip, |source, positions| match source {
+        VertexSource::Face(_) => Point3::centroid(positions),
+        VertexSource::Edge(_) if positions[0].x == 1.0 && positions[1].x == 1.0 => Point3::new(1.0, 0.5, 0.0),
+        VertexSource::Edge(_) => positions[0].midpoint(positions[1]),
+        VertexSource::Vertex(_) => {
+            // Corners pull 1/8 towards both neighbors, the middle vertices
+            // have theirs on a straight line and stay.
+            let p = positions[0];
+            let x = if p.x == 1.0 { 1.0 } else { 0.125 + 0.875 * p.x };
+            Point3::new(x, if p.x == 1.0 { p.y } else { 0.125 + 0.75 * p.y }, 0.0)
+        },
+    });
+}
+
+#[test]
+fn verify_pentagonal_prism_subdivision() {
+    let mut mesh = Mesh::new();
+    let mut bottom = Vec::new();
+    let mut top = Vec::new();
+    for i in 0..5 {
+        let angle = i as f32 * 2.0 * std::f32::consts::PI / 5.0;
+        bottom.push(mesh.add_vertex(Point3::new(angle.cos(), angle.sin(), 0.0)));
+        top.push(mesh.add_vertex(Point3::new(angle.cos(), angle.sin(), 1.0)));
+    }
+    mesh.add_vertices(top.clone());
+    mesh.add_vertices(bottom.iter().rev().cloned().collect());
+    for i in 0..5 {
+        let j = (i + 1) % 5;
+        mesh.add_vertices(vec![bottom[i], bottom[j], top[j], top[i]]);
+    }
+    let sub = mesh.subdivide();
+    assert_eq!(10 + 15 + 7, sub.vertex_count);
+    assert_eq!(30, sub.face_count);
+    assert_closed_quad_mesh(&sub);
+}
+
+#[test]
+fn verify_split_face() {
+    let mut mesh = Mesh::new();
+    let quad = mesh.add_positions(vec![
+        Point3::new(0.0, 0.0, 0.0),
+        Point3::new(1.0, 0.0, 0.0),
+        Point3::new(1.0, 1.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0),
+    ]);
+    assert_eq!(Err(TopologyError::WouldCreateDuplicateEdge { first: 1, second: 2 }), mesh.split_face(quad, 1, 2));
+    assert_eq!(Err(TopologyError::InvalidElement { id: 1 }), mesh.split_face(quad, 1, 1));
+    assert_eq!(Err(TopologyError::ElementNotOnFace { face: quad, element: 9 }), mesh.split_face(quad, 1, 9));
+    assert_eq!(Err(TopologyError::InvalidElement { id: 9 }), mesh.split_face(9, 1, 3));
+    mesh.attributes.add_face_attribute::<f32>("layer");
+    mesh.attributes.set_face_attribute("layer", quad, 2.0f32);
+    mesh.attributes.add_halfedge_attribute::<f32>("corner");
+    for halfedge_id in FaceHalfedgeIterator::new(&mesh, mesh.face_first_halfedge_id(quad).unwrap()).into_vec() {
+        let vertex = mesh.halfedge_start_vertex(halfedge_id).unwrap();
+        let (uv, corner) = (Point2::new(vertex.position.x, vertex.position.y), vertex.id as f32);
+        mesh.set_halfedge_uv(halfedge_id, uv);
+        mesh.attributes.set_halfedge_attribute("corner", halfedge_id, corner);
+    }
+    let added = mesh.split_face(quad, 1, 3).unwrap();
+    assert_eq!(2, mesh.face_count);
+    assert_eq!(6, mesh.halfedge_count);
+    for &face_id in [quad, added].iter() {
+        let halfedges = FaceHalfedgeIterator::new(&mesh, mesh.face_first_halfedge_id(face_id).unwrap()).into_vec();
+        assert_eq!(3, halfedges.len());
+        assert_eq!(Some(&2.0), mesh.attributes.face_attribute::<f32>("layer", face_id));
+        for &halfedge_id in halfedges.iter() {
+            assert_eq!(Some(face_id), mesh.halfedge_face_id(halfedge_id));
+            let vertex = mesh.halfedge_start_vertex(halfedge_id).unwrap();
+            assert_eq!(Some(Point2::new(vertex.position.x, vertex.position.y)), mesh.halfedge_uv(halfedge_id));
+            assert_eq!(Some(&(vertex.id as f32)), mesh.attributes.halfedge_attribute::<f32>("corner", halfedge_id));
+        }
+    }
+    assert_eq!(Err(TopologyError::WouldCreateDuplicateEdge { first: 1, second: 3 }), mesh.split_face(quad, 1, 3));
+}
+
+#[test]
+fn verify_cube_edge_bevel() {
+    let mut mesh = cube();
+    let edge_id = mesh.edges[&EdgeEndpoints::new(1, 2)];
+    let chamfer = mesh.bevel_edge(edge_id, 0.1, 1).unwrap();
+    assert_eq!(1, chamfer.len());
+    assert_eq!(7, mesh.face_count);
+    assert_eq!(10, mesh.vertex_count);
+    assert_closed_mesh(&mesh);
+    assert!((mesh.face_norm(chamfer[0]).dot(Vector3::new(0.0, -1.0, 1.0).normalize()) - 1.0).abs() < 1e-4);
+
+    let mut mesh = cube();
+    let edge_id = mesh.edges[&EdgeEndpoints::new(1, 2)];
+    assert_eq!(3, mesh.bevel_edge(edge_id, 0.1, 3).unwrap().len());
+    assert_eq!(9, mesh.face_count);
+    assert_eq!(14, mesh.vertex_count);
+    assert_closed_mesh(&mesh);
+
+    // Every side projected along its normal, so the UVs of a face follow its
+    // positions and the rebuilt sides can be checked exactly.
+    let project = |mesh: &Mesh, face_id: Id, position: Point3<f32>| {
+        let normal = mesh.face_norm(face_id);
+        if normal.x.abs() > 0.5 {
+            Point2::new(position.y, position.z)
+        } else if normal.y.abs() > 0.5 {
+            Point2::new(position.x, position.z)
+        } else {
+            Point2::new(position.x, position.y)
+        }
+    };
+    let mut mesh = cube();
+    let red = mesh.add_material(Material::new("red"));
+    for face_id in FaceIterator::new(&mesh).into_vec() {
+        mesh.set_face_material(face_id, Some(red));
+        for halfedge_id in FaceHalfedgeIterator::new(&mesh, mesh.face_first_halfedge_id(face_id).unwrap()).into_vec() {
+            let uv = project(&mesh, face_id, mesh.halfedge_start_vertex(halfedge_id).unwrap().position);
+            mesh.set_halfedge_uv(halfedge_id, uv);
+        }
+    }
+    let edge_id = mesh.edges[&EdgeEndpoints::new(1, 2)];
+    let chamfer = mesh.bevel_edge(edge_id, 0.1, 2).unwrap();
+    assert_eq!(8, mesh.face_count);
+    for face_id in FaceIterator::new(&mesh) {
+        assert_eq!(Some(red), mesh.face_material(face_id));
+        for halfedge_id in FaceHalfedgeIterator::new(&mesh, mesh.face_first_halfedge_id(face_id).unwrap()) {
+            let uv = mesh.halfedge_uv(halfedge_id).unwrap();
+            if !chamfer.contains(&face_id) {
+                let expected = project(&mesh, face_id, mesh.halfedge_start_vertex(halfedge_id).unwrap().position);
+                assert!(uv.distance(expected) < 1e-5, "{:?} is not {:?}", uv, expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn verify_streaming_obj_load() {
+    let mesh = cube().subdivide();
+    let path = TempPath::new("meshlite_verify_streaming_obj_load.obj");
+    let filename = path.0.to_str().unwrap();
+    mesh.export(filename).unwrap();
+    let mut imported = Mesh::new();
+    imported.import(filename).unwrap();
+    let streamed = Mesh::load_obj_streaming(filename).unwrap();
+    assert_eq!(imported.vertex_count, streamed.vertex_count);
+    assert_eq!(imported.face_count, streamed.face_count);
+    assert_eq!(imported.halfedge_count, streamed.halfedge_count);
+    assert_eq!(imported.edges.len(), streamed.edges.len());
+    for (a, b) in imported.vertices.iter().zip(streamed.vertices.iter()) {
+        assert_eq!(a.position, b.position);
+    }
+    for (a, b) in imported.halfedges.iter().zip(streamed.halfedges.iter()) {
+        assert_eq!((a.vertex, a.next, a.opposite), (b.vertex, b.next, b.opposite));
+    }
+}
+
+/// A path in the temp directory, removed with everything under it when
+/// dropped, so a failing test does not leave it behind.
+struct TempPath(std::path::PathBuf);
+
+impl TempPath {
+    fn new(name: &str) -> Self {
+        TempPath(std::env::temp_dir().join(name))
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        if self.0.is_dir() {
+            std::fs::remove_dir_all(&self.0).ok();
+        } else {
+            std::fs::remove_file(&self.0).ok();
+        }
+    }
+}
+
+/// A unit sphere of `columns` by `rows` faces around the z axis, triangle
+/// fans at the poles.
+fn uv_sphere(columns: usize, rows: usize) -> Mesh {
+    Mesh::from_grid(columns, rows, |u, v| {
+        let (u, v) = (u * 2.0 * std::f32::consts::PI, v * std::f32::consts::PI);
+        Point3::new(v.sin() * u.cos(), v.sin() * u.sin(), -v.cos())
+    }, true, false)
+}
+
+/// A cube with every side split into a grid of `n` by `n` quads.
+fn grid_cube(n: usize) -> Mesh {
+    let corners = cube();
+    let mut mesh = Mesh::new();
+    let mut welded: HashMap<(i64, i64, i64), usize> = HashMap::new();
+    for face_id in FaceIterator::new(&corners) {
+        let halfedge_id = corners.face_first_halfedge_id(face_id).unwrap();
+        let p: Vec<Point3<f32>> = FaceHalfedgeIterator::new(&corners, halfedge_id)
+            .map(|id| corners.halfedge_start_vertex(id).unwrap().position)
+            .collect();
+        let mut vertex_at = |i: usize, j: usize| {
+            let (u, v) = (i as f32 / n as f32, j as f32 / n as f32);
+            let position = Point3::from_vec(p[0].to_vec() * ((1.0 - u) * (1.0 - v)) + p[1].to_vec() * (u * (1.0 - v)) +
+                p[2].to_vec() * (u * v) + p[3].to_vec() * ((1.0 - u) * v));
+            let key = ((position.x * 1000.0).round() as i64, (position.y * 1000.0).round() as i64,
+                (position.z * 1000.0).round() as i64);
+            *welded.entry(key).or_insert_with(|| mesh.add_vertex(position))
+        };
+        let mut quads = Vec::new();
+        for i in 0..n {
+            for j in 0..n {
+                quads.push(vec![vertex_at(i, j), vertex_at(i + 1, j), vertex_at(i + 1, j + 1), vertex_at(i, j + 1)]);
+            }
+        }
+        for quad in quads {
+            mesh.add_vertices(quad);
+        }
+    }
+    mesh
+}
+
+fn cube_surface_error(mesh: &Mesh) -> f32 {
+    let mut total = 0.0;
+    for vertex in mesh.vertices.iter().filter(|vertex| vertex.alive) {
+        let p = vertex.position;
+        total += (p.x.abs().max(p.y.abs()).max(p.z.abs()) - 0.5).abs();
+    }
+    total / mesh.vertex_count as f32
+}
+
+#[test]
+fn verify_bilateral_smooth_keeps_cube_edges() {
+    let mut mesh = grid_cube(4);
+    assert_closed_quad_mesh(&mesh);
+    for vertex in mesh.vertices.iter_mut() {
+        let noise = |seed: f32| ((seed * 12.9898).sin() * 43758.547).fract() * 0.02;
+        let seed = vertex.id as f32;
+        vertex.position += Vector3::new(noise(seed), noise(seed + 0.3), noise(seed + 0.7));
+    }
+    assert_eq!(48, mesh.sharp_edges(45.0).len());
+    let smoothed = mesh.bilateral_smooth(10, 0.25, 0.35);
+    assert_eq!(48, smoothed.sharp_edges(45.0).len());
+    assert!(cube_surface_error(&smoothed) < cube_surface_error(&mesh) * 0.5);
+}
+
+#[test]
+fn verify_eval_limit_on_subdivided_cube() {
+    let mesh = cube().subdivide().subdivide();
+    let reference = mesh.subdivide().subdivide().subdivide();
+    for face_id in FaceIterator::new(&mesh) {
+        for &(u, v) in [(0.0, 0.0), (0.5, 0.5), (0.25, 0.75), (1.0, 0.5)].iter() {
+            let (position, normal) = mesh.eval_limit(face_id, u, v).unwrap();
+            let distance = reference.vertices.iter()
+                .map(|vertex| vertex.position.distance(position))
+                .fold(f32::MAX, f32::min);
+            assert!(distance < 1e-3);
+            assert!(normal.dot(position.to_vec().normalize()) > 0.99);
+        }
+    }
+    assert_eq!(None, mesh.eval_limit(0, 0.5, 0.5));
+    assert_eq!(None, mesh.eval_limit(mesh.faces.len() + 1, 0.5, 0.5));
+}
+
+#[test]
+fn verify_open_plane_subdivision() {
+    let mut mesh = Mesh::new();
+    mesh.add_plane(1.0, 1.0);
+    let sub = mesh.subdivide();
+    assert_eq!(9, sub.vertex_count);
+    assert_eq!(4, sub.face_count);
+}
+
+#[test]
+fn verify_catmull_clark_limit_converges() {
+    let mut mesh = cube();
+    let mut last_displacement = f32::MAX;
+    for _ in 0..4 {
+        mesh = mesh.subdivide();
+        let limit = mesh.catmull_clark_limit();
+        assert_eq!(mesh.vertex_count, limit.vertex_count);
+        let displacement = mesh.vertices.iter().zip(limit.vertices.iter())
+            .map(|(a, b)| a.position.distance(b.position))
+            .fold(0.0, f32::max);
+        assert!(displacement < last_displacement);
+        last_displacement = displacement;
+    }
+    for face_id in FaceIterator::new(&mesh).take(10) {
+        let corner = mesh.halfedge_start_vertex_id(mesh.face_first_halfedge_id(face_id).unwrap()).unwrap();
+        let (position, _) = mesh.eval_limit(face_id, 0.0, 0.0).unwrap();
+        assert!(position.distance(mesh.vertex_limit_position(corner)) < 1e-4);
+    }
+}
+
+#[test]
+fn verify_buffers_round_trip() {
+    let mesh = cube();
+    let (positions, indices) = mesh.to_buffers();
+    assert_eq!(8 * 3, positions.len());
+    assert_eq!(12 * 3, indices.len());
+    let rebuilt = Mesh::from_buffers(&positions, &indices);
+    assert_eq!(8, rebuilt.vertex_count);
+    assert_eq!(12, rebuilt.face_count);
+    assert_closed_mesh(&rebuilt);
+    for (a, b) in mesh.vertices.iter().zip(rebuilt.vertices.iter()) {
+        assert_eq!(a.position, b.position);
+    }
+}
+
+#[test]
+fn verify_buffers_with_uv_seams() {
+    let mut mesh = cube();
+    let corner_uvs = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(1.0, 1.0), Point2::new(0.0, 1.0)];
+    for face_id in FaceIterator::new(&cube()) {
+        let halfedges = FaceHalfedgeIterator::new(&mesh, mesh.face_first_halfedge_id(face_id).unwrap()).into_vec();
+        for (&halfedge_id, &uv) in halfedges.iter().zip(corner_uvs.iter()) {
+            mesh.set_halfedge_uv(halfedge_id, uv);
+        }
+    }
+    let layout = VertexLayout { normals: true, uvs: true };
+    let (vertices, indices) = mesh.to_buffers_with_layout(&layout);
+    assert_eq!(8, layout.stride());
+    assert_eq!(0, vertices.len() % layout.stride());
+    assert!(vertices.len() / layout.stride() > 8);
+    assert_eq!(12 * 3, indices.len());
+    for vertex_id in 1..=8 {
+        let position = mesh.vertex(vertex_id).unwrap().position;
+        let offset = (vertex_id - 1) * layout.stride();
+        assert_eq!(&[position.x, position.y, position.z], &vertices[offset..offset + 3]);
+    }
+}
+
+#[test]
+fn verify_positions_round_trip() {
+    let mut mesh = cube().subdivide();
+    let positions: Vec<Point3<f32>> = mesh.positions().iter().map(|p| p + Vector3::new(1.0, 2.0, 3.0)).collect();
+    mesh.set_positions(&positions);
+    assert_eq!(positions, mesh.positions());
+
+    let mut topology = mesh.clone_topology_without_positions();
+    assert!(topology.positions().iter().all(|&p| p == Point3::new(0.0, 0.0, 0.0)));
+    topology.set_positions(&positions);
+    assert_eq!(mesh.positions(), topology.positions());
+    assert_eq!(mesh.edges.len(), topology.edges.len());
+}
+
+#[test]
+fn verify_obj_materials() {
+    let temp = TempPath::new("meshlite_verify_obj_materials");
+    let directory = &temp.0;
+    std::fs::create_dir_all(directory).unwrap();
+    std::fs::write(directory.join("two.mtl"), concat!(
+        "newmtl red\n",
+        "Kd 1 0 0\n",
+        "Ks 0.5 0.5 0.5\n",
+        "Ns 10\n",
+        "map_Kd textures/red.png\n",
+        "newmtl blue\n",
+        "Kd 0 0 1\n",
+    )).unwrap();
+    std::fs::write(directory.join("two.obj"), concat!(
+        "mtllib two.mtl\n",
+        "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 2 0 0\nv 2 1 0\n",
+        "usemtl blue\n",
+        "f 1/1/1 2/2/1 3/3/1 4/4/1\n",
+        "usemtl red\n",
+        "f 2 5 6 3\n",
+    )).unwrap();
+    let filename = directory.join("two.obj");
+    let mut mesh = Mesh::new();
+    mesh.import(filename.to_str().unwrap()).unwrap();
+    assert_eq!(2, mesh.materials.len());
+    assert_eq!("blue", mesh.material(mesh.face_material(1).unwrap()).unwrap().name);
+    let red = mesh.material(mesh.face_material(2).unwrap()).unwrap();
+    assert_eq!("red", red.name);
+    assert_eq!([1.0, 0.0, 0.0], red.diffuse);
+    assert_eq!(10.0, red.shininess);
+    assert_eq!(Some("textures/red.png".to_string()), red.diffuse_texture);
+
+    let exported = directory.join("exported.obj");
+    mesh.export(exported.to_str().unwrap()).unwrap();
+    let mut reloaded = Mesh::new();
+    reloaded.import(exported.to_str().unwrap()).unwrap();
+    assert_eq!(mesh.materials, reloaded.materials);
+    assert_eq!(mesh.face_materials, reloaded.face_materials);
+}
+
+#[test]
+fn verify_degenerate_faces() {
+    let mut mesh = Mesh::new();
+    let a = mesh.add_vertex(Point3::new(0.0, 0.0, 0.0));
+    let b = mesh.add_vertex(Point3::new(1.0, 0.0, 0.0));
+    let c = mesh.add_vertex(Point3::new(0.5, 0.0, 0.0));
+    let d = mesh.add_vertex(Point3::new(0.5, 1.0, 0.0));
+    let e = mesh.add_vertex(Point3::new(0.5, -1.0, 0.0));
+    let sliver = mesh.add_vertices(vec![a, c, b]);
+    mesh.add_vertices(vec![a, b, d]);
+    mesh.add_vertices(vec![c, a, e]);
+    mesh.add_vertices(vec![b, c, e]);
+    assert_eq!(Vector3::zero(), mesh.face_norm(sliver));
+    assert_eq!(Point3::new(0.5, 0.0, 0.0), mesh.face_center(sliver));
+
+    let subdivided = mesh.subdivide();
+    for position in subdivided.positions() {
+        assert!(position.x.is_finite() && position.y.is_finite() && position.z.is_finite());
+    }
+
+    assert_eq!(1, mesh.remove_degenerate_faces(1e-6));
+    assert_eq!(3, mesh.face_count);
+    let mut loops: Vec<Vec<Id>> = FaceIterator::new(&mesh).map(|face_id| mesh.face_vertex_ids(face_id)).collect();
+    loops.sort_by_key(|vertices| vertices.len());
+    let quad = loops.pop().unwrap();
+    let start = quad.iter().position(|&id| id == a).unwrap();
+    let rotated: Vec<Id> = (0..4).map(|i| quad[(start + i) % 4]).collect();
+    assert_eq!(vec![a, c, b, d], rotated);
+}
+
+#[test]
+fn verify_subdivision_provenance() {
+    let input = cube();
+    let (output, map) = CatmullClarkSubdivider::new(&input).generate_with_provenance();
+    assert_eq!(output.face_count, map.faces.len());
+    for face_id in FaceIterator::new(&output) {
+        let source = map.faces[&face_id];
+        assert!(input.face(source).is_some());
+        let corner = map.face_corners[&face_id];
+        assert_eq!(Some(source), input.halfedge_face_id(corner));
+    }
+    assert_eq!(output.vertex_count, map.vertices.len());
+    let mut counts = [0; 3];
+    for vertex in output.vertices.iter() {
+        match map.vertices[&vertex.id] {
+            VertexSource::Vertex(id) => {
+                assert!(input.vertex(id).is_some());
+                counts[0] += 1;
+            },
+            VertexSource::Edge(id) => {
+                assert!(input.halfedge(id).is_some());
+                counts[1] += 1;
+            },
+            VertexSource::Face(id) => {
+                assert!(input.face(id).is_some());
+                counts[2] += 1;
+            },
+        }
+    }
+    assert_eq!([8, 12, 6], counts);
+}
+
+#[test]
+fn verify_triangle_soup() {
+    let mesh = cube();
+    let triangles = mesh.triangle_soup();
+    assert_eq!(12, triangles.len());
+    let (positions, indices) = mesh.triangle_soup_indexed();
+    assert_eq!(8, positions.len());
+    assert_eq!(12, indices.len());
+    for (triangle, indexed) in triangles.iter().zip(indices.iter()) {
+        for i in 0..3 {
+            assert_eq!(triangle[i], positions[indexed[i] as usize]);
+        }
+    }
+}
+
+#[test]
+fn verify_grid_surfaces() {
+    use std::f32::consts::PI;
+    let euler_characteristic = |mesh: &Mesh| {
+        mesh.vertex_count as i32 - (mesh.halfedge_count / 2) as i32 + mesh.face_count as i32
+    };
+    let torus = Mesh::from_grid(24, 12, |u, v| {
+        let (u, v) = (u * 2.0 * PI, v * 2.0 * PI);
+        let radius = 1.0 + 0.3 * v.cos();
+        Point3::new(radius * u.cos(), radius * u.sin(), 0.3 * v.sin())
+    }, true, true);
+    assert_eq!(24 * 12, torus.vertex_count);
+    assert_eq!(24 * 12, torus.face_count);
+    assert_closed_mesh(&torus);
+    assert_eq!(0, euler_characteristic(&torus));
+
+    let sphere = uv_sphere(16, 8);
+    assert_eq!(16 * 7 + 2, sphere.vertex_count);
+    assert_closed_mesh(&sphere);
+    assert_eq!(2, euler_characteristic(&sphere));
+    for face_id in FaceIterator::new(&sphere) {
+        assert!(sphere.face_norm(face_id).dot(sphere.face_center(face_id).to_vec()) > 0.0);
+    }
+}
+
+#[test]
+fn verify_nonmanifold_repair() {
+    let mut mesh = Mesh::new();
+    let a = mesh.add_vertex(Point3::new(0.0, 0.0, 0.0));
+    let b = mesh.add_vertex(Point3::new(1.0, 0.0, 0.0));
+    let c = mesh.add_vertex(Point3::new(0.5, 1.0, 0.0));
+    let d = mesh.add_vertex(Point3::new(0.5, -1.0, 0.0));
+    let e = mesh.add_vertex(Point3::new(0.5, 0.0, 1.0));
+    mesh.add_vertices(vec![a, b, c]);
+    mesh.add_vertices(vec![b, a, d]);
+    mesh.add_vertices(vec![a, b, e]);
+    let report = mesh.validate();
+    assert_eq!(vec![EdgeEndpoints::new(a, b)], report.nonmanifold_edges);
+    assert!(!report.is_manifold());
+
+    assert_eq!(1, mesh.split_nonmanifold_edges());
+    let report = mesh.validate();
+    assert!(report.nonmanifold_edges.is_empty());
+    assert!(report.is_manifold());
+    assert_eq!(3, mesh.face_count);
+    assert_eq!(7, mesh.vertex_count);
+    assert_eq!(7, report.boundary_edges);
+
+    let mut bowtie = Mesh::new();
+    let center = bowtie.add_vertex(Point3::new(0.0, 0.0, 0.0));
+    let corners: Vec<Id> = [(1.0, 0.0), (1.0, 1.0), (-1.0, 0.0), (-1.0, -1.0)].iter()
+        .map(|&(x, y)| bowtie.add_vertex(Point3::new(x, y, 0.0)))
+        .collect();
+    bowtie.add_vertices(vec![center, corners[0], corners[1]]);
+    bowtie.add_vertices(vec![center, corners[2], corners[3]]);
+    assert_eq!(vec![center], bowtie.validate().nonmanifold_vertices);
+    assert_eq!(1, bowtie.remove_nonmanifold_vertices());
+    assert!(bowtie.validate().is_manifold());
+    assert_eq!(6, bowtie.vertex_count);
+    assert!(bowtie.halfedges.iter().all(|halfedge| bowtie.halfedge_opposite_id(halfedge.id).is_none()));
+}
+
+#[test]
+fn verify_resample_boundary() {
+    let mut mesh = Mesh::from_grid(8, 2, |u, v| Point3::new(u * u, v, 0.0), false, false);
+    let interior: Vec<Point3<f32>> = (1..8).map(|i| {
+        let u = i as f32 / 8.0;
+        Point3::new(u * u, 0.5, 0.0)
+    }).collect();
+    for halfedge_id in 1..=mesh.halfedges.len() {
+        let position = mesh.halfedge_start_vertex(halfedge_id).unwrap().position;
+        mesh.set_halfedge_uv(halfedge_id, Point2::new(position.x, position.y));
+    }
+    mesh.resample_boundary(0.1);
+    // The UVs map the positions on the plane, so every corner keeps
+    // matching its position through the collapses and splits.
+    for face_id in FaceIterator::new(&mesh) {
+        for halfedge_id in FaceHalfedgeIterator::new(&mesh, mesh.face_first_halfedge_id(face_id).unwrap()) {
+            let position = mesh.halfedge_start_vertex(halfedge_id).unwrap().position;
+            assert_eq!(Some(Point2::new(position.x, position.y)), mesh.halfedge_uv(halfedge_id));
+        }
+    }
+    let boundary_loops = mesh.boundary_loops();
+    assert_eq!(1, boundary_loops.len());
+    for &halfedge_id in boundary_loops[0].iter() {
+        let length = mesh.halfedge_direct(halfedge_id).magnitude();
+        assert!((0.05..=0.15).contains(&length), "boundary edge of length {}", length);
+    }
+    let positions = mesh.positions();
+    for corner in [Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(1.0, 1.0, 0.0), Point3::new(0.0, 1.0, 0.0)].iter() {
+        assert!(positions.contains(corner));
+    }
+    for position in interior.iter() {
+        assert!(positions.contains(position));
+    }
+}
+
+#[test]
+fn verify_bridge() {
+    use std::f32::consts::PI;
+    let tube = |u: f32, v: f32| {
+        let (u, v) = (u * 2.0 * PI, v * 1.5 * PI);
+        let radius = 1.0 + 0.3 * u.cos();
+        Point3::new(radius * v.cos(), radius * v.sin(), 0.3 * u.sin())
+    };
+    let mut mesh = Mesh::from_grid(12, 6, tube, true, false);
+    let boundary_loops = mesh.boundary_loops();
+    assert_eq!(2, boundary_loops.len());
+    let added = mesh.bridge(boundary_loops[0][0], boundary_loops[1][0]).unwrap();
+    assert_eq!(12, added.len());
+    assert!(added.iter().all(|&face_id| mesh.face_vertex_ids(face_id).len() == 4));
+    assert_closed_mesh(&mesh);
+    assert_eq!(0, mesh.vertex_count as i32 - (mesh.halfedge_count / 2) as i32 + mesh.face_count as i32);
+
+    let mut mesh = Mesh::from_grid(8, 1, |u, v| {
+        let u = u * 2.0 * PI;
+        Point3::new(u.cos(), u.sin(), v)
+    }, true, false);
+    mesh.add_mesh(&Mesh::from_grid(6, 1, |u, v| {
+        let u = u * 2.0 * PI;
+        Point3::new(u.cos(), u.sin(), v + 2.0)
+    }, true, false));
+    let top = mesh.boundary_loops().into_iter()
+        .find(|boundary_loop| mesh.halfedge_start_vertex(boundary_loop[0]).unwrap().position.z == 1.0)
+        .unwrap();
+    let bottom = mesh.boundary_loops().into_iter()
+        .find(|boundary_loop| mesh.halfedge_start_vertex(boundary_loop[0]).unwrap().position.z == 2.0)
+        .unwrap();
+    let added = mesh.bridge(top[0], bottom[0]).unwrap();
+    assert!(added.len() >= 8 && added.len() <= 14);
+    assert!(added.iter().all(|&face_id| face_id > 0));
+    assert!(mesh.validate().is_manifold());
+    for halfedge in mesh.halfedges.iter().filter(|halfedge| halfedge.alive) {
+        if let Some(opposite) = mesh.halfedge_opposite_id(halfedge.id) {
+            assert_eq!(Some(halfedge.vertex), mesh.halfedge_start_vertex_id(mesh.halfedge_next_id(opposite).unwrap()));
+        }
+    }
+    let boundary_loops = mesh.boundary_loops();
+    assert_eq!(2, boundary_loops.len());
+    assert_eq!(Err(TopologyError::NonManifoldResult), mesh.bridge(boundary_loops[0][0], boundary_loops[0][1]));
+}
+
+#[test]
+fn verify_subdivision_vertex_tags() {
+    const PINNED: u32 = 1;
+    const SELECTED: u32 = 2;
+    let mut input = cube();
+    input.set_vertex_tags(1, PINNED);
+    input.set_vertex_tags(2, SELECTED);
+    let (output, map) = CatmullClarkSubdivider::new(&input).generate_with_provenance();
+    for (&vertex_id, &source) in map.vertices.iter() {
+        let expected = match source {
+            VertexSource::Vertex(id) => input.vertex_tags(id),
+            _ => 0,
+        };
+        assert_eq!(expected, output.vertex_tags(vertex_id));
+    }
+    assert_eq!(PINNED, output.vertex_tags(map.vertices.iter()
+        .find(|&(_, &source)| source == VertexSource::Vertex(1)).map(|(&id, _)| id).unwrap()));
+
+    let (output, map) = CatmullClarkSubdivider::new(&input).propagate_tags(true).generate_with_provenance();
+    let edge_vertex = map.vertices.iter().find(|&(_, &source)| match source {
+        VertexSource::Edge(id) => EdgeEndpoints::new(input.halfedge_start_vertex_id(id).unwrap(),
+            input.halfedge_start_vertex_id(input.halfedge_next_id(id).unwrap()).unwrap()) == EdgeEndpoints::new(1, 2),
+        _ => false,
+    }).map(|(&id, _)| id).unwrap();
+    assert_eq!(PINNED | SELECTED, output.vertex_tags(edge_vertex));
+}
+
+#[test]
+fn verify_subdivide_until() {
+    let plane = Mesh::from_grid(3, 3, |u, v| Point3::new(u, v, 0.0), false, false);
+    let (_, displacement) = plane.subdivide_n_with_displacement(1);
+    assert_eq!(0.0, displacement);
+    let (subdivided, levels) = plane.subdivide_until(5, 0.0001);
+    assert_eq!(1, levels);
+    assert_eq!(36, subdivided.face_count);
+
+    let mut previous = f32::MAX;
+    for levels in 1..4 {
+        let (_, displacement) = cube().subdivide_n_with_displacement(levels);
+        assert!(displacement > 0.0 && displacement < previous);
+        previous = displacement;
+    }
+    let (subdivided, levels) = cube().subdivide_until(5, 0.01);
+    assert_eq!(4, levels);
+    assert_eq!(6 * 4usize.pow(levels as u32), subdivided.face_count);
+    assert_eq!(subdivided.face_count, cube().subdivide_n(levels).face_count);
+}
+
+#[test]
+fn verify_nearest_elements() {
+    let mesh = cube();
+    for vertex in mesh.vertices.iter() {
+        let offset = vertex.position.to_vec() * 0.01;
+        assert_eq!(Some(vertex.id), mesh.nearest_vertex(vertex.position + offset));
+    }
+    assert_eq!(Some(1), mesh.nearest_face(Point3::new(0.1, 0.0, 2.0)));
+    assert_eq!(Some(6), mesh.nearest_face(Point3::new(0.0, -0.1, -0.4)));
+    assert_eq!(None, Mesh::new().nearest_vertex(Point3::new(0.0, 0.0, 0.0)));
+    assert_eq!(None, Mesh::new().nearest_face(Point3::new(0.0, 0.0, 0.0)));
+
+    let dense = mesh.subdivide().subdivide().subdivide();
+    assert!(dense.vertex_count > 64 && dense.face_count > 64);
+    for i in 0..50 {
+        let t = i as f32 * 0.37;
+        let point = Point3::new(t.sin() * 1.3, (t * 1.7).cos() * 0.8, (t * 0.3).sin() * 1.1);
+        let by_distance = |a: &(Id, f32), b: &(Id, f32)| a.1.partial_cmp(&b.1).unwrap();
+        let expected_vertex = dense.iter_vertices()
+            .map(|(vertex_id, vertex)| (vertex_id, vertex.position.distance2(point)))
+            .min_by(by_distance)
+            .unwrap();
+        let found_vertex = dense.nearest_vertex(point).unwrap();
+        assert_eq!(expected_vertex.1, dense.vertex(found_vertex).unwrap().position.distance2(point));
+        let expected_face = FaceIterator::new(&dense)
+            .map(|face_id| (face_id, dense.face_center(face_id).distance2(point)))
+            .min_by(by_distance)
+            .unwrap();
+        let found_face = dense.nearest_face(point).unwrap();
+        assert_eq!(expected_face.1, dense.face_center(found_face).distance2(point));
+    }
+}
+
+#[test]
+fn verify_subdivide_into() {
+    let mut out = grid_cube(3).subdivide();
+    out.set_vertex_tags(1, 1);
+    let input = cube();
+    input.subdivide_into(&mut out);
+    let expected = input.subdivide();
+    assert_eq!(expected.face_count, out.face_count);
+    assert_eq!(expected.halfedge_count, out.halfedge_count);
+    assert_eq!(expected.positions(), out.positions());
+    assert!(out.vertex_tags.is_empty());
+    assert_closed_quad_mesh(&out);
+    out.subdivide().subdivide_into(&mut out);
+    assert_eq!(6 * 16 * 4, out.face_count);
+}
+
+fn polyline_perimeter(polyline: &[Point3<f32>], closed: bool) -> f32 {
+    let segment_count = if closed { polyline.len() } else { polyline.len() - 1 };
+    (0..segment_count).map(|i| polyline[i].distance(polyline[(i + 1) % polyline.len()])).sum()
+}
+
+#[test]
+fn verify_slice() {
+    let loops = cube().slice(Vector3::new(0.0, 0.0, 1.0), Point3::new(0.0, 0.0, 0.0));
+    assert_eq!(1, loops.len());
+    assert_eq!(4, loops[0].len());
+    assert!((polyline_perimeter(&loops[0], true) - 4.0).abs() < 1e-5);
+    let signed_area: f32 = (0..4).map(|i| {
+        let (a, b) = (loops[0][i], loops[0][(i + 1) % 4]);
+        a.x * b.y - b.x * a.y
+    }).sum::<f32>() * 0.5;
+    assert!((signed_area - 1.0).abs() < 1e-5);
+
+    let loops = grid_cube(3).slice(Vector3::new(1.0, 1.0, 0.0).normalize(), Point3::new(0.1, 0.0, 0.0));
+    assert_eq!(1, loops.len());
+    assert!(loops[0].iter().all(|point| point.z.abs() <= 0.5 + 1e-5));
+
+    let mut open_box = cube();
+    open_box.remove_face(1);
+    let polylines = open_box.slice(Vector3::new(1.0, 0.0, 0.0), Point3::new(0.0, 0.0, 0.0));
+    assert_eq!(1, polylines.len());
+    assert_eq!(4, polylines[0].len());
+    assert!((polyline_perimeter(&polylines[0], false) - 3.0).abs() < 1e-5);
+
+    assert!(cube().slice(Vector3::new(0.0, 0.0, 1.0), Point3::new(0.0, 0.0, 2.0)).is_empty());
+}
+
+#[test]
+fn verify_obj_export_options() {
+    let temp = TempPath::new("meshlite_obj_export_options");
+    let directory = &temp.0;
+    std::fs::create_dir_all(directory).unwrap();
+    let filename = directory.join("cube.obj");
+    let filename = filename.to_str().unwrap();
+    let lines_of = |options: &ObjExportOptions| {
+        cube().save_obj_with_options(filename, options).unwrap();
+        std::fs::read_to_string(filename).unwrap().lines().map(|line| line.to_string()).collect::<Vec<String>>()
+    };
+
+    let flat = lines_of(&ObjExportOptions { normals: NormalMode::Flat, uvs: false, precision: 2 });
+    let normals: Vec<&String> = flat.iter().filter(|line| line.starts_with("vn ")).collect();
+    assert_eq!(6, normals.len());
+    for (i, normal) in normals.iter().enumerate() {
+        assert!(!normals[i + 1..].contains(normal));
+    }
+    let faces: Vec<&String> = flat.iter().filter(|line| line.starts_with("f ")).collect();
+    assert_eq!("f 1//1 2//1 3//1 4//1", faces[0].as_str());
+    assert!(flat.contains(&"v -0.50 -0.50 0.50".to_string()));
+
+    let smooth = lines_of(&ObjExportOptions { normals: NormalMode::Smooth, uvs: false, precision: 6 });
+    assert_eq!(8, smooth.iter().filter(|line| line.starts_with("vn ")).count());
+    for face in smooth.iter().filter(|line| line.starts_with("f ")) {
+        for corner in face.split_whitespace().skip(1) {
+            let indices: Vec<&str> = corner.split("//").collect();
+            assert_eq!(indices[0], indices[1]);
+        }
+    }
+
+    let mut mesh = cube();
+    for halfedge in mesh.halfedges.clone() {
+        let position = mesh.vertex(halfedge.vertex).unwrap().position;
+        mesh.set_halfedge_uv(halfedge.id, Point2::new(position.x + 0.5, position.y + 0.5));
+    }
+    mesh.save_obj_with_options(filename, &ObjExportOptions { uvs: true, ..ObjExportOptions::default() }).unwrap();
+    let textured = std::fs::read_to_string(filename).unwrap();
+    assert_eq!(4, textured.lines().filter(|line| line.starts_with("vt ")).count());
+    assert!(textured.lines().any(|line| line == "f 1/1 2/2 3/3 4/4"));
+    let mut reloaded = Mesh::new();
+    reloaded.import(filename).unwrap();
+    assert_eq!(mesh.face_count, reloaded.face_count);
+}
+
+#[test]
+fn verify_mesh_hashes() {
+    let mut mesh = cube().subdivide();
+    mesh.remove_face(3);
+    let compacted = mesh.clone();
+    assert!(compacted.halfedges.len() < mesh.halfedges.len());
+    assert_eq!(mesh.topology_hash(), compacted.topology_hash());
+    assert_eq!(mesh.geometry_hash(0.001), compacted.geometry_hash(0.001));
+
+    let mut reversed = Mesh::new();
+    let vertex_ids: HashMap<usize, usize> = mesh.vertices.iter().rev().filter(|vertex| vertex.alive)
+        .map(|vertex| (vertex.id, reversed.add_vertex(vertex.position)))
+        .collect();
+    for face_id in FaceIterator::new(&mesh).collect::<Vec<_>>().into_iter().rev() {
+        let mut corners: Vec<Id> = mesh.face_vertex_ids(face_id).iter().map(|id| vertex_ids[id]).collect();
+        corners.rotate_left(1);
+        reversed.add_vertices(corners);
+    }
+    assert_eq!(mesh.topology_hash(), reversed.topology_hash());
+    assert_eq!(mesh.geometry_hash(0.001), reversed.geometry_hash(0.001));
+
+    // Labels repeat on the corners of most faces of a denser mesh, so the
+    // face loops need the whole rotation compared, not only the first label.
+    let dense = cube().subdivide_n(3);
+    let mut rotated = Mesh::new();
+    for vertex in dense.vertices.iter() {
+        rotated.add_vertex(vertex.position);
+    }
+    for face_id in FaceIterator::new(&dense) {
+        let mut corners = dense.face_vertex_ids(face_id);
+        corners.rotate_left(1);
+        rotated.add_vertices(corners);
+    }
+    assert_eq!(dense.topology_hash(), rotated.topology_hash());
+    assert_eq!(dense.geometry_hash(0.001), rotated.geometry_hash(0.001));
+
+    assert_ne!(mesh.topology_hash(), cube().subdivide().topology_hash());
+    let mut moved = mesh.duplicate();
+    assert_eq!(mesh.topology_hash(), moved.topology_hash());
+    moved.vertices[0].position.x += 0.1;
+    assert_ne!(mesh.geometry_hash(0.001), moved.geometry_hash(0.001));
+    moved.vertices[0].position.x -= 0.1;
+    moved.vertices[0].position.x += 0.00001;
+    assert_eq!(mesh.geometry_hash(0.001), moved.geometry_hash(0.001));
+}
+
+#[test]
+fn verify_isolated_vertices() {
+    let mut mesh = cube();
+    let stray = mesh.add_vertex(Point3::new(2.0, 2.0, 2.0));
+    mesh.set_vertex_tags(stray, 1);
+    let subdivided = mesh.subdivide();
+    assert_eq!(26, subdivided.vertex_count);
+    assert!(subdivided.positions().iter().all(|position| position.x.is_finite() && position.x.abs() <= 0.5));
+    assert_eq!(mesh.vertex_limit_position(stray), Point3::new(2.0, 2.0, 2.0));
+
+    assert_eq!(1, mesh.remove_isolated_vertices());
+    assert_eq!(8, mesh.vertex_count);
+    assert!(mesh.vertex(stray).is_none());
+    assert_eq!(0, mesh.vertex_tags(stray));
+    assert_eq!(0, mesh.remove_isolated_vertices());
+}
+
+#[test]
+fn verify_element_iterators() {
+    let mut mesh = cube();
+    let vertices: Vec<(Id, Point3<f32>)> = mesh.iter_vertices().map(|(id, vertex)| (id, vertex.position)).collect();
+    assert_eq!(8, vertices.len());
+    for (id, position) in vertices {
+        assert_eq!(mesh.vertex(id).unwrap().position, position);
+        assert_eq!(0.5, position.x.abs());
+    }
+    mesh.remove_face(2);
+    assert_eq!(FaceIterator::new(&mesh).into_vec(), mesh.iter_faces().map(|(id, _)| id).collect::<Vec<Id>>());
+    assert_eq!(mesh.halfedge_count, mesh.iter_halfedges().count());
+    assert!(mesh.iter_halfedges().all(|(id, halfedge)| id == halfedge.id && halfedge.face != 2));
+}
+
+#[test]
+fn verify_high_valence_subdivision() {
+    use std::f32::consts::PI;
+    const VALENCE: usize = 120;
+    let mut mesh = Mesh::new();
+    let top = mesh.add_vertex(Point3::new(0.0, 0.0, 1.0));
+    let bottom = mesh.add_vertex(Point3::new(0.0, 0.0, -1.0));
+    let ring: Vec<Id> = (0..VALENCE).map(|i| {
+        let angle = i as f32 / VALENCE as f32 * 2.0 * PI;
+        mesh.add_vertex(Point3::new(angle.cos(), angle.sin(), 0.0))
+    }).collect();
+    for i in 0..VALENCE {
+        let (a, b) = (ring[i], ring[(i + 1) % VALENCE]);
+        mesh.add_vertices(vec![top, a, b]);
+        mesh.add_vertices(vec![bottom, b, a]);
+    }
+    assert_eq!(VALENCE, mesh.vertex(top).unwrap().halfedges.len());
+    let subdivided = mesh.subdivide();
+    assert_closed_quad_mesh(&subdivided);
+    assert_eq!(VALENCE * 2 * 3, subdivided.face_count);
+    let apex = subdivided.vertices.iter()
+        .map(|vertex| vertex.position)
+        .filter(|position| position.x.abs() < 1e-4 && position.y.abs() < 1e-4)
+        .collect::<Vec<Point3<f32>>>();
+    assert_eq!(2, apex.len());
+    for position in apex {
+        assert!(position.z.abs() > 0.5 && position.z.abs() < 1.0);
+    }
+}
+
+fn subdivided_vertex_position(input: &Mesh, vertex_id: Id) -> Point3<f32> {
+    let (output, map) = CatmullClarkSubdivider::new(input).generate_with_provenance();
+    let output_id = map.vertices.iter()
+        .find(|&(_, &source)| source == VertexSource::Vertex(vertex_id))
+        .map(|(&id, _)| id)
+        .unwrap();
+    output.vertex(output_id).unwrap().position
+}
+
+fn assert_close(expected: Point3<f32>, actual: Point3<f32>) {
+    assert!(expected.distance(actual) < 1e-5, "expected {:?}, got {:?}", expected, actual);
+}
+
+#[test]
+fn verify_vertex_point_valences() {
+    // Valence 3: (F + 2R) / 3 with F = (1/6, 1/6, 1/6) and R = (1/3, 1/3, 1/3).
+    let corner = cube().vertices.iter().find(|vertex| vertex.position == Point3::new(0.5, 0.5, 0.5)).unwrap().id;
+    assert_close(Point3::new(5.0 / 18.0, 5.0 / 18.0, 5.0 / 18.0), subdivided_vertex_position(&cube(), corner));
+
+    // Valence 4 in the middle of a flat face stays in place.
+    let mesh = grid_cube(2);
+    let center = mesh.vertices.iter().find(|vertex| vertex.position == Point3::new(0.0, 0.0, 0.5)).unwrap().id;
+    assert_close(Point3::new(0.0, 0.0, 0.5), subdivided_vertex_position(&mesh, center));
+
+    // Valence 5 apex of a bipyramid: F = (0, 0, 1/3), R = (0, 0, 1/2), so
+    // (F + 2R + 2P) / 5 = (0, 0, 2/3).
+    let mut mesh = Mesh::new();
+    let top = mesh.add_vertex(Point3::new(0.0, 0.0, 1.0));
+    let bottom = mesh.add_vertex(Point3::new(0.0, 0.0, -1.0));
+    let ring: Vec<Id> = (0..5).map(|i| {
+        let angle = i as f32 / 5.0 * 2.0 * std::f32::consts::PI;
+        mesh.add_vertex(Point3::new(angle.cos(), angle.sin(), 0.0))
+    }).collect();
+    for i in 0..5 {
+        mesh.add_vertices(vec![top, ring[i], ring[(i + 1) % 5]]);
+        mesh.add_vertices(vec![bottom, ring[(i + 1) % 5], ring[i]]);
+    }
+    assert_close(Point3::new(0.0, 0.0, 2.0 / 3.0), subdivided_vertex_position(&mesh, top));
+
+    // Valence 2 on a closed pillow of two quads: R is halfway between P and
+    // the center F, so (F + 2R - P) / 2 lands on the center, wherever the
+    // mesh is.
+    let offset = Vector3::new(3.0, 0.0, 0.0);
+    let mut mesh = Mesh::new();
+    let corners: Vec<Id> = [(1.0, 1.0), (-1.0, 1.0), (-1.0, -1.0), (1.0, -1.0)].iter()
+        .map(|&(x, y)| mesh.add_vertex(Point3::new(x, y, 0.0) + offset))
+        .collect();
+    mesh.add_vertices(corners.clone());
+    mesh.add_vertices(corners.iter().rev().cloned().collect());
+    assert_close(Point3::from_vec(offset), subdivided_vertex_position(&mesh, corners[0]));
+}
+
+#[test]
+fn verify_raycast_batch_matches_individual_raycasts() {
+    let mut mesh = cube().subdivide_n(2);
+    mesh.translate(2.0, 0.0, 0.0);
+    let mut rays = Vec::new();
+    for i in 0..200 {
+        let t = i as f32 * 0.618;
+        rays.push((Point3::new(2.0, 0.0, 0.0), Vector3::new(t.cos(), (t * 1.7).sin(), (t * 0.3).cos() - 0.5)));
+    }
+    // Pointing away from the mesh, and passing beside it.
+    rays.push((Point3::new(0.0, 0.0, 0.0), Vector3::new(-1.0, 0.0, 0.0)));
+    rays.push((Point3::new(0.0, 2.0, 0.0), Vector3::new(1.0, 0.0, 0.0)));
+    let batch = mesh.raycast_batch(&rays);
+    assert_eq!(batch.len(), rays.len());
+    for (&(origin, direct), hit) in rays.iter().zip(batch.iter()) {
+        let expected = mesh.raycast(origin, direct);
+        assert_eq!(expected.map(|hit| hit.face_id), hit.map(|hit| hit.face_id));
+        if let (Some(expected), Some(hit)) = (expected, *hit) {
+            assert!((expected.distance - hit.distance).abs() < 1e-5);
+        }
+    }
+    assert!(batch[..200].iter().all(|hit| hit.is_some()));
+    assert!(batch[200..].iter().all(|hit| hit.is_none()));
+
+    let hit = mesh.raycast(Point3::new(2.0, 0.0, 5.0), Vector3::new(0.0, 0.0, -1.0)).unwrap();
+    assert!((hit.distance - 5.0 + mesh.vertices.iter().map(|vertex| vertex.position.z).fold(0.0, f32::max)).abs() < 1e-5);
+}
+
+fn max_planar_uv_error(mesh: &Mesh) -> f32 {
+    let mut max_error: f32 = 0.0;
+    for face_id in FaceIterator::new(mesh) {
+        for halfedge_id in FaceHalfedgeIterator::new(mesh, mesh.face_first_halfedge_id(face_id).unwrap()) {
+            let position = mesh.halfedge_start_vertex(halfedge_id).unwrap().position;
+            let uv = mesh.halfedge_uv(halfedge_id).unwrap();
+            max_error = max_error.max((uv - Point2::new(position.x, position.y)).magnitude());
+        }
+    }
+    max_error
+}
+
+#[test]
+fn verify_simplify_preserves_uvs() {
+    let mut mesh = Mesh::from_grid(8, 8, |u, v| Point3::new(u, v, 0.0), false, false);
+    for halfedge_id in 1..=mesh.halfedges.len() {
+        let position = mesh.halfedge_start_vertex(halfedge_id).unwrap().position;
+        mesh.set_halfedge_uv(halfedge_id, Point2::new(position.x, position.y));
+    }
+    let simplified = mesh.simplify_to(16, &SimplifyOptions { preserve_attributes: true });
+    assert!(simplified.face_count <= 16);
+    assert!(max_planar_uv_error(&simplified) < 1e-4);
+    for corner in [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].iter() {
+        assert!(simplified.vertices.iter().any(|vertex| vertex.position == Point3::new(corner.0, corner.1, 0.0)));
+    }
+
+    let smeared = mesh.simplify_to(16, &SimplifyOptions::default());
+    assert!(smeared.face_count <= 16);
+    assert!(max_planar_uv_error(&smeared) > 0.05);
+}
+
+#[test]
+fn verify_closed_and_manifold_predicates() {
+    let mesh = cube();
+    assert!(mesh.is_closed());
+    assert!(mesh.is_manifold());
+
+    let grid = Mesh::from_grid(3, 3, |u, v| Point3::new(u, v, 0.0), false, false);
+    assert!(!grid.is_closed());
+    assert!(grid.is_manifold());
+
+    let mut t_junction = Mesh::new();
+    let a = t_junction.add_vertex(Point3::new(0.0, 0.0, 0.0));
+    let b = t_junction.add_vertex(Point3::new(1.0, 0.0, 0.0));
+    let c = t_junction.add_vertex(Point3::new(0.5, 1.0, 0.0));
+    let d = t_junction.add_vertex(Point3::new(0.5, -1.0, 0.0));
+    let e = t_junction.add_vertex(Point3::new(0.5, 0.0, 1.0));
+    t_junction.add_vertices(vec![a, b, c]);
+    t_junction.add_vertices(vec![b, a, d]);
+    t_junction.add_vertices(vec![a, b, e]);
+    assert!(!t_junction.is_closed());
+    assert!(!t_junction.is_manifold());
+
+    let mut bowtie = Mesh::new();
+    let center = bowtie.add_vertex(Point3::new(0.0, 0.0, 0.0));
+    let corners: Vec<Id> = [(1.0, 0.0), (1.0, 1.0), (-1.0, 0.0), (-1.0, -1.0)].iter()
+        .map(|&(x, y)| bowtie.add_vertex(Point3::new(x, y, 0.0)))
+        .collect();
+    bowtie.add_vertices(vec![center, corners[0], corners[1]]);
+    bowtie.add_vertices(vec![center, corners[2], corners[3]]);
+    assert!(!bowtie.is_manifold());
+}
+
+#[test]
+fn verify_subdivide_region() {
+    let mut mesh = Mesh::from_grid(4, 4, |u, v| Point3::new(u, v, 0.0), false, false);
+    let block: Vec<Id> = FaceIterator::new(&mesh).filter(|&face_id| {
+        let center = mesh.face_center(face_id);
+        center.x > 0.25 && center.x < 0.75 && center.y > 0.25 && center.y < 0.75
+    }).collect();
+    assert_eq!(4, block.len());
+    mesh.subdivide_region(&Selection::from_faces(block));
+    assert_eq!(16 - 4 + 16, mesh.face_count);
+    assert_eq!(25 + 12 + 4, mesh.vertex_count);
+    assert!(mesh.is_manifold());
+    // A crack or T-vertex would leave edges with a single face inside the
+    // grid, the outer boundary has 16.
+    assert_eq!(16, mesh.validate().boundary_edges);
+    let sides: Vec<usize> = FaceIterator::new(&mesh).map(|face_id| mesh.face_vertex_ids(face_id).len()).collect();
+    assert_eq!(8, sides.iter().filter(|&&count| count == 5).count());
+    assert!(mesh.vertices.iter().filter(|vertex| vertex.alive).all(|vertex| vertex.position.z == 0.0));
+}
+
+#[test]
+fn verify_graph_export() {
+    let mesh = cube();
+    let (edges, positions) = mesh.to_graph();
+    assert_eq!(8, positions.len());
+    assert_eq!(12, edges.len());
+    for &(first, second) in edges.iter() {
+        assert!(first < second);
+        // Cube edges join corners differing in exactly one coordinate.
+        let offset = positions[second] - positions[first];
+        assert_eq!(1, [offset.x, offset.y, offset.z].iter().filter(|value| value.abs() > 0.5).count());
+        assert!((offset.magnitude() - 1.0).abs() < 1e-6);
+    }
+
+    let (face_edges, centers) = mesh.to_dual_graph();
+    assert_eq!(6, centers.len());
+    assert_eq!(12, face_edges.len());
+    for &(first, second) in face_edges.iter() {
+        // Only opposite faces of a cube are not adjacent.
+        assert!(centers[first].to_vec().dot(centers[second].to_vec()).abs() < 1e-6);
+    }
+}
+
+fn mean_offset_error(mesh: &Mesh, reference: &Mesh, offset: Vector3<f32>) -> f32 {
+    let total: f32 = mesh.vertices.iter().zip(reference.vertices.iter())
+        .map(|(vertex, expected)| (vertex.position - offset).distance(expected.position))
+        .sum();
+    total / mesh.vertices.len() as f32
+}
+
+#[test]
+fn verify_f64_accumulation_reduces_drift() {
+    let subdivide = |mesh: &Mesh, precise: bool| {
+        let mut mesh = mesh.duplicate();
+        for _ in 0..4 {
+            mesh = CatmullClarkSubdivider::new(&mesh).accumulate_in_f64(precise).generate();
+        }
+        mesh
+    };
+    // Near the origin f32 is precise enough to serve as the reference.
+    let reference = subdivide(&cube(), true);
+    let offset = Vector3::new(1e5, 1e5, 1e5);
+    let mut far = cube();
+    far.translate(offset.x, offset.y, offset.z);
+    let f32_error = mean_offset_error(&subdivide(&far, false), &reference, offset);
+    let f64_error = mean_offset_error(&subdivide(&far, true), &reference, offset);
+    assert!(f64_error < f32_error * 0.75, "f64 error {} against f32 error {}", f64_error, f32_error);
+}
+
+#[test]
+fn verify_face_triangle_iterator() {
+    let mut mesh = Mesh::new();
+    let pentagon: Vec<Id> = (0..5).map(|i| {
+        let angle = i as f32 / 5.0 * 2.0 * std::f32::consts::PI;
+        mesh.add_vertex(Point3::new(angle.cos(), angle.sin(), 0.0))
+    }).collect();
+    let pentagon_face = mesh.add_vertices(pentagon.clone());
+    let triangle: Vec<Id> = pentagon[..3].to_vec();
+    let triangle_face = mesh.add_vertices(triangle.clone());
+    assert_eq!(vec![[pentagon[0], pentagon[1], pentagon[2]], [pentagon[0], pentagon[2], pentagon[3]], [pentagon[0], pentagon[3], pentagon[4]]],
+        FaceTriangleIterator::new(&mesh, pentagon_face).into_vec());
+    assert_eq!(vec![[triangle[0], triangle[1], triangle[2]]], FaceTriangleIterator::new(&mesh, triangle_face).into_vec());
+    mesh.remove_face(triangle_face);
+    assert_eq!(0, FaceTriangleIterator::new(&mesh, triangle_face).count());
+}
+
+#[test]
+fn verify_obj_recenter_on_load() {
+    // A 3x3 grid of quads 0.25 apart near 1e7, every face with its own
+    // corners, where f32 can only tell whole units apart.
+    let mut contents = String::new();
+    let mut face_lines = String::new();
+    let mut index = 1;
+    for i in 0..3 {
+        for j in 0..3 {
+            for &(di, dj) in [(0, 0), (1, 0), (1, 1), (0, 1)].iter() {
+                contents += &format!("v {} {} 5.0\n", 10000000.0 + (i + di) as f64 * 0.25, 20000000.0 + (j + dj) as f64 * 0.25);
+            }
+            face_lines += &format!("f {} {} {} {}\n", index, index + 1, index + 2, index + 3);
+            index += 4;
+        }
+    }
+    contents += &face_lines;
+    let path = TempPath::new("meshlite_verify_obj_recenter_on_load.obj");
+    std::fs::write(&path.0, contents).unwrap();
+    let filename = path.0.to_str().unwrap();
+
+    let (mesh, offset) = Mesh::load_obj_with_options(filename, &ObjImportOptions { recenter_on_load: true, ..ObjImportOptions::default() }).unwrap();
+    assert_eq!(Vector3::new(10000000.0, 20000000.0, 5.0), offset);
+    let welded = mesh.weld();
+    assert_eq!(16, welded.vertex_count);
+    assert_eq!(9, welded.face_count);
+    assert_eq!(4 * 3, welded.validate().boundary_edges);
+    assert!(welded.is_manifold());
+
+    let (mesh, offset) = Mesh::load_obj_with_options(filename, &ObjImportOptions::default()).unwrap();
+    assert_eq!(Vector3::new(0.0, 0.0, 0.0), offset);
+    assert!(mesh.weld().face_count != 9);
+
+    std::fs::write(&path.0, "v 0 0 0\nv 1 0 0\nv 1 one 0\nf 1 2 3\n").unwrap();
+    let error = Mesh::load_obj_with_options(filename, &ObjImportOptions { recenter_on_load: true, ..ObjImportOptions::default() }).unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+    assert_eq!(MeshError::InvalidObjLine { line: 3 }.to_string(), error.to_string());
+}
+
+#[test]
+fn verify_simplify_to_error() {
+    let sphere = uv_sphere(32, 16);
+    // The faces of the grid sag below the unit sphere by at most this much.
+    let sag = 1.0 - (std::f32::consts::PI / 16.0).cos();
+    let mut face_counts = Vec::new();
+    for &max_error in [0.0001, 0.001, 0.01].iter() {
+        let (simplified, face_count) = sphere.simplify_to_error(max_error);
+        assert_eq!(simplified.face_count, face_count);
+        assert!(simplified.is_manifold());
+        assert!(simplified.is_closed());
+        for vertex in simplified.vertices.iter().filter(|vertex| vertex.alive) {
+            let radius = vertex.position.to_vec().magnitude();
+            assert!(radius <= 1.0 + 1e-5 && radius >= 1.0 - sag - max_error.sqrt(),
+                "radius {} with max error {}", radius, max_error);
+        }
+        face_counts.push(simplified.face_count);
+    }
+    assert!(face_counts[0] < sphere.face_count);
+    assert!(face_counts[1] < face_counts[0]);
+    assert!(face_counts[2] < face_counts[1]);
+}
+
+#[test]
+fn verify_hausdorff_distance() {
+    let mesh = cube().subdivide_n(1);
+    assert!(mesh.hausdorff_distance(&mesh, 500) < 1e-5);
+    assert!(mesh.mean_distance(&mesh, 500) < 1e-5);
+
+    // The corners of the doubled cube stick out furthest, sqrt(3) / 2 away
+    // from the corners of the unit cube, while every point of the unit cube
+    // is exactly 0.5 from the doubled one.
+    let small = cube();
+    let mut large = cube();
+    large.scale(2.0);
+    assert!((small.hausdorff_distance(&large, 500) - 3.0f32.sqrt() * 0.5).abs() < 1e-5);
+    let mean = small.mean_distance(&large, 500);
+    assert!(mean > 0.5 && mean < 3.0f32.sqrt() * 0.5, "mean distance {}", mean);
+}
+
+#[test]
+fn verify_compute_tangents() {
+    let mut mesh = Mesh::new();
+    let corners: Vec<Id> = [(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)].iter()
+        .map(|&(x, y)| mesh.add_vertex(Point3::new(x, y, 0.0)))
+        .collect();
+    let face_id = mesh.add_vertices(corners.clone());
+    for halfedge_id in FaceHalfedgeIterator::new(&mesh, mesh.face_first_halfedge_id(face_id).unwrap()).into_vec() {
+        let position = mesh.halfedge_start_vertex(halfedge_id).unwrap().position;
+        mesh.set_halfedge_uv(halfedge_id, Point2::new(position.x * 0.5, position.y));
+    }
+    let tangents = mesh.compute_tangents();
+    assert_eq!(4, tangents.len());
+    for &vertex_id in corners.iter() {
+        let tangent = tangents[&vertex_id];
+        assert!((tangent.truncate() - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+        assert_eq!(1.0, tangent.w);
+    }
+
+    // Mirroring U flips the tangent and its handedness.
+    for halfedge_id in FaceHalfedgeIterator::new(&mesh, mesh.face_first_halfedge_id(face_id).unwrap()).into_vec() {
+        let uv = mesh.halfedge_uv(halfedge_id).unwrap();
+        mesh.set_halfedge_uv(halfedge_id, Point2::new(1.0 - uv.x, uv.y));
+    }
+    let tangent = mesh.compute_tangents()[&corners[0]];
+    assert!((tangent.truncate() - Vector3::new(-1.0, 0.0, 0.0)).magnitude() < 1e-5);
+    assert_eq!(-1.0, tangent.w);
+
+    let (vertices, _) = mesh.to_buffers_with_tangents(&VertexLayout::default());
+    assert_eq!(4 * 7, vertices.len());
+    assert_eq!(&[-1.0, 0.0, 0.0, -1.0], &vertices[3..7]);
+}
+
+#[test]
+fn verify_subdivision_child_map() {
+    let input = cube();
+    let (output, children) = CatmullClarkSubdivider::new(&input).generate_with_child_map();
+    assert_eq!(6, children.len());
+    let mut all_children = Vec::new();
+    for face_id in FaceIterator::new(&input) {
+        let face_children = &children[&face_id];
+        assert_eq!(4, face_children.len());
+        // The children tile the face, so their normals agree with it.
+        for &child_id in face_children.iter() {
+            assert!(output.face_norm(child_id).dot(input.face_norm(face_id)) > 0.7);
+        }
+        all_children.extend(face_children.iter().cloned());
+    }
+    all_children.sort();
+    assert_eq!(FaceIterator::new(&output).into_vec(), all_children);
+}
+
+#[test]
+fn verify_displace_along_normals() {
+    let mut sphere = uv_sphere(32, 16);
+    sphere.displace(|_, _| 0.1);
+    for vertex in sphere.vertices.iter().filter(|vertex| vertex.alive) {
+        assert!((vertex.position.to_vec().magnitude() - 1.1).abs() < 1e-3);
+    }
+
+    // Displacing by the height above the plane doubles it.
+    let mut grid = Mesh::from_grid(4, 4, |u, v| Point3::new(u, v, 0.0), false, false);
+    grid.translate(0.0, 0.0, 0.5).displace(|position, _| position.z);
+    assert!(grid.vertices.iter().all(|vertex| vertex.position.z == 1.0));
+}
+
+#[test]
+fn verify_sample_surface_poisson() {
+    let mesh = cube();
+    let radius = 0.1;
+    let samples = mesh.sample_surface_poisson(radius);
+    assert!(samples.len() > 100);
+    for (i, &(point, _)) in samples.iter().enumerate() {
+        for &(other, _) in samples[i + 1..].iter() {
+            assert!(point.distance(other) >= radius);
+        }
+    }
+    for face_id in FaceIterator::new(&mesh) {
+        let on_face: Vec<&(Point3<f32>, Id)> = samples.iter().filter(|&&(_, id)| id == face_id).collect();
+        assert!(on_face.len() > 10);
+        let normal = mesh.face_norm(face_id);
+        let face_point = mesh.vertex(mesh.face_vertex_ids(face_id)[0]).unwrap().position;
+        for &&(point, _) in on_face.iter() {
+            assert!(normal.dot(point - face_point).abs() < 1e-5);
+        }
+    }
+    assert_eq!(samples, mesh.sample_surface_poisson(radius));
+
+    // A radius this small would take 10^12 darts without a cap.
+    let capped = mesh.sample_surface_poisson_with_max_darts(1e-5, 1000);
+    assert!(capped.len() > 900 && capped.len() <= 1000);
+    assert_eq!(samples, mesh.sample_surface_poisson_with_max_darts(radius, POISSON_MAX_DARTS));
+}
+
+#[test]
+fn verify_sample_surface_uniform() {
+    let mut mesh = cube();
+    mesh.transform(&cgmath::Matrix4::from_nonuniform_scale(1.0, 2.0, 3.0));
+    let count = 60000;
+    let samples = mesh.sample_surface_uniform(count, 7);
+    assert_eq!(count, samples.len());
+    let total_area: f32 = FaceIterator::new(&mesh).map(|face_id| mesh.face_area(face_id)).sum();
+    let mut counts: HashMap<Id, usize> = HashMap::new();
+    for &(point, face_id, weights) in samples.iter() {
+        *counts.entry(face_id).or_insert(0) += 1;
+        assert!(weights.iter().all(|&weight| weight >= 0.0));
+        assert!((weights.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        let normal = mesh.face_norm(face_id);
+        let face_point = mesh.vertex(mesh.face_vertex_ids(face_id)[0]).unwrap().position;
+        assert!(normal.dot(point - face_point).abs() < 1e-5);
+    }
+    for face_id in FaceIterator::new(&mesh) {
+        let share = mesh.face_area(face_id) / total_area;
+        let expected = count as f32 * share;
+        let deviation = (expected * (1.0 - share)).sqrt();
+        assert!((counts[&face_id] as f32 - expected).abs() < 5.0 * deviation);
+    }
+    assert_eq!(samples, mesh.sample_surface_uniform(count, 7));
+    assert_ne!(samples, mesh.sample_surface_uniform(count, 8));
+}
+
+#[test]
+fn verify_orient_outward() {
+    assert!((cube().volume() - 1.0).abs() < 1e-5);
+
+    let mut inside_out = cube().flip_mesh();
+    assert!((inside_out.volume() + 1.0).abs() < 1e-5);
+    assert_eq!(6, inside_out.orient_outward());
+    assert!((inside_out.volume() - 1.0).abs() < 1e-5);
+    assert!(inside_out.is_manifold());
+    assert_eq!(0, inside_out.orient_outward());
+
+    let mut mesh = cube();
+    let face_id = FaceIterator::new(&mesh).last().unwrap();
+    let mut reversed = mesh.face_vertex_ids(face_id);
+    reversed.reverse();
+    mesh.replace_faces(&[face_id], &[reversed]);
+    assert!(!mesh.is_manifold());
+    assert_eq!(1, mesh.fix_orientation());
+    assert!(mesh.is_manifold());
+    assert!((mesh.volume() - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn verify_subdivision_matrix() {
+    let assert_points_near = |a: &[Point3<f32>], b: &[Point3<f32>]| {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert!(a.distance(*b) < 1e-5);
+        }
+    };
+    let mut cage = cube();
+    cage.translate(0.0, 0.0, 1.0);
+    let matrix = cage.subdivision_matrix(2);
+    assert_eq!(cage.vertex_count, matrix.columns());
+    assert_points_near(&matrix.transform_points(&cage.positions()), &cage.subdivide_n(2).positions());
+
+    // Deforming the cage needs no new matrix.
+    let mut deformed = cage.positions();
+    deformed[0] += Vector3::new(0.3, -0.2, 0.5);
+    deformed[5] *= 1.5;
+    cage.set_positions(&deformed);
+    assert_points_near(&matrix.transform_points(&deformed), &cage.subdivide_n(2).positions());
+
+    let grid = Mesh::from_grid(3, 2, |u, v| Point3::new(u, v, u * v), false, false);
+    assert_points_near(&grid.subdivision_matrix(1).transform_points(&grid.positions()), &grid.subdivide().positions());
+    assert_eq!(grid.positions(), grid.subdivision_matrix(0).transform_points(&grid.positions()));
+}
+
+#[test]
+fn verify_topology_errors() {
+    let mut mesh = cube();
+    let face_count = mesh.face_count;
+    assert_eq!(Err(TopologyError::InvalidElement { id: 1000 }), mesh.bevel_edge(1000, 0.1, 1));
+    let halfedge_id = mesh.edges[&EdgeEndpoints::new(1, 2)];
+    assert_eq!(Err(TopologyError::InvalidElement { id: halfedge_id }), mesh.bridge(halfedge_id, halfedge_id));
+    assert_eq!(face_count, mesh.face_count);
+    assert!(mesh.vertex(1000).is_none() && mesh.face(1000).is_none() && mesh.halfedge(1000).is_none());
+
+    let mut quad = Mesh::new();
+    quad.add_plane(1.0, 1.0);
+    let boundary_id = quad.boundary_loops()[0][0];
+    assert_eq!(Err(TopologyError::InvalidElement { id: boundary_id }), quad.bevel_edge(boundary_id, 0.1, 1));
+    assert_eq!(Err(TopologyError::InvalidElement { id: 0 }), quad.bridge(boundary_id, 0));
+
+    // The two ends of a one row tube are already joined by its sides.
+    let mut tube = Mesh::from_grid(8, 1, |u, v| {
+        let u = u * 2.0 * std::f32::consts::PI;
+        Point3::new(u.cos(), u.sin(), v)
+    }, true, false);
+    let tube_faces = tube.face_count;
+    let ends = tube.boundary_loops();
+    match tube.bridge(ends[0][0], ends[1][0]) {
+        Err(TopologyError::WouldCreateDuplicateEdge { first, second }) => {
+            assert!(tube.edges.contains_key(&EdgeEndpoints::new(first, second)));
+        },
+        result => panic!("unexpected {:?}", result),
+    }
+    assert_eq!(tube_faces, tube.face_count);
+
+    // Both faces of a pillow wrap around each other at every edge.
+    let mut pillow = Mesh::new();
+    let face_id = pillow.add_plane(1.0, 1.0);
+    let mut back = pillow.face_vertex_ids(face_id);
+    back.reverse();
+    pillow.add_vertices(back);
+    let halfedge_id = pillow.face_first_halfedge_id(face_id).unwrap();
+    assert_eq!(Err(TopologyError::NonManifoldResult), pillow.bevel_edge(halfedge_id, 0.1, 1));
+    assert_eq!(2, pillow.face_count);
+}
+
+#[test]
+fn verify_attribute_subdivision() {
+    let mut mesh = cube();
+    mesh.attributes.add_vertex_attribute::<f32>("height");
+    mesh.attributes.add_face_attribute::<Vector3<f32>>("normal");
+    let vertex_ids: Vec<Id> = mesh.vertices.iter().map(|vertex| vertex.id).collect();
+    for id in vertex_ids {
+        let height = mesh.vertex(id).unwrap().position.z + 0.5;
+        assert!(mesh.attributes.set_vertex_attribute("height", id, height));
+    }
+    for face_id in FaceIterator::new(&mesh).collect::<Vec<Id>>() {
+        let normal = mesh.face_norm(face_id);
+        mesh.attributes.set_face_attribute("normal", face_id, normal);
+    }
+    assert!(!mesh.attributes.set_vertex_attribute("height", 1, 1.0f64));
+    assert!(!mesh.attributes.set_vertex_attribute("weight", 1, 1.0f32));
+    assert_eq!(None, mesh.attributes.vertex_attribute::<f64>("height", 1));
+
+    // The rules are affine, so an attribute linear in the position stays
+    // linear in it.
+    let sub = mesh.subdivide_n(2);
+    assert_eq!(sub.vertex_count, sub.attributes.vertex_attribute_values::<f32>("height").unwrap().len());
+    for vertex in sub.vertices.iter() {
+        let height = *sub.attributes.vertex_attribute::<f32>("height", vertex.id).unwrap();
+        assert!((height - (vertex.position.z + 0.5)).abs() < 1e-5);
+    }
+    for face_id in FaceIterator::new(&sub) {
+        let normal = *sub.attributes.face_attribute::<Vector3<f32>>("normal", face_id).unwrap();
+        assert!(normal.dot(sub.face_norm(face_id)) > 0.5);
+    }
+
+    let copy = sub.clone();
+    assert_eq!(vec!["height"], copy.attributes.vertex_attribute_names());
+    for vertex in copy.vertices.iter() {
+        let height = *copy.attributes.vertex_attribute::<f32>("height", vertex.id).unwrap();
+        assert!((height - (vertex.position.z + 0.5)).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn verify_pinned_subdivision() {
+    let input = cube();
+    let mut pinned = FnvHashSet::default();
+    pinned.insert(1);
+    let (output, map) = CatmullClarkSubdivider::new(&input).with_pinned(pinned).generate_with_provenance();
+    for vertex in output.vertices.iter() {
+        if let VertexSource::Vertex(id) = map.vertices[&vertex.id] {
+            let position = input.vertex(id).unwrap().position;
+            if id == 1 {
+                assert_eq!(position, vertex.position);
+            } else {
+                assert!(vertex.position.distance(Point3::from_vec(position.to_vec() * (5.0 / 9.0))) < 1e-6);
+            }
+        }
+    }
+    // Everything else, including the edge and face points around the pinned
+    // corner, smooths as without pinning.
+    let smoothed = input.subdivide();
+    for vertex in output.vertices.iter() {
+        if map.vertices[&vertex.id] != VertexSource::Vertex(1) {
+            assert_eq!(smoothed.vertex(vertex.id).unwrap().position, vertex.position);
+        }
+    }
+}
+
+#[test]
+fn verify_voxelize() {
+    let mesh = cube();
+    assert!(mesh.contains_point(Point3::new(0.1, 0.2, 0.3)));
+    assert!(!mesh.contains_point(Point3::new(0.1, 0.2, 0.6)));
+
+    let grid = mesh.voxelize([10, 10, 10]);
+    assert_eq!(Point3::new(-0.5, -0.5, -0.5), grid.origin);
+    assert!((grid.cell_size - Vector3::new(0.1, 0.1, 0.1)).magnitude() < 1e-6);
+    assert_eq!(1000, grid.filled_count());
+    assert_eq!(1000 - 8 * 8 * 8, mesh.voxelize_surface([10, 10, 10]).filled_count());
+
+    let sphere = uv_sphere(32, 16);
+    let grid = sphere.voxelize([10, 10, 10]);
+    let expected = 1000.0 * std::f32::consts::PI / 6.0;
+    assert!((grid.filled_count() as f32 - expected).abs() < expected * 0.1);
+    assert!(grid.get(5, 5, 5) && !grid.get(0, 0, 0));
+    let surface = sphere.voxelize_surface([10, 10, 10]);
+    assert!(!surface.get(5, 5, 5) && !surface.get(0, 0, 0) && surface.get(5, 5, 0));
+}
+
+#[test]
+fn verify_catmull_clark_limit_normals() {
+    let mesh = cube().subdivide_n(2);
+    let normals = mesh.catmull_clark_limit_normals();
+    assert_eq!(mesh.vertex_count, normals.len());
+    for vertex in mesh.vertices.iter() {
+        let normal = normals[&vertex.id];
+        assert!((normal.magnitude() - 1.0).abs() < 1e-5);
+        assert!(normal.dot(mesh.vertex_limit_position(vertex.id).to_vec().normalize()) > 0.9);
+    }
+    for halfedge in mesh.halfedges.iter() {
+        let next = mesh.halfedge(halfedge.next).unwrap();
+        assert!(normals[&halfedge.vertex].dot(normals[&next.vertex]) > 0.9);
+    }
+    // At regular vertices the masks give the normal of the limit patch.
+    for face_id in FaceIterator::new(&mesh) {
+        let corner = mesh.face_vertex_ids(face_id)[0];
+        if mesh.vertex(corner).unwrap().halfedges.len() == 4 {
+            let (_, patch_normal) = mesh.eval_limit(face_id, 0.0, 0.0).unwrap();
+            assert!(patch_normal.dot(normals[&corner]) > 1.0 - 1e-4);
+        }
+    }
+}
+
+#[test]
+fn verify_normal_cache() {
+    let mut mesh = cube().subdivide_n(2);
+    let mut cache = NormalCache::new(&mesh);
+    for &(vertex_id, offset) in [(3, Vector3::new(0.2, 0.0, 0.1)), (40, Vector3::new(-0.1, 0.3, 0.0))].iter() {
+        mesh.vertex_mut(vertex_id).unwrap().position += offset;
+        cache.mark_vertex_moved(&mesh, vertex_id);
+    }
+    assert!(cache.is_dirty());
+    for vertex in mesh.vertices.iter() {
+        assert_eq!(mesh.vertex_norm(vertex.id), cache.vertex_norm(&mesh, vertex.id));
+    }
+    assert!(!cache.is_dirty());
+    for face_id in FaceIterator::new(&mesh) {
+        assert_eq!(mesh.face_norm(face_id), cache.face_norm(&mesh, face_id));
+        assert_eq!(mesh.face_area(face_id), cache.face_area(&mesh, face_id));
+    }
+
+    // Added faces are picked up once marked.
+    let face_id = mesh.add_positions(vec![Point3::new(0.0, 0.0, 2.0), Point3::new(1.0, 0.0, 2.0), Point3::new(0.0, 1.0, 2.0)]);
+    cache.mark_dirty(&[face_id]);
+    assert_eq!(Vector3::new(0.0, 0.0, 1.0), cache.face_norm(&mesh, face_id));
+    assert_eq!(0.5, cache.face_area(&mesh, face_id));
+}
+
+#[test]
+fn verify_add_face_with_holes() {
+    let mut mesh = Mesh::new();
+    let outer: Vec<Id> = [(0.0, 0.0), (3.0, 0.0), (3.0, 3.0), (0.0, 3.0)].iter()
+        .map(|&(x, y)| mesh.add_vertex(Point3::new(x, y, 0.0))).collect();
+    // Wound the same way as the outer loop, which is accepted.
+    let hole: Vec<Id> = [(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0)].iter()
+        .map(|&(x, y)| mesh.add_vertex(Point3::new(x, y, 0.0))).collect();
+    let faces = mesh.add_face_with_holes(&outer, &[hole]).unwrap();
+    assert_eq!(8, faces.len());
+    assert_eq!(8, mesh.face_count);
+    let mut area = 0.0;
+    for &face_id in faces.iter() {
+        assert_eq!(3, FaceHalfedgeIterator::new(&mesh, mesh.face_first_halfedge_id(face_id).unwrap()).count());
+        assert!(mesh.face_norm(face_id).dot(Vector3::unit_z()) > 0.999);
+        area += mesh.face_area(face_id);
+        let center = mesh.face_center(face_id);
+        assert!(!(center.x > 1.0 && center.x < 2.0 && center.y > 1.0 && center.y < 2.0));
+    }
+    assert!((area - 8.0).abs() < 1e-4);
+    assert!(mesh.is_manifold());
+    assert_eq!(2, mesh.boundary_loops().len());
+
+    // A hole outside the outer loop cannot be bridged and adds nothing.
+    let outside: Vec<Id> = [(4.0, 1.0), (5.0, 1.0), (5.0, 2.0)].iter()
+        .map(|&(x, y)| mesh.add_vertex(Point3::new(x, y, 0.0))).collect();
+    assert_eq!(Err(TopologyError::InvalidElement { id: outside[0] }), mesh.add_face_with_holes(&outer, &[outside]));
+    assert_eq!(8, mesh.face_count);
+
+    // Ids that are not vertices are rejected before anything is looked up.
+    let unknown = mesh.vertices.len() + 1;
+    assert_eq!(Err(TopologyError::InvalidElement { id: unknown }), mesh.add_face_with_holes(&outer, &[vec![outer[0], unknown, outer[2]]]));
+    assert_eq!(Err(TopologyError::InvalidElement { id: 0 }), mesh.add_face_with_holes(&[outer[0], 0, outer[2]], &[]));
+    assert_eq!(8, mesh.face_count);
+}
+
+#[test]
+fn verify_subdivide_with() {
+    // A cube with one side split into two triangles.
+    let mut mixed = cube();
+    let corners = mixed.face_vertex_ids(1);
+    mixed.split_face(1, corners[0], corners[2]).unwrap();
+    assert_eq!(7, mixed.face_count);
+    for &prepass in [Prepass::None, Prepass::Triangulate, Prepass::Quadrangulate].iter() {
+        for &scheme in [SubdivisionScheme::CatmullClark, SubdivisionScheme::Loop, SubdivisionScheme::DooSabin].iter() {
+            let options = SubdivideOptions { prepass, scheme, levels: 2 };
+            let result = mixed.subdivide_with(&options);
+            if scheme == SubdivisionScheme::Loop && prepass != Prepass::Triangulate {
+                match result {
+                    Err(TopologyError::NotATriangle { .. }) => {},
+                    _ => panic!("{:?} should refuse quads", options),
+                }
+                continue;
+            }
+            let mesh = result.unwrap();
+            assert!(mesh.is_manifold(), "{:?}", options);
+            assert!(mesh.is_closed(), "{:?}", options);
+            assert!(mesh.volume() > 0.2 && mesh.volume() < 1.0, "{:?}", options);
+        }
+    }
+    let options = SubdivideOptions { prepass: Prepass::Triangulate, scheme: SubdivisionScheme::Loop, levels: 1 };
+    assert_eq!(48, mixed.subdivide_with(&options).unwrap().face_count);
+    assert_eq!(6, mixed.triangulate().quadrangulate().face_count);
+    // Faces, edges and vertices of the cube each become a face.
+    let options = SubdivideOptions { prepass: Prepass::Quadrangulate, scheme: SubdivisionScheme::DooSabin, levels: 1 };
+    assert_eq!(26, mixed.subdivide_with(&options).unwrap().face_count);
+}
+
+#[test]
+fn verify_loop_and_doo_sabin_reference_values() {
+    // Loop on a regular tetrahedron: the valence 3 vertex weight is 7/16,
+    // which leaves a quarter of every corner, and edge points land on the
+    // axes, as opposite edges cancel out.
+    let mut tetrahedron = Mesh::new();
+    let corners: Vec<Id> = [(1.0, 1.0, 1.0), (1.0, -1.0, -1.0), (-1.0, 1.0, -1.0), (-1.0, -1.0, 1.0)].iter()
+        .map(|&(x, y, z)| tetrahedron.add_vertex(Point3::new(x, y, z)))
+        .collect();
+    for face in [[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]].iter() {
+        tetrahedron.add_vertices(face.iter().map(|&i| corners[i]).collect());
+    }
+    assert!(tetrahedron.is_closed());
+    let subdivided = tetrahedron.loop_subdivide().unwrap();
+    assert_eq!(10, subdivided.vertex_count);
+    let mut expected: Vec<Point3<f32>> = tetrahedron.positions().iter().map(|&position| position * 0.25).collect();
+    for axis in 0..3 {
+        for &sign in [-0.5, 0.5].iter() {
+            let mut point = Point3::new(0.0, 0.0, 0.0);
+            point[axis] = sign;
+            expected.push(point);
+        }
+    }
+    for point in expected.iter() {
+        assert!(subdivided.positions().iter().any(|position| position.distance(*point) < 1e-6), "missing {:?}", point);
+    }
+
+    // Doo-Sabin on the unit cube: the corners of a square face are 9/16 of
+    // themselves, 3/16 of both neighbors and 1/16 of the opposite corner.
+    let subdivided = cube().doo_sabin_subdivide();
+    assert_eq!(24, subdivided.vertex_count);
+    for position in subdivided.positions().iter() {
+        let mut magnitudes = [position.x.abs(), position.y.abs(), position.z.abs()];
+        magnitudes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!((magnitudes[0] - 0.25).abs() < 1e-6 && (magnitudes[1] - 0.25).abs() < 1e-6 && (magnitudes[2] - 0.5).abs() < 1e-6,
+            "unexpected {:?}", position);
+    }
+}
+
+#[test]
+fn verify_cotangent_weights() {
+    let mut mesh = cube().triangulate();
+    let weights = mesh.cotangent_weights();
+    assert_eq!(18, weights.len());
+    // Diagonals face right angles, the sides of the cube two 45 degree ones.
+    assert_eq!(6, weights.values().filter(|weight| weight.abs() < 1e-5).count());
+    assert_eq!(12, weights.values().filter(|weight| (*weight - 2.0).abs() < 1e-5).count());
+    assert_eq!(1, mesh.cotangent_weight_computations());
+    assert_eq!(weights, mesh.cotangent_weights());
+    assert_eq!(1, mesh.cotangent_weight_computations());
+
+    mesh.vertex_mut(1).unwrap().position += Vector3::new(0.1, -0.2, 0.3);
+    let moved = mesh.cotangent_weights();
+    assert_eq!(2, mesh.cotangent_weight_computations());
+    assert_ne!(weights, moved);
+    assert_eq!(mesh.duplicate().cotangent_weights(), moved);
+    assert!(Arc::ptr_eq(&moved, &mesh.cotangent_weights()));
+
+    // Reorienting keeps the number of halfedges but replaces all of them.
+    let mut flipped = Mesh::new();
+    let (positions, triangles) = cube().triangulate().to_indexed();
+    let ids: Vec<Id> = positions.iter().map(|&position| flipped.add_vertex(position)).collect();
+    for triangle in triangles.iter() {
+        flipped.add_vertices(triangle.iter().rev().map(|&index| ids[index as usize]).collect());
+    }
+    let before = flipped.cotangent_weights();
+    assert_eq!(12, flipped.orient_outward());
+    let after = flipped.cotangent_weights();
+    assert_eq!(2, flipped.cotangent_weight_computations());
+    assert_eq!(18, after.len());
+    assert!(after.keys().all(|&halfedge_id| flipped.halfedge(halfedge_id).is_some()));
+    assert!(before.keys().all(|&halfedge_id| flipped.halfedge(halfedge_id).is_none()));
+}
+
+#[test]
+fn verify_clear() {
+    let mut bytes = Vec::new();
+    cube().write_obj(&mut bytes).unwrap();
+    let mut mesh = Mesh::default();
+    assert!(mesh.is_empty());
+    mesh = cube().subdivide_n(2);
+    mesh.set_vertex_tags(1, 4);
+    mesh.set_halfedge_uv(1, Point2::new(0.5, 0.5));
+    let capacity = mesh.halfedges.capacity();
+    mesh.clear();
+    assert!(mesh.is_empty());
+    assert_eq!(0, mesh.face_count);
+    assert_eq!(0, mesh.halfedge_count);
+    assert!(mesh.edges.is_empty());
+    assert!(mesh.halfedges.capacity() >= capacity);
+
+    mesh.read_obj(&bytes[..]).unwrap();
+    let mut fresh = Mesh::new();
+    fresh.read_obj(&bytes[..]).unwrap();
+    assert!(!mesh.is_empty());
+    assert_eq!(fresh.vertex_count, mesh.vertex_count);
+    assert_eq!(fresh.face_count, mesh.face_count);
+    assert_eq!(fresh.halfedge_count, mesh.halfedge_count);
+    assert_eq!(fresh.edges, mesh.edges);
+    assert_eq!(fresh.positions(), mesh.positions());
+    assert_eq!(fresh.halfedge_uvs, mesh.halfedge_uvs);
+    assert_eq!(fresh.vertex_tags, mesh.vertex_tags);
+    for (a, b) in fresh.halfedges.iter().zip(mesh.halfedges.iter()) {
+        assert_eq!((a.vertex, a.face, a.prev, a.next, a.opposite), (b.vertex, b.face, b.prev, b.next, b.opposite));
+    }
+}
+
+#[test]
+fn verify_subdivider_correspondences() {
+    let input = cube().subdivide();
+    let mut moved = input.duplicate();
+    for vertex in moved.vertices.iter_mut() {
+        vertex.position = vertex.position * 2.0 + Vector3::new(0.1, 0.0, -0.3);
+    }
+    let (_, map) = CatmullClarkSubdivider::new(&input).generate_with_provenance();
+    let mut first = CatmullClarkSubdivider::new(&input);
+    assert_eq!(None, first.output_vertex_for_input_vertex(1));
+    let output_vertex_count = first.run().vertex_count;
+    let mut second = CatmullClarkSubdivider::new(&moved);
+    second.run();
+    let mut seen = FnvHashSet::default();
+    for vertex in input.vertices.iter() {
+        let output_id = first.output_vertex_for_input_vertex(vertex.id).unwrap();
+        assert_eq!(Some(output_id), second.output_vertex_for_input_vertex(vertex.id));
+        assert_eq!(VertexSource::Vertex(vertex.id), map.vertices[&output_id]);
+        assert!(seen.insert(output_id));
+    }
+    for halfedge in input.halfedges.iter() {
+        let output_id = first.output_vertex_for_edge(halfedge.id).unwrap();
+        assert_eq!(Some(output_id), first.output_vertex_for_edge(halfedge.opposite));
+        assert_eq!(Some(output_id), second.output_vertex_for_edge(halfedge.id));
+        assert_eq!(VertexSource::Edge(input.peek_same_halfedge(halfedge.id)), map.vertices[&output_id]);
+        seen.insert(output_id);
+    }
+    for face_id in FaceIterator::new(&input) {
+        let output_id = first.output_vertex_for_face(face_id).unwrap();
+        assert_eq!(Some(output_id), second.output_vertex_for_face(face_id));
+        assert_eq!(VertexSource::Face(face_id), map.vertices[&output_id]);
+        assert!(seen.insert(output_id));
+    }
+    assert_eq!(output_vertex_count, seen.len());
+    assert_eq!(None, first.output_vertex_for_face(input.faces.len() + 1));
+    assert_eq!(output_vertex_count, first.into_output().vertex_count);
+}
+
+#[test]
+fn verify_weighted_subdivision() {
+    let input = cube();
+    let mut weights = FnvHashMap::default();
+    weights.insert(1, 0.0);
+    weights.insert(2, 1.0);
+    weights.insert(3, 0.5);
+    let mut subdivider = CatmullClarkSubdivider::new(&input).with_vertex_weights(weights);
+    subdivider.run();
+    let fixed_id = subdivider.output_vertex_for_input_vertex(1).unwrap();
+    let halfway_id = subdivider.output_vertex_for_input_vertex(3).unwrap();
+    let output = subdivider.into_output();
+    let corner = |vertex_id: Id| input.vertex(vertex_id).unwrap().position;
+    assert_eq!(corner(1), output.vertex(fixed_id).unwrap().position);
+    // Halfway between the corner and its usual vertex point at 5/9.
+    let halfway = Point3::from_vec(corner(3).to_vec() * (7.0 / 9.0));
+    assert!(output.vertex(halfway_id).unwrap().position.distance(halfway) < 1e-6);
+    let smoothed = input.subdivide();
+    for vertex in output.vertices.iter() {
+        if vertex.id != fixed_id && vertex.id != halfway_id {
+            assert_eq!(smoothed.vertex(vertex.id).unwrap().position, vertex.position);
+        }
+    }
+}
+
+#[test]
+fn verify_tri_tri_intersect() {
+    let p = |x: f32, y: f32, z: f32| Point3::new(x, y, z);
+    let ground = [p(-1.0, -1.0, 0.0), p(2.0, -1.0, 0.0), p(-1.0, 2.0, 0.0)];
+
+    // Crossing: a wall through the ground between x = 0 and x = 0.5.
+    let wall = [p(0.0, 0.0, -1.0), p(0.5, 0.0, 1.0), p(0.5, 0.0, -1.0)];
+    let (start, end) = tri_tri_intersect(ground, wall).unwrap();
+    let (low, high) = if start.x < end.x { (start, end) } else { (end, start) };
+    assert!(low.distance(p(0.25, 0.0, 0.0)) < 1e-5);
+    assert!(high.distance(p(0.5, 0.0, 0.0)) < 1e-5);
+    assert!(tri_tri_intersect(wall, ground).is_some());
+
+    // Touching at a point: a corner resting on the ground.
+    let resting = [p(0.0, 0.0, 0.0), p(1.0, 0.0, 1.0), p(0.0, 1.0, 1.0)];
+    let (start, end) = tri_tri_intersect(ground, resting).unwrap();
+    assert!(start.distance(p(0.0, 0.0, 0.0)) < 1e-5);
+    assert!(end.distance(p(0.0, 0.0, 0.0)) < 1e-5);
+
+    // Not intersecting: above the ground, and crossing its plane outside it.
+    let above = [p(0.0, 0.0, 0.5), p(1.0, 0.0, 1.0), p(0.0, 1.0, 1.0)];
+    assert_eq!(None, tri_tri_intersect(ground, above));
+    let beside = [p(3.0, 0.0, -1.0), p(3.5, 0.0, 1.0), p(3.5, 0.0, -1.0)];
+    assert_eq!(None, tri_tri_intersect(ground, beside));
+
+    // Coplanar and overlapping: no segment, the overlap is a polygon.
+    let square = [p(0.0, 0.0, 0.0), p(4.0, 0.0, 0.0), p(0.0, 4.0, 0.0)];
+    assert_eq!(None, tri_tri_intersect(ground, square));
+    let overlap = tri_tri_coplanar_overlap(ground, square).unwrap();
+    let area: f32 = (1..overlap.len() - 1)
+        .map(|i| (overlap[i] - overlap[0]).cross(overlap[i + 1] - overlap[0]).magnitude() * 0.5)
+        .sum();
+    // The part of the ground with x, y >= 0 is the triangle 0, 1, 1.
+    assert!((area - 0.5).abs() < 1e-5);
+    assert_eq!(None, tri_tri_coplanar_overlap(ground, wall));
+    let apart = [p(5.0, 5.0, 0.0), p(6.0, 5.0, 0.0), p(5.0, 6.0, 0.0)];
+    assert_eq!(None, tri_tri_coplanar_overlap(ground, apart));
+}
+
+#[test]
+fn verify_self_intersections() {
+    assert!(cube().subdivide_n(2).self_intersections().is_empty());
+    assert!(cube().triangulate().self_intersections().is_empty());
+
+    // A strip folded back on itself, its last side cutting through the first.
+    let profile = [(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, -1.0)];
+    let folded = Mesh::from_grid(3, 1, |u, v| {
+        let (x, z) = profile[(u * 3.0).round() as usize];
+        Point3::new(x, v, z)
+    }, false, false);
+    assert_eq!(3, folded.face_count);
+    let pairs = folded.self_intersections();
+    assert_eq!(1, pairs.len());
+    let (first, second) = pairs[0];
+    let centers = [folded.face_center(first), folded.face_center(second)];
+    assert!(centers.iter().any(|center| center.z == 0.0 && center.x == 1.0));
+    assert!(centers.iter().any(|center| center.z == 0.0 && center.x == 1.5));
+}
+
+#[test]
+fn verify_pivot_transforms() {
+    let mut mesh = cube();
+    mesh.translate(3.0, 1.0, -2.0);
+    let center = Point3::new(3.0, 1.0, -2.0);
+    mesh.scale_about(center, Vector3::new(2.0, 2.0, 2.0));
+    let (min, max) = mesh.build_bvh().bounds().unwrap();
+    assert!(min.midpoint(max).distance(center) < 1e-6);
+    assert!((max - min - Vector3::new(2.0, 2.0, 2.0)).magnitude() < 1e-6);
+    mesh.scale_about(center, Vector3::new(0.5, 1.0, 0.25));
+    let (min, max) = mesh.build_bvh().bounds().unwrap();
+    assert!(min.midpoint(max).distance(center) < 1e-6);
+    assert!((max - min - Vector3::new(1.0, 2.0, 0.5)).magnitude() < 1e-6);
+
+    let pivot = Point3::new(3.5, 0.0, -2.0);
+    let rotation = Quaternion::from_angle_z(Deg(90.0));
+    let before = mesh.positions();
+    mesh.rotate_about(pivot, rotation);
+    for (old, new) in before.iter().zip(mesh.positions()) {
+        assert!(new.distance(pivot + rotation.rotate_vector(old - pivot)) < 1e-5);
+        assert!((new.distance(pivot) - old.distance(pivot)).abs() < 1e-5);
+    }
+    assert!(mesh.volume() > 0.0);
+}
+
+#[test]
+fn verify_morton_order() {
+    // A 16 by 16 grid of quads added in a scrambled order.
+    let size = 16;
+    let mut mesh = Mesh::new();
+    let corners: Vec<Id> = (0..(size + 1) * (size + 1))
+        .map(|i| mesh.add_vertex(Point3::new((i % (size + 1)) as f32, (i / (size + 1)) as f32, 0.0)))
+        .collect();
+    for k in 0..size * size {
+        let cell = (k * 97) % (size * size);
+        let (x, y) = (cell % size, cell / size);
+        let corner = |dx: usize, dy: usize| corners[(y + dy) * (size + 1) + x + dx];
+        mesh.add_vertices(vec![corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1)]);
+    }
+    let order = mesh.faces_morton_order();
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(FaceIterator::new(&mesh).collect::<Vec<Id>>(), sorted);
+    let average_step = |ids: &[Id]| (1..ids.len())
+        .map(|i| mesh.face_center(ids[i - 1]).distance(mesh.face_center(ids[i])))
+        .sum::<f32>() / (ids.len() - 1) as f32;
+    assert!(average_step(&order) < 2.0);
+    assert!(average_step(&sorted) > 4.0);
+
+    let vertex_order = mesh.vertices_morton_order();
+    assert_eq!(corners.len(), vertex_order.len());
+    assert_eq!(1, vertex_order[0]);
+    assert_eq!(*corners.last().unwrap(), *vertex_order.last().unwrap());
+}
+
+#[test]
+fn verify_map_positions() {
+    let mesh = cube().subdivide();
+    let same = mesh.map_positions(|position| position);
+    assert_eq!(mesh.positions(), same.positions());
+    assert_eq!(mesh.edges, same.edges);
+    assert_eq!(mesh.topology_hash(), same.topology_hash());
+    for (a, b) in mesh.halfedges.iter().zip(same.halfedges.iter()) {
+        assert_eq!((a.vertex, a.face, a.prev, a.next, a.opposite), (b.vertex, b.face, b.prev, b.next, b.opposite));
+    }
+
+    // Twists a quarter turn around y per unit of height.
+    let twist = |position: Point3<f32>| {
+        let angle = position.y * std::f32::consts::FRAC_PI_2;
+        Point3::new(position.x * angle.cos() - position.z * angle.sin(), position.y,
+            position.x * angle.sin() + position.z * angle.cos())
+    };
+    let twisted = mesh.map_positions(twist);
+    let corner_id = mesh.nearest_vertex(Point3::new(0.5, 0.5, 0.5)).unwrap();
+    let before = mesh.vertex(corner_id).unwrap().position;
+    let after = twisted.vertex(corner_id).unwrap().position;
+    let angle = before.y * std::f32::consts::FRAC_PI_2;
+    assert!((after.y - before.y).abs() < 1e-6);
+    assert!((after.x - (before.x * angle.cos() - before.z * angle.sin())).abs() < 1e-6);
+    assert!(after.distance(before) > 0.1);
+
+    let mut in_place = mesh.duplicate();
+    in_place.map_positions_mut(twist).translate(0.0, 1.0, 0.0);
+    assert_eq!(twisted.vertex(corner_id).unwrap().position + Vector3::new(0.0, 1.0, 0.0), in_place.vertex(corner_id).unwrap().position);
+}
+
+#[test]
+fn verify_update_subdivision_local() {
+    let torus = Mesh::from_grid(16, 10, |u, v| {
+        let (u, v) = (u * 2.0 * std::f32::consts::PI, v * 2.0 * std::f32::consts::PI);
+        Point3::new((2.0 + v.cos()) * u.cos(), v.sin(), (2.0 + v.cos()) * u.sin())
+    }, true, true);
+    let strip = Mesh::from_grid(12, 6, |u, v| Point3::new(u * 4.0, v * 2.0, 0.0), false, false);
+    for &(ref cage, moved_vertex) in [(torus, 37), (cube().subdivide().triangulate(), 3), (strip, 2)].iter() {
+        for levels in 0..4 {
+            let original = cage.subdivide_n(levels);
+            let mut subdivided = original.duplicate();
+            let mut moved = cage.duplicate();
+            moved.vertex_mut(moved_vertex).unwrap().position += Vector3::new(0.3, -0.2, 0.25);
+            moved.update_subdivision_local(&mut subdivided, moved_vertex, levels);
+            let expected = moved.subdivide_n(levels);
+            assert_eq!(expected.vertex_count, subdivided.vertex_count);
+            for (vertex, expected_vertex) in subdivided.vertices.iter().zip(expected.vertices.iter()) {
+                assert!(vertex.position.distance(expected_vertex.position) < 1e-5, "{} levels", levels);
+            }
+            let changed = subdivided.positions().iter().zip(original.positions())
+                .filter(|&(&position, original_position)| position != original_position)
+                .count();
+            assert!(changed > 0);
+            if cage.face_count == 160 {
+                assert!(changed * 4 < subdivided.vertex_count);
+            }
+        }
+    }
+}
+
+#[test]
+fn verify_compact() {
+    let mut mesh = cube().subdivide_n(2);
+    assert!(mesh.is_compact());
+    let mut reused = Mesh::new();
+    cube().subdivide_into(&mut reused);
+    cube().subdivide().subdivide_into(&mut reused);
+    assert!(reused.is_compact());
+
+    mesh.attributes.add_vertex_attribute::<f32>("height");
+    for vertex_id in 1..mesh.vertices.len() + 1 {
+        let height = mesh.vertex(vertex_id).unwrap().position.y;
+        mesh.attributes.set_vertex_attribute("height", vertex_id, height);
+    }
+    for halfedge_id in 1..mesh.halfedges.len() + 1 {
+        let position = mesh.halfedge_start_vertex(halfedge_id).unwrap().position;
+        mesh.set_halfedge_uv(halfedge_id, Point2::new(position.x, position.z));
+    }
+    let fan: Vec<Id> = mesh.vertex(1).unwrap().halfedges.iter()
+        .map(|&halfedge_id| mesh.halfedge(halfedge_id).unwrap().face).collect();
+    for face_id in fan {
+        mesh.remove_face(face_id);
+    }
+    mesh.remove_face(40);
+    mesh.remove_isolated_vertices();
+    let lonely = mesh.add_vertex(Point3::new(5.0, 5.0, 5.0));
+    assert!(!mesh.is_compact());
+    let (face_count, vertex_count, hash) = (mesh.face_count, mesh.vertex_count, mesh.topology_hash());
+    let boundary_count = mesh.boundary_loops().len();
+    mesh.compact();
+    assert!(mesh.is_compact());
+    assert_eq!(face_count, mesh.faces.len());
+    assert_eq!(vertex_count, mesh.vertices.len());
+    assert_eq!(hash, mesh.topology_hash());
+    assert_eq!(boundary_count, mesh.boundary_loops().len());
+    assert!(mesh.is_manifold());
+    assert!(mesh.vertices.iter().any(|vertex| vertex.position == Point3::new(5.0, 5.0, 5.0) && vertex.id < lonely));
+    for vertex in mesh.vertices.iter() {
+        if vertex.halfedges.is_empty() {
+            continue;
+        }
+        assert_eq!(Some(&vertex.position.y), mesh.attributes.vertex_attribute::<f32>("height", vertex.id));
+    }
+    for halfedge in mesh.halfedges.iter() {
+        let position = mesh.halfedge_start_vertex(halfedge.id).unwrap().position;
+        assert_eq!(Some(Point2::new(position.x, position.z)), mesh.halfedge_uv(halfedge.id));
+        assert_eq!(Some(halfedge.id), mesh.edges.get(&EdgeEndpoints::new(halfedge.vertex, mesh.halfedge(halfedge.next).unwrap().vertex))
+            .map(|&id| if id == halfedge.id { id } else { mesh.halfedge(id).unwrap().opposite }));
+    }
+}
+
+#[test]
+fn verify_for_each_edge() {
+    let mesh = cube();
+    let mut endpoints = FnvHashSet::default();
+    mesh.for_each_edge(|halfedge_id, from, to| {
+        assert_eq!(halfedge_id, mesh.peek_same_halfedge(halfedge_id));
+        assert_eq!(from, mesh.halfedge_start_vertex(halfedge_id).unwrap().id);
+        assert_eq!(to, mesh.halfedge_start_vertex(mesh.halfedge(halfedge_id).unwrap().next).unwrap().id);
+        assert!(endpoints.insert(EdgeEndpoints::new(from, to)));
+    });
+    assert_eq!(12, endpoints.len());
+
+    let mut triangulated = cube().triangulate();
+    let mut count = 0;
+    triangulated.for_each_edge(|_, _, _| count += 1);
+    assert_eq!(18, count);
+
+    triangulated.remove_face(1);
+    let mut count = 0;
+    triangulated.for_each_edge(|_, _, _| count += 1);
+    assert_eq!(18, count);
+}
+
+#[test]
+fn verify_merge_vertices() {
+    let strip = |split: bool, flip: bool| {
+        let mut mesh = Mesh::new();
+        let ids: Vec<Id> = (0..6).map(|i| mesh.add_vertex(Point3::new((i % 3) as f32, (i / 3) as f32, 0.0))).collect();
+        let (b, e) = if split {
+            (mesh.add_vertex(Point3::new(1.0, 0.0, 0.0)), mesh.add_vertex(Point3::new(1.0, 1.0, 0.0)))
+        } else {
+            (ids[1], ids[4])
+        };
+        mesh.add_vertices(vec![ids[0], ids[1], ids[4], ids[3]]);
+        if flip {
+            mesh.add_vertices(vec![e, ids[5], ids[2], b]);
+        } else {
+            mesh.add_vertices(vec![b, ids[2], ids[5], e]);
+        }
+        (mesh, b, e)
+    };
+    let (original, _, _) = strip(false, false);
+    let (mut stitched, b, e) = strip(true, false);
+    assert_eq!(2, stitched.boundary_loops().len());
+    assert_eq!(Ok(()), stitched.merge_vertices(2, b));
+    assert_eq!(Ok(()), stitched.merge_vertices(5, e));
+    assert_eq!(original.vertex_count, stitched.vertex_count);
+    assert_eq!(1, stitched.boundary_loops().len());
+    assert!(stitched.is_manifold());
+    stitched.compact();
+    assert_eq!(original.topology_hash(), stitched.topology_hash());
+    let mut edge_count = 0;
+    stitched.for_each_edge(|_, _, _| edge_count += 1);
+    assert_eq!(7, edge_count);
+
+    let (mut flipped, b, e) = strip(true, true);
+    assert_eq!(Ok(()), flipped.merge_vertices(2, b));
+    let hash = flipped.topology_hash();
+    assert_eq!(Err(TopologyError::NonManifoldResult), flipped.merge_vertices(5, e));
+    assert_eq!(hash, flipped.topology_hash());
+
+    let mut quad = strip(false, false).0;
+    assert_eq!(Err(TopologyError::NonManifoldResult), quad.merge_vertices(1, 5));
+    assert_eq!(Err(TopologyError::InvalidElement { id: 9 }), quad.merge_vertices(1, 9));
+}
+
+#[test]
+fn verify_flat_grid_subdivision() {
+    let n = 4;
+    let flat = Mesh::from_grid(n, n, |u, v| Point3::new(u * n as f32, v * n as f32, 0.0), false, false);
+    let once = flat.subdivide();
+    for (_, vertex) in once.iter_vertices() {
+        assert_eq!(0.0, vertex.position.z);
+        let near_side = |value: f32| value.min(n as f32 - value) < 0.2;
+        let corner = near_side(vertex.position.x) && near_side(vertex.position.y);
+        let snap = |value: f32| (value * 2.0).round() / 2.0;
+        if corner {
+            // Corners follow the crease rule along both boundary edges and are
+            // cut by an eighth of a cell in each direction.
+            let expected = |value: f32| if value < 1.0 { 0.125 } else { n as f32 - 0.125 };
+            assert!((vertex.position.x - expected(vertex.position.x)).abs() < 1e-5);
+            assert!((vertex.position.y - expected(vertex.position.y)).abs() < 1e-5);
+        } else {
+            // Everything else lands on the half cell lattice, so the interior
+            // of a regular grid is reproduced and the boundary does not move.
+            assert!((vertex.position.x - snap(vertex.position.x)).abs() < 1e-5);
+            assert!((vertex.position.y - snap(vertex.position.y)).abs() < 1e-5);
+        }
+    }
+    let (min, max) = once.build_bvh().bounds().unwrap();
+    assert!(min.distance(Point3::new(0.0, 0.0, 0.0)) < 1e-5);
+    assert!(max.distance(Point3::new(n as f32, n as f32, 0.0)) < 1e-5);
+
+    let plane_z = |x: f32, y: f32| 0.3 * x - 0.2 * y + 1.0;
+    let tilted = Mesh::from_grid(n, n, |u, v| Point3::new(u * n as f32, v * n as f32, plane_z(u * n as f32, v * n as f32)), false, false);
+    for (_, vertex) in tilted.subdivide_n(3).iter_vertices() {
+        assert!((vertex.position.z - plane_z(vertex.position.x, vertex.position.y)).abs() < 1e-4);
+    }
+}
+
+#[test]
+fn verify_flat_buffers() {
+    let mesh = cube();
+    let (positions, normals, indices) = mesh.to_buffers_flat();
+    assert_eq!(6 * 4 * 3, positions.len());
+    assert_eq!(positions.len(), normals.len());
+    assert_eq!(6 * 2 * 3, indices.len());
+    let mut face_of_index = Vec::new();
+    for (face_index, face_id) in FaceIterator::new(&mesh).enumerate() {
+        let normal = mesh.face_norm(face_id);
+        for corner in 0..4 {
+            let index = face_index * 4 + corner;
+            assert_eq!(normal, Vector3::new(normals[index * 3], normals[index * 3 + 1], normals[index * 3 + 2]));
+            face_of_index.push(face_index);
+        }
+    }
+    for triangle in indices.chunks(3) {
+        assert_eq!(face_of_index[triangle[0] as usize], face_of_index[triangle[1] as usize]);
+        assert_eq!(face_of_index[triangle[0] as usize], face_of_index[triangle[2] as usize]);
+        let corner = |i: usize| {
+            let index = triangle[i] as usize * 3;
+            Point3::new(positions[index], positions[index + 1], positions[index + 2])
+        };
+        let winding = (corner(1) - corner(0)).cross(corner(2) - corner(0)).normalize();
+        let index = triangle[0] as usize * 3;
+        assert!(winding.dot(Vector3::new(normals[index], normals[index + 1], normals[index + 2])) > 0.99);
+    }
+
+    let (positions, _, indices) = cube().triangulate().to_buffers_flat();
+    assert_eq!(12 * 3 * 3, positions.len());
+    assert_eq!(12 * 3, indices.len());
+}
+
+#[test]
+fn verify_laplacian_matrix() {
+    let mesh = cube();
+    let (uniform, ids) = mesh.laplacian_matrix(LaplacianKind::Uniform);
+    assert_eq!((8, 8), (uniform.rows(), uniform.columns()));
+    assert_eq!((1..9).collect::<Vec<Id>>(), ids);
+    for (row, &vertex_id) in ids.iter().enumerate() {
+        assert_eq!(mesh.vertex(vertex_id).unwrap().halfedges.len() as f32, uniform.get(row, row));
+        assert_eq!(0.0, uniform.row(row).iter().map(|&(_, value)| value).sum::<f32>());
+    }
+
+    let mut sphere = cube().subdivide_n(2).triangulate();
+    sphere.remove_face(5);
+    let (cotangent, ids) = sphere.laplacian_matrix(LaplacianKind::Cotangent);
+    assert_eq!(sphere.vertex_count, ids.len());
+    let weights = sphere.cotangent_weights();
+    let edge = sphere.peek_same_halfedge(sphere.vertex(ids[3]).unwrap().halfedges[0]);
+    let other = ids.iter().position(|&id| id == sphere.halfedge_start_vertex(sphere.halfedge(edge).unwrap().next).unwrap().id).unwrap();
+    let row = ids.iter().position(|&id| id == sphere.halfedge_start_vertex(edge).unwrap().id).unwrap();
+    assert_eq!(-0.5 * weights[&edge], cotangent.get(row, other));
+    for row in 0..cotangent.rows() {
+        assert!(cotangent.row(row).iter().map(|&(_, value)| value).sum::<f32>().abs() < 1e-5);
+        for &(column, value) in cotangent.row(row) {
+            assert_eq!(value, cotangent.get(column, row));
+        }
+    }
+}
+
+#[test]
+fn verify_remove_duplicate_faces() {
+    let corners = |mesh: &Mesh, face_id: Id| -> Vec<Id> {
+        FaceHalfedgeIterator::new(mesh, mesh.face_first_halfedge_id(face_id).unwrap())
+            .map(|halfedge_id| mesh.halfedge_start_vertex_id(halfedge_id).unwrap())
+            .collect()
+    };
+    let mut mesh = cube();
+    let repeated = corners(&mesh, 2);
+    mesh.add_vertices(repeated);
+    assert!(!mesh.validate().is_manifold());
+    assert_eq!(1, mesh.remove_duplicate_faces());
+    assert_eq!(6, mesh.face_count);
+    assert!(mesh.validate().is_manifold());
+    assert!(mesh.is_closed());
+    assert_eq!(cube().topology_hash(), mesh.topology_hash());
+    assert_eq!(0, mesh.remove_duplicate_faces());
+
+    let mut reversed = cube();
+    let mut flipped = corners(&reversed, 2);
+    flipped.reverse();
+    reversed.add_vertices(flipped);
+    let mut kept = reversed.clone();
+    assert_eq!(1, kept.remove_duplicate_faces());
+    assert!(kept.is_closed());
+    assert_eq!(2, reversed.remove_duplicate_faces_with(DuplicateFacePolicy::CancelOpposite));
+    assert_eq!(5, reversed.face_count);
+    assert!(reversed.validate().is_manifold());
+    assert_eq!(1, reversed.boundary_loops().len());
+    assert_eq!(4, reversed.validate().boundary_edges);
+}
+
+#[test]
+fn verify_bend_and_twist() {
+    let mut tall = cube().subdivide_n(2);
+    tall.scale_about(Point3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 4.0));
+    let original = tall.positions();
+    let bottom = original.iter().map(|position| position.z).fold(f32::MAX, f32::min);
+    tall.twist(Axis::Z, 0.1);
+    for (before, after) in original.iter().zip(tall.positions()) {
+        assert!((before.z - after.z).abs() < 1e-6);
+        if before.x.abs() + before.y.abs() < 1e-4 {
+            continue;
+        }
+        let turned = after.y.atan2(after.x) - before.y.atan2(before.x);
+        let expected = (before.z - bottom) * 0.1 * 2.0 * std::f32::consts::PI;
+        let difference = (turned - expected).rem_euclid(2.0 * std::f32::consts::PI);
+        assert!(!(1e-4..=2.0 * std::f32::consts::PI - 1e-4).contains(&difference));
+        assert!((Vector3::new(before.x, before.y, 0.0).magnitude() - Vector3::new(after.x, after.y, 0.0).magnitude()).abs() < 1e-5);
+    }
+
+    let mut bar = cube();
+    bar.scale_about(Point3::new(-0.5, 0.0, 0.0), Vector3::new(8.0, 1.0, 1.0));
+    let straight = bar.positions();
+    bar.bend(Axis::X, std::f32::consts::FRAC_PI_2, (0.5, 1.0));
+    let radius = 4.0 / std::f32::consts::FRAC_PI_2;
+    for (before, after) in straight.iter().zip(bar.positions()) {
+        if before.x < 3.0 {
+            assert_eq!(*before, after);
+        } else {
+            // The far end now faces along y, level with the arc center.
+            assert!((after.y - radius).abs() < 1e-4);
+            assert!((after.x - (3.5 + radius - before.y)).abs() < 1e-4);
+            assert_eq!(before.z, after.z);
+        }
+    }
+}
+
+#[test]
+fn verify_child_quad_order() {
+    let mut inputs = vec![cube(), cube().triangulate(), cube().subdivide()];
+    let mut prism = Mesh::new();
+    let ring: Vec<Id> = (0..5).map(|i| {
+        let angle = i as f32 * 2.0 * std::f32::consts::PI / 5.0;
+        prism.add_vertex(Point3::new(angle.cos(), angle.sin(), 0.0))
+    }).collect();
+    prism.add_vertices(ring);
+    inputs.push(prism);
+    for input in inputs.iter() {
+        let (output, map) = CatmullClarkSubdivider::new(input).generate_with_provenance();
+        let (_, children) = CatmullClarkSubdivider::new(input).generate_with_child_map();
+        let edge_of = |vertex_id: Id| match map.vertices[&vertex_id] {
+            VertexSource::Edge(halfedge_id) => input.peek_same_halfedge(halfedge_id),
+            source => panic!("expected an edge point, found {:?}", source),
+        };
+        let mut expected_face_id = 0;
+        for parent_id in FaceIterator::new(input) {
+            let loop_halfedges = FaceHalfedgeIterator::new(input, input.face_first_halfedge_id(parent_id).unwrap()).into_vec();
+            assert_eq!(loop_halfedges.len(), children[&parent_id].len());
+            for (corner, &child_id) in children[&parent_id].iter().enumerate() {
+                expected_face_id += 1;
+                assert_eq!(expected_face_id, child_id);
+                let halfedge_id = loop_halfedges[corner];
+                assert_eq!(halfedge_id, map.face_corners[&child_id]);
+                let quad: Vec<Id> = FaceHalfedgeIterator::new(&output, output.face_first_halfedge_id(child_id).unwrap())
+                    .map(|id| output.halfedge_start_vertex_id(id).unwrap())
+                    .collect();
+                let next_id = input.halfedge_next_id(halfedge_id).unwrap();
+                assert_eq!(4, quad.len());
+                assert_eq!(VertexSource::Face(parent_id), map.vertices[&quad[0]]);
+                assert_eq!(input.peek_same_halfedge(halfedge_id), edge_of(quad[1]));
+                assert_eq!(VertexSource::Vertex(input.halfedge_start_vertex_id(next_id).unwrap()), map.vertices[&quad[2]]);
+                assert_eq!(input.peek_same_halfedge(next_id), edge_of(quad[3]));
+                assert!(output.face_norm(child_id).dot(input.face_norm(parent_id)) > 0.0);
+            }
+        }
+    }
+}
+
+#[test]
+fn verify_quad_fast_path() {
+    let grid = Mesh::from_grid(8, 8, |u, v| Point3::new(u, v, (u * v).sin()), false, false).subdivide_n(1);
+    for input in [cube().subdivide(), grid].iter() {
+        let fast = CatmullClarkSubdivider::new(input).generate();
+        let general = CatmullClarkSubdivider::new(input).with_quad_fast_path(false).generate();
+        assert_eq!(general.vertices.len(), fast.vertices.len());
+        for (general_vertex, fast_vertex) in general.vertices.iter().zip(fast.vertices.iter()) {
+            assert_eq!(general_vertex.position, fast_vertex.position);
+        }
+        assert_eq!(general.face_count, fast.face_count);
+        for face_id in FaceIterator::new(&general) {
+            let corners = |mesh: &Mesh| FaceHalfedgeIterator::new(mesh, mesh.face_first_halfedge_id(face_id).unwrap())
+                .map(|id| mesh.halfedge_start_vertex_id(id).unwrap())
+                .collect::<Vec<Id>>();
+            assert_eq!(corners(&general), corners(&fast));
+        }
+    }
+}
+
+#[test]
+fn verify_face_perimeter_and_compactness() {
+    let mut mesh = Mesh::new();
+    let square = mesh.add_plane(1.0, 1.0);
+    let equilateral = mesh.add_positions(vec![
+        Point3::new(0.0, 0.0, 1.0),
+        Point3::new(1.0, 0.0, 1.0),
+        Point3::new(0.5, 3.0f32.sqrt() / 2.0, 1.0),
+    ]);
+    let sliver = mesh.add_positions(vec![
+        Point3::new(0.0, 0.0, 2.0),
+        Point3::new(1.0, 0.0, 2.0),
+        Point3::new(0.5, 0.001, 2.0),
+    ]);
+    assert!((mesh.face_perimeter(square) - 4.0).abs() < 1e-6);
+    assert!((mesh.face_compactness(square) - std::f32::consts::FRAC_PI_4).abs() < 1e-6);
+    assert!((mesh.face_perimeter(equilateral) - 3.0).abs() < 1e-6);
+    assert!((mesh.face_compactness(equilateral) - std::f32::consts::PI / (3.0 * 3.0f32.sqrt())).abs() < 1e-5);
+    assert!(mesh.face_compactness(sliver) < 0.01);
+    mesh.remove_face(sliver);
+    assert_eq!(0.0, mesh.face_perimeter(sliver));
+    assert_eq!(0.0, mesh.face_compactness(sliver));
+}
+
+#[test]
+fn verify_obj_import_winding() {
+    let source = cube();
+    let mut contents = String::new();
+    for vertex in source.vertices.iter() {
+        contents += &format!("v {} {} {}\n", vertex.position.x, vertex.position.y, vertex.position.z);
+    }
+    for face_id in FaceIterator::new(&source) {
+        let mut corners: Vec<Id> = FaceHalfedgeIterator::new(&source, source.face_first_halfedge_id(face_id).unwrap())
+            .map(|halfedge_id| source.halfedge_start_vertex_id(halfedge_id).unwrap())
+            .collect();
+        corners.reverse();
+        contents += "f";
+        for vertex_id in corners {
+            contents += &format!(" {}", vertex_id);
+        }
+        contents += "\n";
+    }
+    let path = TempPath::new("meshlite_verify_obj_import_winding.obj");
+    std::fs::write(&path.0, contents).unwrap();
+    let filename = path.0.to_str().unwrap();
+
+    let options = ObjImportOptions::default();
+    let (clockwise, _) = Mesh::load_obj_with_options(filename, &options).unwrap();
+    assert!((clockwise.volume() + 1.0).abs() < 1e-5);
+    let (flipped, _) = Mesh::load_obj_with_options(filename, &ObjImportOptions { flip_winding: true, ..options }).unwrap();
+    assert!((flipped.volume() - 1.0).abs() < 1e-5);
+    assert!(flipped.is_closed());
+    assert_eq!(source.topology_hash(), flipped.topology_hash());
+    let (oriented, _) = Mesh::load_obj_with_options(filename, &ObjImportOptions { orient_closed: true, ..options }).unwrap();
+    assert!((oriented.volume() - 1.0).abs() < 1e-5);
+    let bytes = std::fs::read(&path.0).unwrap();
+    let (from_bytes, _) = Mesh::load_obj_from_bytes_with_options(&bytes, std::path::Path::new("."), &ObjImportOptions { flip_winding: true, ..options }).unwrap();
+    assert_eq!(flipped.positions(), from_bytes.positions());
+    assert!((from_bytes.volume() - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn verify_subdivision_keeps_uv_seams() {
+    // One texture island per side of the cube, so every edge is a seam.
+    let mut islands = cube();
+    let square = [Point2::new(0.0, 0.0), Point2::new(1.0, 0.0), Point2::new(1.0, 1.0), Point2::new(0.0, 1.0)];
+    for face_id in FaceIterator::new(&cube()) {
+        let halfedges = FaceHalfedgeIterator::new(&islands, islands.face_first_halfedge_id(face_id).unwrap()).into_vec();
+        for (&halfedge_id, &uv) in halfedges.iter().zip(square.iter()) {
+            islands.set_halfedge_uv(halfedge_id, uv);
+        }
+    }
+    let (output, map) = CatmullClarkSubdivider::new(&islands).generate_with_provenance();
+    assert_eq!(cube().subdivide().positions(), output.positions());
+    for face_id in FaceIterator::new(&output) {
+        let halfedges = FaceHalfedgeIterator::new(&output, output.face_first_halfedge_id(face_id).unwrap()).into_vec();
+        let uvs: Vec<Point2<f32>> = halfedges.iter().map(|&id| output.halfedge_uv(id).unwrap()).collect();
+        // The center of the island, the midpoints of its sides and its
+        // corner, which stays in place.
+        assert_eq!(Point2::new(0.5, 0.5), uvs[0]);
+        assert!(square.contains(&uvs[2]));
+        for &side in [uvs[1], uvs[3]].iter() {
+            assert!((side.x - 0.5).abs() + (side.y - 0.5).abs() == 0.5);
+            assert_eq!(0.5, (side - uvs[2]).magnitude());
+        }
+        // Along the sides of the input face the UVs are those of that face
+        // alone, whatever the neighbor across the seam has.
+        let corner_id = map.face_corners[&face_id];
+        let next_id = islands.halfedge_next_id(corner_id).unwrap();
+        let (from, to) = (islands.halfedge_uv(corner_id).unwrap(), islands.halfedge_uv(next_id).unwrap());
+        assert_eq!(Point2::midpoint(from, to), uvs[1]);
+        assert_eq!(to, uvs[2]);
+        // Inside the input face the quads join without a seam.
+        for &halfedge_id in [halfedges[0], halfedges[3]].iter() {
+            let opposite_id = output.halfedge_opposite_id(halfedge_id).unwrap();
+            assert_eq!(map.faces[&face_id], map.faces[&output.halfedge_face_id(opposite_id).unwrap()]);
+            assert_eq!(output.halfedge_uv(halfedge_id), output.halfedge_uv(output.halfedge_next_id(opposite_id).unwrap()));
+            assert_eq!(output.halfedge_uv(output.halfedge_next_id(halfedge_id).unwrap()), output.halfedge_uv(opposite_id));
+        }
+    }
+
+    // A single chart over a flat grid: the UVs take the same rules as the
+    // positions, whatever the settings, and reproduce the planar mapping.
+    let mut grid = Mesh::from_grid(4, 4, |u, v| Point3::new(u * 4.0, v * 4.0, 0.0), false, false);
+    for halfedge_id in 1..grid.halfedges.len() + 1 {
+        let position = grid.halfedge_start_vertex(halfedge_id).unwrap().position;
+        grid.set_halfedge_uv(halfedge_id, Point2::new(position.x, position.y));
+    }
+    let assert_planar = |mesh: &Mesh| {
+        for (halfedge_id, _) in mesh.iter_halfedges() {
+            let position = mesh.halfedge_start_vertex(halfedge_id).unwrap().position;
+            let uv = mesh.halfedge_uv(halfedge_id).unwrap();
+            assert!((uv.x - position.x).abs() < 1e-5 && (uv.y - position.y).abs() < 1e-5, "{:?} at {:?}", uv, position);
+        }
+    };
+    assert_planar(&grid.subdivide_n(2));
+    let mut weights = FnvHashMap::default();
+    weights.insert(7, 0.25);
+    for &mode in [BoundaryInterpolation::None, BoundaryInterpolation::EdgesOnly, BoundaryInterpolation::EdgesAndCorners].iter() {
+        assert_planar(&CatmullClarkSubdivider::new(&grid)
+            .with_boundary_interpolation(mode)
+            .with_pinned([1, 12].iter().cloned().collect())
+            .with_vertex_weights(weights.clone())
+            .generate());
+    }
+}
+
+#[test]
+fn verify_obj_load_from_bytes() {
+    let mut bytes = Vec::new();
+    cube().subdivide().write_obj(&mut bytes).unwrap();
+    let mut imported = Mesh::new();
+    imported.read_obj(&bytes[..]).unwrap();
+    let directory = std::path::Path::new(".");
+    let parsed = Mesh::load_obj_from_bytes(&bytes, directory).unwrap();
+    assert_eq!(imported.positions(), parsed.positions());
+    assert_eq!(imported.face_count, parsed.face_count);
+    assert_eq!(imported.topology_hash(), parsed.topology_hash());
+
+    let error = Mesh::load_obj_from_bytes(&[b'v', b' ', 0xff, 0xfe], directory).unwrap_err();
+    assert_eq!(std::io::ErrorKind::InvalidData, error.kind());
+
+    let far = b"v 1000000.5 2.0 3.0\nv 1000001.5 2.0 3.0\nv 1000001.5 3.0 3.0\nf 1 2 3\n";
+    let (recentered, offset) = Mesh::load_obj_from_bytes_with_options(far, directory, &ObjImportOptions { recenter_on_load: true, ..ObjImportOptions::default() }).unwrap();
+    assert_eq!(Vector3::new(1000000.5, 2.0, 3.0), offset);
+    assert_eq!(vec![Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 0.0, 0.0), Point3::new(1.0, 1.0, 0.0)], recentered.positions());
+    let error = Mesh::load_obj_from_bytes_with_options(b"v 0 0 0\nv 1 x 0\n", directory, &ObjImportOptions { recenter_on_load: true, ..ObjImportOptions::default() }).unwrap_err();
+    assert_eq!(MeshError::InvalidObjLine { line: 2 }.to_string(), error.to_string());
+}
+
+#[cfg(all(feature = "mmap", unix))]
+#[test]
+fn verify_obj_load_mmap() {
+    let path = TempPath::new("meshlite_verify_obj_load_mmap.obj");
+    let filename = path.0.to_str().unwrap();
+    cube().subdivide().export(filename).unwrap();
+    let options = ObjImportOptions { flip_winding: true, ..ObjImportOptions::default() };
+    let (loaded, _) = Mesh::load_obj_with_options(filename, &options).unwrap();
+    let (mapped, _) = Mesh::load_obj_mmap(filename, &options).unwrap();
+    assert_eq!(loaded.positions(), mapped.positions());
+    assert_eq!(loaded.topology_hash(), mapped.topology_hash());
+
+    std::fs::write(&path.0, "").unwrap();
+    assert_eq!(0, Mesh::load_obj_mmap(filename, &options).unwrap().0.vertex_count);
+    std::fs::write(&path.0, "v 0 0 0\nf 1 2 3\n").unwrap();
+    let error = Mesh::load_obj_mmap(filename, &options).unwrap_err();
+    assert_eq!(MeshError::InvalidObjLine { line: 2 }.to_string(), error.to_string());
+}
+
+#[test]
+fn verify_boundary_interpolation() {
+    let mut grid = Mesh::from_grid(4, 4, |u, v| Point3::new(u * 4.0, v * 4.0, 0.0), false, false);
+    grid.attributes.add_vertex_attribute::<f32>("x");
+    for vertex_id in 1..grid.vertices.len() + 1 {
+        let x = grid.vertex(vertex_id).unwrap().position.x;
+        grid.attributes.set_vertex_attribute("x", vertex_id, x);
+    }
+    let (corner, side) = (1, 2);
+    let subdivide = |mode: BoundaryInterpolation| {
+        let mut subdivider = CatmullClarkSubdivider::new(&grid).with_boundary_interpolation(mode);
+        subdivider.run();
+        let ids = (subdivider.output_vertex_for_input_vertex(corner).unwrap(),
+            subdivider.output_vertex_for_input_vertex(side).unwrap());
+        let output = subdivider.into_output();
+        for (vertex_id, vertex) in output.iter_vertices() {
+            let x = *output.attributes.vertex_attribute::<f32>("x", vertex_id).unwrap();
+            assert!((x - vertex.position.x).abs() < 1e-5);
+        }
+        let position = |id: Id| output.vertex(id).unwrap().position;
+        (position(ids.0), position(ids.1))
+    };
+
+    let (smoothed_corner, edge_side) = subdivide(BoundaryInterpolation::EdgesOnly);
+    assert_eq!(Point3::new(0.125, 0.125, 0.0), smoothed_corner);
+    assert_eq!(Point3::new(1.0, 0.0, 0.0), edge_side);
+    assert_eq!(grid.subdivide().positions(), CatmullClarkSubdivider::new(&grid).generate().positions());
+
+    let (pinned_corner, pinned_side) = subdivide(BoundaryInterpolation::EdgesAndCorners);
+    assert_eq!(Point3::new(0.0, 0.0, 0.0), pinned_corner);
+    assert_eq!(edge_side, pinned_side);
+
+    // Without boundary rules the sides are pulled into the grid as well.
+    let (free_corner, free_side) = subdivide(BoundaryInterpolation::None);
+    assert!(free_side.y > 0.1);
+    assert!(free_corner.x > smoothed_corner.x && free_corner.y > smoothed_corner.y);
+}
+
+#[test]
+fn verify_decimate_flat_regions() {
+    let area = |mesh: &Mesh| FaceIterator::new(mesh).map(|face_id| mesh.face_area(face_id)).sum::<f32>();
+    let boundary_vertices = |mesh: &Mesh| {
+        mesh.iter_halfedges().filter(|&(_, halfedge)| halfedge.opposite == 0)
+            .map(|(_, halfedge)| halfedge.vertex).collect::<FnvHashSet<Id>>().len()
+    };
+    let mut wall = Mesh::from_grid(4, 4, |u, v| Point3::new(u * 4.0, 0.0, v * 4.0), false, false).subdivide_n(2);
+    wall.attributes.add_vertex_attribute::<f32>("x");
+    wall.attributes.add_face_attribute::<f32>("layer");
+    for vertex_id in 1..=wall.vertices.len() {
+        let x = wall.vertex(vertex_id).unwrap().position.x;
+        wall.attributes.set_vertex_attribute("x", vertex_id, x);
+    }
+    for face_id in 1..=wall.faces.len() {
+        wall.attributes.set_face_attribute("layer", face_id, 3.0f32);
+    }
+    let (face_count, wall_area, wall_boundary) = (wall.face_count, area(&wall), boundary_vertices(&wall));
+    let generation = wall.generation();
+    let removed = wall.decimate_flat_regions(1e-5);
+    assert!(removed > 0);
+    assert!(wall.generation() > generation);
+    assert!(wall.is_manifold());
+    // Only the outline is left, spanned by polygons between its vertices.
+    assert_eq!(wall_boundary, wall.vertex_count);
+    assert_eq!(wall_boundary, boundary_vertices(&wall));
+    assert!(wall.face_count * 4 < face_count);
+    assert!((area(&wall) - wall_area).abs() < 1e-3 * wall_area);
+    for (vertex_id, vertex) in wall.iter_vertices() {
+        assert!(vertex.position.y.abs() < 1e-6);
+        assert_eq!(Some(&vertex.position.x), wall.attributes.vertex_attribute::<f32>("x", vertex_id));
+    }
+    for face_id in FaceIterator::new(&wall) {
+        assert_eq!(Some(&3.0), wall.attributes.face_attribute::<f32>("layer", face_id));
+    }
+
+    // Half of the sheet curls into a quarter cylinder, which has to stay as
+    // dense as it was.
+    let n = 12;
+    let curled = |u: f32, v: f32| {
+        let x = u * n as f32;
+        if x <= 6.0 {
+            Point3::new(x, 0.0, v * n as f32)
+        } else {
+            let angle = (x - 6.0) / 6.0 * std::f32::consts::FRAC_PI_2 * 0.5;
+            Point3::new(6.0 + angle.sin() * 6.0, 6.0 - angle.cos() * 6.0, v * n as f32)
+        }
+    };
+    let mut sheet = Mesh::from_grid(n, n, curled, false, false);
+    let curved_before = sheet.iter_vertices().filter(|&(_, vertex)| vertex.position.x > 6.0 + 1e-3).count();
+    let face_count = sheet.face_count;
+    sheet.decimate_flat_regions(1e-5);
+    let curved_after = sheet.iter_vertices().filter(|&(_, vertex)| vertex.position.x > 6.0 + 1e-3).count();
+    assert_eq!(curved_before, curved_after);
+    assert!(sheet.face_count < face_count);
+    assert!(sheet.face_count > face_count / 2 - n);
+}
+
+#[test]
+fn verify_progressive_mesh() {
+    let face_loops = |mesh: &Mesh| FaceIterator::new(mesh).map(|face_id| {
+        let face_halfedge = mesh.face_first_halfedge_id(face_id).unwrap();
+        FaceHalfedgeIterator::new(mesh, face_halfedge).into_vec().into_iter()
+            .map(|halfedge_id| (mesh.halfedge_start_vertex(halfedge_id).unwrap().position, mesh.halfedge_uv(halfedge_id)))
+            .collect::<Vec<_>>()
+    }).collect::<Vec<_>>();
+    let mut mesh = cube().subdivide_n(2);
+    for halfedge_id in 1..=mesh.halfedges.len() {
+        let position = mesh.halfedge_start_vertex(halfedge_id).unwrap().position;
+        mesh.set_halfedge_uv(halfedge_id, Point2::new(position.x, position.y));
+    }
+    mesh.attributes.add_vertex_attribute::<Point3<f32>>("rest");
+    mesh.attributes.add_face_attribute::<f32>("index");
+    mesh.attributes.add_halfedge_attribute::<f32>("corner");
+    for vertex_id in 1..=mesh.vertices.len() {
+        let position = mesh.vertex(vertex_id).unwrap().position;
+        mesh.attributes.set_vertex_attribute("rest", vertex_id, position);
+    }
+    for (index, face_id) in FaceIterator::new(&mesh).collect::<Vec<Id>>().into_iter().enumerate() {
+        mesh.attributes.set_face_attribute("index", face_id, index as f32);
+    }
+    for halfedge_id in 1..=mesh.halfedges.len() {
+        let position = mesh.halfedge_start_vertex(halfedge_id).unwrap().position;
+        mesh.attributes.set_halfedge_attribute("corner", halfedge_id, position.z);
+    }
+    let progressive = mesh.to_progressive();
+    assert!(!progressive.splits.is_empty());
+
+    let full = progressive.at_detail(usize::MAX);
+    assert_eq!(mesh.face_count, full.face_count);
+    assert_eq!(mesh.vertex_count, full.vertex_count);
+    assert_eq!(face_loops(&mesh), face_loops(&full));
+    assert!(full.is_manifold());
+    for (vertex_id, vertex) in full.iter_vertices() {
+        assert_eq!(Some(&vertex.position), full.attributes.vertex_attribute::<Point3<f32>>("rest", vertex_id));
+    }
+    for (index, face_id) in FaceIterator::new(&full).enumerate() {
+        assert_eq!(Some(&(index as f32)), full.attributes.face_attribute::<f32>("index", face_id));
+    }
+    for (halfedge_id, _) in full.iter_halfedges() {
+        let position = full.halfedge_start_vertex(halfedge_id).unwrap().position;
+        assert_eq!(Some(&position.z), full.attributes.halfedge_attribute::<f32>("corner", halfedge_id));
+    }
+
+    let base = progressive.at_detail(0);
+    assert!(base.face_count < mesh.face_count / 10);
+    let mut last_face_count = base.face_count;
+    for &faces in [10, 20, 40, 60, 80].iter() {
+        let level = progressive.at_detail(faces);
+        assert!(level.face_count <= faces);
+        assert!(level.face_count >= last_face_count);
+        assert!(level.face_count + 2 >= faces);
+        assert!(level.is_manifold());
+        for (vertex_id, _) in level.iter_vertices() {
+            assert!(level.attributes.vertex_attribute::<Point3<f32>>("rest", vertex_id).is_some());
+        }
+        last_face_count = level.face_count;
+    }
+}
+
+#[test]
+fn verify_t_junction_detection() {
+    let mut mesh = Mesh::new();
+    let ids: Vec<Id> = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (1.0, 0.5), (2.0, 0.0), (2.0, 0.5), (2.0, 1.0)].iter()
+        .map(|&(x, y)| mesh.add_vertex(Point3::new(x, y, 0.0)))
+        .collect();
+    mesh.add_vertices(vec![ids[0], ids[1], ids[2], ids[3]]);
+    mesh.add_vertices(vec![ids[1], ids[5], ids[6], ids[4]]);
+    mesh.add_vertices(vec![ids[4], ids[6], ids[7], ids[2]]);
+
+    let t_junctions = mesh.find_t_junctions();
+    assert_eq!(1, t_junctions.len());
+    let halfedge_id = t_junctions[0];
+    assert_eq!(ids[1], mesh.halfedge_start_vertex_id(halfedge_id).unwrap());
+    assert_eq!(ids[2], mesh.halfedge_start_vertex_id(mesh.halfedge_next_id(halfedge_id).unwrap()).unwrap());
+    assert_eq!(&t_junctions[..], mesh.validate().t_junctions());
+    assert_eq!(Some(MeshError::TJunctionUnsupported { halfedge: halfedge_id }),
+        CatmullClarkSubdivider::new(&mesh).try_generate().err());
+
+    let grid = Mesh::from_grid(3, 3, |u, v| Point3::new(u, v, 0.0), false, false);
+    assert!(grid.find_t_junctions().is_empty());
+    assert!(cube().validate().t_junctions().is_empty());
+    assert_eq!(grid.subdivide().face_count, CatmullClarkSubdivider::new(&grid).try_generate().unwrap().face_count);
+}
+
+#[test]
+fn verify_boundary_length() {
+    let grid = Mesh::from_grid(4, 3, |u, v| Point3::new(u * 4.0, v * 1.5, 0.0), false, false);
+    assert!((grid.boundary_length() - 11.0).abs() < 1e-4);
+    assert_eq!(1, grid.boundary_loop_count());
+
+    let mut mesh = cube();
+    assert_eq!(0.0, mesh.boundary_length());
+    assert_eq!(0, mesh.boundary_loop_count());
+    let face_id = FaceIterator::new(&mesh).next().unwrap();
+    mesh.remove_face(face_id);
+    assert!((mesh.boundary_length() - 4.0).abs() < 1e-5);
+    assert_eq!(1, mesh.boundary_loop_count());
+}
+
+#[test]
+fn verify_subdivision_is_order_independent() {
+    let mut original = cube();
+    original.vertex_mut(1).unwrap().position += Vector3::new(0.1, -0.37, 0.23);
+    let original = original.subdivide();
+    let loops: Vec<Vec<Point3<f32>>> = FaceIterator::new(&original).map(|face_id| {
+        original.face_vertex_ids(face_id).into_iter().map(|vertex_id| original.vertex(vertex_id).unwrap().position).collect()
+    }).collect();
+    // The same surface with the vertices and faces added in reverse and
+    // every face starting at another corner.
+    let mut shuffled = Mesh::new();
+    let mut vertex_ids: Vec<(Point3<f32>, Id)> = Vec::new();
+    for (_, vertex) in original.iter_vertices().collect::<Vec<_>>().into_iter().rev() {
+        vertex_ids.push((vertex.position, shuffled.add_vertex(vertex.position)));
+    }
+    for (i, face) in loops.iter().enumerate().rev() {
+        let corners = (0..face.len())
+            .map(|j| face[(i + j) % face.len()])
+            .map(|position| vertex_ids.iter().find(|&&(other, _)| other == position).unwrap().1)
+            .collect();
+        shuffled.add_vertices(corners);
+    }
+    let positions = |mesh: &Mesh| {
+        let mut positions: Vec<[u32; 3]> = mesh.subdivide_n(2).iter_vertices()
+            .map(|(_, vertex)| [vertex.position.x.to_bits(), vertex.position.y.to_bits(), vertex.position.z.to_bits()])
+            .collect();
+        positions.sort();
+        positions
+    };
+    assert_eq!(positions(&original), positions(&shuffled));
+}
+
+#[test]
+fn verify_read_and_write_obj() {
+    let source: &[u8] = b"v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv 0 0 1\nv 1 0 1\nv 1 1 1\nv 0 1 1\n\
+        f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4 1 5 8\n";
+    let mut mesh = Mesh::new();
+    mesh.read_obj(source).unwrap();
+    assert_eq!(8, mesh.vertex_count);
+    assert_eq!(6, mesh.face_count);
+    assert!(mesh.is_closed());
+
+    let mut written = Vec::new();
+    mesh.write_obj(&mut written).unwrap();
+    let mut read_back = Mesh::new();
+    read_back.read_obj(&written[..]).unwrap();
+    assert_eq!(6, read_back.face_count);
+    assert!(read_back.is_closed());
+    let mut written_again = Vec::new();
+    read_back.write_obj(&mut written_again).unwrap();
+    assert_eq!(written, written_again);
+
+    let mut broken = Mesh::new();
+    assert_eq!(Err(MeshError::InvalidObjLine { line: 3 }), broken.read_obj(&b"v 0 0 0\nv 1 0 0\nf 1 2 3\n"[..]));
+    assert_eq!(2, broken.vertex_count);
+    assert_eq!(0, broken.face_count);
+    assert_eq!(Err(MeshError::InvalidObjLine { line: 1 }), Mesh::new().read_obj(&b"v 0 zero 0\n"[..]));
+
+    let mut mixed = cube();
+    let red = mixed.add_material(Material::new("red"));
+    let face_ids: Vec<Id> = FaceIterator::new(&mixed).collect();
+    mixed.set_face_material(face_ids[1], Some(red));
+    mixed.set_face_material(face_ids[4], Some(red));
+    let mut written = Vec::new();
+    mixed.write_obj(&mut written).unwrap();
+    let mut read_back = Mesh::new();
+    read_back.read_obj(&written[..]).unwrap();
+    let material_names = |mesh: &Mesh| {
+        let mut names: Vec<(Point3<i32>, Option<String>)> = FaceIterator::new(mesh)
+            .map(|face_id| {
+                let center = mesh.face_center(face_id) * 2.0;
+                (Point3::new(center.x.round() as i32, center.y.round() as i32, center.z.round() as i32),
+                    mesh.face_material(face_id).map(|index| mesh.materials[index].name.clone()))
+            })
+            .collect();
+        names.sort_by_key(|&(center, _)| (center.x, center.y, center.z));
+        names
+    };
+    assert_eq!(material_names(&mixed), material_names(&read_back));
+    assert_eq!(4, FaceIterator::new(&read_back).filter(|&face_id| read_back.face_material(face_id).is_none()).count());
+}
+
+#[test]
+fn verify_bake_ambient_occlusion() {
+    let flat = Mesh::from_grid(8, 8, |u, v| Point3::new(u * 8.0, v * 8.0, 0.0), false, false);
+    let center = flat.iter_vertices()
+        .find(|&(_, vertex)| vertex.position.distance(Point3::new(4.0, 4.0, 0.0)) < 1e-5)
+        .map(|(vertex_id, _)| vertex_id)
+        .unwrap();
+    let occlusion = flat.bake_ambient_occlusion(64, 7);
+    assert_eq!(flat.vertex_count, occlusion.len());
+    assert!(occlusion.values().all(|&value| value > 0.99));
+
+    // A narrow pit opening towards the side the normals face.
+    let up = flat.vertex_norm(center).z.signum();
+    let mut pit = flat.clone();
+    pit.vertex_mut(center).unwrap().position.z = -20.0 * up;
+    let occlusion = pit.bake_ambient_occlusion(256, 7);
+    assert!(occlusion[&center] < 0.1);
+    assert_eq!(occlusion, pit.bake_ambient_occlusion(256, 7));
+
+    pit.bake_ambient_occlusion_attribute("ao", 256, 7);
+    assert_eq!(Some(&occlusion[&center]), pit.attributes.vertex_attribute::<f32>("ao", center));
+}
+
+#[test]
+fn verify_subdivision_jacobian_row() {
+    let mut cage = cube();
+    cage.vertex_mut(3).unwrap().position += Vector3::new(0.4, 0.2, -0.3);
+    let grid = Mesh::from_grid(3, 2, |u, v| Point3::new(u * 3.0, v * 2.0, u * v), false, false);
+    for mesh in [cage, grid].iter() {
+        let levels = 2;
+        let subdivided = mesh.subdivide_n(levels);
+        let matrix = mesh.subdivision_matrix(levels);
+        let jacobian = mesh.subdivision_jacobian(levels);
+        assert_eq!(subdivided.positions(), jacobian.output().positions());
+        for (output_vertex, vertex) in subdivided.iter_vertices() {
+            let row = jacobian.row(output_vertex);
+            let sum: f32 = row.iter().map(|&(_, weight)| weight).sum();
+            assert!((sum - 1.0).abs() < 1e-5);
+            let position = row.iter().fold(Vector3::zero(), |position, &(vertex_id, weight)| {
+                position + mesh.vertex(vertex_id).unwrap().position.to_vec() * weight
+            });
+            assert!(Point3::from_vec(position).distance(vertex.position) < 1e-4);
+            for &(vertex_id, weight) in row.iter() {
+                assert!((matrix.get(output_vertex - 1, vertex_id - 1) - weight).abs() < 1e-6);
+            }
+        }
+        assert_eq!(jacobian.row(7), mesh.subdivision_jacobian_row(7, levels));
+        assert!(mesh.subdivision_jacobian_row(subdivided.vertices.len() + 1, levels).is_empty());
+    }
+}
+
+#[test]
+fn verify_volume_preserving_smooth() {
+    let mesh = cube().subdivide();
+    let volume = mesh.volume();
+    let smoothed = mesh.volume_preserving_smooth(50);
+    assert!((smoothed.volume() - volume).abs() < 1e-4 * volume);
+    assert_eq!(mesh.vertex_count, smoothed.vertex_count);
+    assert!(mesh.iter_vertices().zip(smoothed.iter_vertices())
+        .any(|((_, before), (_, after))| before.position.distance(after.position) > 0.01));
+
+    let mut plain = mesh.clone();
+    for _ in 0..50 {
+        plain.smooth(0.5, None);
+    }
+    assert!(plain.volume() < volume * 0.9);
+}
+
+#[test]
+fn verify_spatial_face_selection() {
+    let mesh = cube().subdivide().subdivide();
+    let (min, max) = mesh.build_bvh().bounds().unwrap();
+    let center = min.midpoint(max);
+    let top = mesh.select_faces_by_plane_side(Vector3::unit_z(), center);
+    let expected: Vec<Id> = FaceIterator::new(&mesh).filter(|&face_id| mesh.face_center(face_id).z > center.z).collect();
+    assert_eq!(mesh.face_count / 2, top.len());
+    assert_eq!(Selection::from_faces(expected), top);
+    let bottom = mesh.select_faces_by_plane_side(-Vector3::unit_z(), center);
+    assert_eq!(mesh.face_count / 2, bottom.len());
+    assert!(top.faces.is_disjoint(&bottom.faces));
+
+    let everything = mesh.select_faces_in_box(min, max);
+    assert_eq!(mesh.face_count, everything.len());
+    let upper_box = mesh.select_faces_in_box(Point3::new(min.x, min.y, center.z), max);
+    assert_eq!(top, upper_box);
+
+    assert_eq!(mesh.face_count, mesh.select_faces_in_sphere(center, min.distance(max)).len());
+    assert!(mesh.select_faces_in_sphere(center, 0.1).is_empty());
+    let face_id = FaceIterator::new(&mesh).next().unwrap();
+    let around = mesh.select_faces_in_sphere(mesh.face_center(face_id), 0.2);
+    assert!(around.contains(face_id));
+    assert!(around.len() > 1 && around.len() < mesh.face_count / 8);
+}
+
+#[test]
+fn verify_subdivision_keeps_face_materials() {
+    let mut mesh = cube();
+    let red = mesh.add_material(Material::new("red"));
+    let blue = mesh.add_material(Material::new("blue"));
+    for (i, face_id) in FaceIterator::new(&mesh).into_vec().into_iter().enumerate() {
+        if i != 5 {
+            mesh.set_face_material(face_id, Some(if i % 2 == 0 { red } else { blue }));
+        }
+    }
+    let (subdivided, children) = CatmullClarkSubdivider::new(&mesh).generate_with_child_map();
+    assert_eq!(mesh.materials, subdivided.materials);
+    for face_id in FaceIterator::new(&mesh) {
+        for &child_id in children[&face_id].iter() {
+            assert_eq!(mesh.face_material(face_id), subdivided.face_material(child_id));
+        }
+    }
+    let twice = mesh.subdivide_n(2);
+    let count = |mesh: &Mesh, material: Option<usize>| FaceIterator::new(mesh)
+        .filter(|&face_id| mesh.face_material(face_id) == material).count();
+    assert_eq!(16 * 3, count(&twice, Some(red)));
+    assert_eq!(16 * 2, count(&twice, Some(blue)));
+    assert_eq!(16, count(&twice, None));
+}
+
+#[test]
+fn verify_live_counts() {
+    let mut mesh = cube();
+    let live = |mesh: &Mesh| (mesh.vertices.iter().filter(|vertex| vertex.alive).count(),
+        mesh.faces.iter().filter(|face| face.alive).count(),
+        mesh.halfedges.iter().filter(|halfedge| halfedge.alive).count());
+    let face_ids: Vec<Id> = FaceIterator::new(&mesh).take(2).collect();
+    let corners = mesh.face_vertex_ids(face_ids[0]);
+    for &face_id in face_ids.iter() {
+        mesh.remove_face(face_id);
+    }
+    mesh.add_vertices(corners);
+    mesh.add_positions(vec![Point3::new(2.0, 0.0, 0.0), Point3::new(3.0, 0.0, 0.0), Point3::new(3.0, 1.0, 0.0)]);
+    assert_eq!((mesh.vertex_count, mesh.face_count, mesh.halfedge_count), live(&mesh));
+    assert_eq!((11, 6, 23), live(&mesh));
+    assert_eq!(8, mesh.faces.len());
+    assert_eq!(31, mesh.halfedges.len());
+    assert_eq!(11, mesh.vertices.len());
+    assert_eq!(live(&mesh).0, FaceIterator::new(&mesh).flat_map(|face_id| mesh.face_vertex_ids(face_id))
+        .collect::<std::collections::HashSet<Id>>().len());
+}
+
+#[test]
+fn verify_indexed_round_trip() {
+    let mesh = cube();
+    let (positions, triangles) = mesh.to_indexed();
+    let rebuilt = Mesh::from_indexed(&positions, &triangles).unwrap();
+    assert_eq!(8, rebuilt.vertex_count);
+    assert_eq!(12, rebuilt.face_count);
+    assert_eq!(36, rebuilt.halfedge_count);
+    assert_closed_mesh(&rebuilt);
+    let report = rebuilt.validate();
+    assert_eq!(0, report.boundary_edges);
+    assert!(report.is_manifold());
+    assert_eq!(mesh.positions(), rebuilt.positions());
+    assert_eq!(triangles, rebuilt.to_indexed().1);
+    // The quads of the cube are split, but all of their edges remain.
+    for endpoints in mesh.edges.keys() {
+        assert!(rebuilt.edges.contains_key(endpoints));
+    }
+
+    // Triangles repeating an index are left out, indices without a position
+    // are refused.
+    let mut with_degenerate = triangles.clone();
+    with_degenerate.push([0, 0, 1]);
+    assert_eq!(12, Mesh::from_indexed(&positions, &with_degenerate).unwrap().face_count);
+    let mut out_of_range = triangles.clone();
+    out_of_range.push([0, 1, 8]);
+    assert_eq!(Err(MeshError::IndexOutOfRange { triangle: 12, index: 8 }), Mesh::from_indexed(&positions, &out_of_range).map(|mesh| mesh.face_count));
+}
+
+#[test]
+fn verify_subdivision_of_misoriented_faces() {
+    let original = cube();
+    let mut mesh = Mesh::new();
+    let ids: Vec<Id> = original.vertices.iter().map(|vertex| mesh.add_vertex(vertex.position)).collect();
+    for (i, face_id) in FaceIterator::new(&original).enumerate() {
+        let mut corners: Vec<Id> = original.face_vertex_ids(face_id).iter().map(|&vertex_id| ids[vertex_id - 1]).collect();
+        if i == 3 {
+            corners.reverse();
+        }
+        mesh.add_vertices(corners);
+    }
+    let misoriented = mesh.find_misoriented_edges();
+    assert_eq!(4, misoriented.len());
+    assert_eq!(&misoriented[..], mesh.validate().misoriented_edges());
+    assert!(original.find_misoriented_edges().is_empty());
+    assert_eq!(Some(MeshError::InconsistentOrientation { halfedge: misoriented[0] }),
+        CatmullClarkSubdivider::new(&mesh).try_generate().err());
+
+    let subdivided = CatmullClarkSubdivider::new(&mesh).with_repair_orientation(true).try_generate().unwrap();
+    let expected = original.subdivide();
+    assert_eq!(expected.face_count, subdivided.face_count);
+    assert_closed_mesh(&subdivided);
+    assert!(subdivided.find_misoriented_edges().is_empty());
+    assert!((expected.volume() - subdivided.volume()).abs() < 1e-5);
+}
+
+#[test]
+fn verify_vertices_within_radius() {
+    let mesh = cube();
+    let (corner_id, corner) = mesh.iter_vertices().map(|(vertex_id, vertex)| (vertex_id, vertex.position)).next().unwrap();
+    assert_eq!(vec![corner_id], mesh.vertices_within_radius(corner, 0.1));
+    let all: Vec<Id> = mesh.iter_vertices().map(|(vertex_id, _)| vertex_id).collect();
+    assert_eq!(all, mesh.vertices_within_radius(corner, 10.0));
+    assert!(mesh.vertices_within_radius(corner, -1.0).is_empty());
+
+    let dense = mesh.subdivide().subdivide().subdivide();
+    assert!(dense.vertex_count > 64);
+    let tree = dense.build_kd_tree();
+    for (_, vertex) in dense.iter_vertices().step_by(7) {
+        for &radius in [0.0, 0.2, 0.5, 1.5].iter() {
+            let expected: Vec<Id> = dense.iter_vertices()
+                .filter(|&(_, other)| other.position.distance2(vertex.position) <= radius * radius)
+                .map(|(vertex_id, _)| vertex_id)
+                .collect();
+            assert_eq!(expected, tree.within_radius(vertex.position, radius));
+            assert_eq!(expected, dense.vertices_within_radius(vertex.position, radius));
+        }
+    }
+
+    let mut dense = dense;
+    assert!(Arc::ptr_eq(&dense.cached_kd_tree(), &dense.cached_kd_tree()));
+    let far = Point3::new(9.0, 9.0, 9.0);
+    assert!(dense.vertices_within_radius(far, 0.1).is_empty());
+    dense.vertex_mut(corner_id).unwrap().position = far;
+    assert_eq!(vec![corner_id], dense.vertices_within_radius(far, 0.1));
+}
+
+#[test]
+fn verify_soft_translate() {
+    let original = Mesh::from_grid(8, 1, |u, _| Point3::new(u * 8.0, 0.0, 0.0), false, false);
+    let center = original.nearest_vertex(Point3::new(0.0, 0.0, 0.0)).unwrap();
+    let delta = Vector3::new(0.0, 0.0, 2.0);
+    for &falloff in [Falloff::Linear, Falloff::Smooth, Falloff::Sharp].iter() {
+        let mut mesh = original.duplicate();
+        mesh.soft_translate(center, delta, 4.0, falloff);
+        for (vertex_id, vertex) in mesh.iter_vertices() {
+            let before = original.vertex(vertex_id).unwrap().position;
+            let expected = delta * falloff.weight(before.x / 4.0);
+            assert!((vertex.position - before - expected).magnitude() < 1e-5);
+            if before.x >= 4.0 {
+                assert_eq!(before, vertex.position);
+            }
+        }
+        assert_eq!(original.vertex(center).unwrap().position + delta, mesh.vertex(center).unwrap().position);
+    }
+    assert_eq!(0.5, Falloff::Linear.weight(0.5));
+    assert_eq!(0.5, Falloff::Smooth.weight(0.5));
+    assert_eq!(0.25, Falloff::Sharp.weight(0.5));
+    assert!(Falloff::Smooth.weight(0.25) > Falloff::Linear.weight(0.25));
+    assert!(Falloff::Sharp.weight(0.25) < Falloff::Linear.weight(0.25));
+}
+
+#[test]
+fn verify_flat_optimization() {
+    let assert_same_positions = |full: &Mesh, flat: &Mesh| {
+        assert_eq!(full.face_count, flat.face_count);
+        assert_eq!(full.vertex_count, flat.vertex_count);
+        for (full_position, flat_position) in full.positions().iter().zip(flat.positions().iter()) {
+            assert!(full_position.distance(*flat_position) < 1e-4);
+        }
+    };
+    let plane = Mesh::from_grid(12, 12, |u, v| Point3::new(u * 6.0, v * 6.0, 0.0), false, false);
+    let full = CatmullClarkSubdivider::new(&plane).generate();
+    let flat = CatmullClarkSubdivider::new(&plane).with_flat_optimization(1e-4).generate();
+    assert_same_positions(&full, &flat);
+
+    // Flat but unevenly spaced, the full rules slide the points sideways.
+    let uneven = Mesh::from_grid(12, 12, |u, v| Point3::new(u * u * 6.0, v * 6.0, 0.0), false, false);
+    let full = CatmullClarkSubdivider::new(&uneven).generate();
+    let flat = CatmullClarkSubdivider::new(&uneven).with_flat_optimization(1e-4).generate();
+    assert_same_positions(&full, &flat);
+    assert!(flat.iter_vertices().all(|(_, vertex)| vertex.position.z == 0.0));
+
+    let curved = cube().subdivide();
+    let full = CatmullClarkSubdivider::new(&curved).generate();
+    let optimized = CatmullClarkSubdivider::new(&curved).with_flat_optimization(1e-4).generate();
+    assert_eq!(full.positions(), optimized.positions());
+}
+
+#[test]
+fn verify_normal_cache_after_removing_faces() {
+    let mut mesh = cube().subdivide();
+    let mut cache = NormalCache::new(&mesh);
+    let face_id = FaceIterator::new(&mesh).nth(5).unwrap();
+    let corners = mesh.face_vertex_ids(face_id);
+    mesh.remove_face(face_id);
+    cache.mark_dirty(&[face_id]);
+    for &vertex_id in corners.iter() {
+        assert_eq!(mesh.vertex_norm(vertex_id), cache.vertex_norm(&mesh, vertex_id));
+    }
+}
+
+#[test]
+fn verify_cached_normals_and_bvh_on_mesh() {
+    let faces_around = |mesh: &Mesh, vertex_id: Id| -> Vec<Id> {
+        mesh.vertex(vertex_id).unwrap().halfedges.iter().map(|&halfedge_id| mesh.halfedge_face_id(halfedge_id).unwrap()).collect()
+    };
+    let mut mesh = cube().subdivide_n(2);
+    let origin = Point3::new(0.05, 0.1, 3.0);
+    let down = Vector3::new(0.0, 0.0, -1.0);
+    assert_eq!(mesh.vertex_norm(3), mesh.cached_vertex_norm(3));
+    let before = mesh.cached_bvh();
+    assert_eq!(mesh.build_bvh().raycast(origin, down), before.raycast(origin, down));
+
+    // Marked moves refresh the marked region and refit the tree.
+    let top = mesh.build_bvh().raycast(origin, down).unwrap().face_id;
+    let moved: Vec<Id> = mesh.face_vertex_ids(top);
+    let mut dirty = Vec::new();
+    for &vertex_id in moved.iter() {
+        mesh.vertex_mut(vertex_id).unwrap().position.z += 0.5;
+        dirty.extend(faces_around(&mesh, vertex_id));
+    }
+    mesh.mark_dirty(&dirty);
+    for vertex in mesh.vertices.iter() {
+        assert_eq!(mesh.vertex_norm(vertex.id), mesh.cached_vertex_norm(vertex.id));
+    }
+    for face_id in FaceIterator::new(&mesh) {
+        assert_eq!(mesh.face_area(face_id), mesh.cached_face_area(face_id));
+        assert_eq!(mesh.face_norm(face_id), mesh.cached_face_norm(face_id));
+    }
+    let refit = mesh.cached_bvh();
+    assert_eq!(mesh.build_bvh().raycast(origin, down), refit.raycast(origin, down));
+    assert!(refit.raycast(origin, down).unwrap().distance < before.raycast(origin, down).unwrap().distance);
+
+    // Unmarked edits are caught by the generation.
+    mesh.vertex_mut(moved[0]).unwrap().position.z += 0.5;
+    assert_eq!(mesh.vertex_norm(moved[0]), mesh.cached_vertex_norm(moved[0]));
+    assert_eq!(mesh.build_bvh().raycast(origin, down), mesh.cached_bvh().raycast(origin, down));
+
+    // Removed faces need a rebuild of the tree, which happens by itself.
+    mesh.remove_face(top);
+    mesh.mark_dirty(&[top]);
+    assert_eq!(mesh.vertex_norm(moved[1]), mesh.cached_vertex_norm(moved[1]));
+    assert_eq!(mesh.build_bvh().raycast(origin, down), mesh.cached_bvh().raycast(origin, down));
+    assert_ne!(Some(top), mesh.cached_bvh().raycast(origin, down).map(|hit| hit.face_id));
+}